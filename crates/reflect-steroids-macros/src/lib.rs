@@ -0,0 +1,266 @@
+//! Procedural macros for [`reflect-steroids`](https://docs.rs/reflect-steroids).
+//!
+//! Use these through the re-exports in `reflect_steroids` (with the "macros"
+//! crate feature), not directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, spanned::Spanned, AttributeArgs, Fields, GenericArgument,
+    Ident, Item, Lit, Meta, NestedMeta, Path, PathArguments, Type,
+};
+
+/// Expand `#[serde_dyn]` field attributes into the `#[serde(with = "...")]`
+/// attributes needed for tagged (de)serialization of trait objects.
+///
+/// See the documentation of `reflect_steroids::serde_dyn`.
+#[proc_macro_attribute]
+pub fn serde_dyn(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as AttributeArgs);
+    let krate = match parse_crate_path(args) {
+        Ok(krate) => krate,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let mut item = parse_macro_input!(input as Item);
+    let mut helpers = TokenStream2::new();
+
+    let result = match item {
+        Item::Struct(ref mut item) => {
+            expand_fields(&krate, &item.ident, None, &mut item.fields, &mut helpers)
+        }
+        Item::Enum(ref mut item) => item.variants.iter_mut().try_for_each(|variant| {
+            expand_fields(
+                &krate,
+                &item.ident,
+                Some(&variant.ident),
+                &mut variant.fields,
+                &mut helpers,
+            )
+        }),
+        ref other => Err(syn::Error::new(
+            other.span(),
+            "#[serde_dyn] can only be used on structs and enums",
+        )),
+    };
+
+    match result {
+        Ok(()) => quote!(#item #helpers).into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Parse the arguments of the item attribute: nothing, or `crate = "..."` with
+/// the path of the `reflect_steroids` crate, for crates that rename it.
+fn parse_crate_path(args: AttributeArgs) -> syn::Result<Path> {
+    let mut krate: Path = parse_quote!(::reflect_steroids);
+    for arg in args {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(ref name_value))
+                if name_value.path.is_ident("crate") =>
+            {
+                match name_value.lit {
+                    Lit::Str(ref path) => krate = path.parse()?,
+                    ref other => {
+                        return Err(syn::Error::new(
+                            other.span(),
+                            "expected the path of the reflect_steroids crate as a string",
+                        ))
+                    }
+                }
+            }
+            other => {
+                return Err(syn::Error::new(
+                    other.span(),
+                    r#"expected #[serde_dyn] or #[serde_dyn(crate = "...")] here; put other arguments on the fields"#,
+                ))
+            }
+        }
+    }
+    Ok(krate)
+}
+
+fn expand_fields(
+    krate: &Path,
+    item: &Ident,
+    variant: Option<&Ident>,
+    fields: &mut Fields,
+    helpers: &mut TokenStream2,
+) -> syn::Result<()> {
+    for (index, field) in fields.iter_mut().enumerate() {
+        let mut marker = None;
+        let mut attrs = Vec::with_capacity(field.attrs.len());
+        for attr in field.attrs.drain(..) {
+            if attr.path.is_ident("serde_dyn") {
+                marker = Some(attr);
+            } else {
+                attrs.push(attr);
+            }
+        }
+        field.attrs = attrs;
+
+        let marker = match marker {
+            Some(marker) => marker,
+            None => continue,
+        };
+        let registry = parse_registry(&marker)?;
+        check_field_type(&field.ty)?;
+
+        match registry {
+            None => {
+                let with = format!("{}::serialization::dyn_field", path_to_string(krate));
+                field.attrs.push(parse_quote!(#[serde(with = #with)]));
+            }
+            Some(registry) => {
+                let module = match (variant, &field.ident) {
+                    (None, Some(name)) => format_ident!("__serde_dyn_{}_{}", item, name),
+                    (None, None) => format_ident!("__serde_dyn_{}_{}", item, index),
+                    (Some(variant), Some(name)) => {
+                        format_ident!("__serde_dyn_{}_{}_{}", item, variant, name)
+                    }
+                    (Some(variant), None) => {
+                        format_ident!("__serde_dyn_{}_{}_{}", item, variant, index)
+                    }
+                };
+                let with = module.to_string();
+                field.attrs.push(parse_quote!(#[serde(with = #with)]));
+                helpers.extend(registry_module(krate, &module, &registry));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parse `#[serde_dyn]` or `#[serde_dyn(registry = "path::to::fn")]`.
+fn parse_registry(attr: &syn::Attribute) -> syn::Result<Option<Path>> {
+    if attr.tokens.is_empty() {
+        return Ok(None);
+    }
+
+    let list = match attr.parse_meta()? {
+        Meta::List(list) => list,
+        other => {
+            return Err(syn::Error::new(
+                other.span(),
+                r#"expected #[serde_dyn] or #[serde_dyn(registry = "...")]"#,
+            ))
+        }
+    };
+
+    let mut registry = None;
+    for nested in list.nested {
+        match nested {
+            NestedMeta::Meta(Meta::NameValue(ref name_value))
+                if name_value.path.is_ident("registry") =>
+            {
+                match name_value.lit {
+                    Lit::Str(ref path) => registry = Some(path.parse()?),
+                    ref other => {
+                        return Err(syn::Error::new(
+                            other.span(),
+                            "expected the path of a function as a string",
+                        ))
+                    }
+                }
+            }
+            other => {
+                return Err(syn::Error::new(
+                    other.span(),
+                    "unknown #[serde_dyn] argument",
+                ))
+            }
+        }
+    }
+    Ok(registry)
+}
+
+/// The path as written in source, for use in string attributes.
+fn path_to_string(path: &Path) -> String {
+    let segments: Vec<String> = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect();
+    let prefix = if path.leading_colon.is_some() { "::" } else { "" };
+    format!("{}{}", prefix, segments.join("::"))
+}
+
+/// Check that the field is a `Box<dyn Trait>`, or an `Option` or `Vec` of
+/// supported types.
+fn check_field_type(ty: &Type) -> syn::Result<()> {
+    let unsupported = || {
+        syn::Error::new(
+            ty.span(),
+            "#[serde_dyn] supports fields of type `Box<dyn Trait>`, `Option<...>` and `Vec<...>`",
+        )
+    };
+
+    let segment = match ty {
+        Type::Path(path) if path.qself.is_none() => {
+            path.path.segments.last().ok_or_else(unsupported)?
+        }
+        _ => return Err(unsupported()),
+    };
+    let argument = match segment.arguments {
+        PathArguments::AngleBracketed(ref args) if args.args.len() == 1 => {
+            match args.args.first() {
+                Some(GenericArgument::Type(ty)) => ty,
+                _ => return Err(unsupported()),
+            }
+        }
+        _ => return Err(unsupported()),
+    };
+
+    if segment.ident == "Box" {
+        match argument {
+            Type::TraitObject(_) => Ok(()),
+            // `Box<SomeTrait>` without `dyn` in old code, or a type alias.
+            Type::Path(_) => Ok(()),
+            _ => Err(unsupported()),
+        }
+    } else if segment.ident == "Option" || segment.ident == "Vec" {
+        check_field_type(argument)
+    } else {
+        Err(unsupported())
+    }
+}
+
+/// Generate a `with` module that (de)serializes a field with the registry
+/// returned by `registry()` as the current registry.
+fn registry_module(krate: &Path, module: &Ident, registry: &Path) -> TokenStream2 {
+    quote! {
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        mod #module {
+            #[allow(unused_imports)]
+            use super::*;
+
+            pub fn serialize<F, S>(value: &F, serializer: S) -> ::core::result::Result<S::Ok, S::Error>
+            where
+                F: #krate::serialization::dyn_field::DynField,
+                S: #krate::serde::Serializer,
+            {
+                let registry = #registry();
+                let registry: &#krate::reflect::TypeRegistry =
+                    ::core::borrow::Borrow::borrow(&registry);
+                #krate::TypeRegistryExt::set_current(registry, || {
+                    #krate::serialization::dyn_field::serialize(value, serializer)
+                })
+            }
+
+            pub fn deserialize<'de, F, D>(deserializer: D) -> ::core::result::Result<F, D::Error>
+            where
+                F: #krate::serialization::dyn_field::DynField,
+                D: #krate::serde::Deserializer<'de>,
+            {
+                let registry = #registry();
+                let registry: &#krate::reflect::TypeRegistry =
+                    ::core::borrow::Borrow::borrow(&registry);
+                #krate::TypeRegistryExt::set_current(registry, || {
+                    #krate::serialization::dyn_field::deserialize(deserializer)
+                })
+            }
+        }
+    }
+}
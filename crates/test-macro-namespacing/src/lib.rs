@@ -1,5 +1,13 @@
-#[bevy_reflect::reflect_trait]
-pub trait TestTrait: reflect_steroids::DowncastReflect {}
+mod traits {
+    // `#[reflect_trait]` comes from `bevy_reflect` and refers to it by name,
+    // so it is made available under that name through `reflect_steroids`.
+    use reflect_steroids::reflect as bevy_reflect;
+
+    #[bevy_reflect::reflect_trait]
+    pub trait TestTrait: reflect_steroids::DowncastReflect {}
+}
+
+pub use traits::{ReflectTestTrait, TestTrait};
 
 reflect_steroids::impl_dynamic_trait!(TestTrait, ReflectTestTrait);
 
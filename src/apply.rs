@@ -0,0 +1,489 @@
+use std::fmt::Write as _;
+
+use bevy_reflect::{List, Reflect, ReflectRef, TypeInfo, TypeRegistry};
+
+use crate::{ApplyError, TypeRegistryExt};
+
+/// Apply `src` to `dst` (see [`Reflect::apply()`]), after checking that the
+/// two values are compatible.
+///
+/// [`Reflect::apply()`] panics when the structure of `src` does not line up
+/// with `dst`, typically from deep inside `bevy_reflect`. This function walks
+/// both values first (see [`check_apply()`]), and returns an error describing
+/// where and why they differ, leaving `dst` untouched.
+///
+/// Both arguments can be any reflected trait object, for example `&mut dyn
+/// MyTrait` and `&dyn MyTrait` with different concrete types.
+///
+/// # Errors
+///
+/// Fails with the first difference found by [`check_apply()`], in which case
+/// `dst` is not modified.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, try_apply_dyn};
+/// #[reflect_trait]
+/// trait MyTrait: DowncastReflect {}
+/// impl_dynamic_trait!(MyTrait, ReflectMyTrait);
+///
+/// #[derive(Reflect)]
+/// struct Foo { num: i32 }
+/// impl MyTrait for Foo {}
+///
+/// #[derive(Reflect)]
+/// struct Bar { num: f32 }
+/// impl MyTrait for Bar {}
+///
+/// let mut dst: Box<dyn MyTrait> = Box::new(Foo { num: 1 });
+/// let src: Box<dyn MyTrait> = Box::new(Foo { num: 2 });
+/// try_apply_dyn(&mut *dst, &*src).unwrap();
+/// assert_eq!(dst.downcast_ref::<Foo>().unwrap().num, 2);
+///
+/// let src: Box<dyn MyTrait> = Box::new(Bar { num: 3.0 });
+/// let err = try_apply_dyn(&mut *dst, &*src).unwrap_err();
+/// assert_eq!(err.to_string(), "cannot apply value of type 'f32' to 'i32' at 'num'");
+/// ```
+pub fn try_apply_dyn<T, U>(dst: &mut T, src: &U) -> Result<(), ApplyError>
+where
+    T: Reflect + ?Sized,
+    U: Reflect + ?Sized,
+{
+    check_apply(dst.as_reflect(), src.as_reflect())?;
+    dst.as_reflect_mut().apply(src.as_reflect());
+    Ok(())
+}
+
+/// Check that `src` can be applied to `dst` without panicking.
+///
+/// The rules mirror those of the [`Reflect::apply()`] implementations in
+/// `bevy_reflect`:
+///
+/// - Structs, tuple structs and tuples must both be of the same kind. Fields
+///   present in `src` but not in `dst` are ignored.
+/// - Lists must both be lists. Elements present in `src` but not in `dst` are
+///   appended, and are checked against the first element of `dst`. If `dst`
+///   is empty, they are checked against its item type instead, which must be
+///   in the current registry for elements that are not values.
+/// - Arrays must have the same length.
+/// - Maps must both be maps.
+/// - Enums must both be enums. If they are of the same variant, their fields
+///   are checked as for structs and tuples. Otherwise, the destination enum
+///   must have a variant of the same name, which replaces the current one.
+/// - Values (primitives and other opaque types) must have the same concrete
+///   type.
+///
+/// # Errors
+///
+/// Describes the first place where `src` does not line up with `dst`.
+pub fn check_apply(dst: &dyn Reflect, src: &dyn Reflect) -> Result<(), ApplyError> {
+    check_apply_at(dst, src, &mut String::new())
+}
+
+// One arm per kind of value, which reads best as a single match.
+#[allow(clippy::too_many_lines)]
+fn check_apply_at(
+    dst: &dyn Reflect,
+    src: &dyn Reflect,
+    path: &mut String,
+) -> Result<(), ApplyError> {
+    let len = path.len();
+    let result = match (dst.reflect_ref(), src.reflect_ref()) {
+        (ReflectRef::Struct(dst), ReflectRef::Struct(src)) => src
+            .iter_fields()
+            .enumerate()
+            .try_for_each(|(index, src_field)| {
+                let name = src.name_at(index).unwrap_or_default();
+                match dst.field(name) {
+                    Some(dst_field) => {
+                        path.truncate(len);
+                        if !path.is_empty() {
+                            path.push('.');
+                        }
+                        path.push_str(name);
+                        check_apply_at(dst_field, src_field, path)
+                    }
+                    None => Ok(()),
+                }
+            }),
+        (ReflectRef::TupleStruct(dst), ReflectRef::TupleStruct(src)) => src
+            .iter_fields()
+            .enumerate()
+            .try_for_each(|(index, src_field)| match dst.field(index) {
+                Some(dst_field) => {
+                    path.truncate(len);
+                    let _ = write!(path, ".{index}");
+                    check_apply_at(dst_field, src_field, path)
+                }
+                None => Ok(()),
+            }),
+        (ReflectRef::Tuple(dst), ReflectRef::Tuple(src)) => src
+            .iter_fields()
+            .enumerate()
+            .try_for_each(|(index, src_field)| match dst.field(index) {
+                Some(dst_field) => {
+                    path.truncate(len);
+                    let _ = write!(path, ".{index}");
+                    check_apply_at(dst_field, src_field, path)
+                }
+                None => Ok(()),
+            }),
+        (ReflectRef::List(dst), ReflectRef::List(src)) => {
+            src.iter().enumerate().try_for_each(|(index, src_element)| {
+                // New elements are appended, so check them against the first
+                // existing element, if any.
+                path.truncate(len);
+                let _ = write!(path, "[{index}]");
+                match dst.get(index).or_else(|| dst.get(0)) {
+                    Some(dst_element) => check_apply_at(dst_element, src_element, path),
+                    None => check_list_item(dst, src_element, path),
+                }
+            })
+        }
+        (ReflectRef::Array(dst), ReflectRef::Array(src)) => {
+            if dst.len() == src.len() {
+                dst.iter().zip(src.iter()).enumerate().try_for_each(
+                    |(index, (dst_element, src_element))| {
+                        path.truncate(len);
+                        let _ = write!(path, "[{index}]");
+                        check_apply_at(dst_element, src_element, path)
+                    },
+                )
+            } else {
+                Err(ApplyError::MismatchedLength {
+                    path: path.clone(),
+                    dst: dst.len(),
+                    src: src.len(),
+                })
+            }
+        }
+        (ReflectRef::Map(dst), ReflectRef::Map(src)) => {
+            src.iter()
+                .try_for_each(|(key, src_value)| match dst.get(key) {
+                    Some(dst_value) => {
+                        path.truncate(len);
+                        let _ = write!(path, "[{key:?}]");
+                        check_apply_at(dst_value, src_value, path)
+                    }
+                    None => Ok(()),
+                })
+        }
+        (ReflectRef::Enum(dst_enum), ReflectRef::Enum(src_enum)) => {
+            if dst_enum.variant_name() == src_enum.variant_name() {
+                src_enum
+                    .iter_fields()
+                    .enumerate()
+                    .try_for_each(|(index, src_field)| {
+                        let dst_field = match src_field.name() {
+                            Some(name) => dst_enum.field(name),
+                            None => dst_enum.field_at(index),
+                        };
+                        match dst_field {
+                            Some(dst_field) => {
+                                path.truncate(len);
+                                match src_field.name() {
+                                    Some(name) if path.is_empty() => path.push_str(name),
+                                    Some(name) => {
+                                        let _ = write!(path, ".{name}");
+                                    }
+                                    None => {
+                                        let _ = write!(path, ".{index}");
+                                    }
+                                }
+                                check_apply_at(dst_field, src_field.value(), path)
+                            }
+                            None => Ok(()),
+                        }
+                    })
+            } else if has_variant(dst, src_enum.variant_name()) {
+                Ok(())
+            } else {
+                Err(ApplyError::UnknownVariant {
+                    path: path.clone(),
+                    dst: dst.type_name().to_string(),
+                    variant: src_enum.variant_name().to_string(),
+                })
+            }
+        }
+        (ReflectRef::Value(_), ReflectRef::Value(_)) => {
+            if dst.as_any().type_id() == src.as_any().type_id() {
+                Ok(())
+            } else {
+                Err(ApplyError::MismatchedTypes {
+                    path: path.clone(),
+                    dst: dst.type_name().to_string(),
+                    src: src.type_name().to_string(),
+                })
+            }
+        }
+        (dst_ref, src_ref) => Err(ApplyError::MismatchedKinds {
+            path: path.clone(),
+            dst: dst.type_name().to_string(),
+            dst_kind: kind_name(&dst_ref),
+            src: src.type_name().to_string(),
+            src_kind: kind_name(&src_ref),
+        }),
+    };
+    path.truncate(len);
+    result
+}
+
+/// Check that `src` can be appended to the empty list `dst`, using the type
+/// info of its items.
+fn check_list_item(dst: &dyn List, src: &dyn Reflect, path: &str) -> Result<(), ApplyError> {
+    let TypeInfo::List(info) = dst.get_type_info() else {
+        return Ok(());
+    };
+    if src.as_any().type_id() == info.item_type_id() {
+        return Ok(());
+    }
+
+    let item_kind = TypeRegistry::try_with_current(|registry| {
+        registry
+            .get_type_info(info.item_type_id())
+            .and_then(type_info_kind)
+    })
+    .ok()
+    .flatten();
+    let src_kind = kind_name(&src.reflect_ref());
+    match item_kind {
+        Some(item_kind) if item_kind != src_kind => Err(ApplyError::MismatchedKinds {
+            path: path.to_string(),
+            dst: info.item_type_name().to_string(),
+            dst_kind: item_kind,
+            src: src.type_name().to_string(),
+            src_kind,
+        }),
+        // A value only converts to the same type.
+        _ if src_kind == "value" => Err(ApplyError::MismatchedTypes {
+            path: path.to_string(),
+            dst: info.item_type_name().to_string(),
+            src: src.type_name().to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Human-readable name of the kind of values described by `info`, or `None`
+/// for dynamic types.
+fn type_info_kind(info: &TypeInfo) -> Option<&'static str> {
+    Some(match info {
+        TypeInfo::Struct(_) => "struct",
+        TypeInfo::TupleStruct(_) => "tuple struct",
+        TypeInfo::Tuple(_) => "tuple",
+        TypeInfo::List(_) => "list",
+        TypeInfo::Array(_) => "array",
+        TypeInfo::Map(_) => "map",
+        TypeInfo::Enum(_) => "enum",
+        TypeInfo::Value(_) => "value",
+        TypeInfo::Dynamic(_) => return None,
+    })
+}
+
+/// True if `dst` is an enum with a variant named `name`, or a dynamic enum,
+/// which can take any variant.
+fn has_variant(dst: &dyn Reflect, name: &str) -> bool {
+    match dst.get_type_info() {
+        TypeInfo::Enum(info) => info.variant(name).is_some(),
+        _ => true,
+    }
+}
+
+/// Human-readable name of the kind of a reflected value.
+pub(crate) fn kind_name(reflect_ref: &ReflectRef) -> &'static str {
+    match reflect_ref {
+        ReflectRef::Struct(_) => "struct",
+        ReflectRef::TupleStruct(_) => "tuple struct",
+        ReflectRef::Tuple(_) => "tuple",
+        ReflectRef::List(_) => "list",
+        ReflectRef::Array(_) => "array",
+        ReflectRef::Map(_) => "map",
+        ReflectRef::Enum(_) => "enum",
+        ReflectRef::Value(_) => "value",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{FromReflect, Reflect};
+
+    use super::*;
+
+    #[derive(Reflect)]
+    struct Foo {
+        list: Vec<i32>,
+        array: [u8; 2],
+    }
+
+    #[derive(Reflect)]
+    struct Bar {
+        list: Vec<u32>,
+        array: [u8; 3],
+    }
+
+    #[test]
+    fn nested_paths() {
+        let dst = Foo {
+            list: vec![1],
+            array: [1, 2],
+        };
+        let src = Bar {
+            list: vec![],
+            array: [1, 2, 3],
+        };
+        assert_eq!(
+            check_apply(&dst, &src),
+            Err(ApplyError::MismatchedLength {
+                path: "array".into(),
+                dst: 2,
+                src: 3,
+            })
+        );
+
+        let src = Bar {
+            list: vec![1, 2],
+            array: [1, 2, 3],
+        };
+        assert_eq!(
+            check_apply(&dst, &src),
+            Err(ApplyError::MismatchedTypes {
+                path: "list[0]".into(),
+                dst: "i32".into(),
+                src: "u32".into(),
+            })
+        );
+    }
+
+    #[derive(Reflect)]
+    enum Shape {
+        Circle { radius: f32 },
+        Rect(u32, u32),
+    }
+
+    #[derive(Reflect)]
+    enum Other {
+        Circle { radius: f64 },
+        Line,
+    }
+
+    #[derive(Reflect)]
+    struct Drawing {
+        shape: Shape,
+        label: Option<String>,
+    }
+
+    #[test]
+    fn enums() {
+        let mut dst = Drawing {
+            shape: Shape::Circle { radius: 1.0 },
+            label: None,
+        };
+        let src = Drawing {
+            shape: Shape::Rect(2, 3),
+            label: Some("box".into()),
+        };
+        try_apply_dyn(&mut dst, &src).unwrap();
+        assert!(matches!(dst.shape, Shape::Rect(2, 3)));
+        assert_eq!(dst.label.as_deref(), Some("box"));
+
+        let src = Shape::Rect(4, 5);
+        try_apply_dyn(&mut dst.shape, &src).unwrap();
+        assert!(matches!(dst.shape, Shape::Rect(4, 5)));
+
+        let mut dst = Shape::Circle { radius: 1.0 };
+        assert_eq!(
+            check_apply(&dst, &Other::Circle { radius: 2.0 }),
+            Err(ApplyError::MismatchedTypes {
+                path: "radius".into(),
+                dst: "f32".into(),
+                src: "f64".into(),
+            })
+        );
+        assert_eq!(
+            try_apply_dyn(&mut dst, &Other::Line),
+            Err(ApplyError::UnknownVariant {
+                path: String::new(),
+                dst: std::any::type_name::<Shape>().into(),
+                variant: "Line".into(),
+            })
+        );
+    }
+
+    #[derive(Reflect, FromReflect)]
+    struct Point {
+        x: i32,
+    }
+
+    #[derive(Reflect)]
+    struct Points {
+        list: Vec<Point>,
+    }
+
+    #[derive(Reflect)]
+    struct Pairs {
+        list: Vec<(i32, i32)>,
+    }
+
+    #[test]
+    fn empty_lists() {
+        let mut dst = Points { list: vec![] };
+        try_apply_dyn(
+            &mut dst,
+            &Points {
+                list: vec![Point { x: 1 }],
+            },
+        )
+        .unwrap();
+        assert_eq!(dst.list[0].x, 1);
+
+        let dst = Foo {
+            list: vec![],
+            array: [0, 0],
+        };
+        let src = Bar {
+            list: vec![1],
+            array: [0, 0, 0],
+        };
+        assert_eq!(
+            check_apply(&dst.list, &src.list),
+            Err(ApplyError::MismatchedTypes {
+                path: "[0]".into(),
+                dst: "i32".into(),
+                src: "u32".into(),
+            })
+        );
+
+        // Without a registry, only values can be checked against the item
+        // type.
+        let dst = Points { list: vec![] };
+        assert_eq!(
+            check_apply(&dst, &src),
+            Err(ApplyError::MismatchedTypes {
+                path: "list[0]".into(),
+                dst: std::any::type_name::<Point>().into(),
+                src: "u32".into(),
+            })
+        );
+        let pairs = Pairs { list: vec![(1, 2)] };
+        assert_eq!(check_apply(&dst, &pairs), Ok(()));
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<Point>();
+        registry.set_current(|| {
+            assert_eq!(
+                check_apply(&dst, &pairs),
+                Err(ApplyError::MismatchedKinds {
+                    path: "list[0]".into(),
+                    dst: std::any::type_name::<Point>().into(),
+                    dst_kind: "struct",
+                    src: std::any::type_name::<(i32, i32)>().into(),
+                    src_kind: "tuple",
+                })
+            );
+            assert!(matches!(
+                check_apply(&dst, &src),
+                Err(ApplyError::MismatchedKinds { .. })
+            ));
+        });
+    }
+}
@@ -0,0 +1,374 @@
+//! Random values of registered types, for property tests and fuzzing of code
+//! that consumes trait objects. Requires the "arbitrary" crate feature.
+//!
+//! [`arbitrary_object()`] picks a registered implementor of a dynamic trait
+//! and builds a value of it with random contents, drawn from an
+//! [`Unstructured`] buffer of the [`arbitrary`](::arbitrary) crate. The same
+//! bytes and registry always give the same value.
+//!
+//! Values are built from the [`TypeInfo`] of their type: the fields of
+//! structs, tuples and enum variants are generated recursively, lists and
+//! maps get a few elements, and primitives and strings are drawn with
+//! [`Arbitrary`](::arbitrary::Arbitrary). Other value types get their
+//! [`ReflectDefault`] value. The fields are then built into the concrete type
+//! as in [`from_dynamic()`](crate::from_dynamic), so implementors must have
+//! `#[reflect(Default)]` or `#[reflect(FromDynamic)]`. Fields whose types are
+//! not registered keep their default values.
+//!
+//! ## Example
+//! ```rust
+//! # use reflect_steroids::{prelude::*, arbitrary::arbitrary_object, reflect::{std_traits::ReflectDefault, TypeRegistry}};
+//! # use arbitrary::Unstructured;
+//! #[reflect_trait]
+//! trait Order: DowncastReflect {
+//!     fn total(&self) -> u64;
+//! }
+//! impl_dynamic_trait!(Order, ReflectOrder);
+//!
+//! #[derive(Reflect, Default)]
+//! #[reflect(Order, Default)]
+//! struct Purchase { items: Vec<u32> }
+//! impl Order for Purchase {
+//!     fn total(&self) -> u64 { self.items.iter().map(|&price| u64::from(price)).sum() }
+//! }
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register::<Purchase>();
+//!
+//! let mut u = Unstructured::new(&[7, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+//! for _ in 0..10 {
+//!     let order: Box<dyn Order> = arbitrary_object(&mut u, &registry).unwrap();
+//!     assert!(order.total() <= 4 * u64::from(u32::MAX));
+//! }
+//! ```
+
+use std::any::TypeId;
+
+use ::arbitrary::{Error, Result, Unstructured};
+use bevy_reflect::{
+    std_traits::ReflectDefault, DynamicArray, DynamicEnum, DynamicList, DynamicMap, DynamicStruct,
+    DynamicTuple, DynamicTupleStruct, DynamicVariant, Map, Reflect, TypeInfo, TypeRegistration,
+    TypeRegistry, VariantInfo,
+};
+
+use crate::{
+    from_dynamic::build_as, type_registry::user_registrations, Cast, DynamicTrait,
+    ReflectFromDynamic,
+};
+
+/// Lists and maps deeper than this are empty, and enums deeper than this use
+/// a unit variant if they have one.
+const MAX_DEPTH: usize = 4;
+/// Values deeper than this are not generated, and keep their defaults.
+const MAX_RECURSION: usize = 32;
+/// The maximum number of elements of lists and maps.
+const MAX_LEN: usize = 4;
+
+/// Generate an object of a random implementor of `P` registered in
+/// `registry`, with random contents.
+///
+/// Implementors are candidates if they have `#[reflect(Default)]` or
+/// `#[reflect(FromDynamic)]`.
+///
+/// # Errors
+///
+/// Fails with [`Error::EmptyChoose`] if there are no candidates, and with
+/// [`Error::IncorrectFormat`] if the generated contents cannot be built into
+/// the chosen type.
+pub fn arbitrary_object<P: DynamicTrait + ?Sized>(
+    u: &mut Unstructured<'_>,
+    registry: &TypeRegistry,
+) -> Result<Box<P>> {
+    let mut candidates: Vec<&TypeRegistration> = user_registrations(registry)
+        .filter(|registration| {
+            P::get_type_data(registration).is_some()
+                && (registration.data::<ReflectDefault>().is_some()
+                    || registration.data::<ReflectFromDynamic>().is_some())
+        })
+        .collect();
+    // The registry is unordered, and the same bytes should give the same type.
+    candidates.sort_by_key(|registration| registration.type_name());
+    let registration = *u.choose(&candidates)?;
+    arbitrary_as(u, registration, registry)
+}
+
+/// Generate a value of the type of `registration`, with random contents.
+///
+/// # Errors
+///
+/// Fails with [`Error::IncorrectFormat`] if the type cannot be generated, or
+/// the generated contents cannot be built into it.
+pub fn arbitrary_value(
+    u: &mut Unstructured<'_>,
+    registration: &TypeRegistration,
+    registry: &TypeRegistry,
+) -> Result<Box<dyn Reflect>> {
+    arbitrary_as(u, registration, registry)
+}
+
+fn arbitrary_as<P: DynamicTrait + ?Sized>(
+    u: &mut Unstructured<'_>,
+    registration: &TypeRegistration,
+    registry: &TypeRegistry,
+) -> Result<Box<P>> {
+    let value = generate(u, registration, registry, 0)?.ok_or(Error::IncorrectFormat)?;
+    if value.as_any().type_id() == registration.type_id() {
+        // Primitives and defaults are generated as the concrete type.
+        return Cast::<P>::try_cast_with_registry(value, registry)
+            .map_err(|_| Error::IncorrectFormat);
+    }
+    build_as(registration, Some(&*value), registry).map_err(|_| Error::IncorrectFormat)
+}
+
+/// Generate a dynamic value for the type of `registration`, or `None` if it
+/// cannot be generated.
+// One arm per kind of type, which reads best as a single match.
+#[allow(clippy::too_many_lines)]
+fn generate(
+    u: &mut Unstructured<'_>,
+    registration: &TypeRegistration,
+    registry: &TypeRegistry,
+    depth: usize,
+) -> Result<Option<Box<dyn Reflect>>> {
+    if depth > MAX_RECURSION {
+        return Ok(None);
+    }
+    let field = |u: &mut Unstructured<'_>, type_id: TypeId| {
+        if let Some(value) = primitive(u, type_id)? {
+            return Ok(Some(value));
+        }
+        match registry.get(type_id) {
+            Some(registration) => generate(u, registration, registry, depth + 1),
+            None => Ok(None),
+        }
+    };
+    let len = |u: &mut Unstructured<'_>| {
+        if depth >= MAX_DEPTH {
+            Ok(0)
+        } else {
+            u.int_in_range(0..=MAX_LEN)
+        }
+    };
+
+    let value: Box<dyn Reflect> = match registration.type_info() {
+        TypeInfo::Struct(info) => {
+            let mut value = DynamicStruct::default();
+            value.set_name(info.type_name().to_string());
+            for named in info.iter() {
+                if let Some(field_value) = field(u, named.type_id())? {
+                    value.insert_boxed(named.name(), field_value);
+                }
+            }
+            Box::new(value)
+        }
+        TypeInfo::TupleStruct(info) => {
+            let mut value = DynamicTupleStruct::default();
+            value.set_name(info.type_name().to_string());
+            for unnamed in info.iter() {
+                match field(u, unnamed.type_id())? {
+                    Some(field_value) => value.insert_boxed(field_value),
+                    None => return Ok(None),
+                }
+            }
+            Box::new(value)
+        }
+        TypeInfo::Tuple(info) => {
+            let mut value = DynamicTuple::default();
+            value.set_name(info.type_name().to_string());
+            for unnamed in info.iter() {
+                match field(u, unnamed.type_id())? {
+                    Some(field_value) => value.insert_boxed(field_value),
+                    None => return Ok(None),
+                }
+            }
+            Box::new(value)
+        }
+        TypeInfo::List(info) => {
+            let mut value = DynamicList::default();
+            value.set_name(info.type_name().to_string());
+            for _ in 0..len(u)? {
+                match field(u, info.item_type_id())? {
+                    Some(item) => value.push_box(item),
+                    None => return Ok(None),
+                }
+            }
+            Box::new(value)
+        }
+        TypeInfo::Array(info) => {
+            let mut items = Vec::with_capacity(info.capacity());
+            for _ in 0..info.capacity() {
+                match field(u, info.item_type_id())? {
+                    Some(item) => items.push(item),
+                    None => return Ok(None),
+                }
+            }
+            let mut value = DynamicArray::new(items.into_boxed_slice());
+            value.set_name(info.type_name().to_string());
+            Box::new(value)
+        }
+        TypeInfo::Map(info) => {
+            let mut value = DynamicMap::default();
+            value.set_name(info.type_name().to_string());
+            for _ in 0..len(u)? {
+                let key = field(u, info.key_type_id())?;
+                let entry = field(u, info.value_type_id())?;
+                match (key, entry) {
+                    (Some(key), Some(entry)) => {
+                        value.insert_boxed(key, entry);
+                    }
+                    _ => return Ok(None),
+                }
+            }
+            Box::new(value)
+        }
+        TypeInfo::Enum(info) => {
+            let variants: Vec<&VariantInfo> =
+                if depth >= MAX_DEPTH && info.iter().any(|v| matches!(v, VariantInfo::Unit(_))) {
+                    info.iter()
+                        .filter(|v| matches!(v, VariantInfo::Unit(_)))
+                        .collect()
+                } else {
+                    info.iter().collect()
+                };
+            let variant = match *u.choose(&variants)? {
+                VariantInfo::Unit(variant) => (variant.name(), DynamicVariant::Unit),
+                VariantInfo::Tuple(variant) => {
+                    let mut fields = DynamicTuple::default();
+                    for unnamed in variant.iter() {
+                        match field(u, unnamed.type_id())? {
+                            Some(field_value) => fields.insert_boxed(field_value),
+                            None => return Ok(None),
+                        }
+                    }
+                    (variant.name(), DynamicVariant::Tuple(fields))
+                }
+                VariantInfo::Struct(variant) => {
+                    let mut fields = DynamicStruct::default();
+                    for named in variant.iter() {
+                        match field(u, named.type_id())? {
+                            Some(field_value) => fields.insert_boxed(named.name(), field_value),
+                            None => return Ok(None),
+                        }
+                    }
+                    (variant.name(), DynamicVariant::Struct(fields))
+                }
+            };
+            Box::new(DynamicEnum::new(info.type_name(), variant.0, variant.1))
+        }
+        TypeInfo::Value(info) => match primitive(u, info.type_id())? {
+            Some(value) => value,
+            None => match registration.data::<ReflectDefault>() {
+                Some(default) => default.default(),
+                None => return Ok(None),
+            },
+        },
+        TypeInfo::Dynamic(_) => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+/// Generate a primitive or a string, if `type_id` is the type of one.
+fn primitive(u: &mut Unstructured<'_>, type_id: TypeId) -> Result<Option<Box<dyn Reflect>>> {
+    macro_rules! primitives {
+        ($($ty:ty),*) => {
+            $(
+                if type_id == TypeId::of::<$ty>() {
+                    return Ok(Some(Box::new(u.arbitrary::<$ty>()?)));
+                }
+            )*
+        };
+    }
+    primitives!(
+        bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64,
+        String
+    );
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{reflect_trait, FromReflect};
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect};
+
+    #[reflect_trait]
+    trait Node: DowncastReflect {}
+    impl_dynamic_trait!(Node, ReflectNode);
+
+    #[derive(Reflect, FromReflect, Clone, Copy, PartialEq, Debug)]
+    enum Op {
+        Add,
+        Scale(f32),
+        Clamp { min: i8, max: i8 },
+    }
+
+    #[derive(Reflect, FromReflect)]
+    #[reflect(Node, FromDynamic)]
+    struct Graph {
+        name: String,
+        ops: Vec<Op>,
+        weights: [u8; 2],
+    }
+    impl Node for Graph {}
+
+    #[derive(Reflect, Default)]
+    #[reflect(Node, Default)]
+    struct Leaf(u16);
+    impl Node for Leaf {}
+
+    // Not a candidate: it can be neither defaulted nor built.
+    #[derive(Reflect)]
+    #[reflect(Node)]
+    struct Opaque;
+    impl Node for Opaque {}
+
+    fn registry() -> TypeRegistry {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Graph>();
+        registry.register::<Leaf>();
+        registry.register::<Opaque>();
+        registry.register::<Op>();
+        registry.register::<Vec<Op>>();
+        registry.register::<[u8; 2]>();
+        registry
+    }
+
+    #[test]
+    fn generates_implementors() {
+        let registry = registry();
+        let bytes: Vec<u8> = (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect();
+        let mut u = Unstructured::new(&bytes);
+        let (mut graphs, mut leaves, mut ops) = (0, 0, Vec::new());
+        while !u.is_empty() {
+            let node = arbitrary_object::<dyn Node>(&mut u, &registry).unwrap();
+            if let Some(graph) = node.downcast_ref::<Graph>() {
+                graphs += 1;
+                assert!(graph.ops.len() <= MAX_LEN);
+                ops.extend(graph.ops.iter().copied());
+            } else {
+                assert!(node.is::<Leaf>());
+                leaves += 1;
+            }
+        }
+        assert!(graphs > 0 && leaves > 0);
+        assert!(ops.contains(&Op::Add));
+        assert!(ops.iter().any(|op| matches!(op, Op::Clamp { .. })));
+
+        // The same bytes give the same value.
+        let generate = || {
+            let mut u = Unstructured::new(&bytes[100..]);
+            let registration = registry.get(TypeId::of::<Graph>()).unwrap();
+            let value = arbitrary_value(&mut u, registration, &registry).unwrap();
+            format!("{value:?}")
+        };
+        assert_eq!(generate(), generate());
+
+        let empty = TypeRegistry::empty();
+        let mut u = Unstructured::new(&bytes);
+        assert!(matches!(
+            arbitrary_object::<dyn Node>(&mut u, &empty),
+            Err(Error::EmptyChoose)
+        ));
+    }
+}
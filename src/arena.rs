@@ -0,0 +1,288 @@
+//! Allocating trait objects in a [`bumpalo`] arena.
+//!
+//! Short-lived trait objects, such as per-frame temporaries, can be allocated
+//! in a [`Bump`] arena instead of individually on the heap. Types opt in with
+//! `#[reflect(Arena)]`, which registers [`ReflectArena`] type data that knows
+//! how to copy or move a value of the concrete type into an arena. Objects
+//! can be copied into an arena with [`alloc_cast()`], or deserialized into one
+//! with [`deserialize_in()`].
+//!
+//! Note that destructors of values in a [`Bump`] arena are never run, so
+//! resources owned by arena-allocated objects (such as heap-allocated strings)
+//! are leaked when the arena is reset.
+//!
+//! `Box<dyn Trait, A>` with a custom allocator is not supported, as the
+//! allocator API is not stable.
+//!
+//! Requires the "bumpalo" crate feature.
+//!
+//! ## Example
+//! ```rust
+//! # use reflect_steroids::{prelude::*, arena::{alloc_cast, ReflectArena}, reflect::TypeRegistry};
+//! # use bumpalo::Bump;
+//! #[reflect_trait]
+//! trait Effect: DowncastReflect {
+//!     fn strength(&self) -> f32;
+//! }
+//! impl_dynamic_trait!(Effect, ReflectEffect);
+//!
+//! #[derive(Clone, Reflect)]
+//! #[reflect(Effect, Arena)]
+//! struct Glow { strength: f32 }
+//! impl Effect for Glow {
+//!     fn strength(&self) -> f32 { self.strength }
+//! }
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register::<Glow>();
+//!
+//! let prototype: Box<dyn Effect> = Box::new(Glow { strength: 1.0 });
+//! let frame = Bump::new();
+//! let effect: &mut dyn Effect = alloc_cast(&frame, prototype.as_reflect(), &registry).unwrap();
+//! assert_eq!(effect.strength(), 1.0);
+//! ```
+
+use bevy_reflect::{FromType, Reflect, TypeRegistry};
+use bumpalo::Bump;
+
+use crate::{CastMut, DynamicTrait, TypeError, TypeRegistryExt};
+
+/// Type data for copying values into a [`Bump`] arena. Register it with
+/// `#[reflect(Arena)]` on types that implement [`Clone`].
+#[derive(Clone)]
+pub struct ReflectArena {
+    alloc_clone: for<'a> fn(&'a Bump, &dyn Reflect) -> Option<&'a mut dyn Reflect>,
+    alloc_boxed: AllocBoxedFn,
+}
+
+type AllocBoxedFn =
+    for<'a> fn(&'a Bump, Box<dyn Reflect>) -> Result<&'a mut dyn Reflect, Box<dyn Reflect>>;
+
+impl ReflectArena {
+    /// Allocate a clone of `value` in `arena`. Returns `None` if `value` is
+    /// not of the type this type data was registered for.
+    pub fn alloc_clone<'a>(
+        &self,
+        arena: &'a Bump,
+        value: &dyn Reflect,
+    ) -> Option<&'a mut dyn Reflect> {
+        (self.alloc_clone)(arena, value)
+    }
+
+    /// Move `value` into `arena`.
+    ///
+    /// # Errors
+    ///
+    /// Gives back `value` if it is not of the type this type data was
+    /// registered for.
+    pub fn alloc_boxed<'a>(
+        &self,
+        arena: &'a Bump,
+        value: Box<dyn Reflect>,
+    ) -> Result<&'a mut dyn Reflect, Box<dyn Reflect>> {
+        (self.alloc_boxed)(arena, value)
+    }
+}
+
+impl<T: Reflect + Clone> FromType<T> for ReflectArena {
+    fn from_type() -> Self {
+        ReflectArena {
+            alloc_clone: |arena, value| {
+                let value = value.downcast_ref::<T>()?.clone();
+                let allocated: &mut dyn Reflect = arena.alloc(value);
+                Some(allocated)
+            },
+            alloc_boxed: |arena, value| {
+                let value = value.take::<T>()?;
+                let allocated: &mut dyn Reflect = arena.alloc(value);
+                Ok(allocated)
+            },
+        }
+    }
+}
+
+/// Allocate a clone of `value` in `arena`, and cast it to the dynamic trait
+/// `P`.
+///
+/// The concrete type of `value` must have [`ReflectArena`] type data in
+/// `registry`.
+///
+/// # Errors
+///
+/// Fails if the type of `value` has no [`ReflectArena`] type data, or does not
+/// implement `P`.
+///
+/// # Panics
+///
+/// Panics if the [`ReflectArena`] type data of the type was created for another
+/// type.
+pub fn alloc_cast<'a, P: DynamicTrait + ?Sized>(
+    arena: &'a Bump,
+    value: &dyn Reflect,
+    registry: &TypeRegistry,
+) -> Result<&'a mut P, TypeError> {
+    let type_data = registry
+        .get_type_data::<ReflectArena>(value.as_any().type_id())
+        .ok_or_else(|| {
+            TypeError::UnregisteredTrait(value.type_name().to_string().into(), "Arena")
+        })?;
+    let allocated = type_data
+        .alloc_clone(arena, value)
+        .expect("type data registered for a different type");
+    allocated.try_cast_mut_with_registry(registry)
+}
+
+/// Deserialize a tagged object (see
+/// [`serialization::deserialize()`](crate::serialization::deserialize())) into
+/// `arena`, and cast it to the dynamic trait `P`.
+///
+/// The object is deserialized with `registry` as the current registry. Its
+/// concrete type must have [`ReflectArena`] type data in `registry`. The
+/// object is built by its `ReflectDeserialize` type data, which allocates it
+/// on the heap, and then moved into the arena, so the heap allocation is
+/// freed before this returns, and the object only lives as long as the arena.
+///
+/// # Errors
+///
+/// Fails if the object cannot be deserialized, if its type has no
+/// [`ReflectArena`] type data, or if it does not implement `P`.
+///
+/// # Panics
+///
+/// Panics if the [`ReflectArena`] type data of the type was created for another
+/// type.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, arena::{deserialize_in, ReflectArena}, reflect::TypeRegistry};
+/// # use bumpalo::Bump;
+/// #[reflect_trait]
+/// trait Effect: DowncastReflect {
+///     fn strength(&self) -> f32;
+/// }
+/// impl_dynamic_trait!(Effect, ReflectEffect);
+///
+/// #[derive(Clone, Reflect, serde::Deserialize)]
+/// #[reflect(Effect, Arena, Deserialize)]
+/// struct Glow { strength: f32 }
+/// impl Effect for Glow {
+///     fn strength(&self) -> f32 { self.strength }
+/// }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Glow>();
+///
+/// let frame = Bump::new();
+/// let mut deserializer = serde_json::Deserializer::from_str(r#"{"type":"Glow","strength":2.0}"#);
+/// let effect: &mut dyn Effect = deserialize_in(&frame, &mut deserializer, &registry).unwrap();
+/// assert_eq!(effect.strength(), 2.0);
+/// ```
+pub fn deserialize_in<'a, 'de, P, D>(
+    arena: &'a Bump,
+    deserializer: D,
+    registry: &TypeRegistry,
+) -> Result<&'a mut P, D::Error>
+where
+    P: DynamicTrait + ?Sized,
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error as _;
+
+    let object = registry.set_current(|| crate::serialization::deserialize(deserializer))?;
+    let type_data = registry
+        .get_type_data::<ReflectArena>(object.as_any().type_id())
+        .ok_or_else(|| {
+            D::Error::custom(TypeError::UnregisteredTrait(
+                object.type_name().to_string().into(),
+                "Arena",
+            ))
+        })?;
+    let allocated = type_data
+        .alloc_boxed(arena, object)
+        .unwrap_or_else(|_| panic!("type data registered for a different type"));
+    allocated
+        .try_cast_mut_with_registry(registry)
+        .map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{reflect_trait, ReflectDeserialize};
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect};
+
+    #[reflect_trait]
+    trait Particle: DowncastReflect {
+        fn life(&self) -> u32;
+    }
+    impl_dynamic_trait!(Particle, ReflectParticle);
+
+    #[derive(Clone, Reflect, serde::Deserialize)]
+    #[reflect(Particle, Arena, Deserialize)]
+    struct Spark {
+        life: u32,
+        color: String,
+    }
+    impl Particle for Spark {
+        fn life(&self) -> u32 {
+            self.life
+        }
+    }
+
+    #[derive(Clone, Reflect, serde::Deserialize)]
+    #[reflect(Particle, Deserialize)]
+    struct Smoke;
+    impl Particle for Smoke {
+        fn life(&self) -> u32 {
+            0
+        }
+    }
+
+    fn registry() -> TypeRegistry {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Spark>();
+        registry.register::<Smoke>();
+        registry
+    }
+
+    #[test]
+    fn alloc_and_deserialize() {
+        let registry = registry();
+        let frame = Bump::new();
+
+        let spark = Spark {
+            life: 3,
+            color: "red".into(),
+        };
+        let copy: &mut dyn Particle = alloc_cast(&frame, &spark, &registry).unwrap();
+        assert_eq!(copy.life(), 3);
+        assert_eq!(copy.downcast_ref::<Spark>().unwrap().color, "red");
+
+        let mut deserializer =
+            serde_json::Deserializer::from_str(r#"{"type":"Spark","life":5,"color":"blue"}"#);
+        let particle: &mut dyn Particle =
+            deserialize_in(&frame, &mut deserializer, &registry).unwrap();
+        assert_eq!(particle.life(), 5);
+        particle.downcast_mut::<Spark>().unwrap().life = 6;
+        assert_eq!(particle.life(), 6);
+    }
+
+    #[test]
+    fn requires_arena_type_data() {
+        let registry = registry();
+        let frame = Bump::new();
+
+        let err = alloc_cast::<dyn Particle>(&frame, &Smoke, &registry).unwrap_err();
+        assert_eq!(
+            err,
+            TypeError::UnregisteredTrait(std::any::type_name::<Smoke>().into(), "Arena")
+        );
+
+        let mut deserializer = serde_json::Deserializer::from_str(r#"{"type":"Smoke"}"#);
+        let err = deserialize_in::<dyn Particle, _>(&frame, &mut deserializer, &registry)
+            .map(|_| ())
+            .unwrap_err();
+        assert!(err.to_string().contains("Arena"), "{err}");
+    }
+}
@@ -1,6 +1,17 @@
-use bevy_reflect::{Reflect, TypeRegistry};
+use std::{
+    any::TypeId,
+    borrow::Cow,
+    cell::{Ref, RefMut},
+    sync::{MutexGuard, RwLockReadGuard, RwLockWriteGuard},
+};
 
-use crate::TypeRegistryExt;
+use bevy_reflect::{Reflect, TypeRegistration, TypeRegistry};
+
+use crate::{
+    cast_policy::{check_cast, has_cast_policy},
+    supertrait::{upcast_box, upcast_mut, upcast_ref},
+    TypeRegistryExt,
+};
 
 use crate::{DowncastReflect, DynamicCaster, DynamicTrait, DynamicTraitExt, TypeError};
 
@@ -15,7 +26,7 @@ pub trait Cast<P: ?Sized>: Sized {
     /// Try casting `self` using the current global type registry (see
     /// [`TypeRegistryExt::set_current()`]).
     fn try_cast(self) -> Result<Self::Target, TypeError> {
-        TypeRegistry::with_current(|registry| self.try_cast_with_registry(registry))
+        TypeRegistry::try_with_current(|registry| self.try_cast_with_registry(registry))?
     }
 }
 
@@ -63,8 +74,104 @@ pub trait CastBox<T: ?Sized>: Sized {
     /// Try casting `self` using the current global type registry (see
     /// [`TypeRegistryExt::set_current()`]).
     fn try_cast_box<P: DynamicTrait + ?Sized>(self) -> Result<Box<P>, (Self, TypeError)> {
+        if !TypeRegistry::has_current() {
+            return Err((self, TypeError::NoCurrentRegistry));
+        }
         TypeRegistry::with_current(|registry| self.try_cast_box_with_registry(registry))
     }
+
+    /// Cast `self` with a caster resolved up front, without looking up its
+    /// type data in the registry.
+    ///
+    /// # Errors
+    ///
+    /// Gives back `self` if its type is not the type of `caster`, or if the
+    /// registry's [`CastPolicy`](crate::CastPolicy) denies the cast in the
+    /// current context.
+    fn try_cast_box_with_caster<P: DynamicTrait + ?Sized>(
+        self,
+        caster: &Caster<'_, P>,
+    ) -> Result<Box<P>, Self>;
+}
+
+/// The [`DynamicTrait::TypeData`] of one concrete type for the trait `P`,
+/// resolved once, for casting many boxes of that type (see
+/// [`CastBox::try_cast_box_with_caster()`]).
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, Caster, reflect::TypeRegistry};
+/// #[reflect_trait]
+/// trait Particle: DowncastReflect {}
+/// impl_dynamic_trait!(Particle, ReflectParticle);
+///
+/// #[derive(Reflect)]
+/// #[reflect(Particle)]
+/// struct Spark(f32);
+/// impl Particle for Spark {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Spark>();
+///
+/// let caster = Caster::<dyn Particle>::for_type::<Spark>(&registry).unwrap();
+/// let particles: Vec<Box<dyn Particle>> = (0..100)
+///     .map(|n| Box::new(Spark(n as f32)) as Box<dyn Reflect>)
+///     .map(|spark| spark.try_cast_box_with_caster(&caster).ok().unwrap())
+///     .collect();
+/// assert_eq!(particles.len(), 100);
+///
+/// let other: Box<dyn Reflect> = Box::new(1.0f32);
+/// assert!(other.try_cast_box_with_caster(&caster).is_err());
+/// ```
+pub struct Caster<'a, P: DynamicTrait + ?Sized> {
+    type_id: TypeId,
+    type_data: Cow<'a, P::TypeData>,
+    /// The registry the caster was resolved in, if it has a cast policy that
+    /// must be checked again for the context of each cast.
+    policy_registry: Option<&'a TypeRegistry>,
+}
+
+impl<'a, P: DynamicTrait + ?Sized> Caster<'a, P> {
+    /// Resolve the caster of the type `T` in `registry`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `T` is not registered, or if `P` is not registered for `T`.
+    pub fn for_type<T: 'static>(registry: &'a TypeRegistry) -> Result<Self, TypeError> {
+        Ok(Caster {
+            type_id: TypeId::of::<T>(),
+            type_data: P::get_type_data_for_type::<T>(registry)?,
+            policy_registry: has_cast_policy(registry).then_some(registry),
+        })
+    }
+
+    /// Resolve the caster of the type of `object` in `registry`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the type of `object` is not registered, or if `P` is not
+    /// registered for it.
+    pub fn for_object<T: Reflect + ?Sized>(
+        object: &T,
+        registry: &'a TypeRegistry,
+    ) -> Result<Self, TypeError> {
+        let object = object.as_reflect();
+        Ok(Caster {
+            type_id: object.as_any().type_id(),
+            type_data: P::get_type_data_for_object(object, registry)?,
+            policy_registry: has_cast_policy(registry).then_some(registry),
+        })
+    }
+
+    /// The type that the caster casts.
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// The type data of the type for `P`.
+    pub fn type_data(&self) -> &P::TypeData {
+        &self.type_data
+    }
 }
 
 /// Reference casting interface.
@@ -78,7 +185,7 @@ pub trait CastRef<'a, T: ?Sized>: Sized + 'a {
     /// Try casting `self` using the current global type registry (see
     /// [`TypeRegistryExt::set_current()`]).
     fn try_cast_ref<P: DynamicTrait + ?Sized>(self) -> Result<&'a P, TypeError> {
-        TypeRegistry::with_current(|registry| self.try_cast_ref_with_registry(registry))
+        TypeRegistry::try_with_current(|registry| self.try_cast_ref_with_registry(registry))?
     }
 }
 
@@ -93,7 +200,7 @@ pub trait CastMut<'a, T: ?Sized>: Sized + 'a {
     /// Try casting `self` using the current global type registry (see
     /// [`TypeRegistryExt::set_current()`]).
     fn try_cast_mut<P: DynamicTrait + ?Sized>(self) -> Result<&'a mut P, TypeError> {
-        TypeRegistry::with_current(|registry| self.try_cast_mut_with_registry(registry))
+        TypeRegistry::try_with_current(|registry| self.try_cast_mut_with_registry(registry))?
     }
 }
 
@@ -105,19 +212,56 @@ where
         self,
         registry: &TypeRegistry,
     ) -> Result<Box<P>, (Self, TypeError)> {
-        // try casting through a ref to avoid having to cast back on failure
-        match self.try_cast_ref_with_registry::<P>(registry) {
-            Ok(_) => (),
-            Err(err) => return Err((self, err)),
+        let err = match P::get_type_data_for_object((*self).as_reflect(), registry) {
+            Ok(metadata) => {
+                // Check through a ref to avoid having to cast back on failure.
+                if metadata
+                    .try_from_reflect_ref((*self).as_reflect())
+                    .is_none()
+                {
+                    let err = type_data_mismatch::<P>((*self).as_reflect());
+                    return Err((self, err));
+                }
+                return Ok(metadata.from_reflect(self.downcast_into_reflect()));
+            }
+            Err(err @ TypeError::CastDenied(..)) => return Err((self, err)),
+            Err(err) => err,
         };
 
-        // cast will succeed
-        let this = self.downcast_into_reflect();
-        let metadata = P::get_type_data_for_object(&*this, registry).unwrap();
-        Ok(metadata.from_reflect(this))
+        // try upcasting through a ref to avoid having to cast back on failure
+        if upcast_ref::<P>((*self).as_reflect(), registry).is_none() {
+            return Err((self, err));
+        }
+        match upcast_box(self.downcast_into_reflect(), registry) {
+            Ok(upcast) => Ok(upcast),
+            Err(_) => unreachable!("upcast through a reference succeeded"),
+        }
+    }
+
+    fn try_cast_box_with_caster<P: DynamicTrait + ?Sized>(
+        self,
+        caster: &Caster<'_, P>,
+    ) -> Result<Box<P>, Self> {
+        if (*self).as_any().type_id() != caster.type_id {
+            return Err(self);
+        }
+        if let Some(registry) = caster.policy_registry {
+            let allowed = registry.get(caster.type_id).is_some_and(|registration| {
+                check_cast(registry, P::reflect_name(), registration).is_ok()
+            });
+            if !allowed {
+                return Err(self);
+            }
+        }
+        Ok(caster.type_data.from_reflect(self.downcast_into_reflect()))
     }
 }
 
+/// The error for type data of `P` that does not apply to the type of `this`.
+fn type_data_mismatch<P: DynamicTrait + ?Sized>(this: &dyn Reflect) -> TypeError {
+    TypeError::TypeDataMismatch(this.type_name().to_string().into(), P::reflect_name())
+}
+
 impl<'a, T> CastRef<'a, T> for &'a T
 where
     T: Reflect + ?Sized,
@@ -127,8 +271,13 @@ where
         registry: &TypeRegistry,
     ) -> Result<&'a P, TypeError> {
         let this = self.as_reflect();
-        let metadata = P::get_type_data_for_object(this, registry)?;
-        Ok(metadata.from_reflect_ref(this))
+        match P::get_type_data_for_object(this, registry) {
+            Ok(metadata) => metadata
+                .try_from_reflect_ref(this)
+                .ok_or_else(|| type_data_mismatch::<P>(this)),
+            Err(err @ TypeError::CastDenied(..)) => Err(err),
+            Err(err) => upcast_ref(this, registry).ok_or(err),
+        }
     }
 }
 
@@ -141,8 +290,13 @@ where
         registry: &TypeRegistry,
     ) -> Result<&'a P, TypeError> {
         let this = self.as_reflect();
-        let metadata = P::get_type_data_for_object(this, registry)?;
-        Ok(metadata.from_reflect_ref(this))
+        match P::get_type_data_for_object(this, registry) {
+            Ok(metadata) => metadata
+                .try_from_reflect_ref(this)
+                .ok_or_else(|| type_data_mismatch::<P>(this)),
+            Err(err @ TypeError::CastDenied(..)) => Err(err),
+            Err(err) => upcast_ref(this, registry).ok_or(err),
+        }
     }
 }
 
@@ -155,7 +309,290 @@ where
         registry: &TypeRegistry,
     ) -> Result<&'a mut P, TypeError> {
         let this = self.as_reflect_mut();
-        let metadata = P::get_type_data_for_object(this, registry)?;
-        Ok(metadata.from_reflect_mut(this))
+        match P::get_type_data_for_object(this, registry) {
+            Ok(metadata) => {
+                if metadata.try_from_reflect_ref(this).is_none() {
+                    return Err(type_data_mismatch::<P>(this));
+                }
+                Ok(metadata.from_reflect_mut(this))
+            }
+            Err(err @ TypeError::CastDenied(..)) => Err(err),
+            Err(err) => upcast_mut(this, registry).ok_or(err),
+        }
+    }
+}
+
+/// Implement [`CastRef`] (and [`CastMut`], for guards that allow mutation) for
+/// references to smart pointers, casting the dereferenced value. The result
+/// borrows from the guard, so the guard must outlive it.
+macro_rules! impl_cast_for_guards {
+    ($(($guard:ident $(, $mut:ident)?)),*) => {$(
+        impl<'a, 'b, T> CastRef<'a, T> for &'a $guard<'b, T>
+        where
+            T: Reflect + ?Sized,
+        {
+            fn try_cast_ref_with_registry<P: DynamicTrait + ?Sized>(
+                self,
+                registry: &TypeRegistry,
+            ) -> Result<&'a P, TypeError> {
+                (**self).try_cast_ref_with_registry(registry)
+            }
+        }
+
+        impl_cast_for_guards!(@ref_mut $guard);
+        $(impl_cast_for_guards!(@$mut $guard);)?
+    )*};
+    (@ref_mut $guard:ident) => {
+        impl<'a, 'b, T> CastRef<'a, T> for &'a mut $guard<'b, T>
+        where
+            T: Reflect + ?Sized,
+        {
+            fn try_cast_ref_with_registry<P: DynamicTrait + ?Sized>(
+                self,
+                registry: &TypeRegistry,
+            ) -> Result<&'a P, TypeError> {
+                (**self).try_cast_ref_with_registry(registry)
+            }
+        }
+    };
+    (@mut $guard:ident) => {
+        impl<'a, 'b, T> CastMut<'a, T> for &'a mut $guard<'b, T>
+        where
+            T: Reflect + ?Sized,
+        {
+            fn try_cast_mut_with_registry<P: DynamicTrait + ?Sized>(
+                self,
+                registry: &TypeRegistry,
+            ) -> Result<&'a mut P, TypeError> {
+                (**self).try_cast_mut_with_registry(registry)
+            }
+        }
+    };
+}
+
+impl_cast_for_guards!(
+    (Ref),
+    (RefMut, mut),
+    (MutexGuard, mut),
+    (RwLockReadGuard),
+    (RwLockWriteGuard, mut)
+);
+
+impl<'a, T> CastRef<'a, T> for &'a Cow<'_, T>
+where
+    T: Reflect + ToOwned + ?Sized,
+{
+    fn try_cast_ref_with_registry<P: DynamicTrait + ?Sized>(
+        self,
+        registry: &TypeRegistry,
+    ) -> Result<&'a P, TypeError> {
+        (**self).try_cast_ref_with_registry(registry)
+    }
+}
+
+/// A reference to an object that can be cast to several dynamic traits, with
+/// a single lookup of its type in the registry.
+///
+/// This is faster than casting the object to each trait separately, when
+/// probing an object for many traits in a hot loop. See also
+/// [`multi_cast!`](crate::multi_cast).
+pub struct CastView<'a, 'r> {
+    object: &'a dyn Reflect,
+    registration: Option<&'r TypeRegistration>,
+    registry: &'r TypeRegistry,
+}
+
+impl<'a, 'r> CastView<'a, 'r> {
+    /// Look up the type of `object` in `registry`.
+    pub fn new<T: Reflect + ?Sized>(object: &'a T, registry: &'r TypeRegistry) -> Self {
+        let object = object.as_reflect();
+        CastView {
+            object,
+            registration: registry.get(object.as_any().type_id()),
+            registry,
+        }
+    }
+
+    /// Cast the object to `P`, or return `None` if the type of the object is
+    /// not registered, does not implement `P`, or may not be cast to `P` (see
+    /// [`CastPolicy`](crate::CastPolicy)).
+    #[must_use]
+    pub fn get<P: DynamicTrait + ?Sized>(&self) -> Option<&'a P> {
+        let registration = self.registration?;
+        check_cast(self.registry, P::reflect_name(), registration).ok()?;
+        match P::get_type_data(registration) {
+            Some(metadata) => metadata.try_from_reflect_ref(self.object),
+            None => upcast_ref(self.object, self.registry),
+        }
+    }
+}
+
+/// Cast a reference to several dynamic traits at once, returning a tuple of
+/// `Option<&dyn Trait>`. The type of the object is looked up in the registry
+/// only once (see [`CastView`]).
+///
+/// `multi_cast!(object, &registry => (dyn A, dyn B))` uses the given registry.
+/// `multi_cast!(object => (dyn A, dyn B))` uses the current registry (see
+/// [`TypeRegistryExt::set_current()`]), and returns a `Result`, which is an
+/// error if there is no current registry.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, multi_cast, reflect::TypeRegistry};
+/// #[reflect_trait]
+/// trait Damageable: DowncastReflect {}
+/// #[reflect_trait]
+/// trait Burnable: DowncastReflect {}
+/// #[reflect_trait]
+/// trait Movable: DowncastReflect {}
+/// impl_dynamic_trait!(Damageable, ReflectDamageable);
+/// impl_dynamic_trait!(Burnable, ReflectBurnable);
+/// impl_dynamic_trait!(Movable, ReflectMovable);
+///
+/// #[derive(Reflect)]
+/// #[reflect(Damageable, Burnable)]
+/// struct Crate;
+/// impl Damageable for Crate {}
+/// impl Burnable for Crate {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Crate>();
+///
+/// let object: &dyn Reflect = &Crate;
+/// let (damageable, burnable, movable) =
+///     multi_cast!(object, &registry => (dyn Damageable, dyn Burnable, dyn Movable));
+/// assert!(damageable.is_some());
+/// assert!(burnable.is_some());
+/// assert!(movable.is_none());
+///
+/// let (damageable,) = registry
+///     .set_current(|| multi_cast!(object => (dyn Damageable)))
+///     .unwrap();
+/// assert!(damageable.unwrap().is::<Crate>());
+/// ```
+#[macro_export]
+macro_rules! multi_cast {
+    ($object:expr, $registry:expr => ($($trait_ty:ty),+ $(,)?)) => {{
+        let view = $crate::CastView::new($object, $registry);
+        ($(view.get::<$trait_ty>(),)+)
+    }};
+    ($object:expr => ($($trait_ty:ty),+ $(,)?)) => {
+        <$crate::reflect::TypeRegistry as $crate::TypeRegistryExt>::try_with_current(|registry| {
+            $crate::multi_cast!($object, registry => ($($trait_ty),+))
+        })
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        borrow::Cow,
+        cell::RefCell,
+        sync::{Mutex, RwLock},
+    };
+
+    use bevy_reflect::{reflect_trait, TypeRegistry};
+
+    use super::*;
+    use crate::impl_dynamic_trait;
+
+    #[reflect_trait]
+    trait Named: DowncastReflect {
+        fn name(&self) -> &str;
+        fn rename(&mut self, name: &str);
+    }
+    impl_dynamic_trait!(Named, ReflectNamed);
+
+    #[derive(Clone, Reflect)]
+    #[reflect(Named)]
+    struct Foo {
+        name: String,
+    }
+    impl Named for Foo {
+        fn name(&self) -> &str {
+            &self.name
+        }
+        fn rename(&mut self, name: &str) {
+            self.name = name.to_string();
+        }
+    }
+
+    fn foo() -> Foo {
+        Foo { name: "foo".into() }
+    }
+
+    #[test]
+    fn cast_guards() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Foo>();
+        let name = |named: &dyn Named| named.name().to_string();
+
+        let cell = RefCell::new(foo());
+        let named = cell.borrow();
+        assert_eq!(
+            name((&named).try_cast_ref_with_registry(&registry).unwrap()),
+            "foo"
+        );
+        drop(named);
+        let mut named = cell.borrow_mut();
+        CastMut::<Foo>::try_cast_mut_with_registry::<dyn Named>(&mut named, &registry)
+            .unwrap()
+            .rename("bar");
+        drop(named);
+        assert_eq!(cell.borrow().name, "bar");
+
+        let mutex = Mutex::new(foo());
+        let mut named = mutex.lock().unwrap();
+        CastMut::<Foo>::try_cast_mut_with_registry::<dyn Named>(&mut named, &registry)
+            .unwrap()
+            .rename("baz");
+        assert_eq!(
+            name((&named).try_cast_ref_with_registry(&registry).unwrap()),
+            "baz"
+        );
+        drop(named);
+
+        let lock = RwLock::new(foo());
+        assert_eq!(
+            name(
+                (&lock.read().unwrap())
+                    .try_cast_ref_with_registry(&registry)
+                    .unwrap()
+            ),
+            "foo"
+        );
+
+        let cow: Cow<Foo> = Cow::Owned(foo());
+        assert_eq!(
+            name((&cow).try_cast_ref_with_registry(&registry).unwrap()),
+            "foo"
+        );
+    }
+
+    #[test]
+    fn no_current_registry() {
+        let foo = foo();
+        assert_eq!(
+            (&foo).try_cast_ref::<dyn Named>().map(|_| ()),
+            Err(TypeError::NoCurrentRegistry)
+        );
+        let boxed: Box<dyn Reflect> = Box::new(foo);
+        let (_, err) = boxed.try_cast_box::<dyn Named>().map(|_| ()).unwrap_err();
+        assert_eq!(err, TypeError::NoCurrentRegistry);
+    }
+
+    #[test]
+    fn multi_cast_unregistered() {
+        let foo = foo();
+        let mut registry = TypeRegistry::default();
+        let (named, reflect) = crate::multi_cast!(&foo, &registry => (dyn Named, dyn Reflect));
+        assert!(named.is_none());
+        assert!(reflect.is_none());
+        assert!(crate::multi_cast!(&foo => (dyn Named)).is_err());
+
+        registry.register::<Foo>();
+        let (named, reflect) = crate::multi_cast!(&foo, &registry => (dyn Named, dyn Reflect));
+        assert_eq!(named.unwrap().name(), "foo");
+        assert!(reflect.unwrap().is::<Foo>());
     }
 }
@@ -0,0 +1,210 @@
+use std::{any::Any, sync::Arc};
+
+use bevy_reflect::{TypeRegistration, TypeRegistry};
+
+use crate::{type_registry::registry_data, TypeError};
+
+scoped_tls::scoped_thread_local!(static CAST_CONTEXT: Box<dyn Any>);
+
+/// Type data holding the policy installed with
+/// [`TypeRegistryExt::set_cast_policy()`](crate::TypeRegistryExt::set_cast_policy),
+/// which decides whether objects may be cast to a dynamic trait.
+///
+/// The callback receives the name of the trait (see
+/// [`DynamicTrait::reflect_name()`](crate::DynamicTrait::reflect_name)), the
+/// registration of the object's type, and the context token set with
+/// [`with_cast_context()`], if any. Casts that it rejects fail with
+/// [`TypeError::CastDenied`].
+///
+/// The policy applies to all casts that look up type data in the registry,
+/// including upcasts through subtraits. A [`Caster`](crate::Caster) is checked
+/// both when it is resolved and each time it is used, since the context may
+/// differ between the two.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, with_cast_context, Cast, TypeError};
+/// #[reflect_trait]
+/// trait Door: DowncastReflect {}
+/// impl_dynamic_trait!(Door, ReflectDoor);
+///
+/// #[reflect_trait]
+/// trait AdminOnly: DowncastReflect {}
+/// impl_dynamic_trait!(AdminOnly, ReflectAdminOnly);
+///
+/// #[derive(Reflect)]
+/// #[reflect(Door, AdminOnly)]
+/// struct VaultDoor;
+/// impl Door for VaultDoor {}
+/// impl AdminOnly for VaultDoor {}
+///
+/// #[derive(PartialEq)]
+/// enum Caller { Script, Admin }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<VaultDoor>();
+/// registry.set_cast_policy(|trait_name, _, context| {
+///     trait_name != "AdminOnly" || context.and_then(|c| c.downcast_ref()) == Some(&Caller::Admin)
+/// });
+///
+/// let door: &dyn Reflect = &VaultDoor;
+/// assert!(Cast::<dyn Door>::try_cast_with_registry(door, &registry).is_ok());
+///
+/// let err = with_cast_context(Caller::Script, || {
+///     Cast::<dyn AdminOnly>::try_cast_with_registry(door, &registry).map(|_| ())
+/// });
+/// assert!(matches!(err, Err(TypeError::CastDenied(..))));
+///
+/// let ok = with_cast_context(Caller::Admin, || {
+///     Cast::<dyn AdminOnly>::try_cast_with_registry(door, &registry).map(|_| ())
+/// });
+/// assert!(ok.is_ok());
+/// ```
+#[derive(Clone)]
+pub struct CastPolicy(Arc<PolicyFn>);
+
+type PolicyFn = dyn Fn(&str, &TypeRegistration, Option<&dyn Any>) -> bool + Send + Sync;
+
+impl CastPolicy {
+    /// Wrap a callback.
+    pub fn new<F>(policy: F) -> Self
+    where
+        F: Fn(&str, &TypeRegistration, Option<&dyn Any>) -> bool + Send + Sync + 'static,
+    {
+        CastPolicy(Arc::new(policy))
+    }
+
+    /// True if objects of the type of `registration` may be cast to the trait
+    /// named `trait_name` by a caller with `context`.
+    #[must_use]
+    pub fn allows(
+        &self,
+        trait_name: &str,
+        registration: &TypeRegistration,
+        context: Option<&dyn Any>,
+    ) -> bool {
+        (self.0)(trait_name, registration, context)
+    }
+}
+
+impl std::fmt::Debug for CastPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("CastPolicy")
+    }
+}
+
+/// Run `f` with `context` as the token passed to the registry's
+/// [`CastPolicy`], identifying the caller of the casts in `f`.
+pub fn with_cast_context<C: Any, F: FnOnce() -> R, R>(context: C, f: F) -> R {
+    CAST_CONTEXT.set(&(Box::new(context) as Box<dyn Any>), f)
+}
+
+/// True if the registry has a cast policy.
+pub(crate) fn has_cast_policy(registry: &TypeRegistry) -> bool {
+    registry_data::<CastPolicy>(registry).is_some()
+}
+
+/// Check the registry's cast policy, if any, for casting an object of the type
+/// of `registration` to the trait named `trait_name`.
+pub(crate) fn check_cast(
+    registry: &TypeRegistry,
+    trait_name: &'static str,
+    registration: &TypeRegistration,
+) -> Result<(), TypeError> {
+    let Some(policy) = registry_data::<CastPolicy>(registry) else {
+        return Ok(());
+    };
+    let allowed = if CAST_CONTEXT.is_set() {
+        CAST_CONTEXT.with(|context| policy.allows(trait_name, registration, Some(&**context)))
+    } else {
+        policy.allows(trait_name, registration, None)
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(TypeError::CastDenied(
+            registration.type_name().into(),
+            trait_name,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{reflect_trait, Reflect};
+
+    use super::*;
+    use crate::{
+        impl_dynamic_trait, impl_supertrait, Cast, CastBox, Caster, DowncastReflect,
+        TypeRegistryExt,
+    };
+
+    #[reflect_trait]
+    trait Readable: DowncastReflect {}
+    impl_dynamic_trait!(Readable, ReflectReadable);
+
+    #[reflect_trait]
+    trait Writable: Readable {}
+    impl_dynamic_trait!(Writable, ReflectWritable);
+    impl_supertrait!(Writable: Readable);
+
+    #[derive(Reflect)]
+    #[reflect(Writable)]
+    struct File;
+    impl Readable for File {}
+    impl Writable for File {}
+
+    struct Sandboxed;
+
+    #[test]
+    fn deny_in_context() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<File>();
+        registry.register_supertrait::<dyn Writable, dyn Readable>();
+        registry.set_cast_policy(|trait_name, registration, context| {
+            assert_eq!(registration.short_name(), "File");
+            !(trait_name == "Writable" && context.is_some_and(<dyn Any>::is::<Sandboxed>))
+        });
+
+        let mut file = File;
+        assert!(Cast::<dyn Writable>::try_cast_with_registry(&mut file, &registry).is_ok());
+
+        with_cast_context(Sandboxed, || {
+            let denied = TypeError::CastDenied(std::any::type_name::<File>().into(), "Writable");
+            assert_eq!(
+                Cast::<dyn Writable>::try_cast_with_registry(&file, &registry).err(),
+                Some(denied.clone())
+            );
+            assert_eq!(
+                Cast::<dyn Writable>::try_cast_with_registry(&mut file, &registry).err(),
+                Some(denied.clone())
+            );
+            let boxed: Box<dyn Reflect> = Box::new(File);
+            let (_, err) = boxed
+                .try_cast_box_with_registry::<dyn Writable>(&registry)
+                .err()
+                .unwrap();
+            assert_eq!(err, denied);
+
+            // Upcasting through the denied subtrait is denied as well.
+            assert!(Cast::<dyn Readable>::try_cast_with_registry(&file, &registry).is_err());
+        });
+    }
+
+    #[test]
+    fn check_caster_on_use() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<File>();
+        registry.set_cast_policy(|_, _, context| context.is_none_or(|c| !c.is::<Sandboxed>()));
+
+        // A caster resolved outside of the denied context is checked again
+        // when it is used in it.
+        let caster = Caster::<dyn Writable>::for_type::<File>(&registry).unwrap();
+        with_cast_context(Sandboxed, || {
+            let boxed: Box<dyn Reflect> = Box::new(File);
+            assert!(boxed.try_cast_box_with_caster(&caster).is_err());
+        });
+        let boxed: Box<dyn Reflect> = Box::new(File);
+        assert!(boxed.try_cast_box_with_caster(&caster).is_ok());
+    }
+}
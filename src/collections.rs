@@ -0,0 +1,598 @@
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    hash::Hash,
+};
+
+use bevy_reflect::Reflect;
+use serde::{ser::SerializeMap, Deserialize, Serialize};
+
+use crate::{serialization, DynamicTrait};
+
+struct Entry<P: ?Sized> {
+    /// The `reflect_hash()` of the object, if its type supports hashing.
+    hash: Option<u64>,
+    object: Box<P>,
+}
+
+/// A set of trait objects, such as `DynSet<dyn MyTrait>`, that holds at most
+/// one of each value.
+///
+/// Two objects are the same value if they are of the same concrete type, and
+/// [`Reflect::reflect_partial_eq()`] returns `Some(true)` for them. Objects of
+/// types that support [`Reflect::reflect_hash()`] are only compared to objects
+/// with the same hash. Objects whose types do not support comparison are
+/// never the same value.
+///
+/// Objects are kept grouped by their concrete type, and iteration visits the
+/// groups in the order of the type names.
+///
+/// `Serialize` and `Deserialize` use tagged serialization (see
+/// [`serialization::serialize()`](crate::serialization::serialize())), and
+/// require a current registry (see
+/// [`TypeRegistryExt::set_current()`](crate::TypeRegistryExt::set_current)).
+/// The set is serialized as a sequence, and repeated values are dropped when
+/// it is deserialized.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, DynSet};
+/// #[reflect_trait]
+/// trait Tag: DowncastReflect {}
+/// impl_dynamic_trait!(Tag, ReflectTag);
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Tag, Serialize, Deserialize)]
+/// struct Label(String);
+/// impl Tag for Label {}
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Tag, Serialize, Deserialize)]
+/// struct Hidden;
+/// impl Tag for Hidden {}
+///
+/// let mut tags: DynSet<dyn Tag> = DynSet::new();
+/// assert!(tags.insert(Box::new(Label("red".into()))));
+/// assert!(tags.insert(Box::new(Hidden)));
+/// assert!(!tags.insert(Box::new(Label("red".into()))));
+/// assert_eq!(tags.len(), 2);
+/// assert!(tags.contains(&Hidden));
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Label>();
+/// registry.register::<Hidden>();
+/// registry.set_current(|| {
+///     let json = serde_json::to_string(&tags).unwrap();
+///     let tags: DynSet<dyn Tag> = serde_json::from_str(&json).unwrap();
+///     assert!(tags.contains(&Label("red".into())));
+/// });
+/// ```
+pub struct DynSet<P: DynamicTrait + ?Sized> {
+    groups: BTreeMap<String, Vec<Entry<P>>>,
+    len: usize,
+}
+
+impl<P: DynamicTrait + ?Sized> DynSet<P> {
+    /// Create an empty set.
+    #[must_use]
+    pub fn new() -> Self {
+        DynSet {
+            groups: BTreeMap::new(),
+            len: 0,
+        }
+    }
+
+    /// The number of objects.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// True if there are no objects.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Insert an object. Returns false, and drops `object`, if the set
+    /// already holds the same value.
+    pub fn insert(&mut self, object: Box<P>) -> bool {
+        if self.contains(object.as_reflect()) {
+            return false;
+        }
+        let entry = Entry {
+            hash: object.reflect_hash(),
+            object,
+        };
+        self.groups
+            .entry(entry.object.type_name().to_string())
+            .or_default()
+            .push(entry);
+        self.len += 1;
+        true
+    }
+
+    /// True if the set holds the same value as `value`.
+    pub fn contains(&self, value: &dyn Reflect) -> bool {
+        self.get(value).is_some()
+    }
+
+    /// Get the object holding the same value as `value`.
+    pub fn get(&self, value: &dyn Reflect) -> Option<&P> {
+        let group = self.groups.get(value.type_name())?;
+        let index = position(group, value)?;
+        Some(&*group[index].object)
+    }
+
+    /// Remove the object holding the same value as `value`, and return it.
+    pub fn remove(&mut self, value: &dyn Reflect) -> Option<Box<P>> {
+        let group = self.groups.get_mut(value.type_name())?;
+        let index = position(group, value)?;
+        let entry = group.remove(index);
+        if group.is_empty() {
+            self.groups.remove(value.type_name());
+        }
+        self.len -= 1;
+        Some(entry.object)
+    }
+
+    /// Remove all objects.
+    pub fn clear(&mut self) {
+        self.groups.clear();
+        self.len = 0;
+    }
+
+    /// Iterate over the objects, grouped by concrete type in the order of the
+    /// type names, and in insertion order within each group.
+    pub fn iter(&self) -> impl Iterator<Item = &P> {
+        self.groups
+            .values()
+            .flat_map(|group| group.iter().map(|entry| &*entry.object))
+    }
+
+    /// Iterate over the objects of the concrete type `C`.
+    pub fn iter_of_type<C: Reflect>(&self) -> impl Iterator<Item = &C> {
+        let name = std::any::type_name::<C>();
+        self.groups
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.object.as_reflect().downcast_ref::<C>())
+    }
+
+    /// Iterate over the type names of the concrete types of the objects, and
+    /// the objects of each type.
+    pub fn groups(&self) -> impl Iterator<Item = (&str, impl Iterator<Item = &P>)> {
+        self.groups.iter().map(|(name, group)| {
+            let objects = group.iter().map(|entry| &*entry.object);
+            (name.as_str(), objects)
+        })
+    }
+}
+
+/// The position of the entry holding the same value as `value` in a group of
+/// objects of its type.
+fn position<P: DynamicTrait + ?Sized>(group: &[Entry<P>], value: &dyn Reflect) -> Option<usize> {
+    let hash = value.reflect_hash();
+    group.iter().position(|entry| {
+        let same_hash = match (entry.hash, hash) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        };
+        same_hash && entry.object.as_reflect().reflect_partial_eq(value) == Some(true)
+    })
+}
+
+impl<P: DynamicTrait + ?Sized> Default for DynSet<P> {
+    fn default() -> Self {
+        DynSet::new()
+    }
+}
+
+impl<P: DynamicTrait + ?Sized> Extend<Box<P>> for DynSet<P> {
+    fn extend<I: IntoIterator<Item = Box<P>>>(&mut self, iter: I) {
+        for object in iter {
+            self.insert(object);
+        }
+    }
+}
+
+impl<P: DynamicTrait + ?Sized> FromIterator<Box<P>> for DynSet<P> {
+    fn from_iter<I: IntoIterator<Item = Box<P>>>(iter: I) -> Self {
+        let mut set = DynSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<P: DynamicTrait + ?Sized> fmt::Debug for DynSet<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries(self.iter().map(Reflect::as_reflect))
+            .finish()
+    }
+}
+
+impl<P: DynamicTrait + ?Sized> Serialize for DynSet<P> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serialization::serialize_seq_of_dyn(self.iter().map(Reflect::as_reflect), serializer)
+    }
+}
+
+impl<'de, P: DynamicTrait + ?Sized> Deserialize<'de> for DynSet<P> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let objects = Vec::<Object<P>>::deserialize(deserializer)?;
+        Ok(objects.into_iter().map(|object| object.0).collect())
+    }
+}
+
+/// A map from keys to trait objects, such as `DynMap<String, dyn MyTrait>`.
+///
+/// This is a `HashMap<K, Box<P>>`, which can additionally visit its objects
+/// grouped by their concrete type.
+///
+/// `Serialize` and `Deserialize` use tagged serialization for the objects
+/// (see [`serialization::serialize()`](crate::serialization::serialize())),
+/// and require a current registry (see
+/// [`TypeRegistryExt::set_current()`](crate::TypeRegistryExt::set_current)).
+/// The keys are (de)serialized with their own `Serialize` and `Deserialize`
+/// implementations.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, DynMap};
+/// #[reflect_trait]
+/// trait Widget: DowncastReflect {}
+/// impl_dynamic_trait!(Widget, ReflectWidget);
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Widget, Serialize, Deserialize)]
+/// struct Button { label: String }
+/// impl Widget for Button {}
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Widget, Serialize, Deserialize)]
+/// struct Slider { value: f32 }
+/// impl Widget for Slider {}
+///
+/// let mut widgets: DynMap<String, dyn Widget> = DynMap::new();
+/// widgets.insert("ok".into(), Box::new(Button { label: "OK".into() }));
+/// widgets.insert("volume".into(), Box::new(Slider { value: 0.5 }));
+/// widgets.insert("cancel".into(), Box::new(Button { label: "Cancel".into() }));
+///
+/// let mut buttons: Vec<&str> = widgets.iter_of_type::<Button>().map(|(key, _)| key.as_str()).collect();
+/// buttons.sort();
+/// assert_eq!(buttons, ["cancel", "ok"]);
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Button>();
+/// registry.register::<Slider>();
+/// registry.set_current(|| {
+///     let json = serde_json::to_string(&widgets).unwrap();
+///     let widgets: DynMap<String, dyn Widget> = serde_json::from_str(&json).unwrap();
+///     assert!(widgets["volume"].is::<Slider>());
+/// });
+/// ```
+pub struct DynMap<K, P: DynamicTrait + ?Sized> {
+    objects: HashMap<K, Box<P>>,
+}
+
+impl<K: Eq + Hash, P: DynamicTrait + ?Sized> DynMap<K, P> {
+    /// Create an empty map.
+    #[must_use]
+    pub fn new() -> Self {
+        DynMap {
+            objects: HashMap::new(),
+        }
+    }
+
+    /// The number of objects.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// True if there are no objects.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Insert an object for `key`. Returns the object that was previously
+    /// inserted for `key`, if any.
+    pub fn insert(&mut self, key: K, object: Box<P>) -> Option<Box<P>> {
+        self.objects.insert(key, object)
+    }
+
+    /// True if there is an object for `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.objects.contains_key(key)
+    }
+
+    /// Get the object for `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&P>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.objects.get(key).map(|object| &**object)
+    }
+
+    /// Get the object for `key` by mutable reference.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut P>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.objects.get_mut(key).map(|object| &mut **object)
+    }
+
+    /// Remove the object for `key`, and return it.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Box<P>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.objects.remove(key)
+    }
+
+    /// Remove all objects.
+    pub fn clear(&mut self) {
+        self.objects.clear();
+    }
+
+    /// Iterate over the keys, in no particular order.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.objects.keys()
+    }
+
+    /// Iterate over the keys and objects, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &P)> {
+        self.objects.iter().map(|(key, object)| (key, &**object))
+    }
+
+    /// Iterate over the keys and objects by mutable reference, in no
+    /// particular order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut P)> {
+        self.objects
+            .iter_mut()
+            .map(|(key, object)| (key, &mut **object))
+    }
+
+    /// Iterate over the keys and objects of the concrete type `C`.
+    pub fn iter_of_type<C: Reflect>(&self) -> impl Iterator<Item = (&K, &C)> {
+        self.iter()
+            .filter_map(|(key, object)| Some((key, object.as_reflect().downcast_ref::<C>()?)))
+    }
+
+    /// Group the keys and objects by the type name of their concrete type.
+    #[must_use]
+    pub fn group_by_type(&self) -> BTreeMap<&str, Vec<(&K, &P)>> {
+        let mut groups: BTreeMap<&str, Vec<(&K, &P)>> = BTreeMap::new();
+        for (key, object) in self.iter() {
+            groups
+                .entry(object.type_name())
+                .or_default()
+                .push((key, object));
+        }
+        groups
+    }
+}
+
+impl<K: Eq + Hash, P: DynamicTrait + ?Sized> Default for DynMap<K, P> {
+    fn default() -> Self {
+        DynMap::new()
+    }
+}
+
+impl<K, Q, P> std::ops::Index<&Q> for DynMap<K, P>
+where
+    K: Eq + Hash + Borrow<Q>,
+    Q: Eq + Hash + ?Sized,
+    P: DynamicTrait + ?Sized,
+{
+    type Output = P;
+
+    fn index(&self, key: &Q) -> &P {
+        self.get(key).expect("no object for key in `DynMap`")
+    }
+}
+
+impl<K: Eq + Hash, P: DynamicTrait + ?Sized> Extend<(K, Box<P>)> for DynMap<K, P> {
+    fn extend<I: IntoIterator<Item = (K, Box<P>)>>(&mut self, iter: I) {
+        self.objects.extend(iter);
+    }
+}
+
+impl<K: Eq + Hash, P: DynamicTrait + ?Sized> FromIterator<(K, Box<P>)> for DynMap<K, P> {
+    fn from_iter<I: IntoIterator<Item = (K, Box<P>)>>(iter: I) -> Self {
+        DynMap {
+            objects: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<K: fmt::Debug, P: DynamicTrait + ?Sized> fmt::Debug for DynMap<K, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                self.objects
+                    .iter()
+                    .map(|(key, object)| (key, object.as_reflect())),
+            )
+            .finish()
+    }
+}
+
+impl<K: Serialize, P: DynamicTrait + ?Sized> Serialize for DynMap<K, P> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.objects.len()))?;
+        for (key, object) in &self.objects {
+            map.serialize_entry(key, &ObjectRef(&**object))?;
+        }
+        map.end()
+    }
+}
+
+impl<'de, K, P> Deserialize<'de> for DynMap<K, P>
+where
+    K: Eq + Hash + Deserialize<'de>,
+    P: DynamicTrait + ?Sized,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let objects = HashMap::<K, Object<P>>::deserialize(deserializer)?;
+        Ok(objects
+            .into_iter()
+            .map(|(key, object)| (key, object.0))
+            .collect())
+    }
+}
+
+/// Adapts a trait object to `Serialize`, serializing it with a type tag.
+struct ObjectRef<'a, P: ?Sized>(&'a P);
+
+impl<P: DynamicTrait + ?Sized> Serialize for ObjectRef<'_, P> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serialization::serialize(self.0.as_reflect(), serializer)
+    }
+}
+
+/// Adapts a boxed trait object to `Deserialize`, deserializing it from a
+/// tagged object.
+struct Object<P: ?Sized>(Box<P>);
+
+impl<'de, P: DynamicTrait + ?Sized> Deserialize<'de> for Object<P> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        serialization::deserialize_trait_object(deserializer).map(Object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{reflect_trait, ReflectDeserialize, ReflectSerialize, TypeRegistry};
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect, TypeRegistryExt};
+
+    #[reflect_trait]
+    trait Effect: DowncastReflect {}
+    impl_dynamic_trait!(Effect, ReflectEffect);
+
+    #[derive(Reflect, Hash, PartialEq, serde::Serialize, serde::Deserialize)]
+    #[reflect(Effect, Hash, PartialEq, Serialize, Deserialize)]
+    struct Burn(u32);
+    impl Effect for Burn {}
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Effect, Serialize, Deserialize)]
+    struct Slow {
+        factor: f32,
+    }
+    impl Effect for Slow {}
+
+    #[test]
+    fn set_dedup_and_groups() {
+        let mut effects: DynSet<dyn Effect> = DynSet::new();
+        assert!(effects.insert(Box::new(Slow { factor: 0.5 })));
+        assert!(effects.insert(Box::new(Burn(1))));
+        assert!(effects.insert(Box::new(Burn(2))));
+        assert!(!effects.insert(Box::new(Burn(1))));
+        // Compared field by field, without a hash.
+        assert!(!effects.insert(Box::new(Slow { factor: 0.5 })));
+        assert!(effects.insert(Box::new(Slow { factor: 0.25 })));
+        assert_eq!(effects.len(), 4);
+
+        let groups: Vec<(&str, usize)> = effects
+            .groups()
+            .map(|(name, objects)| (name, objects.count()))
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                (std::any::type_name::<Burn>(), 2),
+                (std::any::type_name::<Slow>(), 2)
+            ]
+        );
+        let burns: Vec<u32> = effects.iter_of_type::<Burn>().map(|b| b.0).collect();
+        assert_eq!(burns, [1, 2]);
+
+        assert!(effects.remove(&Burn(1)).unwrap().is::<Burn>());
+        assert!(effects.remove(&Burn(1)).is_none());
+        assert!(!effects.contains(&1u32));
+        assert_eq!(effects.len(), 3);
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<Burn>();
+        registry.register::<Slow>();
+        let json =
+            r#"[{"type":"Burn","value":2},{"type":"Burn","value":2},{"type":"Slow","factor":1.0}]"#;
+        let effects: DynSet<dyn Effect> =
+            registry.set_current(|| serde_json::from_str(json).unwrap());
+        assert_eq!(effects.len(), 2);
+        let json = registry.set_current(|| serde_json::to_string(&effects).unwrap());
+        assert_eq!(
+            json,
+            r#"[{"type":"Burn","value":2},{"type":"Slow","factor":1.0}]"#
+        );
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn map_groups_and_serialize() {
+        let mut effects: DynMap<u32, dyn Effect> = DynMap::new();
+        effects.insert(1, Box::new(Burn(1)));
+        effects.insert(2, Box::new(Slow { factor: 0.5 }));
+        assert!(effects.insert(3, Box::new(Burn(3))).is_none());
+        assert!(effects.insert(3, Box::new(Burn(4))).unwrap().is::<Burn>());
+
+        let groups = effects.group_by_type();
+        let mut burns: Vec<u32> = groups[std::any::type_name::<Burn>()]
+            .iter()
+            .map(|(key, _)| **key)
+            .collect();
+        burns.sort_unstable();
+        assert_eq!(burns, [1, 3]);
+        assert_eq!(groups[std::any::type_name::<Slow>()].len(), 1);
+
+        effects
+            .get_mut(&2)
+            .unwrap()
+            .downcast_mut::<Slow>()
+            .unwrap()
+            .factor = 0.25;
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<Burn>();
+        registry.register::<Slow>();
+        registry.set_current(|| {
+            let json = serde_json::to_string(&effects).unwrap();
+            let effects: DynMap<u32, dyn Effect> = serde_json::from_str(&json).unwrap();
+            assert_eq!(effects.len(), 3);
+            assert_eq!(effects[&3].downcast_ref::<Burn>().unwrap().0, 4);
+            assert_eq!(effects[&2].downcast_ref::<Slow>().unwrap().factor, 0.25);
+        });
+    }
+}
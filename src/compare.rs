@@ -0,0 +1,276 @@
+use std::{cmp::Ordering, ops::Deref};
+
+use bevy_reflect::{Reflect, ReflectRef};
+
+use crate::DynamicTrait;
+
+/// Compare two reflected values, like [`PartialOrd::partial_cmp()`].
+///
+/// Values of different types are ordered by type name. Values of the same
+/// type are compared field by field (structs, tuple structs, tuples), or
+/// element by element (lists, arrays, maps), lexicographically. Map entries are
+/// compared in key order. Enums are ordered by variant, in declaration order,
+/// and values of the same variant are compared field by field, so `None` is
+/// less than `Some`, as with `derive(PartialOrd)`. Primitive values (numbers,
+/// `bool`, `char` and `String`) are compared with their `PartialOrd`
+/// implementations.
+///
+/// Returns `None` if the values, or any of the fields or elements that are
+/// compared, are of other opaque value types, or are not comparable (such as
+/// `NaN`).
+///
+/// ## Example
+/// ```rust
+/// # use std::cmp::Ordering;
+/// # use reflect_steroids::{prelude::*, reflect_compare};
+/// #[derive(Reflect)]
+/// struct Version { major: u32, minor: u32 }
+///
+/// let a = Version { major: 1, minor: 5 };
+/// let b = Version { major: 2, minor: 0 };
+/// assert_eq!(reflect_compare(&a, &b), Some(Ordering::Less));
+/// assert_eq!(reflect_compare(&b, &b), Some(Ordering::Equal));
+/// ```
+pub fn reflect_compare(a: &dyn Reflect, b: &dyn Reflect) -> Option<Ordering> {
+    if a.type_name() != b.type_name() {
+        return Some(a.type_name().cmp(b.type_name()));
+    }
+
+    match (a.reflect_ref(), b.reflect_ref()) {
+        (ReflectRef::Struct(a), ReflectRef::Struct(b)) => {
+            compare_seq(a.iter_fields(), b.iter_fields())
+        }
+        (ReflectRef::TupleStruct(a), ReflectRef::TupleStruct(b)) => {
+            compare_seq(a.iter_fields(), b.iter_fields())
+        }
+        (ReflectRef::Tuple(a), ReflectRef::Tuple(b)) => {
+            compare_seq(a.iter_fields(), b.iter_fields())
+        }
+        (ReflectRef::List(a), ReflectRef::List(b)) => compare_seq(a.iter(), b.iter()),
+        (ReflectRef::Array(a), ReflectRef::Array(b)) => compare_seq(a.iter(), b.iter()),
+        (ReflectRef::Map(a), ReflectRef::Map(b)) => {
+            let a = sorted_entries(a.iter())?;
+            let b = sorted_entries(b.iter())?;
+            let mut a = a.into_iter();
+            let mut b = b.into_iter();
+            loop {
+                match (a.next(), b.next()) {
+                    (Some((a_key, a_value)), Some((b_key, b_value))) => {
+                        match reflect_compare(a_key, b_key)? {
+                            Ordering::Equal => (),
+                            ordering => return Some(ordering),
+                        }
+                        match reflect_compare(a_value, b_value)? {
+                            Ordering::Equal => (),
+                            ordering => return Some(ordering),
+                        }
+                    }
+                    (a, b) => return Some(a.is_some().cmp(&b.is_some())),
+                }
+            }
+        }
+        (ReflectRef::Enum(a), ReflectRef::Enum(b)) => {
+            match a.variant_index().cmp(&b.variant_index()) {
+                Ordering::Equal => compare_seq(
+                    a.iter_fields().map(|field| field.value()),
+                    b.iter_fields().map(|field| field.value()),
+                ),
+                ordering => Some(ordering),
+            }
+        }
+        (ReflectRef::Value(a), ReflectRef::Value(b)) => compare_values(a, b),
+        _ => None,
+    }
+}
+
+fn compare_seq<'a>(
+    mut a: impl Iterator<Item = &'a dyn Reflect>,
+    mut b: impl Iterator<Item = &'a dyn Reflect>,
+) -> Option<Ordering> {
+    loop {
+        match (a.next(), b.next()) {
+            (Some(a), Some(b)) => match reflect_compare(a, b)? {
+                Ordering::Equal => (),
+                ordering => return Some(ordering),
+            },
+            (a, b) => return Some(a.is_some().cmp(&b.is_some())),
+        }
+    }
+}
+
+type Entry<'a> = (&'a dyn Reflect, &'a dyn Reflect);
+
+fn sorted_entries<'a>(entries: impl Iterator<Item = Entry<'a>>) -> Option<Vec<Entry<'a>>> {
+    let mut entries: Vec<_> = entries.collect();
+    let mut comparable = true;
+    entries.sort_by(|(a, _), (b, _)| {
+        reflect_compare(*a, *b).unwrap_or_else(|| {
+            comparable = false;
+            Ordering::Equal
+        })
+    });
+    comparable.then_some(entries)
+}
+
+fn compare_values(a: &dyn Reflect, b: &dyn Reflect) -> Option<Ordering> {
+    macro_rules! compare_as {
+        ($($t:ty),*) => {$(
+            if let (Some(a), Some(b)) = (a.downcast_ref::<$t>(), b.downcast_ref::<$t>()) {
+                return a.partial_cmp(b);
+            }
+        )*};
+    }
+
+    compare_as!(
+        bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64,
+        String
+    );
+    None
+}
+
+/// A boxed trait object that implements [`Ord`] using [`reflect_compare()`],
+/// so that collections of trait objects can be sorted deterministically.
+///
+/// Values that [`reflect_compare()`] cannot compare are considered equal.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, DynOrd};
+/// #[reflect_trait]
+/// trait Item: DowncastReflect {}
+/// impl_dynamic_trait!(Item, ReflectItem);
+///
+/// #[derive(Reflect)]
+/// struct Coin { value: u32 }
+/// impl Item for Coin {}
+///
+/// #[derive(Reflect)]
+/// struct Apple;
+/// impl Item for Apple {}
+///
+/// let mut items: Vec<DynOrd<dyn Item>> = vec![
+///     DynOrd(Box::new(Coin { value: 5 })),
+///     DynOrd(Box::new(Apple)),
+///     DynOrd(Box::new(Coin { value: 1 })),
+/// ];
+/// items.sort();
+/// assert!(items[0].is::<Apple>());
+/// assert_eq!(items[1].downcast_ref::<Coin>().unwrap().value, 1);
+/// ```
+#[derive(Debug)]
+pub struct DynOrd<T: DynamicTrait + ?Sized>(pub Box<T>);
+
+impl<T: DynamicTrait + ?Sized> DynOrd<T> {
+    /// Unwrap the trait object.
+    #[must_use]
+    pub fn into_inner(self) -> Box<T> {
+        self.0
+    }
+}
+
+impl<T: DynamicTrait + ?Sized> Deref for DynOrd<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DynamicTrait + ?Sized> PartialEq for DynOrd<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: DynamicTrait + ?Sized> Eq for DynOrd<T> {}
+
+impl<T: DynamicTrait + ?Sized> PartialOrd for DynOrd<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: DynamicTrait + ?Sized> Ord for DynOrd<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        reflect_compare((*self.0).as_reflect(), (*other.0).as_reflect()).unwrap_or(Ordering::Equal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::DynamicMap;
+
+    use super::*;
+
+    #[derive(Reflect)]
+    struct Inventory {
+        items: Vec<(String, u32)>,
+        weight: f32,
+    }
+
+    #[test]
+    fn compare_nested() {
+        let a = Inventory {
+            items: vec![("a".into(), 1), ("b".into(), 2)],
+            weight: 1.0,
+        };
+        let b = Inventory {
+            items: vec![("a".into(), 1), ("b".into(), 2), ("c".into(), 0)],
+            weight: 0.0,
+        };
+        assert_eq!(reflect_compare(&a, &b), Some(Ordering::Less));
+        assert_eq!(reflect_compare(&b, &a), Some(Ordering::Greater));
+
+        let c = Inventory {
+            items: vec![("a".into(), 1), ("b".into(), 2)],
+            weight: f32::NAN,
+        };
+        assert_eq!(reflect_compare(&a, &c), None);
+    }
+
+    #[derive(Reflect)]
+    enum Priority {
+        Low,
+        High(u8),
+        Custom { level: u32, label: String },
+    }
+
+    #[test]
+    fn compare_enums() {
+        use Priority::*;
+        let custom = |level: u32| Custom {
+            level,
+            label: "x".into(),
+        };
+        assert_eq!(reflect_compare(&Low, &High(0)), Some(Ordering::Less));
+        assert_eq!(reflect_compare(&High(3), &Low), Some(Ordering::Greater));
+        assert_eq!(reflect_compare(&High(3), &High(3)), Some(Ordering::Equal));
+        assert_eq!(reflect_compare(&High(3), &High(5)), Some(Ordering::Less));
+        assert_eq!(
+            reflect_compare(&custom(2), &custom(1)),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            reflect_compare(&None::<u32>, &Some(0u32)),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            reflect_compare(&Some(2u32), &Some(1u32)),
+            Some(Ordering::Greater)
+        );
+    }
+
+    #[test]
+    fn compare_maps_in_key_order() {
+        let mut a = DynamicMap::default();
+        a.insert(1u32, String::from("x"));
+        a.insert(2u32, String::from("y"));
+        let mut b = DynamicMap::default();
+        b.insert(2u32, String::from("y"));
+        b.insert(1u32, String::from("x"));
+        assert_eq!(reflect_compare(&a, &b), Some(Ordering::Equal));
+
+        b.insert(0u32, String::from("z"));
+        assert_eq!(reflect_compare(&a, &b), Some(Ordering::Greater));
+    }
+}
@@ -0,0 +1,72 @@
+//! Adapters for the differences between the supported versions of
+//! `bevy_reflect`, selected by the `bevy_reflect_0_9` and `bevy_reflect_0_10`
+//! crate features. If both are enabled, 0.9 is used.
+
+use bevy_reflect::{GetPath, Reflect, ReflectPathError};
+
+/// Get the value at `path` in `object`, in the syntax of [`GetPath`].
+pub(crate) fn reflect_path<'r, 'p>(
+    object: &'r dyn Reflect,
+    path: &'p str,
+) -> Result<&'r dyn Reflect, ReflectPathError<'p>> {
+    #[cfg(feature = "bevy_reflect_0_9")]
+    return object.path(path);
+    #[cfg(all(feature = "bevy_reflect_0_10", not(feature = "bevy_reflect_0_9")))]
+    return object.reflect_path(path);
+}
+
+/// Get the value at `path` in `object` mutably, in the syntax of [`GetPath`].
+pub(crate) fn reflect_path_mut<'r, 'p>(
+    object: &'r mut dyn Reflect,
+    path: &'p str,
+) -> Result<&'r mut dyn Reflect, ReflectPathError<'p>> {
+    #[cfg(feature = "bevy_reflect_0_9")]
+    return object.path_mut(path);
+    #[cfg(all(feature = "bevy_reflect_0_10", not(feature = "bevy_reflect_0_9")))]
+    return object.reflect_path_mut(path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Reflect)]
+    struct Inner {
+        values: Vec<u32>,
+    }
+
+    #[derive(Reflect)]
+    struct Outer {
+        inner: Inner,
+        scale: f32,
+        name: String,
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn paths() {
+        let mut outer = Outer {
+            inner: Inner { values: vec![1, 2] },
+            scale: 0.5,
+            name: "a".into(),
+        };
+        let value = reflect_path(&outer, "inner.values[1]").unwrap();
+        assert_eq!(value.downcast_ref::<u32>(), Some(&2));
+        let value = reflect_path(&outer, "name").unwrap();
+        assert_eq!(value.downcast_ref::<String>().unwrap(), "a");
+        assert!(reflect_path(&outer, "inner.missing").is_err());
+        assert!(reflect_path(&outer, "inner.values[2]").is_err());
+
+        *reflect_path_mut(&mut outer, "inner.values[0]")
+            .unwrap()
+            .downcast_mut::<u32>()
+            .unwrap() = 10;
+        *reflect_path_mut(&mut outer, "scale")
+            .unwrap()
+            .downcast_mut::<f32>()
+            .unwrap() = 1.5;
+        assert_eq!(outer.inner.values, [10, 2]);
+        assert_eq!(outer.scale, 1.5);
+        assert!(reflect_path_mut(&mut outer, "name.missing").is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use std::{fmt, sync::Arc};
+
+use bevy_reflect::{Reflect, TypeRegistry};
+
+use crate::{CastBox, DowncastReflect, DynamicTrait, TypeError};
+
+type ConvertFn<P> = dyn Fn(Box<dyn Reflect>) -> Box<P> + Send + Sync;
+
+/// Type data for converting a value of a concrete type into a different trait
+/// object `P`, such as an adapter implementing a new interface for a legacy
+/// type. See
+/// [`TypeRegistryExt::register_conversion()`](crate::TypeRegistryExt::register_conversion)
+/// and [`convert()`].
+pub struct ReflectInto<P: ?Sized> {
+    convert: Arc<ConvertFn<P>>,
+}
+
+impl<P: ?Sized + 'static> ReflectInto<P> {
+    /// Create type data for converting values of type `T` with `f`.
+    // The stored closure panics, not `new`; see `convert()`.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn new<T, F>(f: F) -> Self
+    where
+        T: Reflect,
+        F: Fn(T) -> Box<P> + Send + Sync + 'static,
+    {
+        ReflectInto {
+            convert: Arc::new(move |value| {
+                let value = value
+                    .downcast::<T>()
+                    .expect("type data registered for a different type");
+                f(*value)
+            }),
+        }
+    }
+
+    /// Convert `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is not of the type this type data was registered
+    /// for.
+    #[must_use]
+    pub fn convert(&self, value: Box<dyn Reflect>) -> Box<P> {
+        (self.convert)(value)
+    }
+}
+
+impl<P: ?Sized> Clone for ReflectInto<P> {
+    fn clone(&self) -> Self {
+        ReflectInto {
+            convert: self.convert.clone(),
+        }
+    }
+}
+
+impl<P: ?Sized> fmt::Debug for ReflectInto<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReflectInto").finish_non_exhaustive()
+    }
+}
+
+/// Convert the trait object `object` into the trait object `B`.
+///
+/// If the concrete object implements `B`, it is cast (see [`Cast`](crate::Cast)).
+/// Otherwise, it is converted with the [`ReflectInto<B>`] type data of its
+/// concrete type.
+///
+/// # Errors
+///
+/// Fails if the concrete object neither implements `B` nor has
+/// [`ReflectInto<B>`] type data. The original object is given back along with
+/// the error of the cast.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, convert, reflect::TypeRegistry};
+/// #[reflect_trait]
+/// trait LegacyShape: DowncastReflect {
+///     fn size(&self) -> (f32, f32);
+/// }
+/// impl_dynamic_trait!(LegacyShape, ReflectLegacyShape);
+///
+/// #[reflect_trait]
+/// trait Shape: DowncastReflect {
+///     fn area(&self) -> f32;
+/// }
+/// impl_dynamic_trait!(Shape, ReflectShape);
+///
+/// #[derive(Reflect)]
+/// #[reflect(LegacyShape)]
+/// struct OldRect { w: f32, h: f32 }
+/// impl LegacyShape for OldRect {
+///     fn size(&self) -> (f32, f32) { (self.w, self.h) }
+/// }
+///
+/// #[derive(Reflect)]
+/// struct Rect { w: f32, h: f32 }
+/// impl Shape for Rect {
+///     fn area(&self) -> f32 { self.w * self.h }
+/// }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register_conversion::<OldRect, dyn Shape, _>(|old| {
+///     Box::new(Rect { w: old.w, h: old.h })
+/// });
+///
+/// let legacy: Box<dyn LegacyShape> = Box::new(OldRect { w: 2.0, h: 3.0 });
+/// let shape = convert::<dyn LegacyShape, dyn Shape>(legacy, &registry).unwrap();
+/// assert_eq!(shape.area(), 6.0);
+/// ```
+pub fn convert<A, B>(object: Box<A>, registry: &TypeRegistry) -> Result<Box<B>, (Box<A>, TypeError)>
+where
+    A: DowncastReflect + ?Sized,
+    B: DynamicTrait + ?Sized,
+{
+    let (object, err) = match object.try_cast_box_with_registry::<B>(registry) {
+        Ok(converted) => return Ok(converted),
+        Err(failure) => failure,
+    };
+
+    let type_id = (*object).as_reflect().as_any().type_id();
+    match registry.get_type_data::<ReflectInto<B>>(type_id) {
+        Some(reflect_into) => Ok(reflect_into.convert(object.downcast_into_reflect())),
+        None => Err((object, err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::reflect_trait;
+
+    use super::*;
+    use crate::{impl_dynamic_trait, TypeRegistryExt};
+
+    #[reflect_trait]
+    trait Speaker: DowncastReflect {
+        fn speak(&self) -> String;
+    }
+    impl_dynamic_trait!(Speaker, ReflectSpeaker);
+
+    #[derive(Reflect)]
+    #[reflect(Speaker)]
+    struct Parrot;
+    impl Speaker for Parrot {
+        fn speak(&self) -> String {
+            "squawk".into()
+        }
+    }
+
+    #[derive(Reflect)]
+    struct Rock;
+
+    #[derive(Reflect)]
+    struct Recording(String);
+    impl Speaker for Recording {
+        fn speak(&self) -> String {
+            self.0.clone()
+        }
+    }
+
+    #[test]
+    fn cast_then_convert() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Parrot>();
+        registry.register::<Rock>();
+
+        // Implements the trait: cast, no conversion needed.
+        let parrot: Box<dyn Reflect> = Box::new(Parrot);
+        let speaker = convert::<dyn Reflect, dyn Speaker>(parrot, &registry).unwrap();
+        assert_eq!(speaker.speak(), "squawk");
+
+        let rock: Box<dyn Reflect> = Box::new(Rock);
+        let (rock, err) = convert::<dyn Reflect, dyn Speaker>(rock, &registry).unwrap_err();
+        assert!(rock.is::<Rock>());
+        assert_eq!(err, TypeError::UnregisteredTrait("Rock".into(), "Speaker"));
+
+        registry.register_conversion::<Rock, dyn Speaker, _>(|_| Box::new(Recording("...".into())));
+        let speaker = convert::<dyn Reflect, dyn Speaker>(rock, &registry).unwrap();
+        assert_eq!(speaker.speak(), "...");
+    }
+}
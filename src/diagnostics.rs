@@ -0,0 +1,109 @@
+//! [`miette::Diagnostic`] implementations for the error types of this crate.
+//!
+//! Requires the "diagnostics" crate feature.
+
+use std::fmt::Display;
+
+use miette::Diagnostic;
+
+use crate::{pretty::short_name, TypeError};
+
+impl Diagnostic for TypeError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        let code = match self {
+            TypeError::UnregisteredType(_) => "reflect_steroids::unregistered_type",
+            TypeError::UnregisteredShortName(_) => "reflect_steroids::unregistered_short_name",
+            TypeError::UnregisteredTrait(..) => "reflect_steroids::unregistered_trait",
+            TypeError::NoCurrentRegistry => "reflect_steroids::no_current_registry",
+            TypeError::ForbiddenType(..) => "reflect_steroids::forbidden_type",
+            TypeError::CastDenied(..) => "reflect_steroids::cast_denied",
+            TypeError::TypeDataMismatch(..) => "reflect_steroids::type_data_mismatch",
+            TypeError::CyclicReference(_) => "reflect_steroids::cyclic_reference",
+            TypeError::NoStableId(_) => "reflect_steroids::no_stable_id",
+            TypeError::UnknownStableId(_) => "reflect_steroids::unknown_stable_id",
+        };
+        Some(Box::new(code))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        let help = match self {
+            TypeError::UnregisteredType(type_name) => {
+                let name = short_name(type_name);
+                format!("register the type before using it:\n\n    registry.register::<{name}>();")
+            }
+            TypeError::UnregisteredShortName(name) => format!(
+                "register the type before deserializing it:\n\n    \
+                 registry.register::<{name}>();\n\n\
+                 if another registered type has the same short name, give one of them a \
+                 distinct tag with `registry.register_serialized_as::<{name}>(\"...\")`"
+            ),
+            TypeError::UnregisteredTrait(type_name, trait_name) => {
+                let name = short_name(type_name);
+                format!(
+                    "add `#[reflect({trait_name})]` to `{name}` and register it:\n\n    \
+                     #[derive(Reflect)]\n    \
+                     #[reflect({trait_name})]\n    \
+                     struct {name} {{ ... }}\n\n    \
+                     registry.register::<{name}>();"
+                )
+            }
+            TypeError::NoCurrentRegistry => "set a current registry for the operation:\n\n    \
+                 registry.set_current(|| ...);"
+                .to_string(),
+            TypeError::ForbiddenType(type_name, trait_name) => {
+                let name = short_name(type_name);
+                format!(
+                    "`dyn {trait_name}` is restricted to a list of types; if `{name}` is \
+                     trusted, add it to the list:\n\n    \
+                     registry.restrict::<dyn {trait_name}>(&[..., \"{name}\"]);"
+                )
+            }
+            TypeError::CastDenied(type_name, trait_name) => {
+                let name = short_name(type_name);
+                format!(
+                    "the registry's cast policy rejected casting `{name}` to `dyn {trait_name}`; \
+                     check the context set with `with_cast_context()`"
+                )
+            }
+            TypeError::TypeDataMismatch(type_name, trait_name) => {
+                let name = short_name(type_name);
+                format!(
+                    "the `dyn {trait_name}` type data of `{name}` was created for another type; \
+                     register `{name}` again instead of inserting type data by hand"
+                )
+            }
+            TypeError::CyclicReference(_) => "the object graph cannot be serialized as a tree; \
+                 break the cycle, or write references with \
+                 `with_cycle_guard(CycleMode::Reference, || ...)`"
+                .to_string(),
+            TypeError::NoStableId(type_name) => {
+                let name = short_name(type_name);
+                format!(
+                    "give the type a stable ID:\n\n    registry.register_stable_id::<{name}>(...);"
+                )
+            }
+            TypeError::UnknownStableId(id) => format!(
+                "register the type with the stable ID {id} before deserializing it:\n\n    \
+                 registry.register_stable_id::<...>({id});"
+            ),
+        };
+        Some(Box::new(help))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unregistered_trait_help() {
+        let err = TypeError::UnregisteredTrait("game::units::Foo<game::Bar>".into(), "MyTrait");
+        assert_eq!(
+            err.code().unwrap().to_string(),
+            "reflect_steroids::unregistered_trait"
+        );
+        let help = err.help().unwrap().to_string();
+        assert!(help.starts_with("add `#[reflect(MyTrait)]` to `Foo<Bar>`"));
+        assert!(help.contains("registry.register::<Foo<Bar>>();"));
+    }
+}
@@ -1,8 +1,8 @@
-use std::{borrow::Cow, marker::PhantomData};
+use std::{any::TypeId, borrow::Cow, marker::PhantomData};
 
 use bevy_reflect::{Reflect, TypeData, TypeRegistration, TypeRegistry};
 
-use crate::TypeError;
+use crate::{cast_policy::check_cast, missing_type::report_missing, TypeError};
 
 /// Description of an interface. This is a way to associate trait object types
 /// with some metadata that lives in the [`TypeRegistry`].
@@ -53,21 +53,191 @@ pub trait DynamicTraitExt: DynamicTrait {
         pointer: &dyn Reflect,
         registry: &'a TypeRegistry,
     ) -> Result<Cow<'a, Self::TypeData>, TypeError> {
-        let registration = registry
-            .get(pointer.as_any().type_id())
-            .ok_or_else(|| TypeError::UnregisteredType(pointer.type_name().to_string().into()))?;
-        let metadata = Self::get_type_data(registration).ok_or_else(|| {
-            TypeError::UnregisteredTrait(
-                registration.short_name().to_string().into(),
-                Self::reflect_name(),
-            )
-        })?;
-        Ok(metadata)
+        type_data_for::<Self>(
+            pointer.as_any().type_id(),
+            || pointer.type_name().to_string(),
+            registry,
+        )
+    }
+
+    /// Find the [`Self::TypeData`](DynamicTrait::TypeData) for the type `T`
+    /// and this [`DynamicTrait`], without an instance of `T`.
+    ///
+    /// This can be used to look up the casters of known types up front.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the type is not registered, if this trait is not registered for
+    /// the type, or if the registry's cast policy denies the cast.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+    /// #[reflect_trait]
+    /// trait Component: DowncastReflect {}
+    /// impl_dynamic_trait!(Component, ReflectComponent);
+    ///
+    /// #[derive(Reflect)]
+    /// #[reflect(Component)]
+    /// struct Health(u32);
+    /// impl Component for Health {}
+    ///
+    /// let mut registry = TypeRegistry::default();
+    /// registry.register::<Health>();
+    ///
+    /// let caster = <dyn Component>::get_type_data_for_type::<Health>(&registry).unwrap();
+    /// let health: Box<dyn Reflect> = Box::new(Health(10));
+    /// let component: Box<dyn Component> = caster.get_boxed(health).unwrap();
+    /// assert!(component.is::<Health>());
+    /// ```
+    fn get_type_data_for_type<T: 'static>(
+        registry: &TypeRegistry,
+    ) -> Result<Cow<'_, Self::TypeData>, TypeError> {
+        type_data_for::<Self>(
+            TypeId::of::<T>(),
+            || std::any::type_name::<T>().to_string(),
+            registry,
+        )
+    }
+
+    /// Like [`get_type_data_for_type()`](DynamicTraitExt::get_type_data_for_type),
+    /// but for the type with the given [`TypeId`].
+    ///
+    /// # Errors
+    ///
+    /// Fails in the same cases as
+    /// [`get_type_data_for_type()`](DynamicTraitExt::get_type_data_for_type).
+    fn get_type_data_for_type_id(
+        type_id: TypeId,
+        registry: &TypeRegistry,
+    ) -> Result<Cow<'_, Self::TypeData>, TypeError> {
+        type_data_for::<Self>(type_id, || format!("{type_id:?}"), registry)
+    }
+
+    /// Get the associated constants of the type of `registration`, as
+    /// exposed by [`impl_trait_consts!`](crate::impl_trait_consts). Returns
+    /// `None` if the type data is not registered for the type.
+    #[must_use]
+    fn const_metadata(registration: &TypeRegistration) -> Option<&Self::Consts>
+    where
+        Self: TraitConsts,
+    {
+        registration.data::<Self::Consts>()
     }
 }
 
 impl<T: DynamicTrait + ?Sized> DynamicTraitExt for T {}
 
+/// Find the type data of the dynamic trait `P` for the type with the given
+/// [`TypeId`]. `type_name` is called for the name used in the error if the
+/// type is not registered.
+fn type_data_for<P: DynamicTrait + ?Sized>(
+    type_id: TypeId,
+    type_name: impl FnOnce() -> String,
+    registry: &TypeRegistry,
+) -> Result<Cow<'_, P::TypeData>, TypeError> {
+    let registration = registry
+        .get(type_id)
+        .ok_or_else(|| report_missing(TypeError::UnregisteredType(type_name().into())))?;
+    check_cast(registry, P::reflect_name(), registration)?;
+    P::get_type_data(registration).ok_or_else(|| {
+        TypeError::UnregisteredTrait(
+            registration.short_name().to_string().into(),
+            P::reflect_name(),
+        )
+    })
+}
+
+/// Associates a dynamic trait with type data holding associated constants of
+/// its implementations, so that they can be read from the registry without an
+/// instance of the type. Implemented by
+/// [`impl_trait_consts!`](crate::impl_trait_consts).
+///
+/// Traits with associated constants cannot be made into trait objects, so the
+/// constants are declared in a separate trait.
+pub trait TraitConsts: DynamicTrait {
+    /// The type data holding the constants.
+    type Consts: TypeData;
+}
+
+/// Expose the associated constants of a trait through type data.
+///
+/// `impl_trait_consts!(MyTrait, MyTraitConsts, ReflectMyTraitConsts { A: u32,
+/// B: &'static str })` defines the type data `ReflectMyTraitConsts`, with a
+/// public field for each listed constant of the trait `MyTraitConsts`, and
+/// implements [`TraitConsts`] for `dyn MyTrait`. Register it with
+/// `#[reflect(MyTraitConsts)]`, and read it with
+/// [`DynamicTraitExt::const_metadata()`].
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, impl_trait_consts, DynamicTraitExt, reflect::TypeRegistry};
+/// #[reflect_trait]
+/// trait Behavior: DowncastReflect {}
+/// impl_dynamic_trait!(Behavior, ReflectBehavior);
+///
+/// trait BehaviorConsts {
+///     const PRIORITY: u32;
+/// }
+/// impl_trait_consts!(Behavior, BehaviorConsts, ReflectBehaviorConsts { PRIORITY: u32 });
+///
+/// #[derive(Reflect)]
+/// #[reflect(Behavior, BehaviorConsts)]
+/// struct Movement;
+/// impl Behavior for Movement {}
+/// impl BehaviorConsts for Movement {
+///     const PRIORITY: u32 = 10;
+/// }
+///
+/// #[derive(Reflect)]
+/// #[reflect(Behavior, BehaviorConsts)]
+/// struct Input;
+/// impl Behavior for Input {}
+/// impl BehaviorConsts for Input {
+///     const PRIORITY: u32 = 1;
+/// }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Movement>();
+/// registry.register::<Input>();
+///
+/// let mut behaviors: Vec<_> = registry
+///     .iter()
+///     .filter_map(|registration| {
+///         let consts = <dyn Behavior>::const_metadata(registration)?;
+///         Some((consts.PRIORITY, registration.short_name()))
+///     })
+///     .collect();
+/// behaviors.sort();
+/// assert_eq!(behaviors, [(1, "Input"), (10, "Movement")]);
+/// ```
+#[macro_export]
+macro_rules! impl_trait_consts {
+    ($trait_name:ident, $consts_trait:ident, $type_data_name:ident { $($const_name:ident: $const_ty:ty),+ $(,)? }) => {
+        #[doc = concat!("Associated constants of `", stringify!($consts_trait), "`, as type data.")]
+        #[derive(Clone)]
+        #[allow(non_snake_case)]
+        pub struct $type_data_name {
+            $(
+                #[doc = concat!("The value of `", stringify!($const_name), "`.")]
+                pub $const_name: $const_ty,
+            )+
+        }
+
+        impl<T: $consts_trait> $crate::reflect::FromType<T> for $type_data_name {
+            fn from_type() -> Self {
+                $type_data_name {
+                    $($const_name: T::$const_name,)+
+                }
+            }
+        }
+
+        impl $crate::TraitConsts for dyn $trait_name {
+            type Consts = $type_data_name;
+        }
+    };
+}
+
 /// Cast a reflected pointer to another trait object.
 ///
 /// This can be implemented for the [`TypeData`](bevy_reflect::TypeData)
@@ -82,6 +252,44 @@ pub trait DynamicCaster<T: ?Sized>: Send + Sync + Clone + 'static {
     fn from_reflect_ref<'a>(&self, this: &'a dyn Reflect) -> &'a T;
     /// Cast from mutable reference.
     fn from_reflect_mut<'a>(&self, this: &'a mut dyn Reflect) -> &'a mut T;
+
+    /// Cast from box, giving it back if the type data does not apply to the
+    /// type of `this`.
+    ///
+    /// [`Cast`](crate::Cast) and the other casting interfaces use the fallible
+    /// methods, and fail with [`TypeError::TypeDataMismatch`]. The default
+    /// implementations call the infallible methods.
+    ///
+    /// # Errors
+    ///
+    /// Gives back `this` if the type data does not apply to its type.
+    fn try_from_reflect(&self, this: Box<dyn Reflect>) -> Result<Box<T>, Box<dyn Reflect>> {
+        Ok(self.from_reflect(this))
+    }
+    /// Cast from reference, or `None` if the type data does not apply to the
+    /// type of `this`.
+    fn try_from_reflect_ref<'a>(&self, this: &'a dyn Reflect) -> Option<&'a T> {
+        Some(self.from_reflect_ref(this))
+    }
+    /// Cast from mutable reference, or `None` if the type data does not apply
+    /// to the type of `this`.
+    fn try_from_reflect_mut<'a>(&self, this: &'a mut dyn Reflect) -> Option<&'a mut T> {
+        Some(self.from_reflect_mut(this))
+    }
+}
+
+/// Panic because the type data of the dynamic trait `trait_name` does not
+/// apply to an object of the type `type_name` that it was asked to cast. Used
+/// by the infallible [`DynamicCaster`] methods generated by
+/// [`impl_dynamic_trait!`](crate::impl_dynamic_trait).
+#[doc(hidden)]
+#[cold]
+#[track_caller]
+pub fn __type_data_mismatch(trait_name: &'static str, type_name: &str) -> ! {
+    panic!(
+        "{}",
+        TypeError::TypeDataMismatch(type_name.to_string().into(), trait_name)
+    )
 }
 
 const _: () = {
@@ -105,6 +313,18 @@ const _: () = {
         fn from_reflect_mut<'a>(&self, this: &'a mut dyn Reflect) -> &'a mut T {
             this.downcast_mut().expect("type mismatch")
         }
+
+        fn try_from_reflect(&self, this: Box<dyn Reflect>) -> Result<Box<T>, Box<dyn Reflect>> {
+            this.downcast()
+        }
+
+        fn try_from_reflect_ref<'a>(&self, this: &'a dyn Reflect) -> Option<&'a T> {
+            this.downcast_ref()
+        }
+
+        fn try_from_reflect_mut<'a>(&self, this: &'a mut dyn Reflect) -> Option<&'a mut T> {
+            this.downcast_mut()
+        }
     }
 
     impl<T: Reflect> Default for SelfTrait<T> {
@@ -167,7 +387,9 @@ const _: () = {
 mod tests {
     use bevy_reflect::{reflect_trait, TypeRegistry};
 
-    use crate::{impl_dynamic_trait, Cast, CastMut, CastRef, DowncastReflect, TypeRegistryExt};
+    use crate::{
+        impl_dynamic_trait, Cast, CastBox, CastMut, CastRef, DowncastReflect, TypeRegistryExt,
+    };
 
     use super::*;
 
@@ -176,17 +398,27 @@ mod tests {
     #[reflect_trait]
     trait Trait2: DowncastReflect {}
 
+    #[reflect_trait]
+    trait Trait3: Reflect {}
+
     impl_dynamic_trait!(Trait1, ReflectTrait1);
-    impl_dynamic_trait!(Trait2, ReflectTrait2);
+    impl_dynamic_trait!(pub(self) Trait2, ReflectTrait2);
+    impl_dynamic_trait!(Trait3: Reflect, ReflectTrait3);
 
     #[derive(Debug, Reflect)]
-    #[reflect(Trait1, Trait2)]
+    #[reflect(Trait1, Trait2, Trait3)]
     struct Foo {
         num: i32,
     }
 
     impl Trait1 for Foo {}
     impl Trait2 for Foo {}
+    impl Trait3 for Foo {}
+
+    #[derive(Debug, Reflect)]
+    struct Bar;
+
+    impl Trait1 for Bar {}
 
     #[test]
     fn trait_object_reflection() {
@@ -217,7 +449,7 @@ mod tests {
             _ => panic!("unexpected reflection kind"),
         }
 
-        let foo: Box<dyn Reflect> = foo.into_reflect();
+        let foo: Box<dyn Reflect> = crate::DowncastReflect::downcast_into_reflect(foo);
         assert!(foo.is::<Foo>());
         assert!(foo.as_any().is::<Foo>());
         assert_eq!(foo.type_name(), std::any::type_name::<Foo>());
@@ -249,6 +481,66 @@ mod tests {
             assert!(f.is::<Foo>());
             let g: &mut dyn Trait1 = CastMut::try_cast_mut(&mut e).unwrap();
             assert!(g.is::<Foo>());
+
+            // Traits with only `Reflect` as a supertrait.
+            let h: Box<dyn Trait3> = Cast::try_cast(e).unwrap();
+            let i: &dyn Trait3 = CastRef::try_cast_ref(&h).unwrap();
+            assert!(i.is::<Foo>());
+            let j: Box<dyn Trait2> = Cast::try_cast(h).unwrap();
+            assert!(j.is::<Foo>());
         });
     }
+
+    #[test]
+    fn stale_type_data() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Bar>();
+        registry
+            .get_mut(TypeId::of::<Bar>())
+            .unwrap()
+            .insert(<ReflectTrait1 as bevy_reflect::FromType<Foo>>::from_type());
+
+        let mismatch = TypeError::TypeDataMismatch(std::any::type_name::<Bar>().into(), "Trait1");
+        let mut bar = Bar;
+        assert_eq!(
+            Cast::<dyn Trait1>::try_cast_with_registry(&bar, &registry).err(),
+            Some(mismatch.clone())
+        );
+        assert_eq!(
+            Cast::<dyn Trait1>::try_cast_with_registry(&mut bar, &registry).err(),
+            Some(mismatch.clone())
+        );
+        let boxed: Box<dyn Reflect> = Box::new(Bar);
+        let (boxed, err) = boxed
+            .try_cast_box_with_registry::<dyn Trait1>(&registry)
+            .err()
+            .unwrap();
+        assert_eq!(err, mismatch);
+
+        let caster = <dyn Trait1>::get_type_data_for_type::<Bar>(&registry).unwrap();
+        let panic = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            caster.from_reflect(boxed);
+        }))
+        .unwrap_err();
+        assert_eq!(panic.downcast_ref::<String>(), Some(&mismatch.to_string()));
+    }
+
+    #[test]
+    fn downcast_checked() {
+        let object: Box<dyn Trait1> = Box::new(Foo { num: 123 });
+        let err = object.downcast_checked::<Bar>().unwrap_err();
+        assert_eq!(err.expected, std::any::type_name::<Bar>());
+        assert_eq!(err.actual, std::any::type_name::<Foo>());
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "expected a value of type '{}', found '{}'",
+                std::any::type_name::<Bar>(),
+                std::any::type_name::<Foo>()
+            )
+        );
+
+        let foo = err.into_inner().downcast_checked::<Foo>().unwrap();
+        assert_eq!(foo.num, 123);
+    }
 }
@@ -1,7 +1,7 @@
 use std::borrow::Cow;
 
 /// Type casting errors.
-#[derive(Debug, thiserror::Error, PartialEq)]
+#[derive(Clone, Debug, thiserror::Error, PartialEq)]
 pub enum TypeError {
     /// The type was not part of the [`bevy_reflect::TypeRegistry`].
     #[error("unregistered type: {0}")]
@@ -15,4 +15,363 @@ pub enum TypeError {
     /// missing from the struct.
     #[error("#[reflect({1})] is missing from '{0}'")]
     UnregisteredTrait(Cow<'static, str>, &'static str),
+    /// No registry was set for the current thread. See
+    /// [`TypeRegistryExt::set_current()`](crate::TypeRegistryExt::set_current).
+    #[error("no current type registry - use TypeRegistryExt::set_current")]
+    NoCurrentRegistry,
+    /// The type may not be deserialized as the dynamic trait, which has been
+    /// restricted with
+    /// [`TypeRegistryExt::restrict()`](crate::TypeRegistryExt::restrict).
+    #[error("'{0}' may not be deserialized as dyn {1}")]
+    ForbiddenType(Cow<'static, str>, &'static str),
+    /// The registry's [`CastPolicy`](crate::CastPolicy) does not allow the
+    /// type to be cast to the dynamic trait in the current context.
+    #[error("casting '{0}' to dyn {1} is denied by the cast policy")]
+    CastDenied(Cow<'static, str>, &'static str),
+    /// The type data of the dynamic trait registered for the type does not
+    /// cast objects of that type, for example because it is stale or was
+    /// inserted for another type.
+    #[error("the type data of dyn {1} registered for '{0}' does not apply to it")]
+    TypeDataMismatch(Cow<'static, str>, &'static str),
+    /// An object was reached again while it was being serialized, with a
+    /// cycle guard set (see
+    /// [`with_cycle_guard()`](crate::serialization::with_cycle_guard)). This
+    /// holds the type tags of the objects in the cycle.
+    #[error("cycle in object graph: {0}")]
+    CyclicReference(String),
+    /// The type has no [`StableId`](crate::serialization::StableId), but was
+    /// serialized in the
+    /// [`TagNamespace::Runtime`](crate::serialization::TagNamespace::Runtime)
+    /// namespace.
+    #[error("'{0}' has no stable ID")]
+    NoStableId(Cow<'static, str>),
+    /// No type has the [`StableId`](crate::serialization::StableId) given as
+    /// a type tag.
+    #[error("unknown stable ID {0} - the type may be unregistered")]
+    UnknownStableId(u64),
+}
+
+/// Errors from [`try_apply_dyn()`](crate::try_apply_dyn) and
+/// [`check_apply()`](crate::check_apply).
+///
+/// The `path` of each error is the location of the offending value relative to
+/// the root, in the syntax of [`bevy_reflect::GetPath`].
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum ApplyError {
+    /// The values are of different kinds (e.g., a struct and a list).
+    #[error("cannot apply {src_kind} '{src}' to {dst_kind} '{dst}' at '{path}'")]
+    MismatchedKinds {
+        /// Path of the value.
+        path: String,
+        /// Type name of the destination value.
+        dst: String,
+        /// Kind of the destination value.
+        dst_kind: &'static str,
+        /// Type name of the source value.
+        src: String,
+        /// Kind of the source value.
+        src_kind: &'static str,
+    },
+    /// The values are opaque and of different types.
+    #[error("cannot apply value of type '{src}' to '{dst}' at '{path}'")]
+    MismatchedTypes {
+        /// Path of the value.
+        path: String,
+        /// Type name of the destination value.
+        dst: String,
+        /// Type name of the source value.
+        src: String,
+    },
+    /// The values are arrays of different lengths.
+    #[error("cannot apply array of length {src} to array of length {dst} at '{path}'")]
+    MismatchedLength {
+        /// Path of the value.
+        path: String,
+        /// Length of the destination array.
+        dst: usize,
+        /// Length of the source array.
+        src: usize,
+    },
+    /// The source is an enum variant that the destination enum does not
+    /// have.
+    #[error("cannot apply variant '{variant}' to '{dst}' at '{path}', which has no such variant")]
+    UnknownVariant {
+        /// Path of the value.
+        path: String,
+        /// Type name of the destination value.
+        dst: String,
+        /// Name of the source variant.
+        variant: String,
+    },
+}
+
+/// Errors from [`replicate::encode_update()`](crate::replicate::encode_update)
+/// and [`replicate::apply_update()`](crate::replicate::apply_update).
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum ReplicateError {
+    /// A type involved in the update was not properly registered.
+    #[error(transparent)]
+    Type(#[from] TypeError),
+    /// A value could not be serialized or deserialized.
+    #[error("failed to encode or decode update: {0}")]
+    Encoding(String),
+    /// The update message is malformed.
+    #[error("malformed update: {0}")]
+    Malformed(&'static str),
+    /// The update describes changes to the fields of a different type.
+    #[error("update for type '{update}' cannot be applied to '{target}'")]
+    MismatchedType {
+        /// The type tag of the target.
+        target: String,
+        /// The type tag in the update.
+        update: String,
+    },
+    /// The update changes a field that the target does not have.
+    #[error("unknown field '{0}'")]
+    UnknownField(String),
+}
+
+/// Errors from [`watch()`](crate::watch) and [`Watch`](crate::Watch).
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum WatchError {
+    /// The path could not be resolved in the object.
+    #[error("invalid path '{path}': {message}")]
+    InvalidPath {
+        /// The path.
+        path: String,
+        /// Why the path could not be resolved.
+        message: String,
+    },
+}
+
+/// Errors from [`from_dynamic()`](crate::from_dynamic).
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum FromDynamicError {
+    /// The type is not registered, lacks the type data needed to build it, or
+    /// cannot be cast to the requested trait.
+    #[error(transparent)]
+    Type(#[from] TypeError),
+    /// The dynamic value does not fit the default value of the type.
+    #[error(transparent)]
+    Apply(#[from] ApplyError),
+    /// [`FromReflect`](bevy_reflect::FromReflect) failed to build the type
+    /// from the dynamic value.
+    #[error("cannot build '{0}' from the dynamic value")]
+    Conversion(String),
+}
+
+/// Errors from [`Recorder`](crate::proxy::Recorder) and
+/// [`replay()`](crate::proxy::replay).
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum ProxyError {
+    /// The path could not be resolved in the object.
+    #[error("invalid path '{path}': {message}")]
+    InvalidPath {
+        /// The path.
+        path: String,
+        /// Why the path could not be resolved.
+        message: String,
+    },
+    /// The value to set is of a different type than the value at the path.
+    #[error("cannot set '{path}' of type '{expected}' to a value of type '{found}'")]
+    MismatchedType {
+        /// The path.
+        path: String,
+        /// The type of the value at the path.
+        expected: String,
+        /// The type of the new value.
+        found: String,
+    },
+    /// The value could not be applied.
+    #[error(transparent)]
+    Apply(#[from] ApplyError),
+    /// A recorded dynamic value could not be converted to its concrete type.
+    #[error(transparent)]
+    FromDynamic(#[from] FromDynamicError),
+}
+
+/// Errors from [`serialization::stream`](crate::serialization::stream).
+#[derive(Debug, thiserror::Error)]
+pub enum StreamError {
+    /// The underlying reader or writer failed.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// An object could not be serialized or deserialized.
+    #[error("failed to encode or decode object: {0}")]
+    Encoding(String),
+    /// The stream ended in the middle of a frame.
+    #[error("stream ended in the middle of a frame")]
+    Truncated,
+    /// An encoded object is larger than the maximum frame size (4 GiB).
+    #[error("object is too large for a single frame")]
+    FrameTooLarge,
+    /// A frame is compressed with an unknown codec, or a codec whose crate
+    /// feature is not enabled.
+    #[error("unsupported frame codec {0}")]
+    UnsupportedCodec(u8),
+    /// A frame could not be compressed or decompressed.
+    #[error("failed to compress or decompress frame: {0}")]
+    Compression(String),
+}
+
+/// Error from `downcast_checked()` on trait objects of dynamic traits (see
+/// [`impl_dynamic_trait!`](crate::impl_dynamic_trait)), giving back the
+/// original trait object.
+#[derive(Debug, thiserror::Error)]
+#[error("expected a value of type '{expected}', found '{actual}'")]
+pub struct DowncastError<T> {
+    /// The type name of the requested type.
+    pub expected: &'static str,
+    /// The type name of the underlying value.
+    pub actual: String,
+    /// The original trait object.
+    pub object: T,
+}
+
+impl<T> DowncastError<T> {
+    /// Get back the original trait object.
+    pub fn into_inner(self) -> T {
+        self.object
+    }
+}
+
+/// A value that failed a validation rule. See
+/// [`validate_deep()`](crate::validate_deep).
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("invalid value at '{path}': {message}")]
+pub struct ValidationError {
+    /// The location of the invalid value, in the syntax of
+    /// [`bevy_reflect::GetPath`].
+    pub path: String,
+    /// Why the value is invalid.
+    pub message: String,
+}
+
+impl ValidationError {
+    /// Create an error for the validated value itself.
+    pub fn new(message: impl Into<String>) -> Self {
+        ValidationError {
+            path: String::new(),
+            message: message.into(),
+        }
+    }
+
+    /// Create an error for a value at `path`, relative to the validated
+    /// value.
+    pub fn at(path: impl Into<String>, message: impl Into<String>) -> Self {
+        ValidationError {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Errors from
+/// [`check_roundtrip()`](crate::serialization::testing::check_roundtrip).
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum RoundtripError {
+    /// The value could not be serialized.
+    #[error("failed to serialize to {format}: {message}")]
+    Serialize {
+        /// The format of the round trip.
+        format: &'static str,
+        /// The error message of the serializer.
+        message: String,
+    },
+    /// The serialized value could not be deserialized.
+    #[error("failed to deserialize from {format}: {message}")]
+    Deserialize {
+        /// The format of the round trip.
+        format: &'static str,
+        /// The error message of the deserializer.
+        message: String,
+    },
+    /// The deserialized value is not equal to the original value.
+    #[error(
+        "value changed in {format} round trip at '{path}': expected {expected}, found {actual}"
+    )]
+    Mismatch {
+        /// The format of the round trip.
+        format: &'static str,
+        /// The location of the first difference, in the syntax of
+        /// [`bevy_reflect::GetPath`].
+        path: String,
+        /// The original value at `path`, formatted with `Debug`.
+        expected: String,
+        /// The deserialized value at `path`, formatted with `Debug`.
+        actual: String,
+    },
+}
+
+/// Error from
+/// [`deserialize_arbitrary()`](crate::serialization::fuzzing::deserialize_arbitrary).
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+#[error("malformed input: {0}")]
+pub struct MalformedInput(pub String);
+
+/// Errors from the schema generators in [`schema_export`](crate::schema_export).
+#[derive(Clone, Debug, thiserror::Error, PartialEq, Eq)]
+pub enum SchemaExportError {
+    /// A type cannot be described in the schema language.
+    #[error("cannot export '{type_name}': {reason}")]
+    Unsupported {
+        /// The full name of the type.
+        type_name: String,
+        /// Why the type cannot be described.
+        reason: &'static str,
+    },
+    /// The [`StableId`](crate::serialization::StableId) of a type is not a
+    /// valid protobuf field number.
+    #[error("stable ID {id} of '{type_name}' is not a valid protobuf field number")]
+    InvalidFieldNumber {
+        /// The full name of the type.
+        type_name: String,
+        /// The stable ID.
+        id: u32,
+    },
+}
+
+/// Errors from [`Snapshot::restore()`](crate::Snapshot::restore).
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum SnapshotError {
+    /// The number of objects to restore differs from the number of objects in
+    /// the snapshot.
+    #[error("the snapshot has {snapshot} objects, but {objects} were given")]
+    CountMismatch {
+        /// The number of objects in the snapshot.
+        snapshot: usize,
+        /// The number of objects to restore.
+        objects: usize,
+    },
+    /// A copy in the snapshot does not fit the object it is restored to.
+    #[error("cannot restore object {index}: {error}")]
+    Apply {
+        /// The index of the object.
+        index: usize,
+        /// Why the copy does not fit.
+        error: ApplyError,
+    },
+}
+
+/// Errors from
+/// [`check_fixtures()`](crate::serialization::fixtures::check_fixtures).
+#[cfg(feature = "json")]
+#[derive(Debug, thiserror::Error)]
+pub enum FixtureError {
+    /// The default value of a type could not be serialized.
+    #[error("failed to serialize '{tag}': {message}")]
+    Serialize {
+        /// The type tag.
+        tag: String,
+        /// The error message of the serializer.
+        message: String,
+    },
+    /// A fixture file or the fixture directory could not be read or written.
+    #[error("failed to access '{}': {source}", path.display())]
+    Io {
+        /// The file or directory.
+        path: std::path::PathBuf,
+        /// The underlying error.
+        source: std::io::Error,
+    },
 }
@@ -0,0 +1,166 @@
+use std::fmt;
+
+use bevy_reflect::{Reflect, TypeRegistry};
+
+use crate::{supertrait, DynamicTrait};
+
+/// Why a trait object cast succeeds or fails. Created by [`explain_cast()`].
+///
+/// The [`Display`](fmt::Display) implementation describes each step of the
+/// cast in human-readable form.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CastReport {
+    /// The type name of the object.
+    pub type_name: String,
+    /// The name of the dynamic trait being cast to.
+    pub trait_name: &'static str,
+    /// True if the type of the object is registered.
+    pub registered: bool,
+    /// True if the type has the type data of the trait, i.e. has
+    /// `#[reflect(Trait)]`.
+    pub has_trait_data: bool,
+    /// True if the type implements the trait through a subtrait registered
+    /// with
+    /// [`TypeRegistryExt::register_supertrait()`](crate::TypeRegistryExt::register_supertrait).
+    pub through_subtrait: bool,
+    /// The globally registered dynamic traits that the type implements, if
+    /// the registry has been indexed (see
+    /// [`TypeRegistryExt::index_dynamic_traits()`](crate::TypeRegistryExt::index_dynamic_traits)).
+    /// Always `None` without the "inventory" crate feature.
+    pub implemented_traits: Option<Vec<&'static str>>,
+}
+
+impl CastReport {
+    /// True if the cast succeeds.
+    #[must_use]
+    pub fn succeeds(&self) -> bool {
+        self.registered && (self.has_trait_data || self.through_subtrait)
+    }
+}
+
+impl fmt::Display for CastReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        let verdict = if self.succeeds() { "can" } else { "cannot" };
+        writeln!(
+            f,
+            "'{}' {} be cast to 'dyn {}':",
+            self.type_name, verdict, self.trait_name
+        )?;
+        writeln!(f, "  - type is registered: {}", yes_no(self.registered))?;
+        if !self.registered {
+            return Ok(());
+        }
+        writeln!(
+            f,
+            "  - type has #[reflect({})]: {}",
+            self.trait_name,
+            yes_no(self.has_trait_data)
+        )?;
+        writeln!(
+            f,
+            "  - implemented through a registered subtrait: {}",
+            yes_no(self.through_subtrait)
+        )?;
+        match self.implemented_traits {
+            Some(ref traits) if traits.is_empty() => {
+                writeln!(f, "  - implemented dynamic traits: none")
+            }
+            Some(ref traits) => {
+                writeln!(f, "  - implemented dynamic traits: {}", traits.join(", "))
+            }
+            None => writeln!(
+                f,
+                "  - implemented dynamic traits: unknown (registry not indexed)"
+            ),
+        }
+    }
+}
+
+/// Describe why casting `object` to the dynamic trait `P` with `registry`
+/// succeeds or fails.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, explain_cast, reflect::TypeRegistry};
+/// #[reflect_trait]
+/// trait Tool: DowncastReflect {}
+/// impl_dynamic_trait!(Tool, ReflectTool);
+///
+/// // Note: `#[reflect(Tool)]` is missing.
+/// #[derive(Reflect)]
+/// struct Hammer;
+/// impl Tool for Hammer {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Hammer>();
+///
+/// let report = explain_cast::<dyn Tool, _>(&Hammer, &registry);
+/// assert!(report.registered);
+/// assert!(!report.has_trait_data);
+/// assert!(!report.succeeds());
+/// assert!(report.to_string().contains("type has #[reflect(Tool)]: no"));
+/// ```
+pub fn explain_cast<P, T>(object: &T, registry: &TypeRegistry) -> CastReport
+where
+    P: DynamicTrait + ?Sized,
+    T: Reflect + ?Sized,
+{
+    let object = object.as_reflect();
+    let type_id = object.as_any().type_id();
+    let registration = registry.get(type_id);
+
+    #[cfg(feature = "inventory")]
+    let implemented_traits = registration
+        .and_then(|registration| registration.data::<crate::ImplementedTraits>())
+        .map(|traits| traits.names().to_vec());
+    #[cfg(not(feature = "inventory"))]
+    let implemented_traits = None;
+
+    CastReport {
+        type_name: object.type_name().to_string(),
+        trait_name: P::reflect_name(),
+        registered: registration.is_some(),
+        has_trait_data: registration
+            .is_some_and(|registration| P::get_type_data(registration).is_some()),
+        through_subtrait: supertrait::implements_through_subtrait::<P>(registry, type_id),
+        implemented_traits,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::reflect_trait;
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect};
+
+    #[reflect_trait]
+    trait Speak: DowncastReflect {}
+    impl_dynamic_trait!(Speak, ReflectSpeak);
+
+    #[derive(Reflect)]
+    #[reflect(Speak)]
+    struct Dog;
+    impl Speak for Dog {}
+
+    #[test]
+    fn unregistered_and_registered() {
+        let mut registry = TypeRegistry::default();
+        let report = explain_cast::<dyn Speak, _>(&Dog, &registry);
+        assert!(!report.registered);
+        assert!(!report.succeeds());
+        assert_eq!(
+            report.to_string(),
+            format!(
+                "'{}' cannot be cast to 'dyn Speak':\n  - type is registered: no\n",
+                std::any::type_name::<Dog>()
+            )
+        );
+
+        registry.register::<Dog>();
+        let report = explain_cast::<dyn Speak, _>(&Dog, &registry);
+        assert!(report.has_trait_data);
+        assert!(report.succeeds());
+    }
+}
@@ -0,0 +1,484 @@
+//! A C interface to type registries, dynamic trait casting and tagged
+//! serialization, for embedding Rust code in programs written in C or C++.
+//! Requires the "ffi" crate feature.
+//!
+//! The registry and reflected objects are passed across the boundary as
+//! opaque pointers ([`RegistryHandle`] and [`ObjectHandle`]), which are
+//! created and freed by the functions of this module. Fallible functions
+//! return an [`FfiStatus`], and write their results to out-parameters.
+//!
+//! Rust types are registered by callbacks exported by the Rust side (see
+//! [`reflect_steroids_registry_register()`]), or globally with
+//! [`reflect_steroids_registry_register_global()`]. Since trait objects cannot
+//! be used from C, the C side can only ask whether an object implements a
+//! trait (see [`reflect_steroids_object_implements()`]). The Rust side exports
+//! its own functions to call the methods of the trait, and uses
+//! [`ObjectHandle::cast_mut()`] to get the trait object.
+//!
+//! ## Example
+//! ```rust
+//! # use std::ffi::c_void;
+//! # use reflect_steroids::{prelude::*, ffi::*};
+//! #[reflect_trait]
+//! trait Actor: DowncastReflect {
+//!     fn tick(&mut self);
+//! }
+//! impl_dynamic_trait!(Actor, ReflectActor, global);
+//!
+//! #[derive(Reflect, Default, serde::Serialize, serde::Deserialize)]
+//! #[reflect(Actor, Serialize, Deserialize)]
+//! struct Npc { ticks: u32 }
+//! impl Actor for Npc {
+//!     fn tick(&mut self) { self.ticks += 1; }
+//! }
+//!
+//! // Exported by the Rust side, and called by the embedding program.
+//! unsafe extern "C" fn register_types(registry: *mut RegistryHandle, _: *mut c_void) {
+//!     (*registry).registry_mut().register::<Npc>();
+//! }
+//!
+//! unsafe extern "C" fn actor_tick(registry: *const RegistryHandle, object: *mut ObjectHandle) -> bool {
+//!     match (*object).cast_mut::<dyn Actor>(&*registry) {
+//!         Ok(actor) => { actor.tick(); true }
+//!         Err(_) => false,
+//!     }
+//! }
+//!
+//! unsafe {
+//!     let registry = reflect_steroids_registry_new();
+//!     reflect_steroids_registry_register(registry, Some(register_types), std::ptr::null_mut());
+//!     reflect_steroids_registry_register_global(registry);
+//!
+//!     let json = br#"{"type":"Npc","ticks":1}"#;
+//!     let mut object = std::ptr::null_mut();
+//!     let status = reflect_steroids_object_from_json(registry, json.as_ptr(), json.len(), &mut object);
+//!     assert_eq!(status, FfiStatus::Ok);
+//!
+//!     let mut implements = false;
+//!     reflect_steroids_object_implements(registry, object, b"Actor\0".as_ptr().cast(), &mut implements);
+//!     assert!(implements);
+//!     assert!(actor_tick(registry, object));
+//!
+//!     let mut buffer = FfiBuffer::default();
+//!     reflect_steroids_object_to_json(registry, object, &mut buffer);
+//!     assert_eq!(buffer.as_slice(), br#"{"type":"Npc","ticks":2}"#);
+//!
+//!     reflect_steroids_buffer_free(buffer);
+//!     reflect_steroids_object_free(object);
+//!     reflect_steroids_registry_free(registry);
+//! }
+//! ```
+
+use std::{
+    ffi::{c_char, c_void, CStr},
+    mem::ManuallyDrop,
+    ptr, slice,
+};
+
+use bevy_reflect::{Reflect, TypeRegistry};
+
+use crate::{serialization, CastMut, CastRef, DynamicTrait, TypeError, TypeRegistryExt};
+
+/// Result of the fallible functions of this module.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FfiStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// A required pointer argument was null.
+    NullArgument = 1,
+    /// A string argument was not valid UTF-8.
+    InvalidUtf8 = 2,
+    /// An object could not be serialized or deserialized.
+    Serialization = 3,
+}
+
+/// An opaque type registry.
+pub struct RegistryHandle(TypeRegistry);
+
+impl RegistryHandle {
+    /// The wrapped registry.
+    #[must_use]
+    pub fn registry(&self) -> &TypeRegistry {
+        &self.0
+    }
+
+    /// The wrapped registry, mutably.
+    pub fn registry_mut(&mut self) -> &mut TypeRegistry {
+        &mut self.0
+    }
+}
+
+/// An opaque reflected object.
+pub struct ObjectHandle(Box<dyn Reflect>);
+
+impl ObjectHandle {
+    /// Wrap `object` in a handle to pass to C. The handle is freed by
+    /// [`reflect_steroids_object_free()`].
+    #[must_use]
+    pub fn into_raw(object: Box<dyn Reflect>) -> *mut ObjectHandle {
+        Box::into_raw(Box::new(ObjectHandle(object)))
+    }
+
+    /// The wrapped object.
+    #[must_use]
+    pub fn object(&self) -> &dyn Reflect {
+        &*self.0
+    }
+
+    /// The wrapped object, mutably.
+    pub fn object_mut(&mut self) -> &mut dyn Reflect {
+        &mut *self.0
+    }
+
+    /// Cast the wrapped object to the dynamic trait `P`, using `registry`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the type of the object is not registered, or does not
+    /// implement `P`.
+    pub fn cast_ref<P: DynamicTrait + ?Sized>(
+        &self,
+        registry: &RegistryHandle,
+    ) -> Result<&P, TypeError> {
+        self.object()
+            .try_cast_ref_with_registry(registry.registry())
+    }
+
+    /// Cast the wrapped object to the dynamic trait `P` mutably, using
+    /// `registry`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the type of the object is not registered, or does not
+    /// implement `P`.
+    pub fn cast_mut<P: DynamicTrait + ?Sized>(
+        &mut self,
+        registry: &RegistryHandle,
+    ) -> Result<&mut P, TypeError> {
+        self.object_mut()
+            .try_cast_mut_with_registry(registry.registry())
+    }
+}
+
+/// A byte buffer allocated by Rust, which is freed by
+/// [`reflect_steroids_buffer_free()`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct FfiBuffer {
+    /// The contents, or null for an empty buffer.
+    pub data: *mut u8,
+    /// The length of the contents.
+    pub len: usize,
+    /// The size of the allocation.
+    pub capacity: usize,
+}
+
+impl FfiBuffer {
+    fn from_vec(bytes: Vec<u8>) -> Self {
+        let mut bytes = ManuallyDrop::new(bytes);
+        FfiBuffer {
+            data: bytes.as_mut_ptr(),
+            len: bytes.len(),
+            capacity: bytes.capacity(),
+        }
+    }
+
+    /// The contents of the buffer.
+    #[must_use]
+    pub fn as_slice(&self) -> &[u8] {
+        if self.data.is_null() {
+            &[]
+        } else {
+            // SAFETY: A non-null buffer was created by `from_vec()`.
+            unsafe { slice::from_raw_parts(self.data, self.len) }
+        }
+    }
+}
+
+impl Default for FfiBuffer {
+    fn default() -> Self {
+        FfiBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+            capacity: 0,
+        }
+    }
+}
+
+/// Create an empty type registry.
+#[no_mangle]
+pub extern "C" fn reflect_steroids_registry_new() -> *mut RegistryHandle {
+    Box::into_raw(Box::new(RegistryHandle(TypeRegistry::default())))
+}
+
+/// Free a registry created by [`reflect_steroids_registry_new()`].
+///
+/// # Safety
+///
+/// `registry` must be null, or a registry that has not been freed, and is not
+/// in use by other objects.
+#[no_mangle]
+pub unsafe extern "C" fn reflect_steroids_registry_free(registry: *mut RegistryHandle) {
+    if !registry.is_null() {
+        drop(Box::from_raw(registry));
+    }
+}
+
+/// Call `callback` with `registry` and `user_data`, for it to register types.
+///
+/// # Safety
+///
+/// `registry` must be a valid registry, and `callback` must be safe to call
+/// with `registry` and `user_data`.
+#[no_mangle]
+pub unsafe extern "C" fn reflect_steroids_registry_register(
+    registry: *mut RegistryHandle,
+    callback: Option<unsafe extern "C" fn(*mut RegistryHandle, *mut c_void)>,
+    user_data: *mut c_void,
+) -> FfiStatus {
+    match callback {
+        Some(callback) if !registry.is_null() => {
+            callback(registry, user_data);
+            FfiStatus::Ok
+        }
+        _ => FfiStatus::NullArgument,
+    }
+}
+
+/// Register all globally registered types, and index the dynamic traits they
+/// implement (see [`TypeRegistryExt::register_global_types()`]).
+///
+/// # Safety
+///
+/// `registry` must be a valid registry.
+#[no_mangle]
+pub unsafe extern "C" fn reflect_steroids_registry_register_global(
+    registry: *mut RegistryHandle,
+) -> FfiStatus {
+    let Some(registry) = registry.as_mut() else {
+        return FfiStatus::NullArgument;
+    };
+    registry.0.register_global_types();
+    FfiStatus::Ok
+}
+
+/// Free an object.
+///
+/// # Safety
+///
+/// `object` must be null, or an object that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn reflect_steroids_object_free(object: *mut ObjectHandle) {
+    if !object.is_null() {
+        drop(Box::from_raw(object));
+    }
+}
+
+/// Write to `out` whether `object` implements the globally registered dynamic
+/// trait named by the NUL-terminated string `trait_name` (see
+/// [`TypeRegistryExt::implementors_of()`]).
+///
+/// # Safety
+///
+/// `registry` and `object` must be valid, `trait_name` must be a
+/// NUL-terminated string, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn reflect_steroids_object_implements(
+    registry: *const RegistryHandle,
+    object: *const ObjectHandle,
+    trait_name: *const c_char,
+    out: *mut bool,
+) -> FfiStatus {
+    let (Some(registry), Some(object), false, false) = (
+        registry.as_ref(),
+        object.as_ref(),
+        trait_name.is_null(),
+        out.is_null(),
+    ) else {
+        return FfiStatus::NullArgument;
+    };
+    let Ok(trait_name) = CStr::from_ptr(trait_name).to_str() else {
+        return FfiStatus::InvalidUtf8;
+    };
+    let type_id = object.object().as_any().type_id();
+    *out = registry
+        .0
+        .implementors_of(trait_name)
+        .iter()
+        .any(|registration| registration.type_id() == type_id);
+    FfiStatus::Ok
+}
+
+/// Serialize `object` to tagged JSON (see
+/// [`to_json_tagged()`](serialization::to_json_tagged)), and write the result
+/// to `out`. The buffer must be freed by [`reflect_steroids_buffer_free()`].
+///
+/// # Safety
+///
+/// `registry` and `object` must be valid, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn reflect_steroids_object_to_json(
+    registry: *const RegistryHandle,
+    object: *const ObjectHandle,
+    out: *mut FfiBuffer,
+) -> FfiStatus {
+    let (Some(registry), Some(object), false) = (registry.as_ref(), object.as_ref(), out.is_null())
+    else {
+        return FfiStatus::NullArgument;
+    };
+    match serialization::to_json_tagged(object.object(), &registry.0) {
+        Ok(json) => {
+            out.write(FfiBuffer::from_vec(json.into_bytes()));
+            FfiStatus::Ok
+        }
+        Err(_) => FfiStatus::Serialization,
+    }
+}
+
+/// Deserialize an object from the tagged JSON in the `len` bytes at `data`
+/// (see [`from_json_tagged()`](serialization::from_json_tagged)), and write
+/// it to `out`. The object must be freed by
+/// [`reflect_steroids_object_free()`].
+///
+/// # Safety
+///
+/// `registry` must be valid, `data` must be valid for reads of `len` bytes,
+/// and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn reflect_steroids_object_from_json(
+    registry: *const RegistryHandle,
+    data: *const u8,
+    len: usize,
+    out: *mut *mut ObjectHandle,
+) -> FfiStatus {
+    let (Some(registry), false, false) = (registry.as_ref(), data.is_null(), out.is_null()) else {
+        return FfiStatus::NullArgument;
+    };
+    let Ok(json) = std::str::from_utf8(slice::from_raw_parts(data, len)) else {
+        return FfiStatus::InvalidUtf8;
+    };
+    match serialization::from_json_tagged(json, &registry.0) {
+        Ok(object) => {
+            out.write(ObjectHandle::into_raw(object));
+            FfiStatus::Ok
+        }
+        Err(_) => FfiStatus::Serialization,
+    }
+}
+
+/// Free a buffer written by a function of this module.
+///
+/// # Safety
+///
+/// `buffer` must have been written by a function of this module, and not have
+/// been freed.
+#[no_mangle]
+pub unsafe extern "C" fn reflect_steroids_buffer_free(buffer: FfiBuffer) {
+    if !buffer.data.is_null() {
+        drop(Vec::from_raw_parts(
+            buffer.data,
+            buffer.len,
+            buffer.capacity,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{reflect_trait, ReflectDeserialize, ReflectSerialize};
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect};
+
+    #[reflect_trait]
+    trait Door: DowncastReflect {
+        fn open(&mut self);
+    }
+    impl_dynamic_trait!(Door, ReflectDoor, global);
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Door, Serialize, Deserialize)]
+    struct Gate {
+        open: bool,
+    }
+    impl Door for Gate {
+        fn open(&mut self) {
+            self.open = true;
+        }
+    }
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Serialize, Deserialize)]
+    struct Wall;
+
+    unsafe extern "C" fn register(registry: *mut RegistryHandle, user_data: *mut c_void) {
+        *user_data.cast::<u32>() += 1;
+        let registry = (*registry).registry_mut();
+        registry.register::<Gate>();
+        registry.register::<Wall>();
+    }
+
+    #[test]
+    fn round_trip_and_cast() {
+        unsafe {
+            let registry = reflect_steroids_registry_new();
+            let mut calls = 0u32;
+            let status = reflect_steroids_registry_register(
+                registry,
+                Some(register),
+                ptr::addr_of_mut!(calls).cast(),
+            );
+            assert_eq!(status, FfiStatus::Ok);
+            assert_eq!(calls, 1);
+            reflect_steroids_registry_register_global(registry);
+
+            let mut gate = ptr::null_mut();
+            let json = br#"{"type":"Gate","open":false}"#;
+            let status = reflect_steroids_object_from_json(
+                registry,
+                json.as_ptr(),
+                json.len(),
+                &raw mut gate,
+            );
+            assert_eq!(status, FfiStatus::Ok);
+            let wall = ObjectHandle::into_raw(Box::new(Wall));
+
+            let mut implements = true;
+            let name = c"Door".as_ptr();
+            reflect_steroids_object_implements(registry, wall, name, &raw mut implements);
+            assert!(!implements);
+            reflect_steroids_object_implements(registry, gate, name, &raw mut implements);
+            assert!(implements);
+
+            (*gate).cast_mut::<dyn Door>(&*registry).unwrap().open();
+            assert!((*wall).cast_ref::<dyn Door>(&*registry).is_err());
+
+            let mut buffer = FfiBuffer::default();
+            let status = reflect_steroids_object_to_json(registry, gate, &raw mut buffer);
+            assert_eq!(status, FfiStatus::Ok);
+            assert_eq!(buffer.as_slice(), br#"{"type":"Gate","open":true}"#);
+            reflect_steroids_buffer_free(buffer);
+
+            let mut object = ptr::null_mut();
+            let invalid = b"{\"type\":";
+            assert_eq!(
+                reflect_steroids_object_from_json(
+                    registry,
+                    invalid.as_ptr(),
+                    invalid.len(),
+                    &raw mut object
+                ),
+                FfiStatus::Serialization
+            );
+            assert_eq!(
+                reflect_steroids_object_to_json(ptr::null(), gate, &mut FfiBuffer::default()),
+                FfiStatus::NullArgument
+            );
+
+            reflect_steroids_object_free(gate);
+            reflect_steroids_object_free(wall);
+            reflect_steroids_registry_free(registry);
+        }
+    }
+}
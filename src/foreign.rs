@@ -0,0 +1,273 @@
+use std::any::TypeId;
+
+use bevy_reflect::{GetTypeRegistration, Reflect, TypeRegistration, TypeRegistry};
+
+use crate::{cast_policy::check_cast, missing_type::report_missing, register_hooks, TypeError};
+
+/// Adapter for casting reflected objects to trait objects of traits that do
+/// not have [`DowncastReflect`] as a supertrait, such as traits from other
+/// crates, like `dyn std::error::Error`.
+///
+/// `ForeignDyn<dyn Trait>` is the type data holding the casts of one type to
+/// `dyn Trait`, and its `try_cast_*` functions look it up in a registry.
+/// Since such trait objects cannot be cast back to `dyn Reflect`, they do not
+/// implement [`DynamicTrait`](crate::DynamicTrait), and [`Cast`](crate::Cast)
+/// and serialization are not available for them.
+///
+/// Register the type data with [`register_foreign_dyn!`](crate::register_foreign_dyn).
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, register_foreign_dyn, reflect::TypeRegistry, ForeignDyn};
+/// #[derive(Debug, Reflect)]
+/// struct ParseError { line: u32 }
+///
+/// impl std::fmt::Display for ParseError {
+///     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+///         write!(f, "parse error on line {}", self.line)
+///     }
+/// }
+/// impl std::error::Error for ParseError {}
+///
+/// let mut registry = TypeRegistry::default();
+/// register_foreign_dyn!(&mut registry, ParseError as dyn std::error::Error);
+///
+/// let object: &dyn Reflect = &ParseError { line: 3 };
+/// let error = ForeignDyn::<dyn std::error::Error>::try_cast_ref(object, &registry).unwrap();
+/// assert_eq!(error.to_string(), "parse error on line 3");
+/// ```
+// Named like the fields of the type data generated by `#[reflect_trait]`.
+#[allow(clippy::struct_field_names)]
+pub struct ForeignDyn<P: ?Sized + 'static> {
+    get_func: fn(&dyn Reflect) -> Option<&P>,
+    get_mut_func: fn(&mut dyn Reflect) -> Option<&mut P>,
+    get_boxed_func: GetBoxedFn<P>,
+}
+
+type GetBoxedFn<P> = fn(Box<dyn Reflect>) -> Result<Box<P>, Box<dyn Reflect>>;
+
+impl<P: ?Sized + 'static> ForeignDyn<P> {
+    /// Type data from the casts of one type. The functions return `None` or
+    /// give back the box if the object is not of that type.
+    ///
+    /// [`register_foreign_dyn!`](crate::register_foreign_dyn) creates the
+    /// functions for a type.
+    pub fn new(
+        get_func: fn(&dyn Reflect) -> Option<&P>,
+        get_mut_func: fn(&mut dyn Reflect) -> Option<&mut P>,
+        get_boxed_func: GetBoxedFn<P>,
+    ) -> Self {
+        ForeignDyn {
+            get_func,
+            get_mut_func,
+            get_boxed_func,
+        }
+    }
+
+    /// Cast a reference.
+    pub fn get<'a>(&self, reflect_value: &'a dyn Reflect) -> Option<&'a P> {
+        (self.get_func)(reflect_value)
+    }
+
+    /// Cast a mutable reference.
+    pub fn get_mut<'a>(&self, reflect_value: &'a mut dyn Reflect) -> Option<&'a mut P> {
+        (self.get_mut_func)(reflect_value)
+    }
+
+    /// Cast a box.
+    ///
+    /// # Errors
+    ///
+    /// Gives back the box if it is not of the type of the casts.
+    pub fn get_boxed(&self, reflect_value: Box<dyn Reflect>) -> Result<Box<P>, Box<dyn Reflect>> {
+        (self.get_boxed_func)(reflect_value)
+    }
+
+    /// Register `T` (if it isn't already) and insert `self` as type data for
+    /// it. The casts should be the casts of `T`.
+    // `T` is registered right before it is looked up, so this cannot panic.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn register<T: Reflect + GetTypeRegistration>(self, registry: &mut TypeRegistry) {
+        if registry.get(TypeId::of::<T>()).is_none() {
+            registry.register::<T>();
+        }
+        registry
+            .get_mut(TypeId::of::<T>())
+            .expect("type was just registered")
+            .insert(self);
+        register_hooks::run_hooks(registry);
+    }
+}
+
+impl<P: ?Sized + 'static> Clone for ForeignDyn<P> {
+    fn clone(&self) -> Self {
+        ForeignDyn { ..*self }
+    }
+}
+
+/// Register the type `T` with a [`ForeignDyn`] adapter casting it to the
+/// trait object `dyn Trait`: `register_foreign_dyn!(&mut registry, T as dyn
+/// Trait)`. `T` must implement `Trait`.
+#[macro_export]
+macro_rules! register_foreign_dyn {
+    ($registry:expr, $ty:ty as $trait_ty:ty) => {
+        $crate::ForeignDyn::<$trait_ty>::new(
+            |reflect_value| {
+                reflect_value
+                    .downcast_ref::<$ty>()
+                    .map(|value| value as &$trait_ty)
+            },
+            |reflect_value| {
+                reflect_value
+                    .downcast_mut::<$ty>()
+                    .map(|value| value as &mut $trait_ty)
+            },
+            |reflect_value| {
+                reflect_value
+                    .downcast::<$ty>()
+                    .map(|value| value as Box<$trait_ty>)
+            },
+        )
+        .register::<$ty>($registry)
+    };
+}
+
+/// Find the [`ForeignDyn`] type data for casting the type with the given
+/// [`TypeId`] to `P`.
+fn adapter_for<'a, P: ?Sized + 'static>(
+    type_id: TypeId,
+    type_name: &str,
+    registry: &'a TypeRegistry,
+) -> Result<&'a ForeignDyn<P>, TypeError> {
+    let registration: &TypeRegistration = registry
+        .get(type_id)
+        .ok_or_else(|| report_missing(TypeError::UnregisteredType(type_name.to_string().into())))?;
+    let trait_name = std::any::type_name::<P>();
+    check_cast(registry, trait_name, registration)?;
+    registration.data::<ForeignDyn<P>>().ok_or_else(|| {
+        TypeError::UnregisteredTrait(registration.short_name().to_string().into(), trait_name)
+    })
+}
+
+impl<P: ?Sized + 'static> ForeignDyn<P> {
+    /// Cast `object` to `P`, with the adapter registered for its type in
+    /// `registry`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the type of `object` is not registered, has no adapter for
+    /// `P`, or may not be cast to `P` (see [`CastPolicy`](crate::CastPolicy)).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the adapter does not cast the type it is registered for.
+    pub fn try_cast_ref<'a>(
+        object: &'a dyn Reflect,
+        registry: &TypeRegistry,
+    ) -> Result<&'a P, TypeError> {
+        let adapter = adapter_for::<P>(object.as_any().type_id(), object.type_name(), registry)?;
+        Ok(adapter.get(object).expect("type mismatch"))
+    }
+
+    /// Cast `object` to `P`, with the adapter registered for its type in
+    /// `registry`.
+    ///
+    /// # Errors
+    ///
+    /// Fails in the same cases as [`try_cast_ref()`](Self::try_cast_ref).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the adapter does not cast the type it is registered for.
+    pub fn try_cast_mut<'a>(
+        object: &'a mut dyn Reflect,
+        registry: &TypeRegistry,
+    ) -> Result<&'a mut P, TypeError> {
+        let adapter = adapter_for::<P>(object.as_any().type_id(), object.type_name(), registry)?;
+        Ok(adapter.get_mut(object).expect("type mismatch"))
+    }
+
+    /// Cast `object` to `P`, with the adapter registered for its type in
+    /// `registry`.
+    ///
+    /// # Errors
+    ///
+    /// Fails in the same cases as [`try_cast_ref()`](Self::try_cast_ref), and
+    /// gives back `object`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the adapter does not cast the type it is registered for.
+    pub fn try_cast_box(
+        object: Box<dyn Reflect>,
+        registry: &TypeRegistry,
+    ) -> Result<Box<P>, (Box<dyn Reflect>, TypeError)> {
+        match adapter_for::<P>(object.as_any().type_id(), object.type_name(), registry) {
+            Ok(adapter) => Ok(adapter.get_boxed(object).expect("type mismatch")),
+            Err(err) => Err((object, err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use super::*;
+
+    trait Shape {
+        fn area(&self) -> f32;
+        fn scale(&mut self, factor: f32);
+    }
+
+    #[derive(Reflect)]
+    struct Square(f32);
+
+    impl Shape for Square {
+        fn area(&self) -> f32 {
+            self.0 * self.0
+        }
+
+        fn scale(&mut self, factor: f32) {
+            self.0 *= factor;
+        }
+    }
+
+    impl fmt::Display for Square {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "square of side {}", self.0)
+        }
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn cast_to_foreign_traits() {
+        let mut registry = TypeRegistry::empty();
+        register_foreign_dyn!(&mut registry, Square as dyn Shape);
+        register_foreign_dyn!(&mut registry, Square as dyn fmt::Display + Send);
+
+        let mut square = Square(2.0);
+        let shape = ForeignDyn::<dyn Shape>::try_cast_mut(&mut square, &registry).unwrap();
+        shape.scale(2.0);
+        assert_eq!(shape.area(), 16.0);
+
+        let boxed: Box<dyn Reflect> = Box::new(square);
+        let display =
+            ForeignDyn::<dyn fmt::Display + Send>::try_cast_box(boxed, &registry).unwrap();
+        assert_eq!(display.to_string(), "square of side 4");
+
+        let err = ForeignDyn::<dyn Shape>::try_cast_ref(&1.0f32, &registry)
+            .err()
+            .unwrap();
+        assert!(matches!(err, TypeError::UnregisteredType(_)));
+        registry.register::<f32>();
+        let (object, err) = ForeignDyn::<dyn Shape>::try_cast_box(Box::new(1.0f32), &registry)
+            .err()
+            .unwrap();
+        assert!(object.is::<f32>());
+        assert_eq!(
+            err,
+            TypeError::UnregisteredTrait("f32".into(), std::any::type_name::<dyn Shape>())
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use bevy_reflect::{
+    std_traits::ReflectDefault, FromReflect, FromType, Reflect, TypeRegistration, TypeRegistry,
+};
+
+use crate::{try_apply_dyn, Cast, DynamicTrait, FromDynamicError, TypeError, TypeRegistryExt};
+
+/// Type data for building a value of a concrete type from a dynamic value,
+/// using [`FromReflect`]. Register it with `#[reflect(FromDynamic)]` on types
+/// that derive `FromReflect`.
+#[derive(Clone)]
+pub struct ReflectFromDynamic {
+    from_reflect: fn(&dyn Reflect) -> Option<Box<dyn Reflect>>,
+}
+
+impl ReflectFromDynamic {
+    /// Build a value from `dynamic` with [`FromReflect::from_reflect()`].
+    pub fn from_reflect(&self, dynamic: &dyn Reflect) -> Option<Box<dyn Reflect>> {
+        (self.from_reflect)(dynamic)
+    }
+}
+
+impl<T: FromReflect> FromType<T> for ReflectFromDynamic {
+    fn from_type() -> Self {
+        ReflectFromDynamic {
+            from_reflect: |dynamic| {
+                T::from_reflect(dynamic).map(|value| Box::new(value) as Box<dyn Reflect>)
+            },
+        }
+    }
+}
+
+/// Build a live trait object from a dynamic value, such as a
+/// [`DynamicStruct`](bevy_reflect::DynamicStruct) constructed by an editor.
+///
+/// The concrete type is looked up in `registry` by the name of `dynamic` (its
+/// full type name or its short name). If the type has [`ReflectFromDynamic`]
+/// type data, the value is built with [`FromReflect`]. Otherwise, if it has
+/// [`ReflectDefault`] type data, `dynamic` is applied to the default value
+/// (see [`try_apply_dyn()`]), so missing fields keep their defaults. The value
+/// is then cast to `P`.
+///
+/// # Errors
+///
+/// Fails if the type is not registered, has neither type data, or cannot be
+/// cast to `P`, or if `dynamic` does not fit the type.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, from_dynamic, ReflectFromDynamic, reflect::{DynamicStruct, TypeRegistry}};
+/// #[reflect_trait]
+/// trait Widget: DowncastReflect {
+///     fn label(&self) -> &str;
+/// }
+/// impl_dynamic_trait!(Widget, ReflectWidget);
+///
+/// #[derive(Reflect, FromReflect)]
+/// #[reflect(Widget, FromDynamic)]
+/// struct Button { label: String, width: u32 }
+/// impl Widget for Button {
+///     fn label(&self) -> &str { &self.label }
+/// }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Button>();
+///
+/// let mut form = DynamicStruct::default();
+/// form.set_name(std::any::type_name::<Button>().to_string());
+/// form.insert("label", String::from("OK"));
+/// form.insert("width", 80u32);
+///
+/// let widget = from_dynamic::<dyn Widget>(&form, &registry).unwrap();
+/// assert_eq!(widget.label(), "OK");
+/// ```
+pub fn from_dynamic<P: DynamicTrait + ?Sized>(
+    dynamic: &dyn Reflect,
+    registry: &TypeRegistry,
+) -> Result<Box<P>, FromDynamicError> {
+    let type_name = dynamic.type_name();
+    let registration = resolve_name(registry, type_name)
+        .ok_or_else(|| TypeError::UnregisteredType(type_name.to_string().into()))?;
+    build_as(registration, Some(dynamic), registry)
+}
+
+/// Build a value of the type of `registration` as in [`from_dynamic()`], or its
+/// default value if `dynamic` is `None`, and cast it to `P`.
+pub(crate) fn build_as<P: DynamicTrait + ?Sized>(
+    registration: &TypeRegistration,
+    dynamic: Option<&dyn Reflect>,
+    registry: &TypeRegistry,
+) -> Result<Box<P>, FromDynamicError> {
+    let from_dynamic = registration.data::<ReflectFromDynamic>();
+    let default = registration.data::<ReflectDefault>();
+    let value = match (dynamic, from_dynamic, default) {
+        (Some(dynamic), Some(from_dynamic), _) => from_dynamic
+            .from_reflect(dynamic)
+            .ok_or_else(|| FromDynamicError::Conversion(registration.type_name().to_string()))?,
+        (dynamic, _, Some(default)) => {
+            let mut value = default.default();
+            if let Some(dynamic) = dynamic {
+                try_apply_dyn(&mut *value, dynamic)?;
+            }
+            value
+        }
+        (dynamic, _, None) => {
+            return Err(TypeError::UnregisteredTrait(
+                registration.type_name().to_string().into(),
+                if dynamic.is_some() {
+                    "FromDynamic"
+                } else {
+                    "Default"
+                },
+            )
+            .into())
+        }
+    };
+
+    Ok(Cast::<P>::try_cast_with_registry(value, registry)?)
+}
+
+/// Clone `value` as a value of its concrete type, if the type has
+/// [`ReflectFromDynamic`] type data in the current registry (see
+/// [`TypeRegistryExt::set_current()`](crate::TypeRegistryExt::set_current)).
+/// Otherwise, the clone is a dynamic value (see [`Reflect::clone_value()`]),
+/// which cannot be cast to the traits of the type.
+///
+/// `Box<dyn MyTrait>` implements [`Reflect::clone_value()`] with this, so that
+/// clones of trait objects, and of values that contain them, can be cast back
+/// to `dyn MyTrait`.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, clone_concrete, ReflectFromDynamic, reflect::TypeRegistry};
+/// #[reflect_trait]
+/// trait Shape: DowncastReflect {}
+/// impl_dynamic_trait!(Shape, ReflectShape);
+///
+/// #[derive(Reflect, FromReflect)]
+/// #[reflect(Shape, FromDynamic)]
+/// struct Circle { radius: f32 }
+/// impl Shape for Circle {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Circle>();
+///
+/// let shape: Box<dyn Shape> = Box::new(Circle { radius: 1.0 });
+/// assert!(!clone_concrete(&*shape).is::<Circle>());
+/// registry.set_current(|| {
+///     assert!(clone_concrete(&*shape).is::<Circle>());
+///     assert!(Reflect::clone_value(&shape).is::<Circle>());
+/// });
+/// ```
+pub fn clone_concrete(value: &dyn Reflect) -> Box<dyn Reflect> {
+    let type_id = value.as_any().type_id();
+    let concrete = TypeRegistry::try_with_current(|registry| {
+        registry
+            .get_type_data::<ReflectFromDynamic>(type_id)
+            .and_then(|from_dynamic| from_dynamic.from_reflect(value))
+    });
+    match concrete {
+        Ok(Some(concrete)) => concrete,
+        _ => value.clone_value(),
+    }
+}
+
+/// Build a trait object from `value` as in [`from_dynamic()`], with the
+/// current registry. Used by the `FromReflect` implementation for
+/// `Box<dyn MyTrait>` generated by
+/// [`impl_dynamic_trait!`](crate::impl_dynamic_trait), so that types
+/// containing trait objects, such as `Option<Box<dyn MyTrait>>`, can be built
+/// and applied to.
+#[doc(hidden)]
+pub fn __box_from_reflect<P: DynamicTrait + ?Sized>(value: &dyn Reflect) -> Option<Box<P>> {
+    TypeRegistry::try_with_current(|registry| from_dynamic(value, registry).ok())
+        .ok()
+        .flatten()
+}
+
+/// Find a registered type by its full type name or its short name.
+pub(crate) fn resolve_name<'a>(
+    registry: &'a TypeRegistry,
+    name: &str,
+) -> Option<&'a TypeRegistration> {
+    registry
+        .get_with_name(name)
+        .or_else(|| registry.get_with_short_name(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{
+        reflect_trait, DynamicStruct, FromReflect, ReflectDeserialize, ReflectRef, ReflectSerialize,
+    };
+
+    use super::*;
+    use crate::{impl_dynamic_trait, CastRef, DowncastReflect};
+
+    #[reflect_trait]
+    trait Shape: DowncastReflect {
+        fn area(&self) -> f32;
+    }
+    impl_dynamic_trait!(Shape, ReflectShape);
+
+    #[derive(Reflect)]
+    #[reflect(Shape, Default)]
+    struct Rect {
+        width: f32,
+        height: f32,
+    }
+    impl Default for Rect {
+        fn default() -> Self {
+            Rect {
+                width: 1.0,
+                height: 1.0,
+            }
+        }
+    }
+    impl Shape for Rect {
+        fn area(&self) -> f32 {
+            self.width * self.height
+        }
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn from_default() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Rect>();
+
+        let mut dynamic = DynamicStruct::default();
+        dynamic.set_name("Rect".to_string());
+        dynamic.insert("width", 3.0f32);
+        let shape = from_dynamic::<dyn Shape>(&dynamic, &registry).unwrap();
+        assert_eq!(shape.area(), 3.0);
+
+        dynamic.insert("height", 2u8);
+        assert!(matches!(
+            from_dynamic::<dyn Shape>(&dynamic, &registry),
+            Err(FromDynamicError::Apply(_))
+        ));
+
+        dynamic.set_name("Circle".to_string());
+        assert_eq!(
+            from_dynamic::<dyn Shape>(&dynamic, &registry).unwrap_err(),
+            FromDynamicError::Type(TypeError::UnregisteredType("Circle".into()))
+        );
+    }
+
+    #[derive(Reflect, FromReflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Shape, FromDynamic, Serialize, Deserialize)]
+    struct Square {
+        side: f32,
+    }
+    impl Shape for Square {
+        fn area(&self) -> f32 {
+            self.side * self.side
+        }
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn cloned_trait_objects_stay_castable() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Square>();
+        registry.register::<Rect>();
+
+        let shapes: Vec<Box<dyn Shape>> =
+            vec![Box::new(Square { side: 2.0 }), Box::new(Rect::default())];
+        let clone = registry.set_current(|| shapes.clone_value());
+        let ReflectRef::List(clone) = clone.reflect_ref() else {
+            panic!("not a list");
+        };
+        let square = clone
+            .get(0)
+            .unwrap()
+            .try_cast_ref_with_registry::<dyn Shape>(&registry)
+            .unwrap();
+        assert_eq!(square.area(), 4.0);
+        // Without `FromDynamic`, the clone is dynamic.
+        assert!(!clone.get(1).unwrap().is::<Rect>());
+    }
+
+    #[derive(Reflect, FromReflect, serde::Serialize, serde::Deserialize)]
+    struct Outline {
+        shape: Option<Box<dyn Shape>>,
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn optional_trait_object_fields() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Square>();
+        registry.register::<Rect>();
+
+        let outline = Outline {
+            shape: Some(Box::new(Square { side: 3.0 })),
+        };
+        assert!(<Box<dyn Shape>>::from_reflect(outline.shape.as_ref().unwrap()).is_none());
+
+        registry.set_current(|| {
+            let copy = Outline::from_reflect(&outline).unwrap();
+            assert_eq!(copy.shape.unwrap().area(), 9.0);
+
+            // Rect is built from its default value.
+            let mut empty = Outline { shape: None };
+            empty.apply(&Outline {
+                shape: Some(Box::new(Rect {
+                    width: 2.0,
+                    height: 3.0,
+                })),
+            });
+            assert_eq!(empty.shape.as_ref().unwrap().area(), 6.0);
+
+            let json = serde_json::to_string(&outline).unwrap();
+            assert_eq!(json, r#"{"shape":{"type":"Square","side":3.0}}"#);
+            empty.shape = None;
+            assert_eq!(serde_json::to_string(&empty).unwrap(), r#"{"shape":null}"#);
+            let outline: Outline = serde_json::from_str(&json).unwrap();
+            assert!(outline.shape.unwrap().is::<Square>());
+        });
+    }
+}
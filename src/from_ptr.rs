@@ -0,0 +1,356 @@
+use std::{any::Any, any::TypeId, ptr::NonNull};
+
+use bevy_ptr::{Ptr, PtrMut};
+use bevy_reflect::{Reflect, ReflectFromPtr, TypeRegistry};
+
+use crate::{CastMut, CastRef, DynamicTrait, TypeError, TypeRegistryExt};
+
+/// Casting interface for raw trait object pointers, such as pointers into
+/// memory owned by foreign code.
+///
+/// ## Example
+/// ```rust
+/// # use std::ptr::NonNull;
+/// # use reflect_steroids::{prelude::*, reflect::{Reflect, TypeRegistry}, CastPtr};
+/// #[reflect_trait]
+/// trait Counter: DowncastReflect {
+///     fn increment(&mut self);
+/// }
+/// impl_dynamic_trait!(Counter, ReflectCounter);
+///
+/// #[derive(Reflect)]
+/// #[reflect(Counter)]
+/// struct Clicks(u32);
+/// impl Counter for Clicks {
+///     fn increment(&mut self) { self.0 += 1; }
+/// }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Clicks>();
+///
+/// let ptr: NonNull<dyn Reflect> = NonNull::from(Box::leak(Box::new(Clicks(0))));
+/// unsafe {
+///     let counter = ptr.try_cast_ptr_with_registry::<dyn Counter>(&registry).unwrap();
+///     (*counter.as_ptr()).increment();
+///     assert_eq!(ptr.as_ref().downcast_ref::<Clicks>().unwrap().0, 1);
+///     drop(Box::from_raw(ptr.as_ptr()));
+/// }
+/// ```
+pub trait CastPtr: Sized {
+    /// Try casting `self` using type information from `registry`.
+    ///
+    /// The returned pointer points into the same object as `self`. An
+    /// exclusive reference to the pointee is formed for the duration of the
+    /// cast only, because looking up the type of the pointee and casting it
+    /// go through its vtable. No reference outlives the call.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the type of the pointee is not registered, or does not
+    /// implement `P`.
+    ///
+    /// # Safety
+    ///
+    /// `self` must point to a valid value, which must not be borrowed by
+    /// anything else, or accessed from another thread, during the call.
+    unsafe fn try_cast_ptr_with_registry<P: DynamicTrait + ?Sized>(
+        self,
+        registry: &TypeRegistry,
+    ) -> Result<NonNull<P>, TypeError>;
+
+    /// Try casting `self` using the current global type registry (see
+    /// [`TypeRegistryExt::set_current()`]).
+    ///
+    /// # Errors
+    ///
+    /// Fails if no registry has been set, or if the cast fails.
+    ///
+    /// # Safety
+    ///
+    /// See [`try_cast_ptr_with_registry()`](CastPtr::try_cast_ptr_with_registry).
+    unsafe fn try_cast_ptr<P: DynamicTrait + ?Sized>(self) -> Result<NonNull<P>, TypeError> {
+        TypeRegistry::try_with_current(|registry| self.try_cast_ptr_with_registry(registry))?
+    }
+}
+
+impl CastPtr for NonNull<dyn Reflect> {
+    unsafe fn try_cast_ptr_with_registry<P: DynamicTrait + ?Sized>(
+        self,
+        registry: &TypeRegistry,
+    ) -> Result<NonNull<P>, TypeError> {
+        // SAFETY: The caller guarantees that the pointee is valid and not
+        // borrowed. The reference ends with the cast.
+        let this: &mut dyn Reflect = &mut *self.as_ptr();
+        this.try_cast_mut_with_registry(registry).map(NonNull::from)
+    }
+}
+
+/// Casting interface for type-erased references, such as `&dyn Any` or
+/// `&(dyn Any + Send + Sync)`.
+///
+/// The concrete type of the value must be registered, and have
+/// [`ReflectFromPtr`] type data (which `#[derive(Reflect)]` registers
+/// automatically).
+///
+/// ## Example
+/// ```rust
+/// # use std::any::Any;
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, CastAny};
+/// #[reflect_trait]
+/// trait MyTrait: DowncastReflect {
+///     fn value(&self) -> i32;
+/// }
+/// impl_dynamic_trait!(MyTrait, ReflectMyTrait);
+///
+/// #[derive(Reflect)]
+/// #[reflect(MyTrait)]
+/// struct Foo(i32);
+/// impl MyTrait for Foo {
+///     fn value(&self) -> i32 { self.0 }
+/// }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Foo>();
+///
+/// let resource: Box<dyn Any + Send + Sync> = Box::new(Foo(123));
+/// let object = (&*resource).try_cast_any_with_registry::<dyn MyTrait>(&registry).unwrap();
+/// assert_eq!(object.value(), 123);
+/// ```
+pub trait CastAny<'a>: Sized + 'a {
+    /// Try casting `self` using type information from `registry`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the type of `self` is not registered, or does not implement
+    /// `P`.
+    fn try_cast_any_with_registry<P: DynamicTrait + ?Sized>(
+        self,
+        registry: &TypeRegistry,
+    ) -> Result<&'a P, TypeError>;
+
+    /// Try casting `self` using the current global type registry (see
+    /// [`TypeRegistryExt::set_current()`]).
+    ///
+    /// # Errors
+    ///
+    /// Fails if there is no current registry, or as
+    /// [`try_cast_any_with_registry()`](CastAny::try_cast_any_with_registry)
+    /// does.
+    fn try_cast_any<P: DynamicTrait + ?Sized>(self) -> Result<&'a P, TypeError> {
+        TypeRegistry::try_with_current(|registry| self.try_cast_any_with_registry(registry))?
+    }
+}
+
+/// Casting interface for type-erased mutable references. See [`CastAny`].
+pub trait CastAnyMut<'a>: Sized + 'a {
+    /// Try casting `self` using type information from `registry`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the type of `self` is not registered, or does not implement
+    /// `P`.
+    fn try_cast_any_mut_with_registry<P: DynamicTrait + ?Sized>(
+        self,
+        registry: &TypeRegistry,
+    ) -> Result<&'a mut P, TypeError>;
+
+    /// Try casting `self` using the current global type registry (see
+    /// [`TypeRegistryExt::set_current()`]).
+    ///
+    /// # Errors
+    ///
+    /// Fails if there is no current registry, or as [`try_cast_any_mut_with_reg
+    /// istry()`](CastAnyMut::try_cast_any_mut_with_registry) does.
+    fn try_cast_any_mut<P: DynamicTrait + ?Sized>(self) -> Result<&'a mut P, TypeError> {
+        TypeRegistry::try_with_current(|registry| self.try_cast_any_mut_with_registry(registry))?
+    }
+}
+
+macro_rules! impl_cast_any {
+    ($($any:ty),*) => {$(
+        impl<'a> CastAny<'a> for &'a $any {
+            fn try_cast_any_with_registry<P: DynamicTrait + ?Sized>(
+                self,
+                registry: &TypeRegistry,
+            ) -> Result<&'a P, TypeError> {
+                let type_id = Any::type_id(self);
+                // SAFETY: `type_id` is the type of the pointee.
+                unsafe {
+                    try_cast_ptr_with_registry(
+                        Ptr::new(NonNull::from(self).cast()),
+                        type_id,
+                        registry,
+                    )
+                }
+            }
+        }
+
+        impl<'a> CastAnyMut<'a> for &'a mut $any {
+            fn try_cast_any_mut_with_registry<P: DynamicTrait + ?Sized>(
+                self,
+                registry: &TypeRegistry,
+            ) -> Result<&'a mut P, TypeError> {
+                let type_id = Any::type_id(&*self);
+                // SAFETY: `type_id` is the type of the pointee.
+                unsafe {
+                    try_cast_ptr_mut_with_registry(
+                        PtrMut::new(NonNull::from(self).cast()),
+                        type_id,
+                        registry,
+                    )
+                }
+            }
+        }
+    )*};
+}
+
+impl_cast_any!(dyn Any, dyn Any + Send, dyn Any + Send + Sync);
+
+/// Cast a type-erased pointer, such as a resource or component obtained from a
+/// bevy `World` by its `ComponentId`, to the dynamic trait `P`.
+///
+/// `type_id` is the [`TypeId`] of the pointee, which is used to look up its
+/// [`ReflectFromPtr`] type data in `registry`.
+///
+/// # Safety
+///
+/// `ptr` must point to a valid value of the type identified by `type_id`.
+///
+/// # Errors
+///
+/// Fails if the type is not registered, has no [`ReflectFromPtr`] type data, or
+/// does not implement `P`.
+pub unsafe fn try_cast_ptr_with_registry<'a, P: DynamicTrait + ?Sized>(
+    ptr: Ptr<'a>,
+    type_id: TypeId,
+    registry: &TypeRegistry,
+) -> Result<&'a P, TypeError> {
+    let from_ptr = get_from_ptr(type_id, registry)?;
+    let reflect: &'a dyn Reflect = from_ptr.as_reflect_ptr(ptr);
+    reflect.try_cast_ref_with_registry(registry)
+}
+
+/// Mutable version of [`try_cast_ptr_with_registry()`].
+///
+/// # Safety
+///
+/// `ptr` must point to a valid value of the type identified by `type_id`.
+///
+/// # Errors
+///
+/// Fails if the type is not registered, has no [`ReflectFromPtr`] type data, or
+/// does not implement `P`.
+pub unsafe fn try_cast_ptr_mut_with_registry<'a, P: DynamicTrait + ?Sized>(
+    ptr: PtrMut<'a>,
+    type_id: TypeId,
+    registry: &TypeRegistry,
+) -> Result<&'a mut P, TypeError> {
+    let from_ptr = get_from_ptr(type_id, registry)?;
+    let reflect: &'a mut dyn Reflect = from_ptr.as_reflect_ptr_mut(ptr);
+    reflect.try_cast_mut_with_registry(registry)
+}
+
+fn get_from_ptr(type_id: TypeId, registry: &TypeRegistry) -> Result<&ReflectFromPtr, TypeError> {
+    let registration = registry
+        .get(type_id)
+        .ok_or_else(|| TypeError::UnregisteredType(format!("{type_id:?}").into()))?;
+    let from_ptr = registration.data::<ReflectFromPtr>().ok_or_else(|| {
+        TypeError::UnregisteredTrait(registration.type_name().to_string().into(), "FromPtr")
+    })?;
+    debug_assert_eq!(from_ptr.type_id(), type_id);
+    Ok(from_ptr)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+
+    use bevy_reflect::reflect_trait;
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect};
+
+    #[reflect_trait]
+    trait Counter: DowncastReflect {
+        fn increment(&mut self);
+        fn get(&self) -> u32;
+    }
+    impl_dynamic_trait!(Counter, ReflectCounter);
+
+    #[derive(Reflect)]
+    #[reflect(Counter)]
+    struct Foo(u32);
+    impl Counter for Foo {
+        fn increment(&mut self) {
+            self.0 += 1;
+        }
+        fn get(&self) -> u32 {
+            self.0
+        }
+    }
+
+    #[derive(Reflect)]
+    struct Bar;
+
+    #[derive(Reflect)]
+    struct Unregistered;
+
+    #[test]
+    fn cast_any() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Foo>();
+        registry.register::<Bar>();
+
+        let mut resource: Box<dyn Any + Send + Sync> = Box::new(Foo(1));
+        (&mut *resource)
+            .try_cast_any_mut_with_registry::<dyn Counter>(&registry)
+            .unwrap()
+            .increment();
+        let counter = (&*resource)
+            .try_cast_any_with_registry::<dyn Counter>(&registry)
+            .unwrap();
+        assert_eq!(counter.get(), 2);
+
+        let bar: &dyn Any = &Bar;
+        assert!(matches!(
+            bar.try_cast_any_with_registry::<dyn Counter>(&registry),
+            Err(TypeError::UnregisteredTrait(_, "Counter"))
+        ));
+
+        let unregistered: &dyn Any = &Unregistered;
+        assert!(matches!(
+            unregistered.try_cast_any_with_registry::<dyn Counter>(&registry),
+            Err(TypeError::UnregisteredType(_))
+        ));
+    }
+
+    #[test]
+    fn cast_raw_pointer() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Foo>();
+        registry.register::<Bar>();
+
+        let mut storage: [Box<dyn Reflect>; 2] = [Box::new(Foo(1)), Box::new(Bar)];
+        let foo = NonNull::from(&mut *storage[0]);
+        let bar = NonNull::from(&mut *storage[1]);
+        unsafe {
+            let counter = foo
+                .try_cast_ptr_with_registry::<dyn Counter>(&registry)
+                .unwrap();
+            assert_eq!(counter.cast::<u8>(), foo.cast::<u8>());
+            (*counter.as_ptr()).increment();
+            assert!(matches!(
+                bar.try_cast_ptr_with_registry::<dyn Counter>(&registry),
+                Err(TypeError::UnregisteredTrait(_, "Counter"))
+            ));
+            assert!(matches!(
+                foo.try_cast_ptr::<dyn Counter>(),
+                Err(TypeError::NoCurrentRegistry)
+            ));
+            let counter = registry
+                .set_current(|| foo.try_cast_ptr::<dyn Counter>())
+                .unwrap();
+            assert_eq!(counter.as_ref().get(), 2);
+        }
+    }
+}
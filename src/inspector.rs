@@ -0,0 +1,406 @@
+//! An [egui](https://github.com/emilk/egui) inspector for reflected values.
+//! Requires the "egui" crate feature.
+//!
+//! [`inspect()`] renders editable widgets for any reflected value: checkboxes,
+//! drag values and text fields for primitives and strings, and collapsible
+//! sections for the fields of structs, tuples, lists, maps and enums. Fields of
+//! type `Box<dyn MyTrait>` get a combo box listing the registered implementors
+//! of `MyTrait`, which replaces the object with a default value of the chosen
+//! type.
+//!
+//! Implementors are found with
+//! [`TypeRegistryExt::implementors_of()`](crate::TypeRegistryExt::implementors_of),
+//! so the trait must be registered globally (with
+//! [`impl_dynamic_trait!(MyTrait, ReflectMyTrait, global)`](crate::impl_dynamic_trait)),
+//! and the registry must have been indexed. Only implementors with
+//! `#[reflect(Default)]` can be chosen.
+//!
+//! ## Example
+//! ```rust
+//! # use reflect_steroids::{prelude::*, inspector::inspect, reflect::{std_traits::ReflectDefault, TypeRegistry}};
+//! #[reflect_trait]
+//! trait Brush: DowncastReflect {}
+//! impl_dynamic_trait!(Brush, ReflectBrush, global);
+//!
+//! #[derive(Reflect, Default)]
+//! #[reflect(Brush, Default)]
+//! struct Round { radius: f32 }
+//! impl Brush for Round {}
+//!
+//! #[derive(Reflect)]
+//! struct Tool { name: String, brush: Box<dyn Brush> }
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register::<Round>();
+//! registry.register_global_types();
+//!
+//! let mut tool = Tool { name: "pen".into(), brush: Box::new(Round::default()) };
+//! let ctx = egui::Context::default();
+//! let _ = ctx.run(Default::default(), |ctx| {
+//!     egui::CentralPanel::default().show(ctx, |ui| {
+//!         if inspect(ui, &mut tool, &registry) {
+//!             // The tool was edited.
+//!         }
+//!     });
+//! });
+//! ```
+
+use bevy_reflect::{
+    std_traits::ReflectDefault, DynamicEnum, DynamicVariant, NamedField, Reflect, ReflectMut,
+    ReflectRef, TypeInfo, TypeRegistry, UnnamedField, VariantInfo,
+};
+use egui::{CollapsingHeader, ComboBox, DragValue, SelectableLabel, Ui};
+
+use crate::TypeRegistryExt;
+
+/// Render editable widgets for `value` into `ui`, using `registry` to find the
+/// implementors of dynamic traits. Returns true if `value` was changed.
+///
+/// See the [module documentation](self).
+pub fn inspect(ui: &mut Ui, value: &mut dyn Reflect, registry: &TypeRegistry) -> bool {
+    // Replacing a trait object casts the new object with the current registry.
+    registry.set_current(|| Inspector { registry }.value(ui, value, None))
+}
+
+struct Inspector<'a> {
+    registry: &'a TypeRegistry,
+}
+
+impl Inspector<'_> {
+    /// Render `value`, whose declared type in its container is `declared`, if
+    /// it is known.
+    fn value(&self, ui: &mut Ui, value: &mut dyn Reflect, declared: Option<&str>) -> bool {
+        let mut changed = false;
+        if let Some(trait_name) = declared.and_then(boxed_trait_name) {
+            changed |= self.implementors(ui, value, trait_name);
+        }
+
+        let info = value.get_type_info();
+        changed |= match value.reflect_mut() {
+            ReflectMut::Struct(value) => {
+                let mut changed = false;
+                for index in 0..value.field_len() {
+                    let name = value.name_at(index).unwrap_or_default().to_string();
+                    let declared = match info {
+                        TypeInfo::Struct(info) => info.field_at(index).map(NamedField::type_name),
+                        _ => None,
+                    };
+                    if let Some(field) = value.field_at_mut(index) {
+                        changed |= self.field(ui, &name, field, declared);
+                    }
+                }
+                changed
+            }
+            ReflectMut::TupleStruct(value) => {
+                let mut changed = false;
+                for index in 0..value.field_len() {
+                    let declared = match info {
+                        TypeInfo::TupleStruct(info) => {
+                            info.field_at(index).map(UnnamedField::type_name)
+                        }
+                        _ => None,
+                    };
+                    if let Some(field) = value.field_mut(index) {
+                        changed |= self.field(ui, &index.to_string(), field, declared);
+                    }
+                }
+                changed
+            }
+            ReflectMut::Tuple(value) => {
+                let mut changed = false;
+                for index in 0..value.field_len() {
+                    let declared = match info {
+                        TypeInfo::Tuple(info) => info.field_at(index).map(UnnamedField::type_name),
+                        _ => None,
+                    };
+                    if let Some(field) = value.field_mut(index) {
+                        changed |= self.field(ui, &index.to_string(), field, declared);
+                    }
+                }
+                changed
+            }
+            ReflectMut::List(value) => {
+                let declared = match info {
+                    TypeInfo::List(info) => Some(info.item_type_name()),
+                    _ => None,
+                };
+                let mut changed = false;
+                for index in 0..value.len() {
+                    if let Some(item) = value.get_mut(index) {
+                        changed |= self.field(ui, &format!("[{index}]"), item, declared);
+                    }
+                }
+                changed
+            }
+            ReflectMut::Array(value) => {
+                let declared = match info {
+                    TypeInfo::Array(info) => Some(info.item_type_name()),
+                    _ => None,
+                };
+                let mut changed = false;
+                for index in 0..value.len() {
+                    if let Some(item) = value.get_mut(index) {
+                        changed |= self.field(ui, &format!("[{index}]"), item, declared);
+                    }
+                }
+                changed
+            }
+            ReflectMut::Map(value) => {
+                let declared = match info {
+                    TypeInfo::Map(info) => Some(info.value_type_name()),
+                    _ => None,
+                };
+                let keys: Vec<Box<dyn Reflect>> =
+                    value.iter().map(|(key, _)| key.clone_value()).collect();
+                let mut changed = false;
+                for key in keys {
+                    if let Some(item) = value.get_mut(&*key) {
+                        changed |= self.field(ui, &format!("{key:?}"), item, declared);
+                    }
+                }
+                changed
+            }
+            ReflectMut::Enum(_) => self.enumeration(ui, value, info),
+            ReflectMut::Value(value) => edit_value(ui, value),
+        };
+        changed
+    }
+
+    /// Render a field of a container, labeled `label`: values on one line, and
+    /// anything else in a collapsible section.
+    fn field(
+        &self,
+        ui: &mut Ui,
+        label: &str,
+        value: &mut dyn Reflect,
+        declared: Option<&str>,
+    ) -> bool {
+        ui.push_id(label, |ui| {
+            if let ReflectRef::Value(_) = value.reflect_ref() {
+                ui.horizontal(|ui| {
+                    ui.label(label);
+                    self.value(ui, value, declared)
+                })
+                .inner
+            } else {
+                CollapsingHeader::new(label)
+                    .show(ui, |ui| self.value(ui, value, declared))
+                    .body_returned
+                    .unwrap_or(false)
+            }
+        })
+        .inner
+    }
+
+    /// Render a combo box of the variants of an enum, which switches to the
+    /// chosen variant if it is a unit variant, followed by the fields of the
+    /// current variant.
+    fn enumeration(&self, ui: &mut Ui, value: &mut dyn Reflect, info: &TypeInfo) -> bool {
+        let ReflectMut::Enum(reflect_enum) = value.reflect_mut() else {
+            return false;
+        };
+        let current = reflect_enum.variant_name().to_string();
+        let mut changed = false;
+
+        if let TypeInfo::Enum(info) = info {
+            let mut choice = None;
+            ComboBox::from_id_source("variant")
+                .selected_text(&current)
+                .show_ui(ui, |ui| {
+                    for variant in info.iter() {
+                        let selected = variant.name() == current;
+                        let is_unit = matches!(variant, VariantInfo::Unit(_));
+                        let response = ui
+                            .add_enabled(
+                                is_unit || selected,
+                                SelectableLabel::new(selected, variant.name()),
+                            )
+                            .on_disabled_hover_text("only unit variants can be chosen");
+                        if response.clicked() && !selected {
+                            choice = Some(variant.name());
+                        }
+                    }
+                });
+            if let Some(variant) = choice {
+                let unit = DynamicEnum::new(info.type_name(), variant, DynamicVariant::Unit);
+                value.apply(&unit);
+                return true;
+            }
+        }
+
+        let ReflectMut::Enum(reflect_enum) = value.reflect_mut() else {
+            return false;
+        };
+        let variant = match info {
+            TypeInfo::Enum(info) => info.variant(&current),
+            _ => None,
+        };
+        for index in 0..reflect_enum.field_len() {
+            let name = reflect_enum
+                .name_at(index)
+                .map_or_else(|| index.to_string(), str::to_string);
+            let declared = match variant {
+                Some(VariantInfo::Struct(info)) => info.field_at(index).map(NamedField::type_name),
+                Some(VariantInfo::Tuple(info)) => info.field_at(index).map(UnnamedField::type_name),
+                _ => None,
+            };
+            if let Some(field) = reflect_enum.field_at_mut(index) {
+                changed |= self.field(ui, &name, field, declared);
+            }
+        }
+        changed
+    }
+
+    /// Render a combo box of the registered implementors of the trait named
+    /// `trait_name`, which replaces `object` with a default value of the
+    /// chosen type.
+    fn implementors(&self, ui: &mut Ui, object: &mut dyn Reflect, trait_name: &str) -> bool {
+        let current = object.type_name().to_string();
+        let current_name = self
+            .registry
+            .get_with_name(&current)
+            .map_or(current.as_str(), |registration| registration.short_name());
+
+        let mut choice = None;
+        ComboBox::from_id_source("implementor")
+            .selected_text(current_name)
+            .show_ui(ui, |ui| {
+                for registration in self.registry.implementors_of(trait_name) {
+                    let selected = registration.type_name() == current;
+                    let default = registration.data::<ReflectDefault>();
+                    let response = ui
+                        .add_enabled(
+                            default.is_some() || selected,
+                            SelectableLabel::new(selected, registration.short_name()),
+                        )
+                        .on_disabled_hover_text("#[reflect(Default)] is missing");
+                    if response.clicked() && !selected {
+                        choice = default;
+                    }
+                }
+            });
+
+        choice.is_some_and(|default| object.set(default.default()).is_ok())
+    }
+}
+
+/// The name of the trait in a type name of the form `Box<dyn path::Trait>`.
+fn boxed_trait_name(type_name: &str) -> Option<&str> {
+    let path = type_name
+        .strip_prefix("alloc::boxed::Box<dyn ")?
+        .strip_suffix('>')?;
+    path.rsplit("::").next()
+}
+
+/// Render a widget for a primitive or a string, or a read-only label for
+/// anything else.
+fn edit_value(ui: &mut Ui, value: &mut dyn Reflect) -> bool {
+    macro_rules! drag_value {
+        ($($ty:ty),*) => {
+            $(
+                if let Some(number) = value.as_any_mut().downcast_mut::<$ty>() {
+                    return ui.add(DragValue::new(number)).changed();
+                }
+            )*
+        };
+    }
+
+    if let Some(flag) = value.as_any_mut().downcast_mut::<bool>() {
+        return ui.checkbox(flag, "").changed();
+    }
+    if let Some(string) = value.as_any_mut().downcast_mut::<String>() {
+        return ui.text_edit_singleline(string).changed();
+    }
+    drag_value!(f32, f64, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+    ui.label(format!("{value:?}"));
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::{type_name, TypeId};
+
+    use bevy_reflect::{FromReflect, Struct};
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[reflect_trait]
+    trait Effect: DowncastReflect {}
+    impl_dynamic_trait!(Effect, ReflectEffect, global);
+
+    #[derive(Reflect, Default)]
+    #[reflect(Effect, Default)]
+    struct Blur {
+        radius: f32,
+    }
+    impl Effect for Blur {}
+
+    #[derive(Reflect, Default)]
+    #[reflect(Effect, Default)]
+    struct Tint(u8, u8, u8);
+    impl Effect for Tint {}
+
+    #[derive(Reflect, FromReflect, PartialEq, Debug)]
+    enum Blend {
+        Normal,
+        Multiply,
+        Custom { weight: f32 },
+    }
+
+    #[derive(Reflect)]
+    struct Layer {
+        name: String,
+        visible: bool,
+        blend: Blend,
+        effects: Vec<Box<dyn Effect>>,
+        mask: Box<dyn Effect>,
+    }
+
+    fn registry() -> TypeRegistry {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Blur>();
+        registry.register::<Tint>();
+        registry.register_global_types();
+        registry
+    }
+
+    #[test]
+    fn render_and_replace_objects() {
+        let registry = registry();
+        let mut layer = Layer {
+            name: "background".into(),
+            visible: true,
+            blend: Blend::Custom { weight: 0.5 },
+            effects: vec![Box::new(Blur { radius: 2.0 }), Box::new(Tint(1, 2, 3))],
+            mask: Box::new(Blur::default()),
+        };
+
+        let ctx = egui::Context::default();
+        for _ in 0..2 {
+            let _ = ctx.run(egui::RawInput::default(), |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    assert!(!inspect(ui, &mut layer, &registry));
+                });
+            });
+        }
+
+        // What the combo boxes do when an option is chosen.
+        let tint = registry.get_type_data::<ReflectDefault>(TypeId::of::<Tint>());
+        let tint = tint.unwrap().default();
+        let mask = layer.field_mut("mask").unwrap();
+        assert!(mask.set(tint.clone_value()).is_err());
+        registry.set_current(|| assert!(mask.set(tint).is_ok()));
+        assert!(layer.mask.is::<Tint>());
+
+        let normal = DynamicEnum::new(type_name::<Blend>(), "Normal", DynamicVariant::Unit);
+        layer.blend.apply(&normal);
+        assert_eq!(layer.blend, Blend::Normal);
+
+        assert_eq!(
+            boxed_trait_name(type_name::<Box<dyn Effect>>()),
+            Some("Effect")
+        );
+    }
+}
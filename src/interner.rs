@@ -0,0 +1,219 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use bevy_reflect::{Reflect, ReflectRef};
+
+use crate::DynamicTrait;
+
+/// Deduplicates equal trait objects behind shared [`Arc`]s.
+///
+/// Objects are hashed by their contents, using [`Reflect::reflect_hash()`]
+/// where it is available, and compared with
+/// [`Reflect::reflect_partial_eq()`]. Objects that contain values that cannot
+/// be hashed (opaque values without `#[reflect(Hash)]`, other than floats), or
+/// that cannot be compared, are not deduplicated.
+///
+/// Sequences of interned objects can be serialized with
+/// [`serialization::interned`](crate::serialization::interned), which writes
+/// each object once.
+///
+/// ## Example
+/// ```rust
+/// # use std::sync::Arc;
+/// # use reflect_steroids::{prelude::*, Interner};
+/// #[reflect_trait]
+/// trait Modifier: DowncastReflect {}
+/// impl_dynamic_trait!(Modifier, ReflectModifier);
+///
+/// #[derive(Reflect)]
+/// struct Bonus { stat: String, amount: f32 }
+/// impl Modifier for Bonus {}
+///
+/// let mut interner = Interner::<dyn Modifier>::new();
+/// let a = interner.intern(Box::new(Bonus { stat: "str".into(), amount: 1.5 }));
+/// let b = interner.intern(Box::new(Bonus { stat: "str".into(), amount: 1.5 }));
+/// let c = interner.intern(Box::new(Bonus { stat: "dex".into(), amount: 1.5 }));
+/// assert!(Arc::ptr_eq(&a, &b));
+/// assert!(!Arc::ptr_eq(&a, &c));
+/// assert_eq!(interner.len(), 2);
+/// ```
+pub struct Interner<P: ?Sized> {
+    buckets: HashMap<u64, Vec<Arc<P>>>,
+}
+
+impl<P: DynamicTrait + ?Sized> Interner<P> {
+    /// An empty interner.
+    #[must_use]
+    pub fn new() -> Self {
+        Interner {
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Get the interned object equal to `object`, or intern `object` if there
+    /// is none.
+    pub fn intern(&mut self, object: Box<P>) -> Arc<P> {
+        let Some(hash) = content_hash(P::as_reflect(&*object)) else {
+            return Arc::from(object);
+        };
+        let bucket = self.buckets.entry(hash).or_default();
+        let existing = bucket.iter().find(|interned| {
+            P::as_reflect(&***interned).reflect_partial_eq(P::as_reflect(&*object)) == Some(true)
+        });
+        if let Some(existing) = existing {
+            return existing.clone();
+        }
+        let object: Arc<P> = Arc::from(object);
+        bucket.push(object.clone());
+        object
+    }
+
+    /// The number of distinct interned objects.
+    pub fn len(&self) -> usize {
+        self.buckets.values().map(Vec::len).sum()
+    }
+
+    /// True if no objects are interned.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// Forget the objects that are not referenced outside of the interner.
+    /// Returns the number of objects removed.
+    pub fn remove_unused(&mut self) -> usize {
+        let before = self.len();
+        self.buckets.retain(|_, bucket| {
+            bucket.retain(|object| Arc::strong_count(object) > 1);
+            !bucket.is_empty()
+        });
+        before - self.len()
+    }
+}
+
+impl<P: DynamicTrait + ?Sized> Default for Interner<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hash the contents of `value`, or `None` if any part of it cannot be hashed.
+fn content_hash(value: &dyn Reflect) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    hash_value(value, &mut hasher).then(|| hasher.finish())
+}
+
+fn hash_value(value: &dyn Reflect, state: &mut DefaultHasher) -> bool {
+    value.type_name().hash(state);
+    if let Some(hash) = value.reflect_hash() {
+        hash.hash(state);
+        return true;
+    }
+    match value.reflect_ref() {
+        ReflectRef::Struct(s) => s.iter_fields().all(|field| hash_value(field, state)),
+        ReflectRef::TupleStruct(s) => s.iter_fields().all(|field| hash_value(field, state)),
+        ReflectRef::Tuple(t) => t.iter_fields().all(|field| hash_value(field, state)),
+        ReflectRef::List(list) => {
+            list.len().hash(state);
+            list.iter().all(|element| hash_value(element, state))
+        }
+        ReflectRef::Array(array) => array.iter().all(|element| hash_value(element, state)),
+        ReflectRef::Map(map) => {
+            // Entries are combined independently of their order.
+            let mut combined = 0u64;
+            for (key, element) in map.iter() {
+                let mut entry = DefaultHasher::new();
+                if !hash_value(key, &mut entry) || !hash_value(element, &mut entry) {
+                    return false;
+                }
+                combined = combined.wrapping_add(entry.finish());
+            }
+            combined.hash(state);
+            true
+        }
+        ReflectRef::Enum(e) => {
+            e.variant_name().hash(state);
+            e.iter_fields()
+                .all(|field| hash_value(field.value(), state))
+        }
+        ReflectRef::Value(value) => {
+            if let Some(value) = value.downcast_ref::<f32>() {
+                value.to_bits().hash(state);
+            } else if let Some(value) = value.downcast_ref::<f64>() {
+                value.to_bits().hash(state);
+            } else {
+                return false;
+            }
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{reflect_trait, FromReflect};
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect};
+
+    #[reflect_trait]
+    trait Effect: DowncastReflect {}
+    impl_dynamic_trait!(Effect, ReflectEffect);
+
+    #[derive(Reflect, FromReflect, Clone)]
+    enum Element {
+        Fire,
+        Ice { slow: f64 },
+    }
+
+    #[derive(Reflect, Clone)]
+    struct Damage {
+        amounts: Vec<u32>,
+        element: Element,
+    }
+    impl Effect for Damage {}
+
+    #[derive(Reflect)]
+    struct Opaque {
+        range: std::ops::Range<u32>,
+    }
+    impl Effect for Opaque {}
+
+    #[test]
+    fn deduplicates_equal_objects() {
+        let mut interner = Interner::<dyn Effect>::default();
+        let fire = Damage {
+            amounts: vec![1, 2],
+            element: Element::Fire,
+        };
+        let ice = Damage {
+            element: Element::Ice { slow: 0.5 },
+            ..fire.clone()
+        };
+        let a = interner.intern(Box::new(fire.clone()));
+        let b = interner.intern(Box::new(ice.clone()));
+        let c = interner.intern(Box::new(fire));
+        let d = interner.intern(Box::new(ice));
+        assert!(Arc::ptr_eq(&a, &c));
+        assert!(Arc::ptr_eq(&b, &d));
+        assert!(!Arc::ptr_eq(&a, &b));
+        assert_eq!(interner.len(), 2);
+
+        drop((a, c));
+        assert_eq!(interner.remove_unused(), 1);
+        assert_eq!(interner.len(), 1);
+    }
+
+    #[test]
+    fn unhashable_objects_are_not_deduplicated() {
+        let mut interner = Interner::<dyn Effect>::new();
+        let opaque = || Opaque { range: 0..1 };
+        let a = interner.intern(Box::new(opaque()));
+        let b = interner.intern(Box::new(opaque()));
+        assert!(!Arc::ptr_eq(&a, &b));
+        assert!(interner.is_empty());
+    }
+}
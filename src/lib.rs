@@ -2,31 +2,200 @@
 #![deny(missing_docs, clippy::useless_conversion, clippy::useless_asref)]
 #![warn(clippy::pedantic)]
 
+#[cfg(not(any(feature = "bevy_reflect_0_9", feature = "bevy_reflect_0_10")))]
+compile_error!("one of the \"bevy_reflect_*\" features of reflect-steroids must be enabled");
+
+#[cfg(all(feature = "bevy_reflect_0_10", not(feature = "bevy_reflect_0_9")))]
+extern crate bevy_ptr_0_10 as bevy_ptr;
+#[cfg(all(feature = "bevy_reflect_0_10", not(feature = "bevy_reflect_0_9")))]
+extern crate bevy_reflect_0_10 as bevy_reflect;
+
+mod apply;
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+#[cfg(feature = "bumpalo")]
+pub mod arena;
 mod cast;
+mod cast_policy;
+mod collections;
+mod compare;
+mod compat;
+mod convert;
+#[cfg(feature = "diagnostics")]
+mod diagnostics;
 mod downcast;
 mod dynamic_trait;
 mod error;
+mod explain;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod foreign;
+mod from_dynamic;
+mod from_ptr;
+#[cfg(feature = "egui")]
+pub mod inspector;
+mod interner;
+mod missing_type;
+mod object;
+mod pretty;
+pub mod proxy;
+#[cfg(feature = "pyo3")]
+pub mod python;
+mod register_hooks;
+mod remap;
+pub mod replicate;
+pub mod schema_export;
 pub mod serialization;
+mod snapshot;
+pub mod stats;
+mod storage;
+mod supertrait;
+mod tagged;
+#[cfg(feature = "inventory")]
+mod trait_index;
+mod trait_map;
 mod type_registry;
+mod validate;
+mod walk;
+mod watch;
 
+pub use apply::*;
 pub use cast::*;
+pub use cast_policy::{with_cast_context, CastPolicy};
+pub use collections::{DynMap, DynSet};
+pub use compare::*;
+pub use convert::*;
 pub use downcast::*;
 pub use dynamic_trait::*;
 pub use error::*;
+pub use explain::*;
+pub use foreign::ForeignDyn;
+pub use from_dynamic::*;
+pub use from_ptr::*;
+pub use interner::Interner;
+pub use missing_type::MissingTypeRetry;
+pub use object::ReflectObject;
+pub use pretty::*;
+pub use remap::*;
+pub use snapshot::*;
+pub use storage::*;
+pub use supertrait::Upcast;
+pub use tagged::Tagged;
+#[cfg(feature = "inventory")]
+pub use trait_index::*;
+pub use trait_map::TraitMap;
 pub use type_registry::*;
+pub use validate::*;
+pub use walk::*;
+pub use watch::*;
 
 #[doc(no_inline)]
+#[cfg(feature = "bevy_reflect_0_9")]
 pub use bevy_reflect as reflect;
+#[cfg(all(feature = "bevy_reflect_0_10", not(feature = "bevy_reflect_0_9")))]
+pub use bevy_reflect_0_10 as reflect;
 
 #[doc(no_inline, hidden)]
 pub use serde;
 
+/// Tagged (de)serialization for trait object fields.
+///
+/// Put `#[serde_dyn]` on a struct or enum (above `#[derive(Serialize,
+/// Deserialize)]`), and mark trait object fields with `#[serde_dyn]`. Marked
+/// fields are (de)serialized with
+/// [`serialization::dyn_field`](crate::serialization::dyn_field), which
+/// supports `Box<dyn MyTrait>`, `Option<...>` and `Vec<...>` of those.
+///
+/// With `#[serde_dyn(registry = "path::to::registry")]`, the field is
+/// (de)serialized with the registry returned by calling `registry()` as the
+/// current registry (see [`TypeRegistryExt::set_current()`]), instead of
+/// requiring a current registry to be set by the caller. The function may
+/// return anything that implements `Borrow<TypeRegistry>`, such as
+/// `&'static TypeRegistry` or `Arc<TypeRegistry>`.
+///
+/// The generated code refers to this crate as `::reflect_steroids`. Crates that
+/// depend on it under another name must give its path as
+/// `#[serde_dyn(crate = "path::to::reflect_steroids")]` on the struct or enum.
+///
+/// Requires the "macros" crate feature.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serde_dyn};
+/// #[reflect_trait]
+/// trait MyTrait: DowncastReflect {}
+/// impl_dynamic_trait!(MyTrait, ReflectMyTrait);
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(MyTrait, Serialize, Deserialize)]
+/// struct Foo { num: i32 }
+/// impl MyTrait for Foo {}
+///
+/// fn registry() -> TypeRegistry {
+///     let mut registry = TypeRegistry::default();
+///     registry.register::<Foo>();
+///     registry
+/// }
+///
+/// #[serde_dyn]
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Scene {
+///     #[serde_dyn]
+///     main: Box<dyn MyTrait>,
+///     #[serde_dyn]
+///     others: Vec<Option<Box<dyn Reflect>>>,
+///     #[serde_dyn(registry = "registry")]
+///     standalone: Box<dyn MyTrait>,
+/// }
+///
+/// #[serde_dyn]
+/// #[derive(serde::Deserialize)]
+/// struct Standalone {
+///     #[serde_dyn(registry = "registry")]
+///     standalone: Box<dyn MyTrait>,
+/// }
+///
+/// # fn main() {
+/// let scene = Scene {
+///     main: Box::new(Foo { num: 1 }),
+///     others: vec![Some(Box::new(Foo { num: 2 })), None],
+///     standalone: Box::new(Foo { num: 3 }),
+/// };
+/// let json = registry().set_current(|| serde_json::to_string(&scene).unwrap());
+/// assert_eq!(
+///     json,
+///     r#"{"main":{"type":"Foo","num":1},"others":[{"type":"Foo","num":2},null],"standalone":{"type":"Foo","num":3}}"#
+/// );
+///
+/// // No current registry is needed for fields with an explicit registry.
+/// let standalone: Standalone =
+///     serde_json::from_str(r#"{"standalone":{"type":"Foo","num":4}}"#).unwrap();
+/// assert!(standalone.standalone.is::<Foo>());
+/// # }
+/// ```
+///
+/// With a renamed crate:
+/// ```rust
+/// # use reflect_steroids::{prelude::*, serde_dyn};
+/// use reflect_steroids as steroids;
+///
+/// #[serde_dyn(crate = "steroids")]
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Holder {
+///     #[serde_dyn]
+///     object: Box<dyn Reflect>,
+/// }
+/// # fn main() {}
+/// ```
+#[cfg(feature = "macros")]
+pub use reflect_steroids_macros::serde_dyn;
+
 /// Prelude
 pub mod prelude {
     #[doc(no_inline)]
     pub use super::{
-        impl_dynamic_trait, reflect::prelude::*, Cast as _, CastBox as _, CastMut as _,
-        CastRef as _, DowncastReflect, DynamicTraitExt as _, TypeRegistryExt as _,
+        impl_dynamic_trait, impl_supertrait, reflect::prelude::*, Cast as _, CastBox as _,
+        CastMut as _, CastRef as _, DowncastReflect, DynamicTraitExt as _, TypeRegistryExt as _,
     };
 
     #[doc(no_inline)]
@@ -50,14 +219,115 @@ pub mod prelude {
 /// - [`Reflect`](bevy_reflect::Reflect) for `Box<dyn MyTrait>`, which forwards
 ///   all reflection to the concrete type (meaning it can be reflected upon as
 ///   if it was `Box<dyn Reflect>`).
+/// - [`FromReflect`](bevy_reflect::FromReflect) for `Box<dyn MyTrait>`, which
+///   builds the object as in [`from_dynamic()`] with the current registry (see
+///   [`TypeRegistryExt::set_current()`]). This makes fields such as
+///   `Option<Box<dyn MyTrait>>` and `Vec<Box<dyn MyTrait>>` work with
+///   `#[derive(Reflect, FromReflect)]`, and with `Serialize` and
+///   `Deserialize`, where `None` is `null`. Without a current registry, the
+///   object cannot be built, which `Option<Box<dyn MyTrait>>` treats as an
+///   error and panics.
 /// - [`std::fmt::Debug`] for `dyn MyTrait` (forwarding to
 ///   [`Reflect::debug()`](bevy_reflect::Reflect::debug)).
-/// - [`Serialize`](serde::Serialize) for `dyn MyTrait` (see [serialization]).
+/// - [`Serialize`](serde::Serialize) and
+///   [`SerializeDyn`](serialization::SerializeDyn) for `dyn MyTrait` (see
+///   [serialization]).
 /// - [`Deserialize`](serde::Deserialize) for `Box<dyn MyTrait>` (see
 ///   [serialization]).
 /// - A downcasting interface for `dyn MyTrait`, similar to `dyn Reflect` and
 ///   `dyn Any`.
 ///
+/// With `global` as a third argument, the trait is also registered globally,
+/// so that [`global_dynamic_traits()`] lists it and
+/// [`register_global_types()`](TypeRegistryExt::register_global_types) indexes
+/// its implementors (see
+/// [`TypeRegistryExt::implementors_of()`](TypeRegistryExt::implementors_of)).
+/// This lets plugin crates make their traits discoverable by an application
+/// that does not know about them. Requires the "inventory" crate feature.
+///
+/// Auto traits can be added as in `impl_dynamic_trait!(MyTrait + Send + Sync,
+/// ReflectMyTrait)`. In addition to the implementations for `dyn MyTrait`, this
+/// implements [`DynamicTrait`], serialization, and `Deserialize` for `Box<...>`
+/// for `dyn MyTrait + Send + Sync`, so that such trait objects can be cast,
+/// stored in collections shared between threads, and serialized from worker
+/// threads (such as `rayon` workers). The listed auto traits must already be
+/// implied by the supertraits of `MyTrait`, which is checked at compile time.
+/// Since [`Reflect`](bevy_reflect::Reflect) requires `Send + Sync`, this holds
+/// for `Send` and `Sync`. Note that each worker thread needs its own current
+/// registry (see [`TypeRegistryExt::set_current()`]).
+///
+/// Traits that have [`Reflect`](bevy_reflect::Reflect) rather than
+/// [`DowncastReflect`] as a supertrait are supported with
+/// `impl_dynamic_trait!(MyTrait: Reflect, ReflectMyTrait)`, which also
+/// implements [`DowncastReflect`] for `dyn MyTrait` by trait upcasting
+/// (requires Rust 1.86 or later). Traits with neither supertrait cannot be
+/// cast back to `dyn Reflect`; see [`ForeignDyn`] for those.
+///
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+/// #[reflect_trait]
+/// pub trait Tool: Reflect {
+///     fn name(&self) -> &str;
+/// }
+/// impl_dynamic_trait!(Tool: Reflect, ReflectTool);
+///
+/// #[derive(Reflect)]
+/// #[reflect(Tool)]
+/// struct Hammer;
+/// impl Tool for Hammer {
+///     fn name(&self) -> &str { "hammer" }
+/// }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Hammer>();
+///
+/// let object: Box<dyn Reflect> = Box::new(Hammer);
+/// let tool: Box<dyn Tool> = object.try_cast_box_with_registry(&registry).ok().unwrap();
+/// assert_eq!(tool.name(), "hammer");
+/// assert!(tool.downcast::<Hammer>().is_ok());
+/// ```
+///
+/// The downcasting interface (`is()`, `downcast()`, and so on) is generated as
+/// inherent methods of `dyn MyTrait`, which take precedence over methods of
+/// the same name defined by the trait. With `ext = MyTraitDynExt` as a third
+/// argument, it is generated as the extension trait `MyTraitDynExt` instead:
+///
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+/// #[reflect_trait]
+/// trait Shape: DowncastReflect {
+///     fn is(&self, name: &str) -> bool;
+/// }
+/// impl_dynamic_trait!(Shape, ReflectShape, ext = ShapeDynExt);
+///
+/// #[derive(Reflect)]
+/// struct Circle;
+/// impl Shape for Circle {
+///     fn is(&self, name: &str) -> bool { name == "circle" }
+/// }
+///
+/// let shape: Box<dyn Shape> = Box::new(Circle);
+/// assert!(shape.is("circle"));
+/// assert!(ShapeDynExt::is::<Circle>(&*shape));
+/// assert!(ShapeDynExt::downcast::<Circle>(shape).is_ok());
+/// ```
+///
+/// The generated code refers to `bevy_reflect` and `serde` through this crate,
+/// so they need not be dependencies of the calling crate, or may be renamed.
+///
+/// The generated methods, or the extension trait, are public. A visibility
+/// before the trait name, as in `impl_dynamic_trait!(pub(crate) MyTrait,
+/// ReflectMyTrait)`, applies to them instead, so that private traits do not
+/// add to the public API of the crate. Trait implementations are not affected.
+///
+/// Several traits can be implemented in one invocation, as
+/// `impl_dynamic_trait!(TraitA => ReflectTraitA, TraitB => ReflectTraitB)`.
+/// This also defines a function `register_all_dynamic_traits(&mut
+/// TypeRegistry)`, which calls
+/// [`register_dynamic_trait()`](TypeRegistryExt::register_dynamic_trait) for
+/// each of the traits. Since the function has a fixed name, this form can be
+/// used at most once per module.
+///
 /// ## Usage
 ///
 /// ```rust
@@ -78,10 +348,57 @@ pub mod prelude {
 /// let foo_as_reflect = foo_as_mytrait.as_reflect();
 /// assert_eq!(foo_as_reflect.type_name(), std::any::type_name::<Foo>());
 /// ```
+///
+/// Several traits at once:
+///
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+/// #[reflect_trait]
+/// trait Damageable: DowncastReflect {}
+/// #[reflect_trait]
+/// trait Interactable: DowncastReflect {}
+///
+/// impl_dynamic_trait!(
+///     Damageable => ReflectDamageable,
+///     Interactable => ReflectInteractable,
+/// );
+///
+/// let mut registry = TypeRegistry::default();
+/// register_all_dynamic_traits(&mut registry);
+/// assert_eq!(
+///     registry.dynamic_traits().collect::<Vec<_>>(),
+///     ["Damageable", "Interactable"]
+/// );
+/// ```
 #[macro_export]
 macro_rules! impl_dynamic_trait {
-    ($trait_name:ident, $type_data_name:ident) => {
-        impl $crate::DynamicTrait for dyn $trait_name {
+    ($($trait_name:ident => $type_data_name:ident),+ $(,)?) => {
+        $(
+            $crate::impl_dynamic_trait!($trait_name, $type_data_name);
+        )+
+
+        #[doc = "Register all dynamic traits implemented by this invocation of `impl_dynamic_trait!`."]
+        #[allow(dead_code)]
+        pub fn register_all_dynamic_traits(registry: &mut $crate::reflect::TypeRegistry) {
+            $(
+                $crate::TypeRegistryExt::register_dynamic_trait::<dyn $trait_name>(registry);
+            )+
+        }
+    };
+    ($trait_name:ident $(+ $auto_trait:ident)+, $type_data_name:ident) => {
+        $crate::impl_dynamic_trait!($trait_name, $type_data_name);
+
+        const _: () = {
+            // Adding auto traits to a trait object is only sound if all
+            // implementors of the trait have them.
+            fn assert_implied<T: ?Sized $(+ $auto_trait)+>() {}
+            #[allow(dead_code)]
+            fn check() {
+                assert_implied::<dyn $trait_name>();
+            }
+        };
+
+        impl $crate::DynamicTrait for dyn $trait_name $(+ $auto_trait)+ {
             type TypeData = $type_data_name;
 
             fn reflect_name() -> &'static str {
@@ -89,33 +406,169 @@ macro_rules! impl_dynamic_trait {
             }
         }
 
-        impl $crate::DynamicCaster<dyn $trait_name> for $type_data_name {
-            fn from_reflect(&self, this: Box<dyn $crate::reflect::Reflect>) -> Box<dyn $trait_name> {
-                self.get_boxed(this).unwrap()
+        impl $crate::DynamicCaster<dyn $trait_name $(+ $auto_trait)+> for $type_data_name {
+            fn from_reflect(&self, this: Box<dyn $crate::reflect::Reflect>) -> Box<dyn $trait_name $(+ $auto_trait)+> {
+                match $crate::DynamicCaster::<dyn $trait_name $(+ $auto_trait)+>::try_from_reflect(self, this) {
+                    Ok(this) => this,
+                    Err(this) => $crate::__type_data_mismatch(stringify!($trait_name), this.type_name()),
+                }
             }
 
-            fn from_reflect_ref<'a>(&self, this: &'a dyn $crate::reflect::Reflect) -> &'a dyn $trait_name {
-                self.get(this).unwrap()
+            fn from_reflect_ref<'a>(&self, this: &'a dyn $crate::reflect::Reflect) -> &'a (dyn $trait_name $(+ $auto_trait)+) {
+                match $crate::DynamicCaster::<dyn $trait_name $(+ $auto_trait)+>::try_from_reflect_ref(self, this) {
+                    Some(this) => this,
+                    None => $crate::__type_data_mismatch(stringify!($trait_name), this.type_name()),
+                }
             }
 
-            fn from_reflect_mut<'a>(&self, this: &'a mut dyn $crate::reflect::Reflect) -> &'a mut dyn $trait_name {
-                self.get_mut(this).unwrap()
+            fn from_reflect_mut<'a>(&self, this: &'a mut dyn $crate::reflect::Reflect) -> &'a mut (dyn $trait_name $(+ $auto_trait)+) {
+                if self.get(this).is_none() {
+                    $crate::__type_data_mismatch(stringify!($trait_name), this.type_name());
+                }
+                $crate::DynamicCaster::<dyn $trait_name $(+ $auto_trait)+>::try_from_reflect_mut(self, this).unwrap()
+            }
+
+            fn try_from_reflect(
+                &self,
+                this: Box<dyn $crate::reflect::Reflect>,
+            ) -> Result<Box<dyn $trait_name $(+ $auto_trait)+>, Box<dyn $crate::reflect::Reflect>> {
+                let this: Box<dyn $trait_name> = self.get_boxed(this)?;
+                // SAFETY: The auto traits are implied by the supertraits (see
+                // `assert_implied` above), so the pointee has them.
+                Ok(unsafe { Box::from_raw(Box::into_raw(this) as *mut (dyn $trait_name $(+ $auto_trait)+)) })
+            }
+
+            fn try_from_reflect_ref<'a>(&self, this: &'a dyn $crate::reflect::Reflect) -> Option<&'a (dyn $trait_name $(+ $auto_trait)+)> {
+                let this: &dyn $trait_name = self.get(this)?;
+                // SAFETY: See `try_from_reflect()`.
+                Some(unsafe { &*(::std::ptr::from_ref::<dyn $trait_name>(this) as *const (dyn $trait_name $(+ $auto_trait)+)) })
+            }
+
+            fn try_from_reflect_mut<'a>(&self, this: &'a mut dyn $crate::reflect::Reflect) -> Option<&'a mut (dyn $trait_name $(+ $auto_trait)+)> {
+                let this: &mut dyn $trait_name = self.get_mut(this)?;
+                // SAFETY: See `try_from_reflect()`.
+                Some(unsafe { &mut *(::std::ptr::from_mut::<dyn $trait_name>(this) as *mut (dyn $trait_name $(+ $auto_trait)+)) })
+            }
+        }
+
+        #[allow(dead_code)]
+        impl dyn $trait_name $(+ $auto_trait)+ {
+            #[doc = "Returns `true` if the underlying value is of type `T`, or `false` otherwise."]
+            pub fn is<T: $trait_name>(&self) -> bool {
+                self.as_reflect().is::<T>()
+            }
+
+            #[doc = "Downcasts the value to type `T` by reference."]
+            pub fn downcast_ref<T: $trait_name>(&self) -> Option<&T> {
+                self.as_reflect().downcast_ref()
+            }
+
+            #[doc = "Downcasts the value to type `T` by mutable reference."]
+            pub fn downcast_mut<T: $trait_name>(&mut self) -> Option<&mut T> {
+                self.as_reflect_mut().downcast_mut()
+            }
+        }
+
+        impl ::core::fmt::Debug for dyn $trait_name $(+ $auto_trait)+ {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                self.as_reflect().debug(f)
+            }
+        }
+
+        impl $crate::serialization::SerializeDyn for dyn $trait_name $(+ $auto_trait)+ {
+            fn as_dyn_reflect(&self) -> &dyn $crate::reflect::Reflect {
+                self.as_reflect()
+            }
+        }
+
+        impl $crate::serde::Serialize for dyn $trait_name $(+ $auto_trait)+ {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: $crate::serde::Serializer,
+            {
+                $crate::serialization::serialize(self.as_reflect(), serializer)
+            }
+        }
+
+        impl<'de> $crate::serde::Deserialize<'de> for Box<dyn $trait_name $(+ $auto_trait)+> {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: $crate::serde::Deserializer<'de>,
+            {
+                $crate::serialization::deserialize_trait_object(deserializer)
+            }
+        }
+    };
+    // The casts to `dyn Reflect` below are trait upcasting coercions, which
+    // need Rust 1.86 (the crate's `rust-version`).
+    ($trait_name:ident: Reflect, $type_data_name:ident) => {
+        impl $crate::DowncastReflect for dyn $trait_name {
+            fn downcast_into_reflect(self: Box<Self>) -> Box<dyn $crate::reflect::Reflect> {
+                self
+            }
+
+            fn downcast_into_reflect_rc(
+                self: ::std::rc::Rc<Self>,
+            ) -> ::std::rc::Rc<dyn $crate::reflect::Reflect> {
+                self
+            }
+
+            fn downcast_into_reflect_arc(
+                self: ::std::sync::Arc<Self>,
+            ) -> ::std::sync::Arc<dyn $crate::reflect::Reflect> {
+                self
             }
         }
 
+        $crate::impl_dynamic_trait!($trait_name, $type_data_name);
+    };
+    ($trait_name:ident, $type_data_name:ident, global) => {
+        $crate::impl_dynamic_trait!($trait_name, $type_data_name);
+        $crate::__submit_dynamic_trait!($trait_name, $type_data_name);
+    };
+    (pub $(($($scope:tt)*))? $trait_name:ident, $type_data_name:ident $(, ext = $ext_name:ident)?) => {
+        $crate::impl_dynamic_trait!(@trait [pub $(($($scope)*))?] $trait_name, $type_data_name, [$($ext_name)?]);
+    };
+    ($trait_name:ident, $type_data_name:ident) => {
+        $crate::impl_dynamic_trait!(@trait [pub] $trait_name, $type_data_name, []);
+    };
+    ($trait_name:ident, $type_data_name:ident, ext = $ext_name:ident) => {
+        $crate::impl_dynamic_trait!(@trait [pub] $trait_name, $type_data_name, [$ext_name]);
+    };
+    (@trait [$($vis:tt)*] $trait_name:ident, $type_data_name:ident, []) => {
+        $crate::impl_dynamic_trait!(@dynamic_trait $trait_name, $type_data_name);
+        $crate::impl_dynamic_trait!(@impls $trait_name, $type_data_name);
+        $crate::impl_dynamic_trait!(@inherent [$($vis)*] $trait_name);
+    };
+    (@trait [$($vis:tt)*] $trait_name:ident, $type_data_name:ident, [$ext_name:ident]) => {
+        $crate::impl_dynamic_trait!(@dynamic_trait $trait_name, $type_data_name);
+        $crate::impl_dynamic_trait!(@impls $trait_name, $type_data_name);
+        $crate::impl_dynamic_trait!(@ext [$($vis)*] $trait_name, $ext_name);
+    };
+    (@dynamic_trait $trait_name:ident, $type_data_name:ident) => {
+        impl $crate::DynamicTrait for dyn $trait_name {
+            type TypeData = $type_data_name;
+
+            fn reflect_name() -> &'static str {
+                stringify!($trait_name)
+            }
+        }
+    };
+    // The downcasting interface, as inherent methods of `dyn MyTrait`.
+    (@inherent [$($vis:tt)*] $trait_name:ident) => {
         #[allow(dead_code)]
         impl dyn $trait_name {
             #[doc = "Returns `true` if the underlying value is of type `T`, or `false` otherwise."]
             #[doc = ""]
             #[doc = "The underlying value is the concrete type that is stored in this `dyn` object; it can be downcasted to."]
-            pub fn is<T: $trait_name>(&self) -> bool {
+            $($vis)* fn is<T: $trait_name>(&self) -> bool {
                 self.as_reflect().is::<T>()
             }
 
             #[doc = "Downcasts the value to type `T`, consuming the trait object."]
             #[doc = ""]
             #[doc = "If the underlying value is not of type `T`, returns `Err(self)`."]
-            pub fn downcast<T: $trait_name>(self: Box<Self>) -> Result<Box<T>, Box<Self>> {
+            $($vis)* fn downcast<T: $trait_name>(self: Box<Self>) -> Result<Box<T>, Box<Self>> {
                 if self.is::<T>() {
                     Ok(<dyn $crate::reflect::Reflect>::downcast(
                         $crate::DowncastReflect::downcast_into_reflect(self),
@@ -126,34 +579,158 @@ macro_rules! impl_dynamic_trait {
                 }
             }
 
+            #[doc = "Downcasts the value to type `T`, consuming the trait object."]
+            #[doc = ""]
+            #[doc = "If the underlying value is not of type `T`, returns an error naming the expected and actual types, which gives back the trait object."]
+            $($vis)* fn downcast_checked<T: $trait_name>(self: Box<Self>) -> Result<Box<T>, $crate::DowncastError<Box<Self>>> {
+                if self.is::<T>() {
+                    Ok(self.downcast().ok().unwrap())
+                } else {
+                    Err($crate::DowncastError {
+                        expected: ::core::any::type_name::<T>(),
+                        actual: self.as_reflect().type_name().to_string(),
+                        object: self,
+                    })
+                }
+            }
+
             #[doc = "Downcasts the value to type `T` by reference."]
             #[doc = ""]
             #[doc = "If the underlying value is not of type `T`, returns `None`."]
-            pub fn downcast_ref<T: $trait_name>(&self) -> Option<&T> {
+            $($vis)* fn downcast_ref<T: $trait_name>(&self) -> Option<&T> {
                 self.as_reflect().downcast_ref()
             }
 
             #[doc = "Downcasts the value to type `T` by mutable reference."]
             #[doc = ""]
             #[doc = "If the underlying value is not of type `T`, returns `None`."]
-            pub fn downcast_mut<T: $trait_name>(&mut self) -> Option<&mut T> {
+            $($vis)* fn downcast_mut<T: $trait_name>(&mut self) -> Option<&mut T> {
                 self.as_reflect_mut().downcast_mut()
             }
 
             #[doc = "Cast this trait object to `dyn Reflect`."]
             #[doc = ""]
             #[doc = concat!("This cannot fail because `", stringify!($trait_name), ": DowncastReflect`.")]
-            pub fn into_reflect(self: Box<Self>) -> Box<dyn $crate::reflect::Reflect> {
+            $($vis)* fn into_reflect(self: Box<Self>) -> Box<dyn $crate::reflect::Reflect> {
+                $crate::DowncastReflect::downcast_into_reflect(self)
+            }
+        }
+    };
+    // The downcasting interface, as an extension trait of `dyn MyTrait`.
+    (@ext [$($vis:tt)*] $trait_name:ident, $ext_name:ident) => {
+        #[doc = concat!("Downcasting interface for `dyn ", stringify!($trait_name), "`.")]
+        $($vis)* trait $ext_name {
+            #[doc = "Returns `true` if the underlying value is of type `T`, or `false` otherwise."]
+            fn is<T: $trait_name>(&self) -> bool;
+
+            #[doc = "Downcasts the value to type `T`, consuming the trait object."]
+            #[doc = ""]
+            #[doc = "If the underlying value is not of type `T`, returns `Err(self)`."]
+            fn downcast<T: $trait_name>(self: Box<Self>) -> Result<Box<T>, Box<Self>>;
+
+            #[doc = "Downcasts the value to type `T`, consuming the trait object."]
+            #[doc = ""]
+            #[doc = "If the underlying value is not of type `T`, returns an error naming the expected and actual types, which gives back the trait object."]
+            fn downcast_checked<T: $trait_name>(self: Box<Self>) -> Result<Box<T>, $crate::DowncastError<Box<Self>>>;
+
+            #[doc = "Downcasts the value to type `T` by reference."]
+            fn downcast_ref<T: $trait_name>(&self) -> Option<&T>;
+
+            #[doc = "Downcasts the value to type `T` by mutable reference."]
+            fn downcast_mut<T: $trait_name>(&mut self) -> Option<&mut T>;
+
+            #[doc = "Cast this trait object to `dyn Reflect`."]
+            fn into_reflect(self: Box<Self>) -> Box<dyn $crate::reflect::Reflect>;
+        }
+
+        impl $ext_name for dyn $trait_name {
+            fn is<T: $trait_name>(&self) -> bool {
+                $crate::reflect::Reflect::as_reflect(self).is::<T>()
+            }
+
+            fn downcast<T: $trait_name>(self: Box<Self>) -> Result<Box<T>, Box<Self>> {
+                if $ext_name::is::<T>(&*self) {
+                    Ok(<dyn $crate::reflect::Reflect>::downcast(
+                        $crate::DowncastReflect::downcast_into_reflect(self),
+                    )
+                    .unwrap())
+                } else {
+                    Err(self)
+                }
+            }
+
+            fn downcast_checked<T: $trait_name>(self: Box<Self>) -> Result<Box<T>, $crate::DowncastError<Box<Self>>> {
+                if $ext_name::is::<T>(&*self) {
+                    Ok($ext_name::downcast(self).ok().unwrap())
+                } else {
+                    Err($crate::DowncastError {
+                        expected: ::core::any::type_name::<T>(),
+                        actual: $crate::reflect::Reflect::type_name(&*self).to_string(),
+                        object: self,
+                    })
+                }
+            }
+
+            fn downcast_ref<T: $trait_name>(&self) -> Option<&T> {
+                $crate::reflect::Reflect::as_reflect(self).downcast_ref()
+            }
+
+            fn downcast_mut<T: $trait_name>(&mut self) -> Option<&mut T> {
+                $crate::reflect::Reflect::as_reflect_mut(self).downcast_mut()
+            }
+
+            fn into_reflect(self: Box<Self>) -> Box<dyn $crate::reflect::Reflect> {
                 $crate::DowncastReflect::downcast_into_reflect(self)
             }
         }
+    };
+    // Everything but the `DynamicTrait` implementation, which
+    // `impl_trait_combination!` provides itself, and the downcasting interface.
+    (@impls $trait_name:ident, $type_data_name:ident) => {
+        impl $crate::DynamicCaster<dyn $trait_name> for $type_data_name {
+            fn from_reflect(&self, this: Box<dyn $crate::reflect::Reflect>) -> Box<dyn $trait_name> {
+                match self.get_boxed(this) {
+                    Ok(this) => this,
+                    Err(this) => $crate::__type_data_mismatch(stringify!($trait_name), this.type_name()),
+                }
+            }
+
+            fn from_reflect_ref<'a>(&self, this: &'a dyn $crate::reflect::Reflect) -> &'a dyn $trait_name {
+                match self.get(this) {
+                    Some(this) => this,
+                    None => $crate::__type_data_mismatch(stringify!($trait_name), this.type_name()),
+                }
+            }
+
+            fn from_reflect_mut<'a>(&self, this: &'a mut dyn $crate::reflect::Reflect) -> &'a mut dyn $trait_name {
+                if self.get(this).is_none() {
+                    $crate::__type_data_mismatch(stringify!($trait_name), this.type_name());
+                }
+                self.get_mut(this).unwrap()
+            }
+
+            fn try_from_reflect(
+                &self,
+                this: Box<dyn $crate::reflect::Reflect>,
+            ) -> Result<Box<dyn $trait_name>, Box<dyn $crate::reflect::Reflect>> {
+                self.get_boxed(this)
+            }
+
+            fn try_from_reflect_ref<'a>(&self, this: &'a dyn $crate::reflect::Reflect) -> Option<&'a dyn $trait_name> {
+                self.get(this)
+            }
+
+            fn try_from_reflect_mut<'a>(&self, this: &'a mut dyn $crate::reflect::Reflect) -> Option<&'a mut dyn $trait_name> {
+                self.get_mut(this)
+            }
+        }
 
         impl $crate::reflect::Reflect for Box<dyn $trait_name> {
             fn type_name(&self) -> &str {
                 (**self).as_reflect().type_name()
             }
 
-            fn get_type_info(&self) -> &'static bevy_reflect::TypeInfo {
+            fn get_type_info(&self) -> &'static $crate::reflect::TypeInfo {
                 (**self).as_reflect().get_type_info()
             }
 
@@ -171,6 +748,10 @@ macro_rules! impl_dynamic_trait {
                 (**self).as_reflect_mut().as_any_mut()
             }
 
+            fn into_reflect(self: Box<Self>) -> Box<dyn $crate::reflect::Reflect> {
+                $crate::DowncastReflect::downcast_into_reflect(*self)
+            }
+
             fn as_reflect(&self) -> &dyn $crate::reflect::Reflect {
                 (**self).as_reflect().as_reflect()
             }
@@ -184,19 +765,33 @@ macro_rules! impl_dynamic_trait {
             }
 
             fn set(&mut self, value: Box<dyn $crate::reflect::Reflect>) -> Result<(), Box<dyn $crate::reflect::Reflect>> {
-                (**self).as_reflect_mut().set(value)
+                if ::core::any::Any::type_id(value.as_any()) == ::core::any::Any::type_id((**self).as_reflect().as_any()) {
+                    return (**self).as_reflect_mut().set(value);
+                }
+                // Any other implementor of the trait replaces the object.
+                match $crate::CastBox::try_cast_box::<dyn $trait_name>(value) {
+                    Ok(object) => {
+                        *self = object;
+                        Ok(())
+                    }
+                    Err((value, _)) => Err(value),
+                }
             }
 
-            fn reflect_ref(&self) -> bevy_reflect::ReflectRef {
+            fn reflect_ref(&self) -> $crate::reflect::ReflectRef<'_> {
                 (**self).as_reflect().reflect_ref()
             }
 
-            fn reflect_mut(&mut self) -> bevy_reflect::ReflectMut {
+            fn reflect_mut(&mut self) -> $crate::reflect::ReflectMut<'_> {
                 (**self).as_reflect_mut().reflect_mut()
             }
 
+            fn reflect_owned(self: Box<Self>) -> $crate::reflect::ReflectOwned {
+                $crate::DowncastReflect::downcast_into_reflect(*self).reflect_owned()
+            }
+
             fn clone_value(&self) -> Box<dyn $crate::reflect::Reflect> {
-                (**self).as_reflect().clone_value()
+                $crate::clone_concrete((**self).as_reflect())
             }
 
             fn reflect_hash(&self) -> Option<u64> {
@@ -207,7 +802,7 @@ macro_rules! impl_dynamic_trait {
                 (**self).as_reflect().reflect_partial_eq(value)
             }
 
-            fn debug(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            fn debug(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
                 (**self).as_reflect().debug(f)
             }
 
@@ -217,8 +812,8 @@ macro_rules! impl_dynamic_trait {
         }
 
         impl $crate::reflect::FromReflect for Box<dyn $trait_name> {
-            fn from_reflect(_: &dyn $crate::reflect::Reflect) -> Option<Self> {
-                None
+            fn from_reflect(value: &dyn $crate::reflect::Reflect) -> Option<Self> {
+                $crate::__box_from_reflect(value)
             }
         }
 
@@ -228,6 +823,12 @@ macro_rules! impl_dynamic_trait {
             }
         }
 
+        impl $crate::serialization::SerializeDyn for dyn $trait_name {
+            fn as_dyn_reflect(&self) -> &dyn $crate::reflect::Reflect {
+                self.as_reflect()
+            }
+        }
+
         impl $crate::serde::Serialize for dyn $trait_name {
             fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
             where
@@ -242,8 +843,7 @@ macro_rules! impl_dynamic_trait {
             where
                 D: $crate::serde::Deserializer<'de>,
             {
-                let box_dyn_reflect = $crate::serialization::deserialize(deserializer)?;
-                $crate::Cast::try_cast(box_dyn_reflect).map_err($crate::serde::de::Error::custom)
+                $crate::serialization::deserialize_trait_object(deserializer)
             }
         }
     };
@@ -259,6 +859,36 @@ pub mod global_registration {
     use bevy_reflect::TypeRegistry;
 
     pub struct RegisterFn(pub fn(&mut TypeRegistry));
+
+    pub struct DynamicTraitFn {
+        pub name: &'static str,
+        pub type_data_id: fn() -> std::any::TypeId,
+        pub implemented_by: fn(&bevy_reflect::TypeRegistration) -> bool,
+    }
+}
+
+#[cfg(feature = "inventory")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __submit_dynamic_trait {
+    ($trait_name:ident, $type_data_name:ident) => {
+        $crate::inventory::submit! {
+            $crate::global_registration::DynamicTraitFn {
+                name: stringify!($trait_name),
+                type_data_id: ::core::any::TypeId::of::<$type_data_name>,
+                implemented_by: |registration| registration.data::<$type_data_name>().is_some(),
+            }
+        }
+    };
+}
+
+#[cfg(not(feature = "inventory"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __submit_dynamic_trait {
+    ($trait_name:ident, $type_data_name:ident) => {
+        compile_error!("global dynamic trait registration requires the \"inventory\" feature of reflect-steroids");
+    };
 }
 
 /// Include a type in the global list of registered types.
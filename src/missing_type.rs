@@ -0,0 +1,124 @@
+use std::{cell::RefCell, sync::Arc};
+
+use bevy_reflect::TypeRegistry;
+
+use crate::{type_registry::registry_data, TypeError};
+
+scoped_tls::scoped_thread_local!(
+    static MISSING_TYPE: RefCell<Option<TypeError>>
+);
+
+/// Type data holding the callback installed with
+/// [`TypeRegistryExt::set_missing_type_retry()`](crate::TypeRegistryExt::set_missing_type_retry).
+///
+/// The callback receives the registry and the
+/// [`TypeError::UnregisteredType`] or [`TypeError::UnregisteredShortName`]
+/// error describing the missing type. It should register the type if it can,
+/// and return true if the failed operation should be retried.
+///
+/// The callback is only invoked for operations run with
+/// [`TypeRegistryExt::retry_on_missing_type()`](crate::TypeRegistryExt::retry_on_missing_type).
+/// Casts and (de)serialization outside of it fail with the error as usual,
+/// since they only have shared access to the registry, and cannot register
+/// anything.
+#[derive(Clone)]
+pub struct MissingTypeRetry(Arc<HandlerFn>);
+
+type HandlerFn = dyn Fn(&mut TypeRegistry, &TypeError) -> bool + Send + Sync;
+
+impl MissingTypeRetry {
+    /// Wrap a callback.
+    pub fn new<F>(handler: F) -> Self
+    where
+        F: Fn(&mut TypeRegistry, &TypeError) -> bool + Send + Sync + 'static,
+    {
+        MissingTypeRetry(Arc::new(handler))
+    }
+
+    /// Invoke the callback.
+    pub fn handle(&self, registry: &mut TypeRegistry, missing: &TypeError) -> bool {
+        (self.0)(registry, missing)
+    }
+}
+
+impl std::fmt::Debug for MissingTypeRetry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("MissingTypeRetry")
+    }
+}
+
+/// Record `error` as the missing type of the current
+/// [`retry_on_missing_type()`](crate::TypeRegistryExt::retry_on_missing_type)
+/// call, if any.
+pub(crate) fn report_missing(error: TypeError) -> TypeError {
+    if MISSING_TYPE.is_set() {
+        MISSING_TYPE.with(|missing| *missing.borrow_mut() = Some(error.clone()));
+    }
+    error
+}
+
+pub(crate) fn retry_on_missing_type<F, R, E>(registry: &mut TypeRegistry, mut f: F) -> Result<R, E>
+where
+    F: FnMut(&TypeRegistry) -> Result<R, E>,
+{
+    let missing = RefCell::new(None);
+    let result = MISSING_TYPE.set(&missing, || f(registry));
+    let (Err(_), Some(missing)) = (&result, missing.into_inner()) else {
+        return result;
+    };
+
+    let handler = match registry_data::<MissingTypeRetry>(registry) {
+        Some(handler) => handler.clone(),
+        None => return result,
+    };
+
+    if handler.handle(registry, &missing) {
+        f(registry)
+    } else {
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{reflect_trait, Reflect};
+
+    use super::*;
+    use crate::{impl_dynamic_trait, Cast, DowncastReflect, TypeRegistryExt};
+
+    #[reflect_trait]
+    trait Lazy: DowncastReflect {}
+    impl_dynamic_trait!(Lazy, ReflectLazy);
+
+    #[derive(Reflect)]
+    #[reflect(Lazy)]
+    struct Foo;
+    impl Lazy for Foo {}
+
+    #[test]
+    fn register_on_demand() {
+        let mut registry = TypeRegistry::default();
+        let foo: &dyn Reflect = &Foo;
+
+        let result = registry.retry_on_missing_type(|registry| {
+            Cast::<dyn Lazy>::try_cast_with_registry(foo, registry).map(|_| ())
+        });
+        assert!(matches!(result, Err(TypeError::UnregisteredType(_))));
+
+        registry.set_missing_type_retry(|registry, missing| match missing {
+            TypeError::UnregisteredType(name) if name == std::any::type_name::<Foo>() => {
+                registry.register::<Foo>();
+                true
+            }
+            _ => false,
+        });
+
+        let mut attempts = 0;
+        let result = registry.retry_on_missing_type(|registry| {
+            attempts += 1;
+            Cast::<dyn Lazy>::try_cast_with_registry(foo, registry).map(|_| ())
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(attempts, 2);
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+use bevy_reflect::Reflect;
+use serde::{Deserialize, Serialize};
+
+use crate::{clone_concrete, serialization};
+
+/// A reflected value of any registered type, which can be used like an
+/// ordinary value in other types.
+///
+/// - `Serialize` and `Deserialize` use tagged serialization (see
+///   [`serialization::serialize()`]), and require a current registry (see
+///   [`TypeRegistryExt::set_current()`](crate::TypeRegistryExt::set_current)).
+/// - `Clone` builds a value of the concrete type with `FromReflect`, if the
+///   type has [`ReflectFromDynamic`](crate::ReflectFromDynamic) type data in
+///   the current registry. Otherwise, the clone is a dynamic value (see
+///   [`clone_concrete()`]).
+/// - `PartialEq` uses [`Reflect::reflect_partial_eq()`], and values that
+///   cannot be compared are not equal.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, ReflectObject};
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Serialize, Deserialize)]
+/// struct Spawn { x: f32, y: f32 }
+///
+/// #[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+/// struct Event {
+///     frame: u64,
+///     payload: ReflectObject,
+/// }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Spawn>();
+///
+/// let event = Event { frame: 10, payload: ReflectObject::new(Spawn { x: 1.0, y: 2.0 }) };
+/// registry.set_current(|| {
+///     let json = serde_json::to_string(&event).unwrap();
+///     assert_eq!(json, r#"{"frame":10,"payload":{"type":"Spawn","x":1.0,"y":2.0}}"#);
+///
+///     let deserialized: Event = serde_json::from_str(&json).unwrap();
+///     assert_eq!(deserialized, event);
+///     assert!(deserialized.payload.is::<Spawn>());
+/// });
+/// ```
+pub struct ReflectObject(pub Box<dyn Reflect>);
+
+impl ReflectObject {
+    /// Wrap `value`.
+    pub fn new<T: Reflect>(value: T) -> Self {
+        ReflectObject(Box::new(value))
+    }
+
+    /// Unwrap the reflected value.
+    #[must_use]
+    pub fn into_inner(self) -> Box<dyn Reflect> {
+        self.0
+    }
+}
+
+impl From<Box<dyn Reflect>> for ReflectObject {
+    fn from(value: Box<dyn Reflect>) -> Self {
+        ReflectObject(value)
+    }
+}
+
+impl Deref for ReflectObject {
+    type Target = dyn Reflect;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl DerefMut for ReflectObject {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut *self.0
+    }
+}
+
+impl Clone for ReflectObject {
+    fn clone(&self) -> Self {
+        ReflectObject(clone_concrete(&*self.0))
+    }
+}
+
+impl PartialEq for ReflectObject {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.reflect_partial_eq(&*other.0).unwrap_or(false)
+    }
+}
+
+impl fmt::Debug for ReflectObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ReflectObject").field(&self.0).finish()
+    }
+}
+
+impl Serialize for ReflectObject {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serialization::serialize(&*self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ReflectObject {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        serialization::deserialize(deserializer).map(ReflectObject)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{FromReflect, TypeRegistry};
+
+    use super::*;
+    use crate::{ReflectFromDynamic, TypeRegistryExt};
+
+    #[derive(Debug, PartialEq, Reflect, FromReflect)]
+    #[reflect(FromDynamic, PartialEq)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[test]
+    fn clone_concrete_with_registry() {
+        let object = ReflectObject::new(Point { x: 1, y: 2 });
+
+        // Without a registry, the clone is dynamic.
+        let clone = object.clone();
+        assert!(!clone.is::<Point>());
+        assert_eq!(clone, object);
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<Point>();
+        let clone = registry.set_current(|| object.clone());
+        assert_eq!(clone.downcast_ref::<Point>(), Some(&Point { x: 1, y: 2 }));
+        assert_ne!(clone, ReflectObject::new(Point { x: 1, y: 3 }));
+    }
+}
@@ -0,0 +1,406 @@
+use std::fmt::Write as _;
+
+use bevy_reflect::{
+    NamedField, Reflect, ReflectRef, TypeInfo, TypeRegistry, UnnamedField, VariantInfo,
+};
+
+/// Options of [`pretty_print_with()`].
+///
+/// By default, the whole object graph is printed.
+#[derive(Default)]
+pub struct PrettyOptions {
+    max_depth: Option<usize>,
+    filter: Option<PathFilter>,
+}
+
+type PathFilter = Box<dyn Fn(&str) -> bool>;
+
+impl PrettyOptions {
+    /// The default options.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Print values nested at most `depth` levels below the root. The contents
+    /// of deeper values are elided as `...`.
+    #[must_use]
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Only print the values for whose path `filter` returns true, along with
+    /// their contents. Paths are in the syntax of [`walk()`](crate::walk).
+    #[must_use]
+    pub fn filter(mut self, filter: impl Fn(&str) -> bool + 'static) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+}
+
+/// Print `object` as an indented tree, with a line for each value inside it.
+///
+/// Each line gives the name of a field, element or map entry, its type, and,
+/// for primitives, strings, and unit variants, its value. Types are given by
+/// their short names. The type of a value is its declared type in its
+/// container, followed by its concrete type in parentheses if that is
+/// different, as for boxed trait objects. Concrete types are named by their
+/// registration in `registry`, if they are registered.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, pretty_print, reflect::TypeRegistry};
+/// #[reflect_trait]
+/// trait Light: DowncastReflect {}
+/// impl_dynamic_trait!(Light, ReflectLight);
+///
+/// #[derive(Reflect)]
+/// struct Spot { range: f32, color: (u8, u8, u8) }
+/// impl Light for Spot {}
+///
+/// #[derive(Reflect)]
+/// struct Scene { name: String, lights: Vec<Box<dyn Light>> }
+///
+/// let scene = Scene {
+///     name: "night".into(),
+///     lights: vec![Box::new(Spot { range: 5.0, color: (255, 200, 0) })],
+/// };
+/// assert_eq!(pretty_print(&scene, &TypeRegistry::default()), r#"Scene
+///   name: String = "night"
+///   lights: Vec<Box<dyn Light>>
+///     [0]: Box<dyn Light> (Spot)
+///       range: f32 = 5.0
+///       color: (u8, u8, u8)
+///         0: u8 = 255
+///         1: u8 = 200
+///         2: u8 = 0
+/// "#);
+/// ```
+pub fn pretty_print(object: &dyn Reflect, registry: &TypeRegistry) -> String {
+    pretty_print_with(object, registry, &PrettyOptions::default())
+}
+
+/// Print `object` as in [`pretty_print()`], limited by `options`.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, pretty_print_with, PrettyOptions, reflect::TypeRegistry};
+/// #[derive(Reflect)]
+/// struct Stats { hp: u32, position: (f32, f32), secret: String }
+///
+/// let stats = Stats { hp: 3, position: (1.0, 2.0), secret: "hunter2".into() };
+/// let options = PrettyOptions::new()
+///     .max_depth(1)
+///     .filter(|path| path != "secret");
+/// assert_eq!(pretty_print_with(&stats, &TypeRegistry::default(), &options), "\
+/// Stats
+///   hp: u32 = 3
+///   position: (f32, f32) = ...
+/// ");
+/// ```
+pub fn pretty_print_with(
+    object: &dyn Reflect,
+    registry: &TypeRegistry,
+    options: &PrettyOptions,
+) -> String {
+    let mut printer = Printer {
+        registry,
+        options,
+        out: String::new(),
+        path: String::new(),
+    };
+    printer.value(0, None, None, object);
+    printer.out
+}
+
+struct Printer<'a> {
+    registry: &'a TypeRegistry,
+    options: &'a PrettyOptions,
+    out: String,
+    /// The path of the current value, in the syntax of `walk()`.
+    path: String,
+}
+
+impl Printer<'_> {
+    /// Print the line of `value`, labeled `label` and declared as `declared`
+    /// in its container, and the lines of its contents.
+    // One arm per kind of value, which reads best as a single match.
+    #[allow(clippy::too_many_lines)]
+    fn value(
+        &mut self,
+        depth: usize,
+        label: Option<&str>,
+        declared: Option<&str>,
+        value: &dyn Reflect,
+    ) {
+        let indent = depth * 2;
+        let _ = write!(self.out, "{:indent$}", "");
+        if let Some(label) = label {
+            let _ = write!(self.out, "{label}: ");
+        }
+        let concrete = self.registry.get_with_name(value.type_name()).map_or_else(
+            || short_name(value.type_name()),
+            |registration| registration.short_name().to_string(),
+        );
+        match declared.map(short_name) {
+            Some(declared) if declared != concrete => {
+                let _ = write!(self.out, "{declared} ({concrete})");
+            }
+            _ => self.out.push_str(&concrete),
+        }
+
+        let reflect_ref = value.reflect_ref();
+        match reflect_ref {
+            ReflectRef::Value(value) => {
+                let _ = writeln!(self.out, " = {value:?}");
+                return;
+            }
+            ReflectRef::Enum(e) if e.field_len() == 0 => {
+                let _ = writeln!(self.out, " = {}", e.variant_name());
+                return;
+            }
+            // `List::is_empty()` is missing from `bevy_reflect` 0.9.
+            #[allow(clippy::len_zero)]
+            ReflectRef::List(list) if list.len() == 0 => {
+                self.out.push_str(" = []\n");
+                return;
+            }
+            ReflectRef::Map(map) if map.is_empty() => {
+                self.out.push_str(" = {}\n");
+                return;
+            }
+            ReflectRef::Enum(e) => {
+                let _ = write!(self.out, " = {}", e.variant_name());
+            }
+            _ => (),
+        }
+        if self.options.max_depth.is_some_and(|max| depth >= max) {
+            match reflect_ref {
+                ReflectRef::Enum(_) => self.out.push_str(" ...\n"),
+                _ => self.out.push_str(" = ...\n"),
+            }
+            return;
+        }
+        self.out.push('\n');
+
+        let info = value.get_type_info();
+        let depth = depth + 1;
+        match reflect_ref {
+            ReflectRef::Struct(s) => {
+                for (index, field) in s.iter_fields().enumerate() {
+                    let name = s.name_at(index).unwrap_or_default();
+                    let declared = match info {
+                        TypeInfo::Struct(info) => info.field_at(index).map(NamedField::type_name),
+                        _ => None,
+                    };
+                    self.child(depth, name, &format!(".{name}"), declared, field);
+                }
+            }
+            ReflectRef::TupleStruct(s) => {
+                for (index, field) in s.iter_fields().enumerate() {
+                    let declared = match info {
+                        TypeInfo::TupleStruct(info) => {
+                            info.field_at(index).map(UnnamedField::type_name)
+                        }
+                        _ => None,
+                    };
+                    let name = index.to_string();
+                    self.child(depth, &name, &format!(".{index}"), declared, field);
+                }
+            }
+            ReflectRef::Tuple(t) => {
+                for (index, field) in t.iter_fields().enumerate() {
+                    let declared = match info {
+                        TypeInfo::Tuple(info) => info.field_at(index).map(UnnamedField::type_name),
+                        _ => None,
+                    };
+                    let name = index.to_string();
+                    self.child(depth, &name, &format!(".{index}"), declared, field);
+                }
+            }
+            ReflectRef::List(list) => {
+                let declared = match info {
+                    TypeInfo::List(info) => Some(info.item_type_name()),
+                    _ => None,
+                };
+                for (index, element) in list.iter().enumerate() {
+                    let name = format!("[{index}]");
+                    self.child(depth, &name, &name, declared, element);
+                }
+            }
+            ReflectRef::Array(array) => {
+                let declared = match info {
+                    TypeInfo::Array(info) => Some(info.item_type_name()),
+                    _ => None,
+                };
+                for (index, element) in array.iter().enumerate() {
+                    let name = format!("[{index}]");
+                    self.child(depth, &name, &name, declared, element);
+                }
+            }
+            ReflectRef::Map(map) => {
+                let declared = match info {
+                    TypeInfo::Map(info) => Some(info.value_type_name()),
+                    _ => None,
+                };
+                for (key, element) in map.iter() {
+                    let name = format!("[{key:?}]");
+                    self.child(depth, &name, &name, declared, element);
+                }
+            }
+            ReflectRef::Enum(e) => {
+                let variant = match info {
+                    TypeInfo::Enum(info) => info.variant(e.variant_name()),
+                    _ => None,
+                };
+                for (index, field) in e.iter_fields().enumerate() {
+                    let declared = match variant {
+                        Some(VariantInfo::Struct(info)) => {
+                            info.field_at(index).map(NamedField::type_name)
+                        }
+                        Some(VariantInfo::Tuple(info)) => {
+                            info.field_at(index).map(UnnamedField::type_name)
+                        }
+                        _ => None,
+                    };
+                    if let Some(name) = field.name() {
+                        self.child(depth, name, &format!(".{name}"), declared, field.value());
+                    } else {
+                        let name = index.to_string();
+                        self.child(depth, &name, &format!(".{index}"), declared, field.value());
+                    }
+                }
+            }
+            ReflectRef::Value(_) => (),
+        }
+    }
+
+    /// Print a value inside the current value, if the filter allows its path,
+    /// which is the path of the current value followed by `segment`.
+    fn child(
+        &mut self,
+        depth: usize,
+        label: &str,
+        segment: &str,
+        declared: Option<&str>,
+        value: &dyn Reflect,
+    ) {
+        let len = self.path.len();
+        if self.path.is_empty() {
+            self.path
+                .push_str(segment.strip_prefix('.').unwrap_or(segment));
+        } else {
+            self.path.push_str(segment);
+        }
+        if self
+            .options
+            .filter
+            .as_ref()
+            .is_none_or(|filter| filter(&self.path))
+        {
+            self.value(depth, Some(label), declared, value);
+        }
+        self.path.truncate(len);
+    }
+}
+
+/// The name of a type without its module path, e.g. `Foo<Bar>` for
+/// `my_crate::Foo<my_crate::Bar>`.
+pub(crate) fn short_name(type_name: &str) -> String {
+    let mut short = String::with_capacity(type_name.len());
+    let mut segment_start = 0;
+    for (index, c) in type_name.char_indices() {
+        if matches!(c, '<' | '>' | ',' | ' ' | '(' | ')' | '[' | ']' | ';' | '&') {
+            short.push_str(last_segment(&type_name[segment_start..index]));
+            short.push(c);
+            segment_start = index + c.len_utf8();
+        }
+    }
+    short.push_str(last_segment(&type_name[segment_start..]));
+    short
+}
+
+fn last_segment(path: &str) -> &str {
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[reflect_trait]
+    trait Node: DowncastReflect {}
+    impl_dynamic_trait!(Node, ReflectNode);
+
+    #[derive(Reflect, FromReflect)]
+    enum Shape {
+        Point,
+        Circle { radius: f32 },
+    }
+
+    #[derive(Reflect)]
+    #[reflect(Node)]
+    struct Mesh {
+        shapes: Vec<Shape>,
+        tags: Vec<String>,
+    }
+    impl Node for Mesh {}
+
+    #[derive(Reflect)]
+    struct Root {
+        children: Vec<Box<dyn Node>>,
+    }
+
+    #[test]
+    // The filter compares paths, not file names.
+    #[allow(clippy::case_sensitive_file_extension_comparisons)]
+    fn enums_registry_names_and_filters() {
+        let root = Root {
+            children: vec![Box::new(Mesh {
+                shapes: vec![Shape::Point, Shape::Circle { radius: 1.0 }],
+                tags: vec![],
+            })],
+        };
+        let mut registry = TypeRegistry::default();
+        registry.register::<Mesh>();
+
+        assert_eq!(
+            pretty_print(&root, &registry),
+            "\
+Root
+  children: Vec<Box<dyn Node>>
+    [0]: Box<dyn Node> (Mesh)
+      shapes: Vec<Shape>
+        [0]: Shape = Point
+        [1]: Shape = Circle
+          radius: f32 = 1.0
+      tags: Vec<String> = []
+"
+        );
+
+        let options = PrettyOptions::new()
+            .filter(|path| !path.ends_with(".tags") && !path.ends_with("[0]"))
+            .max_depth(3);
+        assert_eq!(
+            pretty_print_with(&root, &registry, &options),
+            "\
+Root
+  children: Vec<Box<dyn Node>>
+"
+        );
+        let options = PrettyOptions::new()
+            .filter(|path| path != "children[0].tags")
+            .max_depth(3);
+        assert_eq!(
+            pretty_print_with(&root, &registry, &options),
+            "\
+Root
+  children: Vec<Box<dyn Node>>
+    [0]: Box<dyn Node> (Mesh)
+      shapes: Vec<Shape> = ...
+"
+        );
+    }
+}
@@ -0,0 +1,337 @@
+//! Recording and replaying mutations of reflected trait objects.
+//!
+//! A [`Recorder`] wraps a `Box<dyn MyTrait>` and performs all mutations
+//! through reflection, logging each one as an [`Operation`]. The operation
+//! list can be serialized (with a current registry, see
+//! [`TypeRegistryExt::set_current()`](crate::TypeRegistryExt::set_current)),
+//! for example to attach it to a bug report, and applied to another object
+//! with [`replay()`] to reproduce the same state deterministically.
+//!
+//! Values are recorded as [`Reflect::clone_value()`], which is a dynamic value
+//! for structs, tuples and collections. To serialize or replay a `Set` of such
+//! a value, its concrete type must have
+//! [`ReflectFromDynamic`](crate::ReflectFromDynamic) or
+//! [`ReflectDefault`](bevy_reflect::std_traits::ReflectDefault) type data (see
+//! [`from_dynamic()`](crate::from_dynamic)).
+//!
+//! ## Example
+//! ```rust
+//! # use reflect_steroids::{prelude::*, proxy::{self, Recorder}, reflect::TypeRegistry};
+//! #[reflect_trait]
+//! trait Unit: DowncastReflect {}
+//! impl_dynamic_trait!(Unit, ReflectUnit);
+//!
+//! #[derive(Reflect)]
+//! struct Stats { hp: i32, mana: i32 }
+//!
+//! #[derive(Reflect)]
+//! struct Player { stats: Stats }
+//! impl Unit for Player {}
+//!
+//! let new_player = || -> Box<dyn Unit> { Box::new(Player { stats: Stats { hp: 10, mana: 5 } }) };
+//!
+//! let mut recorder = Recorder::new(new_player());
+//! recorder.set_path("stats.hp", Box::new(7i32)).unwrap();
+//! recorder.set_path("stats.mana", Box::new(2i32)).unwrap();
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register::<i32>();
+//! let json = registry.set_current(|| serde_json::to_string(recorder.operations()).unwrap());
+//! assert_eq!(
+//!     json,
+//!     r#"[{"set":{"path":"stats.hp","value":{"type":"i32","value":7}}},{"set":{"path":"stats.mana","value":{"type":"i32","value":2}}}]"#
+//! );
+//!
+//! let operations: Vec<proxy::Operation> =
+//!     registry.set_current(|| serde_json::from_str(&json).unwrap());
+//! let mut copy = new_player();
+//! proxy::replay(&mut *copy, operations, &registry).unwrap();
+//! assert_eq!(copy.downcast_ref::<Player>().unwrap().stats.hp, 7);
+//! ```
+
+use bevy_reflect::{Reflect, TypeRegistry};
+use serde::{Deserialize, Serialize};
+
+use crate::{compat::reflect_path_mut, from_dynamic, try_apply_dyn, DynamicTrait, ProxyError};
+
+/// A mutation recorded by a [`Recorder`].
+///
+/// Paths use the syntax of [`GetPath`](bevy_reflect::GetPath). The empty path
+/// refers to the object itself.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    /// The value at `path` was replaced with `value` (see
+    /// [`Reflect::set()`]).
+    Set {
+        /// Location of the value.
+        path: String,
+        /// The new value.
+        #[serde(with = "recorded_value")]
+        value: Box<dyn Reflect>,
+    },
+    /// `value` was applied to the value at `path` (see
+    /// [`Reflect::apply()`]).
+    Apply {
+        /// Location of the value.
+        path: String,
+        /// The applied value.
+        #[serde(with = "recorded_value")]
+        value: Box<dyn Reflect>,
+    },
+}
+
+/// A wrapper around a trait object that records all mutations made through
+/// it. See the [module documentation](self).
+#[derive(Debug)]
+pub struct Recorder<T: DynamicTrait + ?Sized> {
+    target: Box<T>,
+    operations: Vec<Operation>,
+}
+
+impl<T: DynamicTrait + ?Sized> Recorder<T> {
+    /// Start recording mutations of `target`.
+    #[must_use]
+    pub fn new(target: Box<T>) -> Self {
+        Recorder {
+            target,
+            operations: Vec::new(),
+        }
+    }
+
+    /// The wrapped object.
+    #[must_use]
+    pub fn get(&self) -> &T {
+        &self.target
+    }
+
+    /// The operations recorded so far.
+    #[must_use]
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    /// Remove and return the operations recorded so far.
+    pub fn take_operations(&mut self) -> Vec<Operation> {
+        std::mem::take(&mut self.operations)
+    }
+
+    /// Stop recording, returning the wrapped object and the operations.
+    #[must_use]
+    pub fn into_inner(self) -> (Box<T>, Vec<Operation>) {
+        (self.target, self.operations)
+    }
+
+    /// Replace the value at `path` with `value`, which must be of the same
+    /// type.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `path` cannot be resolved, or if `value` is of a different type
+    /// than the value at `path`. Nothing is recorded.
+    pub fn set_path(&mut self, path: &str, value: Box<dyn Reflect>) -> Result<(), ProxyError> {
+        let recorded = value.clone_value();
+        set_at((*self.target).as_reflect_mut(), path, value)?;
+        self.operations.push(Operation::Set {
+            path: path.to_string(),
+            value: recorded,
+        });
+        Ok(())
+    }
+
+    /// Apply `value` to the value at `path` (see [`try_apply_dyn()`]).
+    ///
+    /// # Errors
+    ///
+    /// Fails if `path` cannot be resolved, or if `value` cannot be applied to
+    /// the value at `path`. Nothing is recorded.
+    pub fn apply_path(&mut self, path: &str, value: &dyn Reflect) -> Result<(), ProxyError> {
+        apply_at((*self.target).as_reflect_mut(), path, value)?;
+        self.operations.push(Operation::Apply {
+            path: path.to_string(),
+            value: value.clone_value(),
+        });
+        Ok(())
+    }
+
+    /// Apply `value` to the whole object.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `value` cannot be applied to the object. Nothing is recorded.
+    pub fn apply(&mut self, value: &dyn Reflect) -> Result<(), ProxyError> {
+        self.apply_path("", value)
+    }
+}
+
+/// Perform `operations` on `target`, in order.
+///
+/// Recorded values that are dynamic are converted to their concrete types
+/// with `registry` before they are set.
+///
+/// # Errors
+///
+/// Fails at the first operation whose path cannot be resolved in `target`,
+/// whose value cannot be set or applied, or whose recorded value cannot be
+/// converted to its concrete type. The earlier operations stay performed.
+pub fn replay<T: Reflect + ?Sized>(
+    target: &mut T,
+    operations: impl IntoIterator<Item = Operation>,
+    registry: &TypeRegistry,
+) -> Result<(), ProxyError> {
+    let target = target.as_reflect_mut();
+    for operation in operations {
+        match operation {
+            Operation::Set { path, value } => {
+                let value = if registry.get(value.as_any().type_id()).is_some() {
+                    value
+                } else {
+                    from_dynamic::<dyn Reflect>(&*value, registry)?
+                };
+                set_at(target, &path, value)?;
+            }
+            Operation::Apply { path, value } => apply_at(target, &path, &*value)?,
+        }
+    }
+    Ok(())
+}
+
+fn resolve_mut<'a>(
+    object: &'a mut dyn Reflect,
+    path: &str,
+) -> Result<&'a mut dyn Reflect, ProxyError> {
+    if path.is_empty() {
+        return Ok(object);
+    }
+    reflect_path_mut(object, path).map_err(|err| ProxyError::InvalidPath {
+        path: path.to_string(),
+        message: err.to_string(),
+    })
+}
+
+fn set_at(object: &mut dyn Reflect, path: &str, value: Box<dyn Reflect>) -> Result<(), ProxyError> {
+    let field = resolve_mut(object, path)?;
+    let expected = field.type_name().to_string();
+    field
+        .set(value)
+        .map_err(|value| ProxyError::MismatchedType {
+            path: path.to_string(),
+            expected,
+            found: value.type_name().to_string(),
+        })
+}
+
+fn apply_at(object: &mut dyn Reflect, path: &str, value: &dyn Reflect) -> Result<(), ProxyError> {
+    let field = resolve_mut(object, path)?;
+    try_apply_dyn(field, value)?;
+    Ok(())
+}
+
+/// (De)serialization of recorded values, which may be dynamic.
+mod recorded_value {
+    use bevy_reflect::{Reflect, TypeRegistry};
+    use serde::ser::Error as _;
+
+    use crate::{from_dynamic, serialization, TypeRegistryExt};
+
+    #[allow(clippy::borrowed_box)]
+    pub fn serialize<S>(value: &Box<dyn Reflect>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let concrete = TypeRegistry::try_with_current(|registry| {
+            if registry.get(value.as_any().type_id()).is_some() {
+                Ok(None)
+            } else {
+                from_dynamic::<dyn Reflect>(&**value, registry).map(Some)
+            }
+        })
+        .map_err(S::Error::custom)?
+        .map_err(S::Error::custom)?;
+
+        match concrete {
+            Some(concrete) => serialization::serialize(&*concrete, serializer),
+            None => serialization::serialize(&**value, serializer),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Box<dyn Reflect>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        serialization::deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{
+        reflect_trait, std_traits::ReflectDefault, ReflectDeserialize, ReflectSerialize,
+    };
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect, TypeRegistryExt};
+
+    #[reflect_trait]
+    trait Entity: DowncastReflect {}
+    impl_dynamic_trait!(Entity, ReflectEntity);
+
+    #[derive(Clone, Default, Reflect, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+    #[reflect(Default, Serialize, Deserialize)]
+    struct Position {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Reflect)]
+    struct Npc {
+        position: Position,
+        name: String,
+    }
+    impl Entity for Npc {}
+
+    fn npc() -> Box<dyn Entity> {
+        Box::new(Npc {
+            position: Position::default(),
+            name: "Bob".into(),
+        })
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Position>();
+        registry.register::<String>();
+
+        let mut recorder = Recorder::new(npc());
+        recorder
+            .set_path("position", Box::new(Position { x: 1, y: 2 }))
+            .unwrap();
+        recorder.apply_path("name", &String::from("Alice")).unwrap();
+        assert!(matches!(
+            recorder.set_path("name", Box::new(3i32)),
+            Err(ProxyError::MismatchedType { .. })
+        ));
+        assert_eq!(recorder.operations().len(), 2);
+
+        // Replay in memory, with dynamic values.
+        let (original, operations) = recorder.into_inner();
+        let json = registry.set_current(|| serde_json::to_string(&operations).unwrap());
+        let mut copy = npc();
+        replay(&mut *copy, operations, &registry).unwrap();
+        let copy = copy.downcast_ref::<Npc>().unwrap();
+        let original = original.downcast_ref::<Npc>().unwrap();
+        assert_eq!(copy.position, original.position);
+        assert_eq!(copy.name, "Alice");
+
+        // Replay from serialized operations.
+        let operations: Vec<Operation> =
+            registry.set_current(|| serde_json::from_str(&json).unwrap());
+        let mut copy = npc();
+        replay(&mut *copy, operations, &registry).unwrap();
+        assert_eq!(
+            copy.downcast_ref::<Npc>().unwrap().position,
+            Position { x: 1, y: 2 }
+        );
+    }
+}
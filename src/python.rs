@@ -0,0 +1,399 @@
+//! Python bindings for inspecting and editing reflected objects, with
+//! [pyo3](https://pyo3.rs). Requires the "pyo3" crate feature.
+//!
+//! A [`PyRegistry`] (`Registry` in Python) wraps a [`TypeRegistry`], and
+//! constructs objects by type name, lists the implementors of dynamic traits,
+//! and loads objects from tagged JSON. A [`PyReflect`] (`Object` in Python)
+//! wraps a `Box<dyn Reflect>`, and gives dict-like access to the values inside
+//! it by path, in the syntax of [`GetPath`](bevy_reflect::GetPath):
+//!
+//! - Booleans, integers, floats and strings are converted to and from the
+//!   corresponding Python values.
+//! - Other values are converted to and from Python dicts and lists in the
+//!   shape of their tagged JSON serialization (see
+//!   [`to_json_value()`](crate::serialization::to_json_value)). Setting such a
+//!   value applies it to the current value (see
+//!   [`try_apply_dyn()`](crate::try_apply_dyn)).
+//!
+//! Add the classes to a Python module with [`add_classes()`].
+//!
+//! ## Example
+//! ```rust
+//! # use pyo3::{prelude::*, types::PyDict};
+//! # use reflect_steroids::{prelude::*, python::PyRegistry, reflect::{std_traits::ReflectDefault, TypeRegistry}};
+//! #[reflect_trait]
+//! trait Enemy: DowncastReflect {}
+//! impl_dynamic_trait!(Enemy, ReflectEnemy, global);
+//!
+//! #[derive(Reflect, Default, serde::Serialize, serde::Deserialize)]
+//! #[reflect(Enemy, Default, Serialize, Deserialize)]
+//! struct Slime { hp: u32, name: String }
+//! impl Enemy for Slime {}
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register::<Slime>();
+//! registry.register_global_types();
+//!
+//! pyo3::prepare_freethreaded_python();
+//! Python::with_gil(|py| {
+//!     let locals = PyDict::new(py);
+//!     locals.set_item("registry", Py::new(py, PyRegistry::new(registry))?)?;
+//!     py.run(r#"
+//! assert registry.implementors("Enemy") == ["Slime"]
+//! slime = registry.construct("Slime")
+//! slime["hp"] = 10
+//! slime["name"] = "Gloop"
+//! assert slime["hp"] == 10
+//! assert slime.to_json() == '{"type":"Slime","hp":10,"name":"Gloop"}'
+//! "#, None, Some(locals))
+//! }).unwrap();
+//! ```
+
+// `#[pymethods]` of this version of `pyo3` registers the methods with an `impl`
+// inside a `const` item.
+#![allow(non_local_definitions)]
+
+use std::sync::Arc;
+
+use bevy_reflect::{Reflect, ReflectRef, TypeRegistry};
+use pyo3::{
+    exceptions::{PyKeyError, PyTypeError, PyValueError},
+    prelude::*,
+    types::{PyBool, PyDict, PyFloat, PyList, PyLong, PyString, PyTuple},
+};
+use serde_json::{Map, Number, Value};
+
+use crate::{
+    compat::{reflect_path, reflect_path_mut},
+    from_dynamic::{build_as, resolve_name},
+    serialization::{self, tag::type_tag},
+    try_apply_dyn, TypeRegistryExt,
+};
+
+/// A type registry, shared with Python as `Registry`.
+#[pyclass(name = "Registry")]
+pub struct PyRegistry {
+    registry: Arc<TypeRegistry>,
+}
+
+impl PyRegistry {
+    /// Wrap `registry`.
+    #[must_use]
+    pub fn new(registry: TypeRegistry) -> Self {
+        PyRegistry {
+            registry: Arc::new(registry),
+        }
+    }
+
+    /// The wrapped registry.
+    #[must_use]
+    pub fn registry(&self) -> &TypeRegistry {
+        &self.registry
+    }
+
+    /// Wrap `object`, to pass it to Python.
+    #[must_use]
+    pub fn wrap(&self, object: Box<dyn Reflect>) -> PyReflect {
+        PyReflect {
+            object,
+            registry: self.registry.clone(),
+        }
+    }
+}
+
+#[pymethods]
+impl PyRegistry {
+    /// The type tags of the registered implementors of a globally registered
+    /// dynamic trait.
+    fn implementors(&self, trait_name: &str) -> Vec<String> {
+        self.registry
+            .implementors_of(trait_name)
+            .into_iter()
+            .map(|registration| type_tag(registration).to_string())
+            .collect()
+    }
+
+    /// Construct the default value of the type named `type_name`.
+    fn construct(&self, type_name: &str) -> PyResult<PyReflect> {
+        let registration = resolve_name(&self.registry, type_name)
+            .ok_or_else(|| PyKeyError::new_err(format!("unregistered type '{type_name}'")))?;
+        let object = build_as::<dyn Reflect>(registration, None, &self.registry)
+            .map_err(|err| PyTypeError::new_err(err.to_string()))?;
+        Ok(self.wrap(object))
+    }
+
+    /// Load an object from tagged JSON.
+    fn load_json(&self, json: &str) -> PyResult<PyReflect> {
+        let object = serialization::from_json_tagged(json, &self.registry)
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(self.wrap(object))
+    }
+}
+
+/// A reflected object, shared with Python as `Object`.
+#[pyclass(name = "Object")]
+pub struct PyReflect {
+    object: Box<dyn Reflect>,
+    registry: Arc<TypeRegistry>,
+}
+
+impl PyReflect {
+    /// The wrapped object.
+    #[must_use]
+    pub fn object(&self) -> &dyn Reflect {
+        &*self.object
+    }
+
+    /// The wrapped object, mutably.
+    pub fn object_mut(&mut self) -> &mut dyn Reflect {
+        &mut *self.object
+    }
+
+    /// Unwrap the object.
+    #[must_use]
+    pub fn into_inner(self) -> Box<dyn Reflect> {
+        self.object
+    }
+}
+
+#[pymethods]
+impl PyReflect {
+    /// The full type name of the object.
+    #[getter]
+    fn type_name(&self) -> String {
+        self.object.type_name().to_string()
+    }
+
+    /// The names of the fields of the object, if it is a struct.
+    fn keys(&self) -> Vec<String> {
+        match self.object.reflect_ref() {
+            ReflectRef::Struct(s) => (0..s.field_len())
+                .filter_map(|index| s.name_at(index).map(str::to_string))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Serialize the object to tagged JSON.
+    fn to_json(&self) -> PyResult<String> {
+        serialization::to_json_tagged(&*self.object, &self.registry)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    fn __getitem__(&self, py: Python<'_>, path: &str) -> PyResult<PyObject> {
+        let value = reflect_path(&*self.object, path)
+            .map_err(|err| PyKeyError::new_err(err.to_string()))?;
+        to_python(py, value, &self.registry)
+    }
+
+    fn __setitem__(&mut self, path: &str, value: &PyAny) -> PyResult<()> {
+        let target = reflect_path_mut(&mut *self.object, path)
+            .map_err(|err| PyKeyError::new_err(err.to_string()))?;
+        from_python(target, value, &self.registry)
+    }
+}
+
+/// Add the `Registry` and `Object` classes to `module`.
+///
+/// # Errors
+///
+/// Fails if a class cannot be added to `module`.
+pub fn add_classes(module: &PyModule) -> PyResult<()> {
+    module.add_class::<PyRegistry>()?;
+    module.add_class::<PyReflect>()
+}
+
+macro_rules! primitives {
+    ($macro:ident!($($args:tt)*)) => {
+        $macro!($($args)*; bool, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64, String)
+    };
+}
+
+macro_rules! get_primitive {
+    ($py:expr, $value:expr; $($ty:ty),*) => {
+        $(
+            if let Some(value) = $value.downcast_ref::<$ty>() {
+                return Ok(value.to_object($py));
+            }
+        )*
+    };
+}
+
+macro_rules! set_primitive {
+    ($target:expr, $value:expr; $($ty:ty),*) => {
+        $(
+            if let Some(target) = $target.downcast_mut::<$ty>() {
+                *target = $value.extract()?;
+                return Ok(());
+            }
+        )*
+    };
+}
+
+fn to_python(py: Python<'_>, value: &dyn Reflect, registry: &TypeRegistry) -> PyResult<PyObject> {
+    primitives!(get_primitive!(py, value));
+    let json = registry
+        .set_current(|| serialization::to_json_value(value))
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+    Ok(json_to_python(py, &json))
+}
+
+fn from_python(target: &mut dyn Reflect, value: &PyAny, registry: &TypeRegistry) -> PyResult<()> {
+    primitives!(set_primitive!(target, value));
+    let json = python_to_json(value)?;
+    let value = registry
+        .set_current(|| serialization::from_json_value(json))
+        .map_err(|err| PyValueError::new_err(err.to_string()))?;
+    try_apply_dyn(target, &*value).map_err(|err| PyTypeError::new_err(err.to_string()))
+}
+
+fn json_to_python(py: Python<'_>, json: &Value) -> PyObject {
+    match json {
+        Value::Null => py.None(),
+        Value::Bool(value) => value.to_object(py),
+        Value::Number(number) => match (number.as_i64(), number.as_u64()) {
+            (Some(value), _) => value.to_object(py),
+            (_, Some(value)) => value.to_object(py),
+            _ => number.as_f64().unwrap_or(f64::NAN).to_object(py),
+        },
+        Value::String(value) => value.to_object(py),
+        Value::Array(values) => {
+            PyList::new(py, values.iter().map(|value| json_to_python(py, value))).into()
+        }
+        Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (key, value) in map {
+                dict.set_item(key, json_to_python(py, value))
+                    .expect("string keys are hashable");
+            }
+            dict.into()
+        }
+    }
+}
+
+fn python_to_json(value: &PyAny) -> PyResult<Value> {
+    Ok(if value.is_none() {
+        Value::Null
+    } else if let Ok(value) = value.downcast::<PyBool>() {
+        Value::Bool(value.is_true())
+    } else if value.downcast::<PyLong>().is_ok() {
+        match value.extract::<i64>() {
+            Ok(value) => Value::from(value),
+            Err(_) => Value::from(value.extract::<u64>()?),
+        }
+    } else if let Ok(value) = value.downcast::<PyFloat>() {
+        Number::from_f64(value.value())
+            .map(Value::Number)
+            .ok_or_else(|| PyValueError::new_err("non-finite floats cannot be converted"))?
+    } else if let Ok(value) = value.downcast::<PyString>() {
+        Value::String(value.to_str()?.to_string())
+    } else if let Ok(list) = value.downcast::<PyList>() {
+        Value::Array(list.iter().map(python_to_json).collect::<PyResult<_>>()?)
+    } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+        Value::Array(tuple.iter().map(python_to_json).collect::<PyResult<_>>()?)
+    } else if let Ok(dict) = value.downcast::<PyDict>() {
+        let mut map = Map::new();
+        for (key, value) in dict {
+            map.insert(key.extract::<String>()?, python_to_json(value)?);
+        }
+        Value::Object(map)
+    } else {
+        return Err(PyTypeError::new_err(format!(
+            "cannot convert '{}' to a reflected value",
+            value.get_type().name()?
+        )));
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{
+        reflect_trait, std_traits::ReflectDefault, ReflectDeserialize, ReflectSerialize,
+    };
+    use pyo3::types::PyDict;
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect};
+
+    #[reflect_trait]
+    trait Item: DowncastReflect {}
+    impl_dynamic_trait!(Item, ReflectItem, global);
+
+    #[derive(Reflect, Default, serde::Serialize, serde::Deserialize)]
+    #[reflect(Serialize, Deserialize, Default)]
+    struct Stats {
+        weight: f32,
+        tags: Vec<String>,
+    }
+
+    #[derive(Reflect, Default)]
+    #[reflect(Item, Default)]
+    struct Potion {
+        name: String,
+        doses: u8,
+        stats: Stats,
+    }
+    impl Item for Potion {}
+
+    #[test]
+    fn edit_from_python() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Potion>();
+        registry.register::<Stats>();
+        registry.register_global_types();
+        let registry = PyRegistry::new(registry);
+        let potion = registry.wrap(Box::new(Potion {
+            name: "Heal".into(),
+            doses: 2,
+            stats: Stats {
+                weight: 0.5,
+                tags: vec!["red".into()],
+            },
+        }));
+
+        pyo3::prepare_freethreaded_python();
+        let potion = Python::with_gil(|py| -> PyResult<Py<PyReflect>> {
+            let potion = Py::new(py, potion)?;
+            let locals = PyDict::new(py);
+            locals.set_item("registry", Py::new(py, registry)?)?;
+            locals.set_item("potion", potion.clone_ref(py))?;
+            py.run(
+                r#"
+assert registry.implementors("Item") == ["Potion"]
+assert potion.keys() == ["name", "doses", "stats"]
+assert potion["name"] == "Heal"
+assert potion["stats.weight"] == 0.5
+stats = potion["stats"]
+assert stats == {"type": "Stats", "weight": 0.5, "tags": ["red"]}
+stats["tags"].append("blue")
+potion["stats"] = stats
+potion["doses"] -= 1
+try:
+    potion["doses"] = "many"
+    assert False
+except TypeError:
+    pass
+try:
+    potion["missing"]
+    assert False
+except KeyError:
+    pass
+assert registry.construct("Potion")["doses"] == 0
+"#,
+                None,
+                Some(locals),
+            )?;
+            Ok(potion)
+        })
+        .unwrap();
+
+        let potion = Python::with_gil(|py| {
+            let potion = potion.as_ref(py).borrow();
+            potion
+                .object()
+                .downcast_ref::<Potion>()
+                .map(|potion| (potion.doses, potion.stats.tags.clone()))
+        });
+        assert_eq!(potion, Some((1, vec!["red".into(), "blue".into()])));
+    }
+}
@@ -0,0 +1,132 @@
+use std::{any::TypeId, collections::HashSet, sync::Arc};
+
+use bevy_reflect::{TypeRegistration, TypeRegistry};
+
+use crate::type_registry::{registry_data, registry_data_mut, user_registrations};
+
+type HookFn = dyn Fn(TypeId, &TypeRegistration) + Send + Sync;
+
+/// Registry-wide type data holding the hooks installed with
+/// [`TypeRegistryExt::on_register()`](crate::TypeRegistryExt::on_register),
+/// and the types they have been called for.
+#[derive(Clone, Default)]
+pub(crate) struct RegistrationHooks {
+    hooks: Vec<Arc<HookFn>>,
+    seen: HashSet<TypeId>,
+}
+
+pub(crate) fn add_hook<F>(registry: &mut TypeRegistry, hook: F)
+where
+    F: Fn(TypeId, &TypeRegistration) + Send + Sync + 'static,
+{
+    // Bring the existing hooks up to date first, so that all hooks have seen
+    // the same types.
+    run_hooks(registry);
+    let mut seen = HashSet::new();
+    for registration in user_registrations(registry) {
+        let type_id = registration.type_id();
+        hook(type_id, registration);
+        seen.insert(type_id);
+    }
+
+    let hooks = hooks_mut(registry);
+    hooks.hooks.push(Arc::new(hook));
+    hooks.seen = seen;
+}
+
+/// Call the hooks for the types that have been registered since the hooks
+/// were last run.
+pub(crate) fn run_hooks(registry: &mut TypeRegistry) {
+    let Some(hooks) = registry_data::<RegistrationHooks>(registry) else {
+        return;
+    };
+    let new: Vec<TypeId> = user_registrations(registry)
+        .map(TypeRegistration::type_id)
+        .filter(|type_id| !hooks.seen.contains(type_id))
+        .collect();
+    if new.is_empty() {
+        return;
+    }
+
+    for type_id in &new {
+        let registration = registry.get(*type_id).expect("type is registered");
+        for hook in &hooks.hooks {
+            hook(*type_id, registration);
+        }
+    }
+    hooks_mut(registry).seen.extend(new);
+}
+
+fn hooks_mut(registry: &mut TypeRegistry) -> &mut RegistrationHooks {
+    let registration = registry_data_mut(registry);
+    if registration.data::<RegistrationHooks>().is_none() {
+        registration.insert(RegistrationHooks::default());
+    }
+    registration
+        .data_mut::<RegistrationHooks>()
+        .expect("type data was just inserted")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use bevy_reflect::Reflect;
+
+    use super::*;
+    use crate::{serialization::SerializedAs, TypeRegistryExt};
+
+    #[derive(Reflect)]
+    struct Early;
+
+    #[derive(Reflect)]
+    struct Late;
+
+    #[derive(Reflect)]
+    struct Tagged;
+
+    #[test]
+    fn hooks_see_each_type_once() {
+        let mut registry = TypeRegistry::empty();
+        registry.register::<Early>();
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let hook_log = log.clone();
+        registry.on_register(move |type_id, registration| {
+            assert_eq!(type_id, registration.type_id());
+            hook_log
+                .lock()
+                .unwrap()
+                .push(registration.short_name().to_string());
+        });
+        assert_eq!(*log.lock().unwrap(), ["Early"]);
+
+        // Hooks see the type data inserted along with the registration.
+        let tags = Arc::new(Mutex::new(Vec::new()));
+        let hook_tags = tags.clone();
+        registry.on_register(move |_, registration| {
+            if let Some(serialized_as) = registration.data::<SerializedAs>() {
+                hook_tags.lock().unwrap().push(serialized_as.tag());
+            }
+        });
+
+        registry.register_with_hooks::<Late>();
+        registry.register_with_hooks::<Late>();
+        registry.register_serialized_as::<Tagged>("tagged");
+        assert_eq!(*log.lock().unwrap(), ["Early", "Late", "Tagged"]);
+        assert_eq!(*tags.lock().unwrap(), ["tagged"]);
+
+        // Registrations made directly are picked up when the hooks are run.
+        registry.register::<u8>();
+        registry.run_register_hooks();
+        registry.run_register_hooks();
+        assert_eq!(*log.lock().unwrap(), ["Early", "Late", "Tagged", "u8"]);
+
+        // A later hook catches up on all types.
+        let count = Arc::new(Mutex::new(0));
+        let hook_count = count.clone();
+        registry.on_register(move |_, _| *hook_count.lock().unwrap() += 1);
+        assert_eq!(*count.lock().unwrap(), 4);
+        assert_eq!(log.lock().unwrap().len(), 4);
+    }
+}
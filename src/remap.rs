@@ -0,0 +1,214 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash},
+};
+
+use bevy_reflect::{Reflect, ReflectMut};
+
+/// Rewrite every value of the ID type `I` inside `value` with `f`.
+///
+/// The object graph is walked recursively through struct, tuple and enum
+/// variant fields (including `Option`), list and array elements, and map
+/// values, including boxed trait objects (see
+/// [`impl_dynamic_trait!`](crate::impl_dynamic_trait)). Values of type `I`
+/// are not descended into. Map keys are not rewritten, because they
+/// cannot be changed in place.
+///
+/// This is typically used after deserializing a scene or a saved game, to
+/// rewrite references between objects (entity IDs, handles) into the IDs of
+/// the newly created objects.
+pub fn remap_ids_with<I, F>(value: &mut dyn Reflect, mut f: F)
+where
+    I: Reflect,
+    F: FnMut(&mut I),
+{
+    walk(value, &mut f);
+}
+
+/// Rewrite every value of the ID type `I` inside `value` that is a key of
+/// `map` to the corresponding value of `map`, and return the number of values
+/// that were rewritten. See [`remap_ids_with()`].
+///
+/// ## Example
+/// ```rust
+/// # use std::collections::HashMap;
+/// # use reflect_steroids::{prelude::*, remap_ids};
+/// #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect, FromReflect)]
+/// #[reflect_value(PartialEq, Hash)]
+/// struct EntityId(u32);
+///
+/// #[reflect_trait]
+/// trait Component: DowncastReflect {}
+/// impl_dynamic_trait!(Component, ReflectComponent);
+///
+/// #[derive(Reflect)]
+/// struct Follow { target: EntityId, fallback: Option<u32>, waypoints: Vec<EntityId> }
+/// impl Component for Follow {}
+///
+/// let mut component: Box<dyn Component> = Box::new(Follow {
+///     target: EntityId(1),
+///     fallback: None,
+///     waypoints: vec![EntityId(2), EntityId(3)],
+/// });
+///
+/// // IDs from the save file mapped to IDs of the spawned entities.
+/// let map = HashMap::from([(EntityId(1), EntityId(101)), (EntityId(3), EntityId(103))]);
+/// let remapped = remap_ids(component.as_reflect_mut(), &map);
+/// assert_eq!(remapped, 2);
+///
+/// let follow = component.downcast_ref::<Follow>().unwrap();
+/// assert_eq!(follow.target, EntityId(101));
+/// assert_eq!(follow.waypoints, [EntityId(2), EntityId(103)]);
+/// ```
+pub fn remap_ids<I, S>(value: &mut dyn Reflect, map: &HashMap<I, I, S>) -> usize
+where
+    I: Reflect + Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    let mut count = 0;
+    remap_ids_with(value, |id: &mut I| {
+        if let Some(new_id) = map.get(id) {
+            *id = new_id.clone();
+            count += 1;
+        }
+    });
+    count
+}
+
+fn walk<I: Reflect, F: FnMut(&mut I)>(value: &mut dyn Reflect, f: &mut F) {
+    if let Some(id) = value.downcast_mut::<I>() {
+        f(id);
+        return;
+    }
+
+    match value.reflect_mut() {
+        ReflectMut::Struct(s) => {
+            for index in 0..s.field_len() {
+                if let Some(field) = s.field_at_mut(index) {
+                    walk(field, f);
+                }
+            }
+        }
+        ReflectMut::TupleStruct(s) => {
+            for index in 0..s.field_len() {
+                if let Some(field) = s.field_mut(index) {
+                    walk(field, f);
+                }
+            }
+        }
+        ReflectMut::Tuple(t) => {
+            for index in 0..t.field_len() {
+                if let Some(field) = t.field_mut(index) {
+                    walk(field, f);
+                }
+            }
+        }
+        ReflectMut::List(list) => {
+            for index in 0..list.len() {
+                if let Some(element) = list.get_mut(index) {
+                    walk(element, f);
+                }
+            }
+        }
+        ReflectMut::Array(array) => {
+            for index in 0..array.len() {
+                if let Some(element) = array.get_mut(index) {
+                    walk(element, f);
+                }
+            }
+        }
+        ReflectMut::Map(map) => {
+            let keys: Vec<_> = map.iter().map(|(key, _)| key.clone_value()).collect();
+            for key in keys {
+                if let Some(value) = map.get_mut(&*key) {
+                    walk(value, f);
+                }
+            }
+        }
+        ReflectMut::Enum(e) => {
+            for index in 0..e.field_len() {
+                if let Some(field) = e.field_at_mut(index) {
+                    walk(field, f);
+                }
+            }
+        }
+        ReflectMut::Value(_) => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{DynamicMap, FromReflect, Map};
+
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect, FromReflect)]
+    #[reflect_value(PartialEq, Hash)]
+    struct Id(u32);
+
+    #[derive(Reflect)]
+    struct Node {
+        id: Id,
+        parent: (Id, u32),
+        children: [Id; 2],
+    }
+
+    #[test]
+    fn remap_nested() {
+        let mut node = Node {
+            id: Id(1),
+            parent: (Id(2), 2),
+            children: [Id(3), Id(1)],
+        };
+        let map = HashMap::from([(Id(1), Id(10)), (Id(2), Id(20))]);
+        assert_eq!(remap_ids(&mut node, &map), 3);
+        assert_eq!(node.id, Id(10));
+        assert_eq!(node.parent, (Id(20), 2));
+        assert_eq!(node.children, [Id(3), Id(10)]);
+    }
+
+    #[derive(Reflect, FromReflect)]
+    enum Target {
+        Entity(Id),
+        Area { center: Id, radius: f32 },
+    }
+
+    #[derive(Reflect)]
+    struct Order {
+        leader: Option<Id>,
+        target: Target,
+        next: Option<Target>,
+    }
+
+    #[test]
+    fn remap_enums() {
+        let mut order = Order {
+            leader: Some(Id(1)),
+            target: Target::Entity(Id(2)),
+            next: Some(Target::Area {
+                center: Id(1),
+                radius: 1.0,
+            }),
+        };
+        let map = HashMap::from([(Id(1), Id(10)), (Id(2), Id(20))]);
+        assert_eq!(remap_ids(&mut order, &map), 3);
+        assert_eq!(order.leader, Some(Id(10)));
+        assert!(matches!(order.target, Target::Entity(Id(20))));
+        assert!(matches!(
+            order.next,
+            Some(Target::Area { center: Id(10), .. })
+        ));
+
+        order.leader = None;
+        assert_eq!(remap_ids(&mut order, &map), 0);
+    }
+
+    #[test]
+    fn remap_map_values_only() {
+        let mut map = DynamicMap::default();
+        map.insert(Id(1), Id(1));
+        let ids = HashMap::from([(Id(1), Id(5))]);
+        assert_eq!(remap_ids(&mut map, &ids), 1);
+        assert_eq!(map.get(&Id(1)).unwrap().downcast_ref(), Some(&Id(5)));
+    }
+}
@@ -0,0 +1,285 @@
+//! Compact, type-tagged deltas for network replication.
+//!
+//! [`encode_update()`] compares two versions of a reflected object and encodes
+//! the fields that changed, along with the type tag of the object (see
+//! [`serialize()`](crate::serialization::serialize)). [`apply_update()`]
+//! applies such an update to the receiving side's copy of the object.
+//!
+//! Fields are compared with [`Reflect::reflect_partial_eq()`]. Fields that
+//! cannot be compared are always sent. If the objects are not structs, or are
+//! of different types, the update contains the whole new object.
+//!
+//! Every changed field (or the whole object) must be of a type with
+//! [`ReflectSerialize`](bevy_reflect::ReflectSerialize) and
+//! [`ReflectDeserialize`] type data in the registry.
+//!
+//! ## Example
+//! ```rust
+//! # use reflect_steroids::{prelude::*, reflect::TypeRegistry, replicate};
+//! #[reflect_trait]
+//! trait Replicated: DowncastReflect {}
+//! impl_dynamic_trait!(Replicated, ReflectReplicated);
+//!
+//! #[derive(Clone, Reflect, serde::Serialize, serde::Deserialize)]
+//! #[reflect(Replicated, Serialize, Deserialize)]
+//! struct Player { hp: i32, name: String }
+//! impl Replicated for Player {}
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register::<Player>();
+//! registry.register::<i32>();
+//! registry.register::<String>();
+//!
+//! let old = Player { hp: 10, name: "Alice".into() };
+//! let new = Player { hp: 7, ..old.clone() };
+//! let update = replicate::encode_update(&old, &new, &registry).unwrap();
+//!
+//! let mut remote: Box<dyn Replicated> = Box::new(old);
+//! replicate::apply_update(&mut remote, &update, &registry).unwrap();
+//! assert_eq!(remote.downcast_ref::<Player>().unwrap().hp, 7);
+//! ```
+
+use bevy_reflect::{Reflect, ReflectDeserialize, ReflectMut, ReflectRef, TypeRegistry};
+use serde::de::IntoDeserializer;
+
+use crate::{
+    serialization::{
+        binary::{read_value, write_value},
+        de::resolve_deserialize,
+        raw::{to_value, Value, ValueDeserializer, ValueError},
+        ser::{reflect_serialize, serialized_type_tag},
+    },
+    Cast, DynamicTrait, ReplicateError, TypeError, TypeRegistryExt,
+};
+
+const TYPE: &str = "type";
+const FIELDS: &str = "fields";
+const FULL: &str = "full";
+
+/// Encode the difference between `old` and `new` as a compact, type-tagged
+/// update message.
+///
+/// # Errors
+///
+/// Fails if a type in `new` is not registered, or cannot be serialized.
+pub fn encode_update(
+    old: &dyn Reflect,
+    new: &dyn Reflect,
+    registry: &TypeRegistry,
+) -> Result<Vec<u8>, ReplicateError> {
+    let tag = serialized_type_tag(registry, new)?;
+
+    let body = match (old.reflect_ref(), new.reflect_ref()) {
+        (ReflectRef::Struct(old_struct), ReflectRef::Struct(new_struct))
+            if old.as_any().type_id() == new.as_any().type_id() =>
+        {
+            let mut fields = Vec::new();
+            for (index, new_field) in new_struct.iter_fields().enumerate() {
+                let unchanged = old_struct
+                    .field_at(index)
+                    .and_then(|old_field| old_field.reflect_partial_eq(new_field))
+                    .unwrap_or(false);
+                if !unchanged {
+                    let name = new_struct.name_at(index).unwrap_or_default();
+                    fields.push((
+                        Value::String(name.to_string()),
+                        serialize_value(registry, new_field)?,
+                    ));
+                }
+            }
+            (FIELDS, Value::Map(fields))
+        }
+        _ => (FULL, serialize_value(registry, new)?),
+    };
+
+    let message = Value::Map(vec![
+        (Value::Str(TYPE), Value::String(tag.to_string())),
+        (Value::Str(body.0), body.1),
+    ]);
+    let mut bytes = Vec::new();
+    write_value(&message, &mut bytes);
+    Ok(bytes)
+}
+
+/// Apply an update produced by [`encode_update()`] to `target`.
+///
+/// If the update contains a whole object of a different type than `target`,
+/// `target` is replaced, provided the new object can be cast to `T`.
+///
+/// # Errors
+///
+/// Fails if the update is malformed, if it changes the fields of a type other
+/// than that of `target`, if a new object cannot be cast to `T`, or if a type
+/// in it is not registered or cannot be deserialized. `target` may be
+/// partially updated when a field fails.
+pub fn apply_update<T: DynamicTrait + ?Sized>(
+    target: &mut Box<T>,
+    update: &[u8],
+    registry: &TypeRegistry,
+) -> Result<(), ReplicateError> {
+    let (tag, body, value) = parse_message(update)?;
+
+    if body == FULL {
+        let object = deserialize_tagged(registry, &tag, value)?;
+        if object.as_any().type_id() == (**target).as_any().type_id() {
+            (**target)
+                .as_reflect_mut()
+                .set(object)
+                .map_err(|_| ReplicateError::Malformed("type changed during update"))?;
+        } else {
+            *target = Cast::<T>::try_cast_with_registry(object, registry)?;
+        }
+        Ok(())
+    } else {
+        let current_tag = serialized_type_tag(registry, (**target).as_reflect())?;
+        if current_tag != tag {
+            return Err(ReplicateError::MismatchedType {
+                target: current_tag.to_string(),
+                update: tag,
+            });
+        }
+        let Value::Map(fields) = value else {
+            return Err(ReplicateError::Malformed("expected a map of fields"));
+        };
+        let ReflectMut::Struct(target) = (**target).as_reflect_mut().reflect_mut() else {
+            return Err(ReplicateError::Malformed("field update for non-struct"));
+        };
+        for (name, value) in fields {
+            let name = name
+                .as_str()
+                .ok_or(ReplicateError::Malformed("invalid field name"))?;
+            let field = target
+                .field_mut(name)
+                .ok_or_else(|| ReplicateError::UnknownField(name.to_string()))?;
+            let new_value = deserialize_as(registry, field, value)?;
+            field
+                .set(new_value)
+                .map_err(|_| ReplicateError::Malformed("type changed during update"))?;
+        }
+        Ok(())
+    }
+}
+
+fn serialize_value(
+    registry: &TypeRegistry,
+    value: &dyn Reflect,
+) -> Result<Value<'static>, ReplicateError> {
+    let serialize = reflect_serialize(registry, value)?;
+    let serializable = serialize.get_serializable(value);
+    registry
+        .set_current(|| to_value(serializable.borrow()))
+        .map_err(|err| ReplicateError::Encoding(err.to_string()))
+}
+
+/// Deserialize `value` as the concrete type of `like`.
+fn deserialize_as(
+    registry: &TypeRegistry,
+    like: &dyn Reflect,
+    value: Value,
+) -> Result<Box<dyn Reflect>, ReplicateError> {
+    let deserialize = registry
+        .get_type_data::<ReflectDeserialize>(like.as_any().type_id())
+        .ok_or_else(|| {
+            TypeError::UnregisteredTrait(like.type_name().to_string().into(), "Deserialize")
+        })?;
+    let deserializer: ValueDeserializer<ValueError> = value.into_deserializer();
+    registry
+        .set_current(|| deserialize.deserialize(deserializer))
+        .map_err(|err| ReplicateError::Encoding(err.to_string()))
+}
+
+fn deserialize_tagged(
+    registry: &TypeRegistry,
+    tag: &str,
+    value: Value,
+) -> Result<Box<dyn Reflect>, ReplicateError> {
+    let (_, deserialize) = resolve_deserialize(registry, tag)?;
+    let deserializer: ValueDeserializer<ValueError> = value.into_deserializer();
+    registry
+        .set_current(|| deserialize.deserialize(deserializer))
+        .map_err(|err| ReplicateError::Encoding(err.to_string()))
+}
+
+fn parse_message(update: &[u8]) -> Result<(String, &'static str, Value<'_>), ReplicateError> {
+    let message = read_value(update).map_err(|err| ReplicateError::Encoding(err.to_string()))?;
+    let mut entries = match message {
+        Value::Map(entries) if entries.len() == 2 => entries,
+        _ => return Err(ReplicateError::Malformed("expected a map with two entries")),
+    };
+    let (body_key, body) = entries.pop().expect("two entries");
+    let (type_key, tag) = entries.pop().expect("two entries");
+    if type_key.as_str() != Some(TYPE) {
+        return Err(ReplicateError::Malformed("missing type tag"));
+    }
+    let tag = tag
+        .as_str()
+        .ok_or(ReplicateError::Malformed("invalid type tag"))?
+        .to_string();
+    let body_kind = match body_key.as_str() {
+        Some(FIELDS) => FIELDS,
+        Some(FULL) => FULL,
+        _ => return Err(ReplicateError::Malformed("unknown update kind")),
+    };
+    Ok((tag, body_kind, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{reflect_trait, ReflectSerialize};
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect};
+
+    #[reflect_trait]
+    trait Replicated: DowncastReflect {}
+    impl_dynamic_trait!(Replicated, ReflectReplicated);
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Replicated, Serialize, Deserialize)]
+    struct Foo {
+        a: i32,
+        b: i32,
+    }
+    impl Replicated for Foo {}
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Replicated, Serialize, Deserialize)]
+    struct Bar(i32);
+    impl Replicated for Bar {}
+
+    fn registry() -> TypeRegistry {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Foo>();
+        registry.register::<Bar>();
+        registry.register::<i32>();
+        registry
+    }
+
+    #[test]
+    fn replace_with_other_type() {
+        let registry = registry();
+        let update = encode_update(&Foo { a: 1, b: 2 }, &Bar(3), &registry).unwrap();
+        let mut target: Box<dyn Replicated> = Box::new(Foo { a: 1, b: 2 });
+        apply_update(&mut target, &update, &registry).unwrap();
+        assert_eq!(target.downcast_ref::<Bar>().unwrap().0, 3);
+    }
+
+    #[test]
+    fn only_changed_fields() {
+        let registry = registry();
+        let update = encode_update(&Foo { a: 1, b: 2 }, &Foo { a: 1, b: 3 }, &registry).unwrap();
+        let mut target: Box<dyn Replicated> = Box::new(Foo { a: 5, b: 2 });
+        apply_update(&mut target, &update, &registry).unwrap();
+        let foo = target.downcast_ref::<Foo>().unwrap();
+        assert_eq!((foo.a, foo.b), (5, 3));
+
+        let mut target: Box<dyn Replicated> = Box::new(Bar(1));
+        assert_eq!(
+            apply_update(&mut target, &update, &registry),
+            Err(ReplicateError::MismatchedType {
+                target: "Bar".into(),
+                update: "Foo".into(),
+            })
+        );
+    }
+}
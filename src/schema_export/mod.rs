@@ -0,0 +1,477 @@
+//! Generation of schema definitions for other languages from reflected type
+//! information.
+//!
+//! [`proto_for_trait()`] writes a [Protocol Buffers](https://protobuf.dev)
+//! (proto3) file describing all registered implementors of a dynamic trait: a
+//! message for the trait with a `oneof` over the implementors, standing in for
+//! the type tag, and a message for each struct, tuple struct, tuple and enum
+//! that the implementors contain.
+//!
+//! [`typescript_for_trait()`] writes TypeScript type definitions for the tagged
+//! JSON representation of the implementors of a dynamic trait, as a
+//! discriminated union on the `type` field.
+//!
+//! ## Example
+//! ```rust
+//! # use reflect_steroids::{prelude::*, reflect::TypeRegistry, schema_export::proto_for_trait};
+//! #[reflect_trait]
+//! trait Event: DowncastReflect {}
+//! impl_dynamic_trait!(Event, ReflectEvent);
+//!
+//! #[derive(Reflect)]
+//! #[reflect(Event)]
+//! struct Damage { amount: u32, source: Option<String>, tags: Vec<String> }
+//! impl Event for Damage {}
+//!
+//! #[derive(Reflect)]
+//! #[reflect(Event)]
+//! struct Despawn(u64);
+//! impl Event for Despawn {}
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register::<Damage>();
+//! registry.register::<Despawn>();
+//! registry.register::<Option<String>>();
+//!
+//! let proto = proto_for_trait::<dyn Event>(&registry, "game.events").unwrap();
+//! assert_eq!(proto, r#"syntax = "proto3";
+//!
+//! package game.events;
+//!
+//! message Event {
+//!   oneof type {
+//!     Damage damage = 1;
+//!     Despawn despawn = 2;
+//!   }
+//! }
+//!
+//! message Damage {
+//!   uint32 amount = 1;
+//!   optional string source = 2;
+//!   repeated string tags = 3;
+//! }
+//!
+//! message Despawn {
+//!   uint64 field_0 = 1;
+//! }
+//! "#);
+//! ```
+
+use std::{collections::BTreeMap, fmt::Write as _};
+
+use bevy_reflect::{
+    NamedField, TypeInfo, TypeRegistration, TypeRegistry, UnnamedField, VariantInfo,
+};
+
+use crate::{
+    serialization::{tag::type_tag, StableId},
+    type_registry::user_registrations,
+    DynamicTrait, SchemaExportError, TypeRegistryExt,
+};
+
+mod typescript;
+
+pub use typescript::typescript_for_trait;
+
+/// The largest protobuf field number.
+const MAX_FIELD_NUMBER: u32 = (1 << 29) - 1;
+/// Field numbers reserved by the protobuf implementation.
+const RESERVED_FIELD_NUMBERS: std::ops::RangeInclusive<u32> = 19000..=19999;
+
+/// Generate a proto3 file in `package` describing the registered implementors
+/// of the dynamic trait `P`.
+///
+/// The trait is described by a message with a `oneof` named `type`, with a
+/// member for each implementor, ordered by type tag. To keep the field numbers
+/// of the members stable as implementors are added, give the implementors a
+/// [`StableId`], which is used as their field number. Implementors without a
+/// stable ID are numbered after the largest stable ID, in order.
+///
+/// Messages are named after the type tags of their types (see
+/// [`serialize()`](crate::serialization::serialize())), and fields of tuple
+/// types are named `field_0`, `field_1`, and so on. An enum is described by a
+/// message with a nested message for each variant and a `oneof` named
+/// `variant`. Types of fields are looked up in `registry`, except for
+/// primitives, `String`, `Option<T>` and `Vec<T>`.
+///
+/// # Errors
+///
+/// Fails if a type cannot be described in protobuf, such as an opaque type,
+/// an unregistered type, an `Option` or `Vec` inside another `Option` or
+/// `Vec`, or a trait object. Also fails if a stable ID is not a valid field
+/// number.
+pub fn proto_for_trait<P: DynamicTrait + ?Sized>(
+    registry: &TypeRegistry,
+    package: &str,
+) -> Result<String, SchemaExportError> {
+    let mut implementors: Vec<&TypeRegistration> = user_registrations(registry)
+        .filter(|registration| registry.implements::<P>(registration.type_id()))
+        .collect();
+    implementors.sort_by_key(|registration| type_tag(registration));
+
+    let mut next_number = implementors
+        .iter()
+        .filter_map(|registration| registration.data::<StableId>())
+        .map(|StableId(id)| id + 1)
+        .max()
+        .unwrap_or(1);
+
+    let mut exporter = Exporter {
+        registry,
+        messages: BTreeMap::new(),
+        order: Vec::new(),
+    };
+    let mut members = String::new();
+    for registration in implementors {
+        let number = match registration.data::<StableId>() {
+            Some(&StableId(id))
+                if id == 0 || id > MAX_FIELD_NUMBER || RESERVED_FIELD_NUMBERS.contains(&id) =>
+            {
+                return Err(SchemaExportError::InvalidFieldNumber {
+                    type_name: registration.type_name().to_string(),
+                    id,
+                });
+            }
+            Some(&StableId(id)) => id,
+            None => {
+                next_number += 1;
+                next_number - 1
+            }
+        };
+        let FieldType::Single(type_name) = exporter.field_type(registration.type_name())? else {
+            return Err(unsupported(
+                registration.type_name(),
+                "a oneof member cannot be optional or repeated",
+            ));
+        };
+        let _ = writeln!(
+            members,
+            "    {type_name} {} = {number};",
+            snake_case(&identifier(type_tag(registration)))
+        );
+    }
+
+    let mut proto = format!("syntax = \"proto3\";\n\npackage {package};\n\n");
+    let _ = write!(
+        proto,
+        "message {} {{\n  oneof type {{\n{members}  }}\n}}\n",
+        identifier(P::reflect_name())
+    );
+    for name in &exporter.order {
+        let _ = write!(
+            proto,
+            "\nmessage {name} {{\n{}}}\n",
+            exporter.messages[name]
+        );
+    }
+    Ok(proto)
+}
+
+/// The type of a protobuf field.
+enum FieldType {
+    Single(String),
+    Optional(String),
+    Repeated(String),
+    Map(String, String),
+}
+
+impl FieldType {
+    /// Write the declaration of a field of this type.
+    fn declare(&self, out: &mut String, indent: &str, name: &str, number: usize) {
+        let _ = match self {
+            FieldType::Single(t) => writeln!(out, "{indent}{t} {name} = {number};"),
+            FieldType::Optional(t) => writeln!(out, "{indent}optional {t} {name} = {number};"),
+            FieldType::Repeated(t) => writeln!(out, "{indent}repeated {t} {name} = {number};"),
+            FieldType::Map(k, v) => writeln!(out, "{indent}map<{k}, {v}> {name} = {number};"),
+        };
+    }
+}
+
+struct Exporter<'a> {
+    registry: &'a TypeRegistry,
+    /// The bodies of the messages, by name.
+    messages: BTreeMap<String, String>,
+    /// The names of the messages, in the order they were first referenced.
+    order: Vec<String>,
+}
+
+impl Exporter<'_> {
+    /// Describe the type named `type_name`, generating messages as needed.
+    fn field_type(&mut self, type_name: &str) -> Result<FieldType, SchemaExportError> {
+        if let Some(scalar) = scalar(type_name) {
+            return Ok(FieldType::Single(scalar.to_string()));
+        }
+
+        let Some(registration) = self.registry.get_with_name(type_name) else {
+            // Common generic types are rarely registered for every parameter.
+            if let Some(item) = generic_argument(type_name, "core::option::Option<") {
+                return Ok(FieldType::Optional(self.single(type_name, item)?));
+            }
+            if let Some(item) = generic_argument(type_name, "alloc::vec::Vec<") {
+                return self.repeated(type_name, item);
+            }
+            return Err(unsupported(type_name, "the type is not registered"));
+        };
+
+        match registration.type_info() {
+            TypeInfo::List(info) => self.repeated(type_name, info.item_type_name()),
+            TypeInfo::Array(info) => self.repeated(type_name, info.item_type_name()),
+            TypeInfo::Map(info) => {
+                let key = match scalar(info.key_type_name()) {
+                    Some(key) if !matches!(key, "float" | "double" | "bytes") => key,
+                    _ => {
+                        return Err(unsupported(
+                            type_name,
+                            "map keys must be integers or strings",
+                        ))
+                    }
+                };
+                let value = self.single(type_name, info.value_type_name())?;
+                Ok(FieldType::Map(key.to_string(), value))
+            }
+            TypeInfo::Enum(info) if type_name.starts_with("core::option::Option<") => {
+                match info.variant("Some") {
+                    Some(VariantInfo::Tuple(some)) if some.field_len() == 1 => {
+                        let item = some.field_at(0).map_or("", UnnamedField::type_name);
+                        Ok(FieldType::Optional(self.single(type_name, item)?))
+                    }
+                    _ => Err(unsupported(type_name, "unexpected layout of `Option`")),
+                }
+            }
+            TypeInfo::Struct(_)
+            | TypeInfo::TupleStruct(_)
+            | TypeInfo::Tuple(_)
+            | TypeInfo::Enum(_) => Ok(FieldType::Single(self.message(registration)?)),
+            TypeInfo::Value(_) | TypeInfo::Dynamic(_) => Err(unsupported(
+                type_name,
+                "opaque types have no protobuf equivalent",
+            )),
+        }
+    }
+
+    /// Describe the type `item` of a value in `container`, which cannot be
+    /// optional or repeated itself.
+    fn single(&mut self, container: &str, item: &str) -> Result<String, SchemaExportError> {
+        match self.field_type(item)? {
+            FieldType::Single(item) => Ok(item),
+            _ => Err(unsupported(
+                container,
+                "optional and repeated values cannot be nested",
+            )),
+        }
+    }
+
+    /// Describe a sequence of `item`s. Sequences of bytes are `bytes`.
+    fn repeated(&mut self, container: &str, item: &str) -> Result<FieldType, SchemaExportError> {
+        if item == "u8" {
+            return Ok(FieldType::Single("bytes".to_string()));
+        }
+        Ok(FieldType::Repeated(self.single(container, item)?))
+    }
+
+    /// Generate the message of a struct, tuple struct, tuple or enum, unless
+    /// it has been generated already, and return its name.
+    fn message(&mut self, registration: &TypeRegistration) -> Result<String, SchemaExportError> {
+        let name = identifier(type_tag(registration));
+        if self.messages.contains_key(&name) {
+            return Ok(name);
+        }
+        // Claim the name first, so that recursive types refer to it.
+        self.messages.insert(name.clone(), String::new());
+        self.order.push(name.clone());
+
+        let mut body = String::new();
+        match registration.type_info() {
+            TypeInfo::Struct(info) => self.named_fields(&mut body, "  ", info.iter())?,
+            TypeInfo::TupleStruct(info) => self.unnamed_fields(&mut body, "  ", info.iter())?,
+            TypeInfo::Tuple(info) => self.unnamed_fields(&mut body, "  ", info.iter())?,
+            TypeInfo::Enum(info) => {
+                let mut oneof = String::new();
+                for (index, variant) in info.iter().enumerate() {
+                    let variant_name = identifier(variant.name());
+                    let mut fields = String::new();
+                    match variant {
+                        VariantInfo::Struct(info) => {
+                            self.named_fields(&mut fields, "    ", info.iter())?;
+                        }
+                        VariantInfo::Tuple(info) => {
+                            self.unnamed_fields(&mut fields, "    ", info.iter())?;
+                        }
+                        VariantInfo::Unit(_) => (),
+                    }
+                    if fields.is_empty() {
+                        let _ = writeln!(body, "  message {variant_name} {{}}");
+                    } else {
+                        let _ = writeln!(body, "  message {variant_name} {{\n{fields}  }}");
+                    }
+                    let _ = writeln!(
+                        oneof,
+                        "    {variant_name} {} = {};",
+                        snake_case(&variant_name),
+                        index + 1
+                    );
+                }
+                let _ = writeln!(body, "  oneof variant {{\n{oneof}  }}");
+            }
+            _ => {
+                return Err(unsupported(
+                    registration.type_name(),
+                    "expected a struct, tuple or enum",
+                ))
+            }
+        }
+        self.messages.insert(name.clone(), body);
+        Ok(name)
+    }
+
+    fn named_fields<'a>(
+        &mut self,
+        out: &mut String,
+        indent: &str,
+        fields: impl Iterator<Item = &'a NamedField>,
+    ) -> Result<(), SchemaExportError> {
+        for (index, field) in fields.enumerate() {
+            self.field_type(field.type_name())?
+                .declare(out, indent, field.name(), index + 1);
+        }
+        Ok(())
+    }
+
+    fn unnamed_fields<'a>(
+        &mut self,
+        out: &mut String,
+        indent: &str,
+        fields: impl Iterator<Item = &'a UnnamedField>,
+    ) -> Result<(), SchemaExportError> {
+        for (index, field) in fields.enumerate() {
+            self.field_type(field.type_name())?.declare(
+                out,
+                indent,
+                &format!("field_{index}"),
+                index + 1,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The protobuf scalar type of a primitive or string type.
+fn scalar(type_name: &str) -> Option<&'static str> {
+    Some(match type_name {
+        "bool" => "bool",
+        "u8" | "u16" | "u32" => "uint32",
+        "u64" | "usize" => "uint64",
+        "i8" | "i16" | "i32" => "int32",
+        "i64" | "isize" => "int64",
+        "f32" => "float",
+        "f64" => "double",
+        "char" | "alloc::string::String" | "&str" => "string",
+        _ => return None,
+    })
+}
+
+/// The argument of the generic type `type_name`, if it starts with `prefix`.
+fn generic_argument<'a>(type_name: &'a str, prefix: &str) -> Option<&'a str> {
+    type_name.strip_prefix(prefix)?.strip_suffix('>')
+}
+
+/// Turn a type tag into a protobuf identifier.
+fn identifier(tag: &str) -> String {
+    let mut identifier: String = tag
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !identifier.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        identifier.insert(0, 'T');
+    }
+    identifier
+}
+
+/// Turn a message name into a field name, e.g., `PlayerMoved` into
+/// `player_moved`.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (index, c) in name.char_indices() {
+        if c.is_ascii_uppercase() {
+            if index > 0 && !snake.ends_with('_') {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+fn unsupported(type_name: &str, reason: &'static str) -> SchemaExportError {
+    SchemaExportError::Unsupported {
+        type_name: type_name.to_string(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_utils::HashMap;
+
+    use bevy_reflect::FromReflect;
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[reflect_trait]
+    trait Command: DowncastReflect {}
+    impl_dynamic_trait!(Command, ReflectCommand);
+
+    #[derive(Reflect, FromReflect)]
+    enum Direction {
+        North,
+        Towards { x: f32, y: f32 },
+    }
+
+    #[derive(Reflect)]
+    #[reflect(Command)]
+    struct Move {
+        direction: Direction,
+        scores: HashMap<String, i64>,
+        payload: Vec<u8>,
+    }
+    impl Command for Move {}
+
+    #[derive(Reflect)]
+    #[reflect(Command)]
+    struct Batch(Vec<Vec<u32>>);
+    impl Command for Batch {}
+
+    #[test]
+    fn enums_maps_and_errors() {
+        let mut registry = TypeRegistry::default();
+        registry.register_stable_id::<Move>(5);
+        registry.register::<Direction>();
+        registry.register::<HashMap<String, i64>>();
+
+        let proto = proto_for_trait::<dyn Command>(&registry, "game").unwrap();
+        assert!(proto.contains("    Move move = 5;\n"));
+        assert!(proto.contains(
+            "message Move {\n  Direction direction = 1;\n  map<string, int64> scores = 2;\n  bytes payload = 3;\n}\n"
+        ));
+        assert!(proto.contains(
+            "message Direction {\n  message North {}\n  message Towards {\n    float x = 1;\n    float y = 2;\n  }\n  oneof variant {\n    North north = 1;\n    Towards towards = 2;\n  }\n}\n"
+        ));
+
+        registry.register::<Batch>();
+        let err = proto_for_trait::<dyn Command>(&registry, "game").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot export 'alloc::vec::Vec<alloc::vec::Vec<u32>>': optional and repeated values cannot be nested"
+        );
+
+        registry.register_stable_id::<Batch>(19_500);
+        let err = proto_for_trait::<dyn Command>(&registry, "game").unwrap_err();
+        assert!(matches!(
+            err,
+            SchemaExportError::InvalidFieldNumber { id: 19_500, .. }
+        ));
+    }
+}
@@ -0,0 +1,379 @@
+use std::{collections::BTreeMap, fmt::Write as _};
+
+use bevy_reflect::{
+    NamedField, TypeInfo, TypeRegistration, TypeRegistry, UnnamedField, VariantInfo,
+};
+
+use crate::{
+    serialization::tag::type_tag, type_registry::user_registrations, DynamicTrait,
+    SchemaExportError, TypeRegistryExt,
+};
+
+use super::{generic_argument, identifier, unsupported};
+
+/// Generate TypeScript type definitions for the tagged JSON representation
+/// (see [`serialize()`](crate::serialization::serialize())) of the registered
+/// implementors of the dynamic trait `P`.
+///
+/// The trait is described by a discriminated union named after the trait, with
+/// a member for each implementor, ordered by type tag, and for each variant of
+/// an implementor that is an enum. Structs, tuple structs and enums that the
+/// implementors contain are described by type aliases named after their type
+/// tags, matching serde's default representation: structs as objects (or
+/// `null` if they have no fields), tuple structs as tuples (or as their field,
+/// if they have one), and enums as externally tagged objects.
+///
+/// Trait objects refer to a type named after their trait, so that the
+/// definitions of other traits can be generated alongside. `Box<dyn Reflect>`
+/// is `unknown`. Types of fields are looked up in `registry`, except for
+/// primitives, `String`, `Option<T>`, `Vec<T>` and boxed trait objects.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, schema_export::typescript_for_trait};
+/// #[reflect_trait]
+/// trait Shape: DowncastReflect {}
+/// impl_dynamic_trait!(Shape, ReflectShape);
+///
+/// #[derive(Reflect)]
+/// #[reflect(Shape)]
+/// struct Foo { num: i32, label: Option<String> }
+/// impl Shape for Foo {}
+///
+/// #[derive(Reflect)]
+/// #[reflect(Shape)]
+/// struct Bar((f32, f32));
+/// impl Shape for Bar {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Foo>();
+/// registry.register::<Bar>();
+/// registry.register::<(f32, f32)>();
+///
+/// let ts = typescript_for_trait::<dyn Shape>(&registry).unwrap();
+/// assert_eq!(ts, r#"export type Shape =
+///   | { type: "Bar"; value: [number, number] }
+///   | { type: "Foo"; num: number; label: string | null };
+/// "#);
+/// ```
+///
+/// # Errors
+///
+/// Fails if a type cannot be described in TypeScript, such as an opaque type
+/// or an unregistered type.
+pub fn typescript_for_trait<P: DynamicTrait + ?Sized>(
+    registry: &TypeRegistry,
+) -> Result<String, SchemaExportError> {
+    let mut implementors: Vec<&TypeRegistration> = user_registrations(registry)
+        .filter(|registration| registry.implements::<P>(registration.type_id()))
+        .collect();
+    implementors.sort_by_key(|registration| type_tag(registration));
+
+    let mut exporter = Exporter {
+        registry,
+        definitions: BTreeMap::new(),
+        order: Vec::new(),
+    };
+    let mut members = Vec::new();
+    for registration in implementors {
+        let tag = format!("type: {:?}", type_tag(registration));
+        match registration.type_info() {
+            TypeInfo::Struct(info) => {
+                let fields = exporter.named_fields(info.iter())?;
+                members.push(object(std::iter::once(tag).chain(fields)));
+            }
+            TypeInfo::Enum(info) => {
+                for variant in info.iter() {
+                    let mut entries = vec![tag.clone(), format!("variant: {:?}", variant.name())];
+                    match variant {
+                        VariantInfo::Struct(info) => {
+                            entries.extend(exporter.named_fields(info.iter())?);
+                        }
+                        VariantInfo::Tuple(info) => {
+                            let value = exporter.unnamed_fields(info.iter())?;
+                            entries.push(format!("value: {value}"));
+                        }
+                        VariantInfo::Unit(_) => (),
+                    }
+                    members.push(object(entries));
+                }
+            }
+            TypeInfo::TupleStruct(info) => {
+                let value = exporter.unnamed_fields(info.iter())?;
+                members.push(object([tag, format!("value: {value}")]));
+            }
+            _ => {
+                let value = exporter.ts_type(registration.type_name())?;
+                members.push(object([tag, format!("value: {value}")]));
+            }
+        }
+    }
+
+    let mut ts = format!("export type {} =", identifier(P::reflect_name()));
+    if members.is_empty() {
+        ts.push_str(" never");
+    }
+    for member in members {
+        let _ = write!(ts, "\n  | {member}");
+    }
+    ts.push_str(";\n");
+    for name in &exporter.order {
+        let _ = write!(
+            ts,
+            "\nexport type {name} = {};\n",
+            exporter.definitions[name]
+        );
+    }
+    Ok(ts)
+}
+
+struct Exporter<'a> {
+    registry: &'a TypeRegistry,
+    /// The definitions of the type aliases, by name.
+    definitions: BTreeMap<String, String>,
+    /// The names of the type aliases, in the order they were first referenced.
+    order: Vec<String>,
+}
+
+impl Exporter<'_> {
+    /// Describe the type named `type_name`, generating type aliases as needed.
+    fn ts_type(&mut self, type_name: &str) -> Result<String, SchemaExportError> {
+        if let Some(primitive) = primitive(type_name) {
+            return Ok(primitive.to_string());
+        }
+        if let Some(trait_path) = generic_argument(type_name, "alloc::boxed::Box<dyn ") {
+            if trait_path == "bevy_reflect::reflect::Reflect" {
+                return Ok("unknown".to_string());
+            }
+            let name = trait_path.rsplit("::").next().unwrap_or(trait_path);
+            return Ok(identifier(name));
+        }
+
+        let Some(registration) = self.registry.get_with_name(type_name) else {
+            // Common generic types are rarely registered for every parameter.
+            if let Some(item) = generic_argument(type_name, "core::option::Option<") {
+                return Ok(format!("{} | null", self.ts_type(item)?));
+            }
+            if let Some(item) = generic_argument(type_name, "alloc::vec::Vec<") {
+                return self.array(item);
+            }
+            return Err(unsupported(type_name, "the type is not registered"));
+        };
+
+        match registration.type_info() {
+            TypeInfo::List(info) => self.array(info.item_type_name()),
+            TypeInfo::Array(info) => self.array(info.item_type_name()),
+            TypeInfo::Map(info) => Ok(format!(
+                "Record<string, {}>",
+                self.ts_type(info.value_type_name())?
+            )),
+            TypeInfo::Tuple(info) => {
+                let types = info
+                    .iter()
+                    .map(|field| self.ts_type(field.type_name()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(format!("[{}]", types.join(", ")))
+            }
+            TypeInfo::Enum(info) if type_name.starts_with("core::option::Option<") => {
+                match info.variant("Some") {
+                    Some(VariantInfo::Tuple(some)) if some.field_len() == 1 => {
+                        let item = some.field_at(0).map_or("", UnnamedField::type_name);
+                        Ok(format!("{} | null", self.ts_type(item)?))
+                    }
+                    _ => Err(unsupported(type_name, "unexpected layout of `Option`")),
+                }
+            }
+            TypeInfo::Struct(_) | TypeInfo::TupleStruct(_) | TypeInfo::Enum(_) => {
+                self.alias(registration)
+            }
+            TypeInfo::Value(_) | TypeInfo::Dynamic(_) => Err(unsupported(
+                type_name,
+                "opaque types have no TypeScript equivalent",
+            )),
+        }
+    }
+
+    /// Describe an array of `item`s.
+    fn array(&mut self, item: &str) -> Result<String, SchemaExportError> {
+        let item = self.ts_type(item)?;
+        if item.contains(' ') {
+            Ok(format!("({item})[]"))
+        } else {
+            Ok(format!("{item}[]"))
+        }
+    }
+
+    /// Generate the type alias of a struct, tuple struct or enum, unless it has
+    /// been generated already, and return its name.
+    fn alias(&mut self, registration: &TypeRegistration) -> Result<String, SchemaExportError> {
+        let name = identifier(type_tag(registration));
+        if self.definitions.contains_key(&name) {
+            return Ok(name);
+        }
+        // Claim the name first, so that recursive types refer to it.
+        self.definitions.insert(name.clone(), String::new());
+        self.order.push(name.clone());
+
+        let definition = match registration.type_info() {
+            TypeInfo::Struct(info) if info.field_len() == 0 => "null".to_string(),
+            TypeInfo::Struct(info) => object(self.named_fields(info.iter())?),
+            TypeInfo::TupleStruct(info) => self.unnamed_fields(info.iter())?,
+            TypeInfo::Enum(info) => {
+                let mut variants = Vec::new();
+                for variant in info.iter() {
+                    let name = format!("{:?}", variant.name());
+                    variants.push(match variant {
+                        VariantInfo::Struct(info) => {
+                            let fields = object(self.named_fields(info.iter())?);
+                            format!("{{ {name}: {fields} }}")
+                        }
+                        VariantInfo::Tuple(info) => {
+                            let fields = self.unnamed_fields(info.iter())?;
+                            format!("{{ {name}: {fields} }}")
+                        }
+                        VariantInfo::Unit(_) => name,
+                    });
+                }
+                if variants.is_empty() {
+                    "never".to_string()
+                } else {
+                    variants.join(" | ")
+                }
+            }
+            _ => {
+                return Err(unsupported(
+                    registration.type_name(),
+                    "expected a struct, tuple struct or enum",
+                ))
+            }
+        };
+        self.definitions.insert(name.clone(), definition);
+        Ok(name)
+    }
+
+    /// Describe named fields as the entries of an object type.
+    fn named_fields<'a>(
+        &mut self,
+        fields: impl Iterator<Item = &'a NamedField>,
+    ) -> Result<Vec<String>, SchemaExportError> {
+        fields
+            .map(|field| {
+                Ok(format!(
+                    "{}: {}",
+                    field.name(),
+                    self.ts_type(field.type_name())?
+                ))
+            })
+            .collect()
+    }
+
+    /// Describe unnamed fields as serde does: a single field as itself, and
+    /// other numbers of fields as a tuple.
+    fn unnamed_fields<'a>(
+        &mut self,
+        fields: impl Iterator<Item = &'a UnnamedField>,
+    ) -> Result<String, SchemaExportError> {
+        let mut types = fields
+            .map(|field| self.ts_type(field.type_name()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if types.len() == 1 {
+            Ok(types.remove(0))
+        } else {
+            Ok(format!("[{}]", types.join(", ")))
+        }
+    }
+}
+
+/// An object type with `entries`, on one line.
+fn object(entries: impl IntoIterator<Item = String>) -> String {
+    let entries: Vec<String> = entries.into_iter().collect();
+    if entries.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{ {} }}", entries.join("; "))
+    }
+}
+
+/// The TypeScript type of a primitive or string type.
+fn primitive(type_name: &str) -> Option<&'static str> {
+    Some(match type_name {
+        "bool" => "boolean",
+        "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64" | "i128"
+        | "isize" | "f32" | "f64" => "number",
+        "char" | "alloc::string::String" | "&str" => "string",
+        "()" => "null",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{FromReflect, Reflect};
+    use bevy_utils::HashMap;
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[reflect_trait]
+    trait Node: DowncastReflect {}
+    impl_dynamic_trait!(Node, ReflectNode);
+
+    #[derive(Reflect, FromReflect)]
+    enum Shape {
+        Empty,
+        Circle(f32),
+        Rect { w: f32, h: f32 },
+    }
+
+    #[derive(Reflect)]
+    #[reflect(Node)]
+    struct Group {
+        children: Vec<Box<dyn Node>>,
+        shapes: HashMap<String, Shape>,
+        parent: Option<Box<dyn Node>>,
+    }
+    impl Node for Group {}
+
+    #[derive(Reflect)]
+    #[reflect(Node)]
+    enum Light {
+        Ambient,
+        Point { range: f32 },
+        Spot(f32, f32),
+    }
+    impl Node for Light {}
+
+    #[derive(Reflect)]
+    #[reflect(Node)]
+    struct Timer(std::time::Duration);
+    impl Node for Timer {}
+
+    #[test]
+    fn unions_aliases_and_errors() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Group>();
+        registry.register::<Light>();
+        registry.register::<Shape>();
+        registry.register::<HashMap<String, Shape>>();
+
+        let ts = typescript_for_trait::<dyn Node>(&registry).unwrap();
+        assert_eq!(
+            ts,
+            r#"export type Node =
+  | { type: "Group"; children: Node[]; shapes: Record<string, Shape>; parent: Node | null }
+  | { type: "Light"; variant: "Ambient" }
+  | { type: "Light"; variant: "Point"; range: number }
+  | { type: "Light"; variant: "Spot"; value: [number, number] };
+
+export type Shape = "Empty" | { "Circle": number } | { "Rect": { w: number; h: number } };
+"#
+        );
+
+        registry.register::<Timer>();
+        let err = typescript_for_trait::<dyn Node>(&registry).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot export 'core::time::Duration': the type is not registered"
+        );
+    }
+}
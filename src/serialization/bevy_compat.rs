@@ -0,0 +1,165 @@
+use bevy_reflect::{serde::UntypedReflectDeserializer, Reflect, TypeRegistry};
+use serde::{
+    de::{DeserializeSeed, Error as _, IntoDeserializer},
+    Deserialize,
+};
+
+use crate::{from_dynamic, TypeRegistryExt};
+
+use super::value::Value;
+
+scoped_tls::scoped_thread_local!(static BEVY_COMPAT: ());
+
+/// Deserialize a value in the layout of `bevy_reflect`'s own
+/// [`ReflectSerializer`](bevy_reflect::serde::ReflectSerializer), as written
+/// by Bevy tooling:
+///
+/// ```json
+/// {"my_crate::Foo":{"num":123}}
+/// ```
+///
+/// The type is looked up by its full type name in the current registry (see
+/// [`TypeRegistryExt::set_current()`]). `bevy_reflect` produces dynamic
+/// values for all but primitive types, so the concrete value is built as by
+/// [`from_dynamic()`](crate::from_dynamic()), which requires the type to have
+/// [`ReflectFromDynamic`](crate::ReflectFromDynamic) or
+/// [`ReflectDefault`](bevy_reflect::std_traits::ReflectDefault) type data.
+///
+/// See also [`with_bevy_compat()`].
+///
+/// # Errors
+///
+/// Fails if no registry has been set for the current thread, if the input is
+/// not a map with a single entry, if the type is not registered, or if the
+/// value cannot be built.
+pub fn deserialize_bevy<'de, D>(deserializer: D) -> Result<Box<dyn Reflect>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    TypeRegistry::try_with_current(|registry| {
+        let value = UntypedReflectDeserializer::new(registry).deserialize(deserializer)?;
+        if registry.get(value.as_any().type_id()).is_some() {
+            return Ok(value);
+        }
+        from_dynamic::<dyn Reflect>(&*value, registry).map_err(D::Error::custom)
+    })
+    .map_err(D::Error::custom)?
+}
+
+/// Enable Bevy compatibility mode while running `f`.
+///
+/// In Bevy compatibility mode, [`deserialize()`](super::deserialize()), and
+/// therefore the `Deserialize` implementations of `Box<dyn MyTrait>`, accept
+/// both the tagged layout of this crate and the layout of `bevy_reflect`'s
+/// `ReflectSerializer` (see [`deserialize_bevy()`]). Objects are buffered
+/// before they are deserialized, to detect their layout.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, ReflectFromDynamic, reflect::{serde::ReflectSerializer, TypeRegistry}, serialization::with_bevy_compat};
+/// #[reflect_trait]
+/// trait Component: DowncastReflect {}
+/// impl_dynamic_trait!(Component, ReflectComponent);
+///
+/// #[derive(Reflect, FromReflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Component, FromDynamic, Serialize, Deserialize)]
+/// struct Health { current: u32 }
+/// impl Component for Health {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Health>();
+///
+/// // Written by Bevy tooling.
+/// let bevy_json = serde_json::to_string(&ReflectSerializer::new(&Health { current: 7 }, &registry)).unwrap();
+///
+/// registry.set_current(|| {
+///     with_bevy_compat(|| {
+///         let component: Box<dyn Component> = serde_json::from_str(&bevy_json).unwrap();
+///         assert_eq!(component.downcast_ref::<Health>().unwrap().current, 7);
+///
+///         let component: Box<dyn Component> =
+///             serde_json::from_str(r#"{"type":"Health","current":8}"#).unwrap();
+///         assert_eq!(component.downcast_ref::<Health>().unwrap().current, 8);
+///     });
+/// });
+/// ```
+pub fn with_bevy_compat<F: FnOnce() -> R, R>(f: F) -> R {
+    BEVY_COMPAT.set(&(), f)
+}
+
+/// True if Bevy compatibility mode is enabled.
+pub(crate) fn is_bevy_compat() -> bool {
+    BEVY_COMPAT.is_set()
+}
+
+/// Deserialize an object in either the tagged layout or Bevy's layout.
+pub(crate) fn deserialize_any_layout<'de, D>(deserializer: D) -> Result<Box<dyn Reflect>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    if is_tagged(&value) {
+        super::de::deserialize_tagged(value.into_deserializer())
+    } else {
+        deserialize_bevy(value.into_deserializer())
+    }
+}
+
+/// True if `value` is a map with a `type` entry, as written by
+/// [`serialize()`](super::serialize()). Bevy's layout is a map with a single
+/// entry, keyed by the full type name.
+fn is_tagged(value: &Value) -> bool {
+    match value {
+        Value::Map(entries) => entries.iter().any(|(key, value)| {
+            key.as_str() == Some("type")
+                && matches!(
+                    value,
+                    Value::Str(_)
+                        | Value::String(_)
+                        | Value::U8(_)
+                        | Value::U16(_)
+                        | Value::U32(_)
+                        | Value::U64(_)
+                )
+        }),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{serde::ReflectSerializer, std_traits::ReflectDefault};
+
+    use super::*;
+
+    #[derive(Default, Reflect)]
+    #[reflect(Default)]
+    struct Transform {
+        position: (f32, f32),
+        scale: f32,
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn bevy_layout_with_default() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Transform>();
+        registry.register::<(f32, f32)>();
+
+        let transform = Transform {
+            position: (1.0, 2.0),
+            scale: 3.0,
+        };
+        let json = serde_json::to_string(&ReflectSerializer::new(&transform, &registry)).unwrap();
+        let value = registry
+            .set_current(|| deserialize_bevy(&mut serde_json::Deserializer::from_str(&json)))
+            .unwrap();
+        let transform = value.downcast_ref::<Transform>().unwrap();
+        assert_eq!(transform.position, (1.0, 2.0));
+        assert_eq!(transform.scale, 3.0);
+
+        let value = registry
+            .set_current(|| deserialize_bevy(&mut serde_json::Deserializer::from_str("5.0")));
+        assert!(value.is_err());
+    }
+}
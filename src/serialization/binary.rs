@@ -0,0 +1,373 @@
+//! Compact self-describing binary encoding of [`Value`].
+//!
+//! This is used where the crate needs to hand an opaque byte representation of
+//! a payload to user code (see
+//! [`SerializationMiddleware`](super::SerializationMiddleware)). The encoding
+//! is not meant to be consumed by anything other than this crate.
+
+use bevy_reflect::Reflect;
+use serde::de::Error as _;
+
+use super::{
+    limits::enter,
+    ser::SerializeTagged,
+    value::{Value, ValueError},
+    value_ser::to_value,
+};
+
+const BOOL: u8 = 0;
+const U8: u8 = 1;
+const U16: u8 = 2;
+const U32: u8 = 3;
+const U64: u8 = 4;
+const I8: u8 = 5;
+const I16: u8 = 6;
+const I32: u8 = 7;
+const I64: u8 = 8;
+const F32: u8 = 9;
+const F64: u8 = 10;
+const CHAR: u8 = 11;
+const STRING: u8 = 12;
+const BYTES: u8 = 13;
+const NONE: u8 = 14;
+const SOME: u8 = 15;
+const UNIT: u8 = 16;
+const NEWTYPE: u8 = 17;
+const SEQ: u8 = 18;
+const MAP: u8 = 19;
+
+/// The size of the encoding of `this` with a type tag. Requires a current
+/// registry.
+pub(crate) fn encoded_size(this: &dyn Reflect) -> Result<usize, ValueError> {
+    let value = to_value(&SerializeTagged(this))?;
+    let mut out = Vec::new();
+    write_value(&value, &mut out);
+    Ok(out.len())
+}
+
+/// Encode `value`, appending to `out`.
+pub(crate) fn write_value(value: &Value, out: &mut Vec<u8>) {
+    match *value {
+        Value::Bool(b) => {
+            out.push(BOOL);
+            out.push(u8::from(b));
+        }
+        Value::U8(n) => {
+            out.push(U8);
+            out.push(n);
+        }
+        Value::U16(n) => {
+            out.push(U16);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        Value::U32(n) => {
+            out.push(U32);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        Value::U64(n) => {
+            out.push(U64);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        Value::I8(n) => {
+            out.push(I8);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        Value::I16(n) => {
+            out.push(I16);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        Value::I32(n) => {
+            out.push(I32);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        Value::I64(n) => {
+            out.push(I64);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        Value::F32(f) => {
+            out.push(F32);
+            out.extend_from_slice(&f.to_le_bytes());
+        }
+        Value::F64(f) => {
+            out.push(F64);
+            out.extend_from_slice(&f.to_le_bytes());
+        }
+        Value::Char(c) => {
+            out.push(CHAR);
+            out.extend_from_slice(&u32::from(c).to_le_bytes());
+        }
+        Value::String(ref s) => write_bytes(STRING, s.as_bytes(), out),
+        Value::Str(s) => write_bytes(STRING, s.as_bytes(), out),
+        Value::ByteBuf(ref b) => write_bytes(BYTES, b, out),
+        Value::Bytes(b) => write_bytes(BYTES, b, out),
+        Value::None => out.push(NONE),
+        Value::Some(ref v) => {
+            out.push(SOME);
+            write_value(v, out);
+        }
+        Value::Unit => out.push(UNIT),
+        Value::Newtype(ref v) => {
+            out.push(NEWTYPE);
+            write_value(v, out);
+        }
+        Value::Seq(ref v) => {
+            out.push(SEQ);
+            write_len(v.len(), out);
+            for element in v {
+                write_value(element, out);
+            }
+        }
+        Value::Map(ref v) => {
+            out.push(MAP);
+            write_len(v.len(), out);
+            for (key, value) in v {
+                write_value(key, out);
+                write_value(value, out);
+            }
+        }
+    }
+}
+
+fn write_len(len: usize, out: &mut Vec<u8>) {
+    out.extend_from_slice(&(len as u64).to_le_bytes());
+}
+
+fn write_bytes(tag: u8, bytes: &[u8], out: &mut Vec<u8>) {
+    out.push(tag);
+    write_len(bytes.len(), out);
+    out.extend_from_slice(bytes);
+}
+
+/// Decode a value previously encoded with [`write_value()`], borrowing strings
+/// and byte arrays from `bytes`.
+pub(crate) fn read_value(bytes: &[u8]) -> Result<Value<'_>, ValueError> {
+    let mut reader = Reader { bytes };
+    let value = reader.value()?;
+    if reader.bytes.is_empty() {
+        Ok(value)
+    } else {
+        Err(ValueError::custom("trailing bytes after encoded value"))
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ValueError> {
+        if self.bytes.len() < n {
+            return Err(ValueError::custom("unexpected end of encoded value"));
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ValueError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn len(&mut self) -> Result<usize, ValueError> {
+        usize::try_from(u64::from_le_bytes(self.array()?))
+            .map_err(|_| ValueError::custom("encoded length out of range"))
+    }
+
+    fn value(&mut self) -> Result<Value<'a>, ValueError> {
+        let _nesting = enter::<ValueError>()?;
+        let [tag] = self.array()?;
+        Ok(match tag {
+            BOOL => Value::Bool(self.array::<1>()?[0] != 0),
+            U8 => Value::U8(self.array::<1>()?[0]),
+            U16 => Value::U16(u16::from_le_bytes(self.array()?)),
+            U32 => Value::U32(u32::from_le_bytes(self.array()?)),
+            U64 => Value::U64(u64::from_le_bytes(self.array()?)),
+            I8 => Value::I8(i8::from_le_bytes(self.array()?)),
+            I16 => Value::I16(i16::from_le_bytes(self.array()?)),
+            I32 => Value::I32(i32::from_le_bytes(self.array()?)),
+            I64 => Value::I64(i64::from_le_bytes(self.array()?)),
+            F32 => Value::F32(f32::from_le_bytes(self.array()?)),
+            F64 => Value::F64(f64::from_le_bytes(self.array()?)),
+            CHAR => Value::Char(
+                char::from_u32(u32::from_le_bytes(self.array()?))
+                    .ok_or_else(|| ValueError::custom("invalid encoded char"))?,
+            ),
+            STRING => {
+                let len = self.len()?;
+                Value::Str(
+                    std::str::from_utf8(self.take(len)?)
+                        .map_err(|_| ValueError::custom("invalid UTF-8 in encoded string"))?,
+                )
+            }
+            BYTES => {
+                let len = self.len()?;
+                Value::Bytes(self.take(len)?)
+            }
+            NONE => Value::None,
+            SOME => Value::Some(Box::new(self.value()?)),
+            UNIT => Value::Unit,
+            NEWTYPE => Value::Newtype(Box::new(self.value()?)),
+            SEQ => {
+                let len = self.len()?;
+                // Don't trust the length for preallocation; every element
+                // takes at least one byte.
+                let mut elements = Vec::with_capacity(len.min(self.bytes.len()));
+                for _ in 0..len {
+                    elements.push(self.value()?);
+                }
+                Value::Seq(elements)
+            }
+            MAP => {
+                let len = self.len()?;
+                let mut entries = Vec::with_capacity(len.min(self.bytes.len() / 2));
+                for _ in 0..len {
+                    let key = self.value()?;
+                    let value = self.value()?;
+                    entries.push((key, value));
+                }
+                Value::Map(entries)
+            }
+            _ => return Err(ValueError::custom("invalid tag in encoded value")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::serialization::value::ValueDeserializer;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    enum Shape {
+        Point,
+        Circle { radius: f64 },
+        Rect(u16, u16),
+    }
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Everything {
+        flag: bool,
+        small: (u8, i8),
+        medium: (u16, i16, u32, i32),
+        large: (u64, i64),
+        floats: (f32, f64),
+        letter: char,
+        name: String,
+        #[serde(with = "serde_bytes_compat")]
+        raw: Vec<u8>,
+        missing: Option<u32>,
+        present: Option<u32>,
+        unit: (),
+        shapes: Vec<Shape>,
+        table: std::collections::BTreeMap<u32, String>,
+    }
+
+    mod serde_bytes_compat {
+        pub fn serialize<S: serde::Serializer>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error> {
+            s.serialize_bytes(bytes)
+        }
+
+        pub fn deserialize<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+            struct BytesVisitor;
+            impl serde::de::Visitor<'_> for BytesVisitor {
+                type Value = Vec<u8>;
+                fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    f.write_str("bytes")
+                }
+                fn visit_bytes<E>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+                    Ok(v.to_owned())
+                }
+            }
+            d.deserialize_bytes(BytesVisitor)
+        }
+    }
+
+    fn everything() -> Everything {
+        Everything {
+            flag: true,
+            small: (u8::MAX, i8::MIN),
+            medium: (u16::MAX, i16::MIN, u32::MAX, i32::MIN),
+            large: (u64::MAX, i64::MIN),
+            floats: (1.5, -0.25),
+            letter: 'ß',
+            name: "reflect".into(),
+            raw: vec![0, 1, 255],
+            missing: None,
+            present: Some(7),
+            unit: (),
+            shapes: vec![
+                Shape::Point,
+                Shape::Circle { radius: 2.0 },
+                Shape::Rect(3, 4),
+            ],
+            table: [(1, "one".into()), (2, "two".into())].into(),
+        }
+    }
+
+    fn encode(value: &Everything) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_value(&to_value(value).unwrap(), &mut out);
+        out
+    }
+
+    #[test]
+    fn round_trip() {
+        let original = everything();
+        let bytes = encode(&original);
+        let decoded = read_value(&bytes).unwrap();
+        let deserialized =
+            Everything::deserialize(ValueDeserializer::<ValueError>::new(decoded)).unwrap();
+        assert_eq!(deserialized, original);
+
+        // Strings and byte arrays are borrowed from the input.
+        let decoded = read_value(&bytes).unwrap();
+        let Value::Map(entries) = decoded else {
+            panic!("expected a map, got {decoded:?}");
+        };
+        assert!(entries
+            .iter()
+            .any(|(key, value)| key.as_str() == Some("name")
+                && matches!(value, Value::Str("reflect"))));
+        assert!(entries
+            .iter()
+            .any(|(_, value)| matches!(value, Value::Bytes([0, 1, 255]))));
+    }
+
+    #[test]
+    fn truncated_input() {
+        let bytes = encode(&everything());
+        for len in 0..bytes.len() {
+            let err = read_value(&bytes[..len]).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "unexpected end of encoded value",
+                "at {len}"
+            );
+        }
+
+        let mut trailing = bytes.clone();
+        trailing.push(UNIT);
+        assert_eq!(
+            read_value(&trailing).unwrap_err().to_string(),
+            "trailing bytes after encoded value"
+        );
+        assert_eq!(
+            read_value(&[MAP + 1]).unwrap_err().to_string(),
+            "invalid tag in encoded value"
+        );
+    }
+
+    #[test]
+    fn untrusted_lengths() {
+        // A huge length must not be used for preallocation.
+        for tag in [SEQ, MAP, STRING, BYTES] {
+            let mut bytes = vec![tag];
+            bytes.extend_from_slice(&(u64::MAX >> 1).to_le_bytes());
+            assert!(read_value(&bytes).is_err());
+        }
+    }
+}
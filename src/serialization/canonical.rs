@@ -0,0 +1,235 @@
+use std::cmp::Ordering;
+
+use super::value::Value;
+
+scoped_tls::scoped_thread_local!(static CANONICAL: ());
+
+/// Enable canonical serialization of tagged trait objects while running `f`.
+///
+/// In canonical mode, the contents of each tagged object (see
+/// [`serialize()`](super::serialize())) are buffered and normalized before they
+/// are written:
+///
+/// - Struct fields and map entries are sorted by key, so the output does not
+///   depend on field declaration order or on the iteration order of hash maps.
+/// - Negative zero is written as zero, and all NaNs are written as the same
+///   NaN.
+///
+/// The `type` tag of a flattened struct is always written first. With a
+/// deterministic serializer (such as `serde_json`), the same object graph
+/// always produces byte-identical output, which makes the output suitable for
+/// hashing.
+///
+/// ## Example
+/// ```rust
+/// # use std::collections::HashMap;
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::with_canonical};
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Serialize, Deserialize)]
+/// struct Chunk {
+///     name: String,
+///     offset: f32,
+///     #[reflect(ignore)]
+///     counts: HashMap<String, u32>,
+/// }
+///
+/// #[derive(serde::Serialize)]
+/// struct Save {
+///     #[serde(with = "reflect_steroids::serialization")]
+///     chunk: Box<dyn Reflect>,
+/// }
+///
+/// let save = Save {
+///     chunk: Box::new(Chunk {
+///         name: "a".into(),
+///         offset: -0.0,
+///         counts: [("z".to_string(), 1), ("y".to_string(), 2)].into_iter().collect(),
+///     }),
+/// };
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Chunk>();
+/// let json = registry.set_current(|| with_canonical(|| serde_json::to_string(&save).unwrap()));
+/// assert_eq!(
+///     json,
+///     r#"{"chunk":{"type":"Chunk","counts":{"y":2,"z":1},"name":"a","offset":0.0}}"#
+/// );
+/// ```
+pub fn with_canonical<F: FnOnce() -> R, R>(f: F) -> R {
+    CANONICAL.set(&(), f)
+}
+
+/// Like [`serialize()`](super::serialize()), but with canonical serialization
+/// enabled (see [`with_canonical()`]).
+///
+/// This function may be used in a `#[serde(serialize_with = "...")]` field
+/// attribute.
+///
+/// # Errors
+///
+/// Fails if `this` cannot be serialized (see
+/// [`serialize()`](super::serialize())).
+pub fn serialize_canonical<T, S>(this: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: super::SerializeDyn + ?Sized,
+    S: serde::Serializer,
+{
+    with_canonical(|| super::serialize(this, serializer))
+}
+
+/// True if canonical serialization is enabled.
+pub(crate) fn is_canonical() -> bool {
+    CANONICAL.is_set()
+}
+
+/// Sort all maps in `value` by key, and normalize all floats, recursively.
+pub(crate) fn canonicalize(value: &mut Value) {
+    match value {
+        Value::F32(f) => *f = normalize_f32(*f),
+        Value::F64(f) => *f = normalize_f64(*f),
+        Value::Some(inner) | Value::Newtype(inner) => canonicalize(inner),
+        Value::Seq(elements) => elements.iter_mut().for_each(canonicalize),
+        Value::Map(entries) => {
+            for (key, value) in entries.iter_mut() {
+                canonicalize(key);
+                canonicalize(value);
+            }
+            entries.sort_by(|(a, _), (b, _)| compare(a, b));
+        }
+        _ => (),
+    }
+}
+
+fn normalize_f32(f: f32) -> f32 {
+    if f.is_nan() {
+        f32::NAN
+    } else if f == 0.0 {
+        0.0
+    } else {
+        f
+    }
+}
+
+fn normalize_f64(f: f64) -> f64 {
+    if f.is_nan() {
+        f64::NAN
+    } else if f == 0.0 {
+        0.0
+    } else {
+        f
+    }
+}
+
+/// A total order of values, used for sorting map keys. Integers are compared
+/// numerically regardless of their width, and strings are compared regardless
+/// of whether they are borrowed.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    if let (Some(a), Some(b)) = (as_integer(a), as_integer(b)) {
+        return a.cmp(&b);
+    }
+    if let (Some(a), Some(b)) = (as_float(a), as_float(b)) {
+        return a.total_cmp(&b);
+    }
+    if let (Some(a), Some(b)) = (a.as_str(), b.as_str()) {
+        return a.cmp(b);
+    }
+    if let (Some(a), Some(b)) = (as_bytes(a), as_bytes(b)) {
+        return a.cmp(b);
+    }
+
+    match (a, b) {
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::Char(a), Value::Char(b)) => a.cmp(b),
+        (Value::Some(a), Value::Some(b)) | (Value::Newtype(a), Value::Newtype(b)) => compare(a, b),
+        (Value::Seq(a), Value::Seq(b)) => a
+            .iter()
+            .zip(b)
+            .map(|(a, b)| compare(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        (Value::Map(a), Value::Map(b)) => a
+            .iter()
+            .zip(b)
+            .map(|((a_key, a_value), (b_key, b_value))| {
+                compare(a_key, b_key).then_with(|| compare(a_value, b_value))
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn as_integer(value: &Value) -> Option<i128> {
+    Some(match *value {
+        Value::U8(n) => n.into(),
+        Value::U16(n) => n.into(),
+        Value::U32(n) => n.into(),
+        Value::U64(n) => n.into(),
+        Value::I8(n) => n.into(),
+        Value::I16(n) => n.into(),
+        Value::I32(n) => n.into(),
+        Value::I64(n) => n.into(),
+        _ => return None,
+    })
+}
+
+fn as_float(value: &Value) -> Option<f64> {
+    match *value {
+        Value::F32(f) => Some(f.into()),
+        Value::F64(f) => Some(f),
+        _ => None,
+    }
+}
+
+fn as_bytes<'a>(value: &'a Value) -> Option<&'a [u8]> {
+    match *value {
+        Value::ByteBuf(ref b) => Some(b),
+        Value::Bytes(b) => Some(b),
+        _ => None,
+    }
+}
+
+/// The order of values of different kinds.
+fn rank(value: &Value) -> u8 {
+    match value {
+        Value::Unit => 0,
+        Value::None => 1,
+        Value::Bool(_) => 2,
+        Value::U8(_)
+        | Value::U16(_)
+        | Value::U32(_)
+        | Value::U64(_)
+        | Value::I8(_)
+        | Value::I16(_)
+        | Value::I32(_)
+        | Value::I64(_) => 3,
+        Value::F32(_) | Value::F64(_) => 4,
+        Value::Char(_) => 5,
+        Value::String(_) | Value::Str(_) => 6,
+        Value::ByteBuf(_) | Value::Bytes(_) => 7,
+        Value::Some(_) => 8,
+        Value::Newtype(_) => 9,
+        Value::Seq(_) => 10,
+        Value::Map(_) => 11,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorts_nested_maps_and_normalizes_floats() {
+        let mut value = Value::map([
+            (
+                Value::U32(10),
+                Value::map([("b", Value::F64(-0.0)), ("a", Value::F32(f32::NAN))]),
+            ),
+            (Value::U8(2), Value::Unit),
+        ]);
+        canonicalize(&mut value);
+
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, r#"{"2":null,"10":{"a":null,"b":0.0}}"#);
+    }
+}
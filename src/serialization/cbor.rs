@@ -0,0 +1,184 @@
+//! Helpers for tagged serialization to and from [CBOR](https://cbor.io).
+//! Requires the "cbor" crate feature.
+//!
+//! The type of an object is identified by a CBOR semantic tag (major type 6)
+//! wrapping its serialized value, instead of a string type tag. The tag number
+//! is [`CBOR_TAG_BASE`] plus the [`StableId`] of the type, so CBOR libraries
+//! in other languages can recognize and route objects by tag number without
+//! knowing the Rust type names.
+//!
+//! Only the outermost object is tagged this way. Trait objects nested in its
+//! fields are written with string type tags, as by
+//! [`serialize()`](super::serialize()).
+//!
+//! ## Example
+//! ```rust
+//! # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::{from_cbor_tagged, to_cbor_tagged, CBOR_TAG_BASE}};
+//! #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+//! #[reflect(Serialize, Deserialize)]
+//! struct Chat { text: String }
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register_stable_id::<Chat>(12);
+//!
+//! let bytes = to_cbor_tagged(&Chat { text: "hi".into() }, &registry).unwrap();
+//! // Tag 65548, as a 4-byte head.
+//! assert_eq!(bytes[..5], [0xda, 0x00, 0x01, 0x00, 0x0c]);
+//! assert_eq!(CBOR_TAG_BASE + 12, 65548);
+//!
+//! let chat = from_cbor_tagged(&bytes, &registry).unwrap();
+//! assert_eq!(chat.downcast_ref::<Chat>().unwrap().text, "hi");
+//! ```
+
+use std::any::Any;
+
+use bevy_reflect::{Reflect, ReflectDeserialize, ReflectSerialize, TypeRegistry};
+use ciborium::value::Value as Cbor;
+use serde::{de::Error as _, ser::Error as _};
+
+use crate::TypeRegistryExt;
+
+use super::{
+    tag::{resolve_stable_id, StableId},
+    value::{Value, ValueDeserializer, ValueError},
+};
+
+/// The CBOR tag number of the type with stable ID 0. The tag numbers are in the
+/// "first come first served" range of the IANA registry of CBOR tags, and are
+/// encoded in 5 bytes for IDs below `u32::MAX - CBOR_TAG_BASE`.
+pub const CBOR_TAG_BASE: u64 = 0x1_0000;
+
+/// Errors from [`to_cbor_tagged()`].
+pub type CborSerializeError = ciborium::ser::Error<std::io::Error>;
+/// Errors from [`from_cbor_tagged()`].
+pub type CborDeserializeError = ciborium::de::Error<std::io::Error>;
+
+/// Serialize `this` to CBOR, tagged with the [`StableId`] of its type in
+/// `registry`. See the [module documentation](self).
+///
+/// # Errors
+///
+/// Fails if the type of `this` is not registered, has no stable ID or no
+/// `ReflectSerialize` type data, or if the value cannot be serialized.
+pub fn to_cbor_tagged(
+    this: &dyn Reflect,
+    registry: &TypeRegistry,
+) -> Result<Vec<u8>, CborSerializeError> {
+    let registration = registry.get(Any::type_id(this.as_any())).ok_or_else(|| {
+        CborSerializeError::custom(format_args!("unregistered type: {}", this.type_name()))
+    })?;
+    let StableId(id) = registration.data::<StableId>().ok_or_else(|| {
+        CborSerializeError::custom(format_args!("'{}' has no stable ID", this.type_name()))
+    })?;
+    let serialize = registration.data::<ReflectSerialize>().ok_or_else(|| {
+        CborSerializeError::custom(format_args!(
+            "#[reflect(Serialize)] is missing from '{}'",
+            this.type_name()
+        ))
+    })?;
+
+    let serializable = serialize.get_serializable(this);
+    let value = registry
+        .set_current(|| Cbor::serialized(serializable.borrow()))
+        .map_err(CborSerializeError::custom)?;
+    let tagged = Cbor::Tag(CBOR_TAG_BASE + u64::from(*id), Box::new(value));
+    let mut bytes = Vec::new();
+    ciborium::ser::into_writer(&tagged, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Deserialize an object written by [`to_cbor_tagged()`], looking up its type
+/// by the [`StableId`] in its tag number in `registry`.
+///
+/// # Errors
+///
+/// Fails if `bytes` is not a single tagged CBOR value, if no type has the
+/// stable ID of the tag, if the type has no `ReflectDeserialize` type data, or
+/// if the value cannot be deserialized.
+pub fn from_cbor_tagged(
+    bytes: &[u8],
+    registry: &TypeRegistry,
+) -> Result<Box<dyn Reflect>, CborDeserializeError> {
+    let mut rest = bytes;
+    let Cbor::Tag(tag, value) = ciborium::de::from_reader(&mut rest)? else {
+        return Err(CborDeserializeError::custom("expected a tagged value"));
+    };
+    if !rest.is_empty() {
+        return Err(CborDeserializeError::custom(format_args!(
+            "{} trailing bytes after the object",
+            rest.len()
+        )));
+    }
+
+    let registration = tag
+        .checked_sub(CBOR_TAG_BASE)
+        .and_then(|id| u32::try_from(id).ok())
+        .and_then(|id| resolve_stable_id(registry, id))
+        .ok_or_else(|| CborDeserializeError::custom(format_args!("unknown CBOR tag {tag}")))?;
+    let deserialize = registration.data::<ReflectDeserialize>().ok_or_else(|| {
+        CborDeserializeError::custom(format_args!(
+            "#[reflect(Deserialize)] is missing from '{}'",
+            registration.type_name()
+        ))
+    })?;
+
+    let value: Value = value.deserialized().map_err(CborDeserializeError::custom)?;
+    registry
+        .set_current(|| deserialize.deserialize(ValueDeserializer::<ValueError>::new(value)))
+        .map_err(CborDeserializeError::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[reflect_trait]
+    trait Shape: DowncastReflect {}
+    impl_dynamic_trait!(Shape, ReflectShape);
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Shape, Serialize, Deserialize)]
+    struct Circle {
+        radius: f32,
+    }
+    impl Shape for Circle {}
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Serialize, Deserialize)]
+    struct Scene {
+        shapes: Vec<Box<dyn Shape>>,
+        name: Option<String>,
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn nested_objects_and_errors() {
+        let mut registry = TypeRegistry::default();
+        registry.register_stable_id::<Scene>(3);
+        registry.register::<Circle>();
+
+        let scene = Scene {
+            shapes: vec![Box::new(Circle { radius: 2.0 })],
+            name: Some("intro".into()),
+        };
+        let bytes = to_cbor_tagged(&scene, &registry).unwrap();
+        let object = from_cbor_tagged(&bytes, &registry).unwrap();
+        let scene = object.downcast_ref::<Scene>().unwrap();
+        assert_eq!(scene.name.as_deref(), Some("intro"));
+        let circle = scene.shapes[0].downcast_ref::<Circle>().unwrap();
+        assert_eq!(circle.radius, 2.0);
+
+        let err = to_cbor_tagged(&Circle { radius: 1.0 }, &registry).unwrap_err();
+        assert!(err.to_string().contains("has no stable ID"));
+
+        let mut unknown = bytes.clone();
+        unknown[4] = 4;
+        let err = from_cbor_tagged(&unknown, &registry).unwrap_err();
+        assert!(err.to_string().contains("unknown CBOR tag 65540"));
+
+        let mut trailing = bytes;
+        trailing.push(0xf6);
+        assert!(from_cbor_tagged(&trailing, &registry).is_err());
+    }
+}
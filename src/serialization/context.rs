@@ -0,0 +1,125 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt,
+};
+
+scoped_tls::scoped_thread_local!(
+    static CURRENT_CONTEXT: DeserializeContext
+);
+
+/// External state for deserialization, such as an asset server or an entity
+/// map, keyed by type.
+///
+/// Set a context for the current thread with
+/// [`DeserializeContext::set_current()`], alongside the current registry (see
+/// [`TypeRegistryExt::set_current()`](crate::TypeRegistryExt::set_current)).
+/// `Deserialize` implementations of concrete types can then read it with
+/// [`DeserializeContext::with_current()`], also when they are deserialized as
+/// part of a `Box<dyn MyTrait>`.
+///
+/// ## Example
+/// ```rust
+/// # use std::collections::HashMap;
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::DeserializeContext};
+/// # use serde::de::Error as _;
+/// struct AssetServer { ids: HashMap<String, u32> }
+///
+/// #[derive(Clone, Copy, Debug, PartialEq, Reflect, serde::Serialize)]
+/// #[reflect_value(Serialize, Deserialize)]
+/// struct Handle(u32);
+///
+/// impl<'de> serde::Deserialize<'de> for Handle {
+///     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+///         let path = String::deserialize(deserializer)?;
+///         DeserializeContext::with_current(|assets: &AssetServer| assets.ids.get(&path).copied())
+///             .flatten()
+///             .map(Handle)
+///             .ok_or_else(|| D::Error::custom(format!("unknown asset: {path}")))
+///     }
+/// }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Handle>();
+/// let context = DeserializeContext::new().with(AssetServer {
+///     ids: HashMap::from([("tree.png".to_string(), 7)]),
+/// });
+///
+/// let json = r#"{"type":"Handle","value":"tree.png"}"#;
+/// let handle: Box<dyn Reflect> = registry.set_current(|| {
+///     context.set_current(|| {
+///         reflect_steroids::serialization::deserialize(&mut serde_json::Deserializer::from_str(json))
+///     })
+/// })
+/// .unwrap();
+/// assert_eq!(handle.downcast_ref::<Handle>(), Some(&Handle(7)));
+/// ```
+#[derive(Default)]
+pub struct DeserializeContext {
+    values: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl DeserializeContext {
+    /// Create an empty context.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `value` to the context, replacing any value of the same type.
+    #[must_use]
+    pub fn with<T: 'static>(mut self, value: T) -> Self {
+        self.insert(value);
+        self
+    }
+
+    /// Add `value` to the context, returning the previous value of the same
+    /// type, if any.
+    pub fn insert<T: 'static>(&mut self, value: T) -> Option<T> {
+        self.values
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|previous| previous.downcast().ok())
+            .map(|previous| *previous)
+    }
+
+    /// Get the value of type `T`.
+    #[must_use]
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.values.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// Remove and return the value of type `T`.
+    pub fn remove<T: 'static>(&mut self) -> Option<T> {
+        self.values
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
+
+    /// Set the current thread's context while running `f`.
+    ///
+    /// Upon return, the previously set context will become current again.
+    /// Contexts do not nest: values of the previous context are not visible
+    /// while `f` runs.
+    pub fn set_current<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        CURRENT_CONTEXT.set(self, f)
+    }
+
+    /// Call `f` with the value of type `T` in the current thread's context.
+    /// Returns `None` if no context is set, or if it has no value of type
+    /// `T`.
+    pub fn with_current<T: 'static, R>(f: impl FnOnce(&T) -> R) -> Option<R> {
+        if !CURRENT_CONTEXT.is_set() {
+            return None;
+        }
+        CURRENT_CONTEXT.with(|context| context.get::<T>().map(f))
+    }
+}
+
+impl fmt::Debug for DeserializeContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeserializeContext")
+            .field("len", &self.values.len())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,258 @@
+use std::{any::TypeId, cell::RefCell, ptr};
+
+use bevy_reflect::Reflect;
+use serde::ser::SerializeMap;
+
+use crate::TypeError;
+
+scoped_tls::scoped_thread_local!(static GUARD: Guard);
+
+/// What to do when [`serialize()`](super::serialize()) reaches an object that
+/// is already being serialized further up, i.e. a cycle in a graph of shared
+/// objects. See [`with_cycle_guard()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CycleMode {
+    /// Fail with [`TypeError::CyclicReference`], listing the type tags of the
+    /// objects in the cycle.
+    Error,
+    /// Write `{"$ref": N}` in place of the object, where `N` is the depth of
+    /// the object among the tagged objects being serialized, counting from 0
+    /// for the outermost one.
+    ///
+    /// References cannot be deserialized, since the object they refer to has
+    /// not been built yet when they are read; this is meant for inspecting
+    /// and logging object graphs.
+    Reference,
+}
+
+struct Guard {
+    mode: CycleMode,
+    /// The objects being serialized, outermost first, by address and type,
+    /// with their type tags.
+    stack: RefCell<Vec<(*const (), TypeId, String)>>,
+}
+
+/// Detect cycles in the object graph while serializing in `f`, and handle them
+/// according to `mode`.
+///
+/// Objects may contain other trait objects through shared pointers, such as
+/// `Arc<Mutex<Box<dyn MyTrait>>>` fields with their own `Serialize`
+/// implementations. If such a graph contains a cycle, serialization does not
+/// terminate without a guard. With a guard, each tagged object is recorded by
+/// its address and type while its contents are serialized, so that reaching
+/// it again is detected.
+///
+/// Only objects on the path from the outermost object are tracked, so objects
+/// that are shared without a cycle are written in full every time. Use
+/// [`interned`](super::interned) to write shared objects once.
+///
+/// ## Example
+/// ```rust
+/// # use std::sync::{Arc, Mutex};
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, TypeError};
+/// # use reflect_steroids::serialization::{with_cycle_guard, CycleMode};
+/// #[reflect_trait]
+/// trait Node: DowncastReflect {}
+/// impl_dynamic_trait!(Node, ReflectNode);
+///
+/// #[derive(Reflect)]
+/// #[reflect(Node, Serialize)]
+/// struct Link {
+///     #[reflect(ignore)]
+///     next: Arc<Mutex<Option<Arc<dyn Node>>>>,
+/// }
+/// impl Node for Link {}
+///
+/// impl serde::Serialize for Link {
+///     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+///         use serde::ser::SerializeStruct;
+///         let next = self.next.lock().unwrap().clone();
+///         let mut s = serializer.serialize_struct("Link", 1)?;
+///         s.serialize_field("next", &next.as_deref())?;
+///         s.end()
+///     }
+/// }
+///
+/// let link = Arc::new(Link { next: Arc::default() });
+/// *link.next.lock().unwrap() = Some(link.clone() as Arc<dyn Node>);
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Link>();
+/// registry.set_current(|| {
+///     let json = with_cycle_guard(CycleMode::Reference, || serde_json::to_string(&*link as &dyn Node)).unwrap();
+///     assert_eq!(json, r#"{"type":"Link","next":{"$ref":0}}"#);
+///
+///     let err = with_cycle_guard(CycleMode::Error, || serde_json::to_string(&*link as &dyn Node)).unwrap_err();
+///     assert!(err.to_string().contains("Link -> Link"));
+/// });
+/// # link.next.lock().unwrap().take();
+/// ```
+pub fn with_cycle_guard<F: FnOnce() -> R, R>(mode: CycleMode, f: F) -> R {
+    let guard = Guard {
+        mode,
+        stack: RefCell::new(Vec::new()),
+    };
+    GUARD.set(&guard, f)
+}
+
+/// The outcome of [`visit()`].
+pub(crate) enum Visit {
+    /// No guard is set.
+    Unguarded,
+    /// The object is recorded until this is dropped.
+    Entered(Entered),
+    /// The object is already being serialized, at this depth, and should be
+    /// written as a reference.
+    Cycle(usize),
+}
+
+/// Removes the object recorded by [`visit()`] when dropped.
+pub(crate) struct Entered(());
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        GUARD.with(|guard| guard.stack.borrow_mut().pop());
+    }
+}
+
+/// Record that the tagged object `this` with the type tag `tag` is being
+/// serialized, if a cycle guard is set.
+pub(crate) fn visit(this: &dyn Reflect, tag: &str) -> Result<Visit, TypeError> {
+    if !GUARD.is_set() {
+        return Ok(Visit::Unguarded);
+    }
+    GUARD.with(|guard| {
+        let address = ptr::from_ref(this).cast::<()>();
+        let type_id = this.as_any().type_id();
+        let mut stack = guard.stack.borrow_mut();
+        let depth = stack
+            .iter()
+            .position(|(a, t, _)| *a == address && *t == type_id);
+        match (depth, guard.mode) {
+            (None, _) => {
+                stack.push((address, type_id, tag.to_string()));
+                Ok(Visit::Entered(Entered(())))
+            }
+            (Some(depth), CycleMode::Reference) => Ok(Visit::Cycle(depth)),
+            (Some(depth), CycleMode::Error) => {
+                let mut path: Vec<&str> = stack[depth..]
+                    .iter()
+                    .map(|(_, _, tag)| tag.as_str())
+                    .collect();
+                path.push(tag);
+                Err(TypeError::CyclicReference(path.join(" -> ")))
+            }
+        }
+    })
+}
+
+/// Write a reference to the object at `depth`, as `{"$ref": depth}`.
+pub(crate) fn serialize_reference<S>(depth: usize, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    let mut map = serializer.serialize_map(Some(1))?;
+    map.serialize_entry("$ref", &depth)?;
+    map.end()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use bevy_reflect::{reflect_trait, ReflectSerialize, TypeRegistry};
+    use serde::ser::SerializeStruct;
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect, TypeRegistryExt};
+
+    #[reflect_trait]
+    trait Task: DowncastReflect {}
+    impl_dynamic_trait!(Task, ReflectTask);
+
+    type Shared = Arc<Mutex<Vec<Arc<dyn Task>>>>;
+
+    #[derive(Reflect)]
+    #[reflect(Task, Serialize)]
+    struct Job {
+        name: String,
+        #[reflect(ignore)]
+        after: Shared,
+    }
+    impl Task for Job {}
+
+    impl serde::Serialize for Job {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let after: Vec<Arc<dyn Task>> = self.after.lock().unwrap().clone();
+            let mut s = serializer.serialize_struct("Job", 2)?;
+            s.serialize_field("name", &self.name)?;
+            s.serialize_field("after", &Jobs(&after))?;
+            s.end()
+        }
+    }
+
+    struct Jobs<'a>(&'a [Arc<dyn Task>]);
+
+    impl serde::Serialize for Jobs<'_> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            crate::serialization::serialize_seq_of_dyn(
+                self.0.iter().map(|task| task.as_reflect()),
+                serializer,
+            )
+        }
+    }
+
+    fn job(name: &str) -> Arc<Job> {
+        Arc::new(Job {
+            name: name.to_string(),
+            after: Shared::default(),
+        })
+    }
+
+    #[test]
+    fn cycles_and_shared_objects() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Job>();
+
+        // `build` is shared by `test` and `deploy`, which is not a cycle.
+        let (build, test, deploy) = (job("build"), job("test"), job("deploy"));
+        test.after.lock().unwrap().push(build.clone());
+        deploy.after.lock().unwrap().push(build.clone());
+        deploy.after.lock().unwrap().push(test.clone());
+        let json = registry.set_current(|| {
+            with_cycle_guard(CycleMode::Error, || {
+                serde_json::to_string(&*deploy as &dyn Task)
+            })
+        });
+        let build_json = r#"{"type":"Job","name":"build","after":[]}"#;
+        assert_eq!(
+            json.unwrap(),
+            format!(
+                r#"{{"type":"Job","name":"deploy","after":[{build_json},{{"type":"Job","name":"test","after":[{build_json}]}}]}}"#
+            )
+        );
+
+        // build -> test -> build
+        build.after.lock().unwrap().push(test.clone());
+        registry.set_current(|| {
+            let err = with_cycle_guard(CycleMode::Error, || {
+                serde_json::to_string(&*deploy as &dyn Task)
+            })
+            .unwrap_err();
+            let expected = TypeError::CyclicReference("Job -> Job -> Job".into()).to_string();
+            assert!(err.to_string().contains(&expected), "{err}");
+
+            let json = with_cycle_guard(CycleMode::Reference, || {
+                serde_json::to_string(&*test as &dyn Task)
+            })
+            .unwrap();
+            assert_eq!(
+                json,
+                r#"{"type":"Job","name":"test","after":[{"type":"Job","name":"build","after":[{"$ref":0}]}]}"#
+            );
+        });
+
+        // Break the cycle, so that the objects are freed.
+        build.after.lock().unwrap().clear();
+    }
+}
@@ -1,23 +1,39 @@
-use std::{borrow::Cow, cell::RefCell, collections::HashMap};
+use std::{borrow::Cow, collections::HashMap, fmt};
 
-use bevy_reflect::{Reflect, ReflectDeserialize, TypeInfo, TypeRegistry};
+use bevy_reflect::{
+    std_traits::ReflectDefault, EnumInfo, Reflect, ReflectDeserialize, StructInfo, TypeInfo,
+    TypeRegistration, TypeRegistry, VariantInfo,
+};
 use serde::{
-    de::{value::MapDeserializer, IntoDeserializer},
+    de::{Error as _, IntoDeserializer, MapAccess, Visitor},
     Deserialize,
 };
 
-use crate::{TypeError, TypeRegistryExt};
+use crate::{missing_type::report_missing, TypeError, TypeRegistryExt};
+
+use super::{
+    bevy_compat::{deserialize_any_layout, is_bevy_compat},
+    policy::SerializationPolicy,
+    restrict::check_expected_trait,
+    ser::reflect_serialize,
+    tag::{resolve_stable_id, resolve_tag},
+    toml_layout::{is_toml_layout, unnest},
+    transparent::SerializeTransparent,
+    value::{Value, ValueDeserializer, ValueError},
+    value_ser::to_value,
+};
 
-use super::value::Value;
+scoped_tls::scoped_thread_local!(static LENIENT: ());
 
-scoped_tls::scoped_thread_local!(
-    static CURRENTLY_DESERIALIZING_TYPE: RefCell<Option<String>>
-);
+scoped_tls::scoped_thread_local!(static DUPLICATE_KEYS: ());
 
 /// Deserialize any dynamic trait pointer.
 ///
 /// The data is expected to contain a field named `type`, which indicates the
-/// short name of the
+/// short name of the type, or the name given by its
+/// [`SerializedAs`](super::SerializedAs) type data, or its
+/// [`StableId`](super::StableId) as an integer (see
+/// [`with_tag_namespace()`](super::with_tag_namespace)).
 ///
 /// Deserialization requires a current global
 /// [`TypeRegistry`](bevy_reflect::TypeRegistry). See
@@ -56,49 +72,79 @@ pub fn deserialize<'de, D>(deserializer: D) -> Result<Box<dyn Reflect>, D::Error
 where
     D: serde::Deserializer<'de>,
 {
-    use serde::de::Error as _;
+    if is_bevy_compat() {
+        return deserialize_any_layout(deserializer);
+    }
+    deserialize_tagged(deserializer)
+}
 
+/// Deserialize an object in the tagged layout. See [`deserialize()`].
+pub(crate) fn deserialize_tagged<'de, D>(deserializer: D) -> Result<Box<dyn Reflect>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
     let DeserializeWithTypeTag {
-        type_name,
+        resolved,
         mut value_map,
     } = DeserializeWithTypeTag::deserialize(deserializer)?;
 
-    let (type_info, deserialize) = TypeRegistry::with_current(|registry| {
-        let registration = match registry.get_with_short_name(&type_name) {
-            Some(registration) => registration,
-            None => {
-                return Err(TypeError::UnregisteredShortName(
-                    type_name.into_owned().into(),
+    let deserialize = match resolved.deserialize {
+        Resolved::Transparent(transparent, deserialize_field) => {
+            let value =
+                take_value(&mut value_map).ok_or_else(|| D::Error::missing_field("value"))?;
+            let field = deserialize_field.deserialize(value.into_deserializer())?;
+            return transparent.from_field(field).ok_or_else(|| {
+                D::Error::custom(format_args!(
+                    "could not build `{}` from its field",
+                    resolved.type_name
                 ))
-            }
-        };
-
-        let deserialize = match registration.data::<ReflectDeserialize>() {
-            Some(deserialize) => deserialize,
-            None => {
-                return Err(TypeError::UnregisteredTrait(
-                    registration.type_name().to_string().into(),
-                    "Deserialize",
-                ));
-            }
-        };
-
-        Ok((registration.type_info(), deserialize.clone()))
-    })
-    .map_err(D::Error::custom)?;
+            });
+        }
+        Resolved::Reflect(deserialize) => deserialize,
+    };
 
     // If the type is a struct, deserialize it with fields from `value_map`.
     // Otherwise, expect the field `value` and deserialize that.
 
-    match type_info {
-        TypeInfo::Struct(_) => {
-            let fields: MapDeserializer<_, D::Error> = value_map.into_deserializer();
+    match resolved.type_info {
+        TypeInfo::Struct(struct_info) => {
+            let policy = resolved.policy;
+            if let Some(ref policy) = policy {
+                value_map = value_map
+                    .into_iter()
+                    .map(|(name, value)| (policy.unrename(name), value))
+                    .collect();
+                if let Some(name) = value_map.keys().find(|name| policy.skips(name)) {
+                    return Err(D::Error::custom(format_args!(
+                        "field `{name}` of `{}` is skipped by its serialization policy",
+                        resolved.type_name
+                    )));
+                }
+            }
+
+            let lenient = LENIENT.is_set();
+            let default = (resolved.default.as_ref()).filter(|_| lenient || policy.is_some());
+            if let Some(default) = default {
+                TypeRegistry::try_with_current(|registry| {
+                    fill_missing_fields(registry, default, struct_info, &mut value_map, |name| {
+                        lenient || matches!(policy, Some(ref policy) if policy.skips(name))
+                    })
+                })
+                .map_err(D::Error::custom)?
+                .map_err(D::Error::custom)?;
+            }
+
+            // Deserialized from a `Value`, which also accepts an empty map as
+            // a unit struct.
+            let fields: ValueDeserializer<D::Error> = fields_value(value_map).into_deserializer();
             deserialize.deserialize(fields)
         }
+        TypeInfo::Enum(enum_info) if value_map.contains_key("variant") => {
+            let value = enum_value(enum_info, value_map).map_err(D::Error::custom)?;
+            deserialize.deserialize(value.into_deserializer())
+        }
         _ => {
-            let value = if let Some(value) = value_map.remove("value") {
-                value
-            } else {
+            let Some(value) = take_value(&mut value_map) else {
                 return Err(D::Error::custom(
                     "expected field `value` for type-erased deserialization of non-struct type",
                 ));
@@ -110,11 +156,465 @@ where
     }
 }
 
-#[derive(Deserialize)]
-#[serde(bound(deserialize = "'de: 'a"))]
+/// Run `f` with lenient deserialization enabled.
+///
+/// While lenient deserialization is enabled, [`deserialize()`] fills any
+/// fields missing from a tagged struct with the corresponding field of the
+/// type's default value, instead of failing. This makes it possible to add
+/// fields to types without invalidating existing serialized data.
+///
+/// The type must have [`ReflectDefault`] and
+/// [`ReflectSerialize`](bevy_reflect::ReflectSerialize) type data (i.e.,
+/// `#[reflect(Default, Serialize)]`). Otherwise, missing fields are reported
+/// by the type's [`Deserialize`] implementation as usual.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, serialization::with_lenient};
+/// # use reflect_steroids::reflect::{std_traits::ReflectDefault, TypeRegistry};
+/// #[reflect_trait]
+/// trait MyTrait: DowncastReflect {}
+/// impl_dynamic_trait!(MyTrait, ReflectMyTrait);
+///
+/// #[derive(Default, Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(MyTrait, Default, Serialize, Deserialize)]
+/// struct Foo { num: i32, name: String }
+///
+/// impl MyTrait for Foo {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Foo>();
+///
+/// let json = r#"{"type":"Foo","num":123}"#;
+///
+/// registry.set_current(|| {
+///     assert!(serde_json::from_str::<Box<dyn MyTrait>>(json).is_err());
+///
+///     let trait_object: Box<dyn MyTrait> =
+///         with_lenient(|| serde_json::from_str(json)).unwrap();
+///     let foo = trait_object.downcast_ref::<Foo>().unwrap();
+///     assert_eq!(foo.num, 123);
+///     assert_eq!(foo.name, "");
+/// });
+/// ```
+pub fn with_lenient<F: FnOnce() -> R, R>(f: F) -> R {
+    LENIENT.set(&(), f)
+}
+
+/// Like [`deserialize()`], but with lenient deserialization enabled (see
+/// [`with_lenient()`]).
+///
+/// This function may be used in a `#[serde(deserialize_with = "...")]`
+/// field attribute.
+///
+/// # Errors
+///
+/// Fails as [`deserialize()`] does, except that missing fields are filled in
+/// where possible.
+pub fn deserialize_lenient<'de, D>(deserializer: D) -> Result<Box<dyn Reflect>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    with_lenient(|| deserialize(deserializer))
+}
+
+/// Run `f` with duplicate keys allowed in tagged objects.
+///
+/// By default, a tagged object with more than one `type` entry is rejected,
+/// and so is a struct nested in a tagged object with a repeated field. While
+/// duplicate keys are allowed, the last value of a repeated key wins, in
+/// tagged objects and in all maps buffered along with them.
+///
+/// This is meant for YAML, which is supported like any other self-describing
+/// format (e.g., with `serde_yaml`). YAML authors often share fields between
+/// objects with anchors and merge keys, and loaders that expand merge keys
+/// into the map can produce both the merged and the overriding entry:
+///
+/// ```yaml
+/// base: &base { type: Enemy, hp: 10, speed: 2 }
+/// boss: { <<: *base, hp: 500 }
+/// ```
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::with_duplicate_keys};
+/// #[reflect_trait]
+/// trait Actor: DowncastReflect {}
+/// impl_dynamic_trait!(Actor, ReflectActor);
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Actor, Serialize, Deserialize)]
+/// struct Enemy { hp: u32, speed: u32 }
+/// impl Actor for Enemy {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Enemy>();
+///
+/// // A merged map, as a loader may present it.
+/// let json = r#"{"type":"Enemy","hp":10,"speed":2,"type":"Enemy","hp":500}"#;
+///
+/// registry.set_current(|| {
+///     assert!(serde_json::from_str::<Box<dyn Actor>>(json).is_err());
+///
+///     let actor: Box<dyn Actor> = with_duplicate_keys(|| serde_json::from_str(json)).unwrap();
+///     let enemy = actor.downcast_ref::<Enemy>().unwrap();
+///     assert_eq!((enemy.hp, enemy.speed), (500, 2));
+/// });
+/// ```
+pub fn with_duplicate_keys<F: FnOnce() -> R, R>(f: F) -> R {
+    DUPLICATE_KEYS.set(&(), f)
+}
+
+/// True if duplicate keys are allowed. See [`with_duplicate_keys()`].
+pub(crate) fn allows_duplicate_keys() -> bool {
+    DUPLICATE_KEYS.is_set()
+}
+
+/// Insert the serialized default value of any field of `struct_info` that is
+/// not present in `value_map`, and for which `fill` returns true.
+fn fill_missing_fields<'a>(
+    registry: &TypeRegistry,
+    default: &ReflectDefault,
+    struct_info: &StructInfo,
+    value_map: &mut HashMap<Cow<'a, str>, Value<'a>>,
+    fill: impl Fn(&str) -> bool,
+) -> Result<(), ValueError> {
+    let any_missing = struct_info.iter().any(|field| {
+        let name: &str = field.name();
+        !value_map.contains_key(name) && fill(name)
+    });
+    if !any_missing {
+        return Ok(());
+    }
+
+    let default = default.default();
+    let serialize = reflect_serialize(registry, &*default).map_err(ValueError::custom)?;
+    let serializable = serialize.get_serializable(&*default);
+    let value = to_value(serializable.borrow())?;
+
+    if let Value::Map(entries) = value {
+        for (key, value) in entries {
+            let key = match key {
+                Value::Str(key) => Cow::Borrowed(key),
+                Value::String(key) => Cow::Owned(key),
+                _ => continue,
+            };
+            if fill(&key) {
+                value_map.entry(key).or_insert(value);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Rebuild the externally tagged representation of an enum from the fields of
+/// a tagged object (see [`serialize()`](super::serialize())).
+fn enum_value<'a>(
+    enum_info: &EnumInfo,
+    mut value_map: HashMap<Cow<'a, str>, Value<'a>>,
+) -> Result<Value<'a>, ValueError> {
+    let variant = value_map
+        .remove("variant")
+        .ok_or_else(|| ValueError::missing_field("variant"))?;
+    let name = variant
+        .as_str()
+        .ok_or_else(|| ValueError::custom("expected field `variant` to be a string"))?;
+
+    let fields = match enum_info.variant(name) {
+        Some(VariantInfo::Struct(_)) => fields_value(value_map),
+        Some(VariantInfo::Tuple(_)) => {
+            take_value(&mut value_map).ok_or_else(|| ValueError::missing_field("value"))?
+        }
+        // Let the `Deserialize` impl report unknown variants.
+        Some(VariantInfo::Unit(_)) | None => return Ok(variant),
+    };
+    Ok(Value::Map(vec![(variant, fields)]))
+}
+
+/// Remove the `value` field of a tagged object, undoing the nesting of the
+/// TOML layout if it is enabled (see [`with_toml_layout()`](super::with_toml_layout)).
+fn take_value<'a>(value_map: &mut HashMap<Cow<'a, str>, Value<'a>>) -> Option<Value<'a>> {
+    let value = value_map.remove("value")?;
+    Some(if is_toml_layout() {
+        unnest(value)
+    } else {
+        value
+    })
+}
+
+/// Turn the fields of a tagged object back into a map.
+fn fields_value<'a>(value_map: HashMap<Cow<'a, str>, Value<'a>>) -> Value<'a> {
+    Value::map(value_map.into_iter().map(|(key, value)| {
+        let key = match key {
+            Cow::Borrowed(key) => Value::Str(key),
+            Cow::Owned(key) => Value::String(key),
+        };
+        (key, value)
+    }))
+}
+
+/// How to deserialize the type of a tagged object.
+enum Resolved {
+    /// Deserialize the field of a [`SerializeTransparent`] type, and wrap it.
+    Transparent(SerializeTransparent, ReflectDeserialize),
+    /// Deserialize the type itself.
+    Reflect(ReflectDeserialize),
+}
+
+/// The type data needed to deserialize a tagged object, resolved from its type
+/// tag.
+struct ResolvedTag {
+    type_name: &'static str,
+    type_info: &'static TypeInfo,
+    deserialize: Resolved,
+    policy: Option<SerializationPolicy>,
+    default: Option<ReflectDefault>,
+}
+
+/// Resolve the type data of the type tagged with `tag`. The tag is only copied
+/// if it cannot be resolved, for the error.
+fn resolve_tagged(registry: &TypeRegistry, tag: &str) -> Result<ResolvedTag, TypeError> {
+    let registration = resolve_tag(registry, tag)
+        .ok_or_else(|| report_missing(TypeError::UnregisteredShortName(tag.to_string().into())))?;
+    resolve_registration(registry, registration)
+}
+
+/// Resolve the type data of the type with the [`StableId`](super::StableId)
+/// `id`.
+fn resolve_tagged_id(registry: &TypeRegistry, id: u64) -> Result<ResolvedTag, TypeError> {
+    let registration = u32::try_from(id)
+        .ok()
+        .and_then(|id| resolve_stable_id(registry, id))
+        .ok_or_else(|| report_missing(TypeError::UnknownStableId(id)))?;
+    resolve_registration(registry, registration)
+}
+
+fn resolve_registration(
+    registry: &TypeRegistry,
+    registration: &TypeRegistration,
+) -> Result<ResolvedTag, TypeError> {
+    let deserialize = match resolve_transparent(registry, registration)? {
+        Some((transparent, deserialize_field)) => {
+            Resolved::Transparent(transparent, deserialize_field)
+        }
+        None => Resolved::Reflect(
+            registration
+                .data::<ReflectDeserialize>()
+                .ok_or_else(|| {
+                    TypeError::UnregisteredTrait(registration.type_name().into(), "Deserialize")
+                })?
+                .clone(),
+        ),
+    };
+
+    Ok(ResolvedTag {
+        type_name: registration.type_name(),
+        type_info: registration.type_info(),
+        deserialize,
+        policy: registration.data::<SerializationPolicy>().cloned(),
+        default: registration.data::<ReflectDefault>().cloned(),
+    })
+}
+
+/// If the type of `registration` has [`SerializeTransparent`] type data, find
+/// that and the [`ReflectDeserialize`] type data of its field.
+fn resolve_transparent(
+    registry: &TypeRegistry,
+    registration: &TypeRegistration,
+) -> Result<Option<(SerializeTransparent, ReflectDeserialize)>, TypeError> {
+    let transparent = match registration.data::<SerializeTransparent>() {
+        Some(transparent) => *transparent,
+        None => return Ok(None),
+    };
+
+    let field_type_name = transparent.field_type_name();
+    let registration = registry
+        .get(transparent.field_type_id())
+        .ok_or_else(|| report_missing(TypeError::UnregisteredType(field_type_name.into())))?;
+    let deserialize = registration
+        .data::<ReflectDeserialize>()
+        .ok_or_else(|| TypeError::UnregisteredTrait(field_type_name.into(), "Deserialize"))?;
+    Ok(Some((transparent, deserialize.clone())))
+}
+
+/// Find the type information and [`ReflectDeserialize`] type data for the
+/// type tagged with `type_name`.
+pub(crate) fn resolve_deserialize(
+    registry: &TypeRegistry,
+    type_name: &str,
+) -> Result<(&'static TypeInfo, ReflectDeserialize), TypeError> {
+    let Some(registration) = resolve_tag(registry, type_name) else {
+        return Err(report_missing(TypeError::UnregisteredShortName(
+            type_name.to_string().into(),
+        )));
+    };
+
+    let Some(deserialize) = registration.data::<ReflectDeserialize>() else {
+        return Err(TypeError::UnregisteredTrait(
+            registration.type_name().to_string().into(),
+            "Deserialize",
+        ));
+    };
+
+    Ok((registration.type_info(), deserialize.clone()))
+}
+
+/// The fields of a tagged object, buffered until the type tag is found.
+///
+/// This is deserialized by hand rather than with `#[serde(flatten)]`, so that
+/// the fields are buffered directly as [`Value`]s, which respect the limits of
+/// [`with_limits()`](super::with_limits()), and so that the type tag is
+/// resolved while it is borrowed from the input, without copying it.
 struct DeserializeWithTypeTag<'a> {
-    #[serde(rename = "type")]
-    type_name: Cow<'a, str>,
-    #[serde(flatten)]
+    resolved: ResolvedTag,
     value_map: HashMap<Cow<'a, str>, Value<'a>>,
 }
+
+impl<'de> Deserialize<'de> for DeserializeWithTypeTag<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct TaggedVisitor;
+
+        impl<'de> Visitor<'de> for TaggedVisitor {
+            type Value = DeserializeWithTypeTag<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map with a `type` field")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut resolved = None;
+                let mut value_map = HashMap::with_capacity(map.size_hint().unwrap_or(0).min(64));
+                while let Some(key) = map.next_key::<Key<'de>>()? {
+                    match key {
+                        Key::Type if resolved.is_some() && !allows_duplicate_keys() => {
+                            return Err(A::Error::duplicate_field("type"));
+                        }
+                        Key::Type => resolved = Some(map.next_value::<Tag>()?.0),
+                        Key::Field(name) => {
+                            value_map.insert(name, map.next_value()?);
+                        }
+                    }
+                }
+                let resolved = resolved.ok_or_else(|| A::Error::missing_field("type"))?;
+                Ok(DeserializeWithTypeTag {
+                    resolved,
+                    value_map,
+                })
+            }
+        }
+
+        deserializer.deserialize_map(TaggedVisitor)
+    }
+}
+
+/// A key of a tagged object, which is only copied if it is a field name that
+/// cannot be borrowed from the input.
+enum Key<'a> {
+    Type,
+    Field(Cow<'a, str>),
+}
+
+impl<'de> Deserialize<'de> for Key<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct KeyVisitor;
+
+        impl<'de> Visitor<'de> for KeyVisitor {
+            type Value = Key<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a field name")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, key: &str) -> Result<Self::Value, E> {
+                Ok(match key {
+                    "type" => Key::Type,
+                    _ => Key::Field(Cow::Owned(key.to_owned())),
+                })
+            }
+
+            fn visit_borrowed_str<E: serde::de::Error>(
+                self,
+                key: &'de str,
+            ) -> Result<Self::Value, E> {
+                Ok(match key {
+                    "type" => Key::Type,
+                    _ => Key::Field(Cow::Borrowed(key)),
+                })
+            }
+
+            fn visit_string<E: serde::de::Error>(self, key: String) -> Result<Self::Value, E> {
+                Ok(match &*key {
+                    "type" => Key::Type,
+                    _ => Key::Field(Cow::Owned(key)),
+                })
+            }
+        }
+
+        deserializer.deserialize_str(KeyVisitor)
+    }
+}
+
+/// The type tag of a tagged object, resolved in the current registry.
+struct Tag(ResolvedTag);
+
+impl<'de> Deserialize<'de> for Tag {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct TagVisitor;
+
+        impl Visitor<'_> for TagVisitor {
+            type Value = Tag;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a type tag")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, tag: &str) -> Result<Self::Value, E> {
+                resolve_checked(|registry| resolve_tagged(registry, tag))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, id: u64) -> Result<Self::Value, E> {
+                resolve_checked(|registry| resolve_tagged_id(registry, id))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, id: i64) -> Result<Self::Value, E> {
+                match u64::try_from(id) {
+                    Ok(id) => self.visit_u64(id),
+                    Err(_) => Err(E::invalid_value(serde::de::Unexpected::Signed(id), &self)),
+                }
+            }
+        }
+
+        /// Resolve a tag in the current registry, and check that the type may
+        /// be deserialized as the expected trait.
+        fn resolve_checked<E: serde::de::Error>(
+            resolve: impl FnOnce(&TypeRegistry) -> Result<ResolvedTag, TypeError>,
+        ) -> Result<Tag, E> {
+            TypeRegistry::try_with_current(|registry| {
+                let resolved = resolve(registry)?;
+                if let Some(registration) = registry.get_with_name(resolved.type_name) {
+                    check_expected_trait(registry, registration)?;
+                }
+                Ok(resolved)
+            })
+            .and_then(|result| result)
+            .map(Tag)
+            .map_err(E::custom)
+        }
+
+        // Stable IDs are integers, so let the format tell the type of the tag.
+        deserializer.deserialize_any(TagVisitor)
+    }
+}
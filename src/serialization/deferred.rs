@@ -0,0 +1,230 @@
+use std::fmt;
+
+use bevy_reflect::{Reflect, TypeRegistry};
+use serde::{
+    de::{Error as _, IntoDeserializer},
+    Deserialize, Serialize,
+};
+
+use crate::TypeRegistryExt;
+
+use super::{
+    de::deserialize_tagged,
+    ser::SerializeTagged,
+    value::{Value, ValueDeserializer, ValueError},
+};
+
+/// A tagged object whose payload is buffered, and only deserialized when it is
+/// first accessed with [`resolve()`](DeferredObject::resolve).
+///
+/// Deserializing a `DeferredObject` does not need a current registry, and only
+/// checks that the data is a map with a `type` field. Objects that are never
+/// resolved are serialized again exactly as they were read, so that large
+/// documents can be loaded and saved without deserializing every object in
+/// them.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::DeferredObject};
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Serialize, Deserialize)]
+/// struct Chunk { blocks: Vec<u8> }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Chunk>();
+///
+/// let json = r#"[{"type":"Chunk","blocks":[1,2]},{"type":"Chunk","blocks":[3]}]"#;
+/// let mut chunks: Vec<DeferredObject> = serde_json::from_str(json).unwrap();
+/// assert_eq!(chunks[0].tag(), "Chunk");
+///
+/// let chunk = chunks[1].resolve(&registry).unwrap();
+/// chunk.downcast_mut::<Chunk>().unwrap().blocks.push(4);
+/// assert!(!chunks[0].is_resolved());
+///
+/// let saved = registry.set_current(|| serde_json::to_string(&chunks)).unwrap();
+/// assert_eq!(
+///     saved,
+///     r#"[{"type":"Chunk","blocks":[1,2]},{"type":"Chunk","blocks":[3,4]}]"#
+/// );
+/// ```
+pub struct DeferredObject {
+    tag: String,
+    state: State,
+}
+
+enum State {
+    Pending(Value<'static>),
+    Resolved(Box<dyn Reflect>),
+}
+
+impl DeferredObject {
+    /// An object with the buffered payload `value`.
+    pub(crate) fn pending(tag: String, value: Value<'static>) -> Self {
+        DeferredObject {
+            tag,
+            state: State::Pending(value),
+        }
+    }
+
+    /// An object that has been deserialized from data tagged with `tag`.
+    pub(crate) fn resolved(tag: String, object: Box<dyn Reflect>) -> Self {
+        DeferredObject {
+            tag,
+            state: State::Resolved(object),
+        }
+    }
+
+    /// The type tag of the object.
+    #[must_use]
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// True if the object has been deserialized.
+    #[must_use]
+    pub fn is_resolved(&self) -> bool {
+        matches!(self.state, State::Resolved(_))
+    }
+
+    /// The object, if it has been deserialized.
+    #[must_use]
+    pub fn get(&self) -> Option<&dyn Reflect> {
+        match self.state {
+            State::Resolved(ref object) => Some(&**object),
+            State::Pending(_) => None,
+        }
+    }
+
+    /// Deserialize the object with the types in `registry`, if it has not been
+    /// deserialized yet, and get it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the payload cannot be deserialized. The payload is kept, so
+    /// the object can be resolved again later, such as after registering more
+    /// types.
+    pub fn resolve(&mut self, registry: &TypeRegistry) -> Result<&mut dyn Reflect, ValueError> {
+        if let State::Pending(ref value) = self.state {
+            let deserializer: ValueDeserializer<ValueError> = value.clone().into_deserializer();
+            let object = registry.set_current(|| deserialize_tagged(deserializer))?;
+            self.state = State::Resolved(object);
+        }
+        match self.state {
+            State::Resolved(ref mut object) => Ok(&mut **object),
+            State::Pending(_) => unreachable!(),
+        }
+    }
+
+    /// Deserialize the object, if it has not been deserialized yet, and take
+    /// it. See [`resolve()`](Self::resolve).
+    ///
+    /// # Errors
+    ///
+    /// Fails if the payload cannot be deserialized.
+    pub fn into_object(self, registry: &TypeRegistry) -> Result<Box<dyn Reflect>, ValueError> {
+        match self.state {
+            State::Resolved(object) => Ok(object),
+            State::Pending(value) => {
+                let deserializer: ValueDeserializer<ValueError> = value.into_deserializer();
+                registry.set_current(|| deserialize_tagged(deserializer))
+            }
+        }
+    }
+}
+
+impl From<Box<dyn Reflect>> for DeferredObject {
+    /// A resolved object. Its tag is its short type name.
+    fn from(object: Box<dyn Reflect>) -> Self {
+        let tag = crate::pretty::short_name(object.type_name());
+        DeferredObject::resolved(tag, object)
+    }
+}
+
+impl fmt::Debug for DeferredObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DeferredObject")
+            .field("tag", &self.tag)
+            .field("resolved", &self.is_resolved())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Serialize for DeferredObject {
+    /// Pending objects are written as they were read. Resolved objects are
+    /// serialized with [`serialize()`](super::serialize), which requires a
+    /// current registry.
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self.state {
+            State::Pending(ref value) => value.serialize(serializer),
+            State::Resolved(ref object) => SerializeTagged(&**object).serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DeferredObject {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?.into_owned();
+        let Value::Map(ref entries) = value else {
+            return Err(D::Error::custom("expected a map with a `type` field"));
+        };
+        let tag = entries
+            .iter()
+            .find(|(key, _)| key.as_str() == Some("type"))
+            .ok_or_else(|| D::Error::missing_field("type"))?
+            .1
+            .as_str()
+            .ok_or_else(|| D::Error::custom("expected field `type` to be a string"))?
+            .to_string();
+        Ok(DeferredObject::pending(tag, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{ReflectDeserialize, ReflectSerialize};
+
+    use super::*;
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Serialize, Deserialize)]
+    struct Sprite {
+        frame: u32,
+    }
+
+    #[test]
+    fn resolve_on_demand() {
+        let mut object: DeferredObject =
+            serde_json::from_str(r#"{"frame":7,"type":"Sprite"}"#).unwrap();
+        assert_eq!(object.tag(), "Sprite");
+        assert!(object.get().is_none());
+
+        let mut registry = TypeRegistry::default();
+        let err = object.resolve(&registry).unwrap_err();
+        assert!(err.to_string().contains("Sprite"), "{err}");
+        assert!(!object.is_resolved());
+
+        registry.register::<Sprite>();
+        let sprite = object.resolve(&registry).unwrap();
+        assert_eq!(sprite.downcast_ref::<Sprite>().unwrap().frame, 7);
+        assert!(object.is_resolved());
+
+        let sprite = object.into_object(&registry).unwrap();
+        assert_eq!(sprite.downcast_ref::<Sprite>().unwrap().frame, 7);
+
+        let object = DeferredObject::from(Box::new(Sprite { frame: 1 }) as Box<dyn Reflect>);
+        assert_eq!(object.tag(), "Sprite");
+        let json = registry
+            .set_current(|| serde_json::to_string(&object))
+            .unwrap();
+        assert_eq!(json, r#"{"type":"Sprite","frame":1}"#);
+
+        assert!(serde_json::from_str::<DeferredObject>(r#"{"frame":7}"#).is_err());
+        assert!(serde_json::from_str::<DeferredObject>("[1]").is_err());
+    }
+}
@@ -0,0 +1,334 @@
+//! Tagged (de)serialization of trait object fields.
+//!
+//! This module may be used in a `#[serde(with = "...")]` field attribute when
+//! the type of the field is `Box<dyn MyTrait>`, `Option<Box<dyn MyTrait>>`,
+//! `Vec<Box<dyn MyTrait>>`, or any nesting of those, including when
+//! `MyTrait` is `Reflect` itself. See also the `serde_dyn` attribute macro
+//! (requires the "macros" crate feature). Fields holding borrowed trait
+//! objects can be serialized with [`serialize_ref()`].
+//!
+//! ## Example
+//! ```rust
+//! # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Scene {
+//!     #[serde(with = "reflect_steroids::serialization::dyn_field")]
+//!     objects: Vec<Box<dyn Reflect>>,
+//!     #[serde(with = "reflect_steroids::serialization::dyn_field")]
+//!     selected: Option<Box<dyn Reflect>>,
+//! }
+//!
+//! #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+//! #[reflect(Serialize, Deserialize)]
+//! struct Foo { num: i32 }
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register::<Foo>();
+//! registry.set_current(|| {
+//!     let scene = Scene {
+//!         objects: vec![Box::new(Foo { num: 1 })],
+//!         selected: None,
+//!     };
+//!     let json = serde_json::to_string(&scene).unwrap();
+//!     assert_eq!(json, r#"{"objects":[{"type":"Foo","num":1}],"selected":null}"#);
+//!
+//!     let scene: Scene = serde_json::from_str(&json).unwrap();
+//!     assert!(scene.objects[0].is::<Foo>());
+//! });
+//! ```
+
+use std::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{SeqAccess, Visitor},
+    ser::SerializeSeq,
+    Deserialize, Serialize,
+};
+
+use crate::DynamicTrait;
+
+use super::SerializeDyn;
+
+/// Field types containing trait objects that can be (de)serialized with
+/// [`serialize()`](super::serialize) and [`deserialize()`](super::deserialize).
+pub trait DynField: Sized {
+    /// Serialize the field.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the objects in the field cannot be serialized.
+    fn serialize_dyn<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer;
+
+    /// Deserialize the field.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the objects in the field cannot be deserialized.
+    fn deserialize_dyn<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>;
+}
+
+impl<T: DynamicTrait + ?Sized> DynField for Box<T> {
+    fn serialize_dyn<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        super::serialize((**self).as_reflect(), serializer)
+    }
+
+    fn deserialize_dyn<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        super::deserialize_trait_object(deserializer)
+    }
+}
+
+impl<F: DynField> DynField for Option<F> {
+    fn serialize_dyn<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Some(value) => serializer.serialize_some(&Wrap(value)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    fn deserialize_dyn<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Option::<Wrap<F>>::deserialize(deserializer)?.map(|wrap| wrap.0))
+    }
+}
+
+impl<F: DynField> DynField for Vec<F> {
+    fn serialize_dyn<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for element in self {
+            seq.serialize_element(&Wrap(element))?;
+        }
+        seq.end()
+    }
+
+    fn deserialize_dyn<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct VecVisitor<F>(PhantomData<F>);
+
+        impl<'de, F: DynField> Visitor<'de> for VecVisitor<F> {
+            type Value = Vec<F>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a sequence")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Vec<F>, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut elements = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+                while let Some(Wrap(element)) = seq.next_element()? {
+                    elements.push(element);
+                }
+                Ok(elements)
+            }
+        }
+
+        deserializer.deserialize_seq(VecVisitor(PhantomData))
+    }
+}
+
+/// Serialize a [`DynField`]. For use with `#[serde(with = "...")]`.
+///
+/// # Errors
+///
+/// Fails as [`serialize()`](super::serialize) does for any of the objects in
+/// the field.
+pub fn serialize<F, S>(value: &F, serializer: S) -> Result<S::Ok, S::Error>
+where
+    F: DynField,
+    S: serde::Serializer,
+{
+    value.serialize_dyn(serializer)
+}
+
+/// Deserialize a [`DynField`]. For use with `#[serde(with = "...")]`.
+///
+/// # Errors
+///
+/// Fails as [`deserialize()`](super::deserialize) does for any of the objects
+/// in the field.
+pub fn deserialize<'de, F, D>(deserializer: D) -> Result<F, D::Error>
+where
+    F: DynField,
+    D: serde::Deserializer<'de>,
+{
+    F::deserialize_dyn(deserializer)
+}
+
+/// Borrowed field types containing trait objects that can be serialized with
+/// [`serialize_ref()`], such as `&dyn MyTrait`, `Option<&dyn Reflect>`, or
+/// `&[Box<dyn MyTrait>]`.
+pub trait DynFieldRef {
+    /// Serialize the field.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the objects in the field cannot be serialized.
+    fn serialize_dyn_ref<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer;
+}
+
+impl<T: SerializeDyn + ?Sized> DynFieldRef for &T {
+    fn serialize_dyn_ref<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        super::serialize(*self, serializer)
+    }
+}
+
+impl<T: DynamicTrait + ?Sized> DynFieldRef for Box<T> {
+    fn serialize_dyn_ref<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.serialize_dyn(serializer)
+    }
+}
+
+impl<F: DynFieldRef> DynFieldRef for Option<F> {
+    fn serialize_dyn_ref<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Some(value) => serializer.serialize_some(&WrapRef(value)),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+impl<F: DynFieldRef> DynFieldRef for [F] {
+    fn serialize_dyn_ref<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for element in self {
+            seq.serialize_element(&WrapRef(element))?;
+        }
+        seq.end()
+    }
+}
+
+impl<F: DynFieldRef> DynFieldRef for &[F] {
+    fn serialize_dyn_ref<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        (**self).serialize_dyn_ref(serializer)
+    }
+}
+
+impl<F: DynFieldRef> DynFieldRef for Vec<F> {
+    fn serialize_dyn_ref<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.as_slice().serialize_dyn_ref(serializer)
+    }
+}
+
+/// Serialize a [`DynFieldRef`], for fields holding borrowed trait objects,
+/// such as read-only snapshots of objects owned elsewhere. For use with
+/// `#[serde(serialize_with = "...")]`.
+///
+/// # Errors
+///
+/// Fails as [`serialize()`](super::serialize) does for any of the objects in
+/// the field.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+/// #[reflect_trait]
+/// trait Component: DowncastReflect {}
+/// impl_dynamic_trait!(Component, ReflectComponent);
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Component, Serialize, Deserialize)]
+/// struct Health(u32);
+/// impl Component for Health {}
+///
+/// #[derive(serde::Serialize)]
+/// struct Snapshot<'a> {
+///     #[serde(serialize_with = "reflect_steroids::serialization::dyn_field::serialize_ref")]
+///     selected: Option<&'a dyn Component>,
+///     #[serde(serialize_with = "reflect_steroids::serialization::dyn_field::serialize_ref")]
+///     components: &'a [Box<dyn Component>],
+/// }
+///
+/// let components: Vec<Box<dyn Component>> = vec![Box::new(Health(7))];
+/// let snapshot = Snapshot { selected: Some(&*components[0]), components: &components };
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Health>();
+/// registry.set_current(|| {
+///     let json = serde_json::to_string(&snapshot).unwrap();
+///     assert_eq!(
+///         json,
+///         r#"{"selected":{"type":"Health","value":7},"components":[{"type":"Health","value":7}]}"#
+///     );
+/// });
+/// ```
+pub fn serialize_ref<F, S>(value: &F, serializer: S) -> Result<S::Ok, S::Error>
+where
+    F: DynFieldRef + ?Sized,
+    S: serde::Serializer,
+{
+    value.serialize_dyn_ref(serializer)
+}
+
+/// Adapts a [`DynFieldRef`] to the `Serialize` trait.
+struct WrapRef<'a, F: ?Sized>(&'a F);
+
+impl<F: DynFieldRef + ?Sized> Serialize for WrapRef<'_, F> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize_dyn_ref(serializer)
+    }
+}
+
+/// Adapts a [`DynField`] to the `Serialize` and `Deserialize` traits.
+struct Wrap<F>(F);
+
+impl<F: DynField> Serialize for Wrap<&F> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize_dyn(serializer)
+    }
+}
+
+impl<'de, F: DynField> Deserialize<'de> for Wrap<F> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        F::deserialize_dyn(deserializer).map(Wrap)
+    }
+}
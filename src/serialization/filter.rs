@@ -0,0 +1,231 @@
+use std::{collections::HashSet, fmt};
+
+use serde::de::{
+    DeserializeSeed, Error as _, IgnoredAny, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+};
+
+use super::{de::deserialize_tagged, value::Value, DeferredObject};
+
+/// Reads sequences of tagged objects, deserializing only the objects whose
+/// type tags are in an allowlist.
+///
+/// Other objects are skipped, or, with [`defer_others()`](Self::defer_others),
+/// kept as pending [`DeferredObject`]s. When the `type` field comes first in a
+/// skipped object, as written by [`serialize()`](super::serialize), the rest
+/// of the object is skipped without buffering it.
+///
+/// Allowed objects are deserialized with [`deserialize()`](super::deserialize),
+/// which requires a current registry. Types of skipped objects do not need to
+/// be registered.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::TagFilter};
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Serialize, Deserialize)]
+/// struct Purchase { amount: u32 }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Purchase>();
+///
+/// let json = r#"[
+///     {"type":"Purchase","amount":5},
+///     {"type":"Movement","path":[[0,0],[1,2]]},
+///     {"type":"Purchase","amount":20}
+/// ]"#;
+///
+/// let filter = TagFilter::new(["Purchase"]);
+/// let mut deserializer = serde_json::Deserializer::from_str(json);
+/// let purchases = registry.set_current(|| filter.deserialize_seq(&mut deserializer)).unwrap();
+/// let total: u32 = purchases
+///     .iter()
+///     .map(|object| object.get().unwrap().downcast_ref::<Purchase>().unwrap().amount)
+///     .sum();
+/// assert_eq!(total, 25);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TagFilter {
+    tags: HashSet<String>,
+    defer_others: bool,
+}
+
+impl TagFilter {
+    /// A filter allowing the type tags `tags`.
+    pub fn new<T: Into<String>>(tags: impl IntoIterator<Item = T>) -> Self {
+        TagFilter {
+            tags: tags.into_iter().map(Into::into).collect(),
+            defer_others: false,
+        }
+    }
+
+    /// Keep the objects that are not allowed as pending [`DeferredObject`]s,
+    /// instead of skipping them.
+    #[must_use]
+    pub fn defer_others(mut self) -> Self {
+        self.defer_others = true;
+        self
+    }
+
+    /// True if objects tagged with `tag` are deserialized.
+    #[must_use]
+    pub fn allows(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+
+    /// Read a sequence of tagged objects from `deserializer`. Allowed objects
+    /// are resolved, and other objects are skipped or pending.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the input is not a sequence of maps with a `type` field, or if
+    /// an allowed object cannot be deserialized.
+    pub fn deserialize_seq<'de, D>(&self, deserializer: D) -> Result<Vec<DeferredObject>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct SeqVisitor<'a>(&'a TagFilter);
+
+        impl<'de> Visitor<'de> for SeqVisitor<'_> {
+            type Value = Vec<DeferredObject>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a sequence of tagged objects")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut objects = Vec::new();
+                while let Some(object) = seq.next_element_seed(Element(self.0))? {
+                    objects.extend(object);
+                }
+                Ok(objects)
+            }
+        }
+
+        deserializer.deserialize_seq(SeqVisitor(self))
+    }
+}
+
+/// Deserializes one element of a sequence, or `None` if it is skipped.
+struct Element<'a>(&'a TagFilter);
+
+impl<'de> DeserializeSeed<'de> for Element<'_> {
+    type Value = Option<DeferredObject>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Element<'_> {
+    type Value = Option<DeferredObject>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map with a `type` field")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut tag = None;
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0).min(64));
+        while let Some(key) = map.next_key::<Value<'de>>()? {
+            let value: Value<'de> = map.next_value()?;
+            if key.as_str() == Some("type") {
+                let name = value
+                    .as_str()
+                    .ok_or_else(|| A::Error::custom("expected field `type` to be a string"))?;
+                if !self.0.defer_others && !self.0.allows(name) {
+                    while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+                    return Ok(None);
+                }
+                tag = Some(name.to_string());
+            }
+            entries.push((key, value));
+        }
+
+        let tag = tag.ok_or_else(|| A::Error::missing_field("type"))?;
+        let value = Value::Map(entries);
+        if self.0.allows(&tag) {
+            let object =
+                deserialize_tagged(IntoDeserializer::<A::Error>::into_deserializer(value))?;
+            Ok(Some(DeferredObject::resolved(tag, object)))
+        } else {
+            Ok(Some(DeferredObject::pending(tag, value.into_owned())))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{Reflect, ReflectDeserialize, ReflectSerialize, TypeRegistry};
+
+    use super::*;
+    use crate::TypeRegistryExt;
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Serialize, Deserialize)]
+    struct Login {
+        user: String,
+    }
+
+    const DUMP: &str = r#"[
+        {"type":"Login","user":"ada"},
+        {"type":"Frame","data":{"nested":[1,2,3]}},
+        {"user":"bob","type":"Login"},
+        {"data":null,"type":"Frame"}
+    ]"#;
+
+    #[test]
+    fn skips_other_tags() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Login>();
+
+        let filter = TagFilter::new(["Login"]);
+        let mut deserializer = serde_json::Deserializer::from_str(DUMP);
+        let objects = registry
+            .set_current(|| filter.deserialize_seq(&mut deserializer))
+            .unwrap();
+        let users: Vec<&str> = objects
+            .iter()
+            .map(|object| &*object.get().unwrap().downcast_ref::<Login>().unwrap().user)
+            .collect();
+        assert_eq!(users, ["ada", "bob"]);
+
+        let mut deserializer = serde_json::Deserializer::from_str(r#"[{"user":"ada"}]"#);
+        assert!(filter.deserialize_seq(&mut deserializer).is_err());
+    }
+
+    #[test]
+    fn defers_other_tags() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Login>();
+
+        let filter = TagFilter::new(["Login"]).defer_others();
+        let mut deserializer = serde_json::Deserializer::from_str(DUMP);
+        let objects = registry
+            .set_current(|| filter.deserialize_seq(&mut deserializer))
+            .unwrap();
+        let tags: Vec<(&str, bool)> = objects
+            .iter()
+            .map(|object| (object.tag(), object.is_resolved()))
+            .collect();
+        assert_eq!(
+            tags,
+            [
+                ("Login", true),
+                ("Frame", false),
+                ("Login", true),
+                ("Frame", false)
+            ]
+        );
+        let json = serde_json::to_string(&objects[1]).unwrap();
+        assert_eq!(json, r#"{"type":"Frame","data":{"nested":[1,2,3]}}"#);
+    }
+}
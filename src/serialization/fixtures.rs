@@ -0,0 +1,313 @@
+//! Golden-file fixtures of the tagged JSON format, for use in tests. Requires
+//! the "json" crate feature.
+//!
+//! [`check_fixtures()`] serializes the default value of each serializable
+//! implementor of a dynamic trait, and compares it with the fixture file for
+//! the type in a directory, such as `tests/fixtures/items/Sword.json`. Missing
+//! fixture files are written, so the first run creates the fixtures, and later
+//! runs flag any change to the serialized format of a type, or a type that is
+//! no longer registered. To accept a change, delete the fixture file and run
+//! the check again.
+//!
+//! Implementors are serializable if they have `#[reflect(Default, Serialize)]`.
+//! Fixture files are named by the type tag (see
+//! [`SerializedAs`](super::SerializedAs)), and compared as JSON values, so
+//! they may be reformatted. Use one directory per trait.
+//!
+//! ## Example
+//! ```rust
+//! # use reflect_steroids::{prelude::*, reflect::{std_traits::ReflectDefault, TypeRegistry}, serialization::fixtures::check_fixtures};
+//! #[reflect_trait]
+//! trait Item: DowncastReflect {}
+//! impl_dynamic_trait!(Item, ReflectItem);
+//!
+//! #[derive(Reflect, Default, serde::Serialize)]
+//! #[reflect(Item, Default, Serialize)]
+//! struct Sword { damage: u32 }
+//! impl Item for Sword {}
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register::<Sword>();
+//!
+//! let dir = std::env::temp_dir().join("reflect-steroids-fixtures-doctest");
+//! # let _ = std::fs::remove_dir_all(&dir);
+//! let report = check_fixtures::<dyn Item>(&dir, &registry).unwrap();
+//! assert_eq!(report.created, ["Sword"]);
+//!
+//! let report = check_fixtures::<dyn Item>(&dir, &registry).unwrap();
+//! assert_eq!(report.unchanged, ["Sword"]);
+//! assert!(report.is_clean());
+//! # std::fs::remove_dir_all(&dir).unwrap();
+//! ```
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy_reflect::{std_traits::ReflectDefault, ReflectSerialize, TypeRegistration, TypeRegistry};
+
+use crate::{type_registry::user_registrations, DynamicTrait, FixtureError, TypeRegistryExt};
+
+use super::{ser::SerializeTagged, tag::type_tag};
+
+/// The outcome of [`check_fixtures()`]. Types are listed by type tag, in
+/// order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FixtureReport {
+    /// Types whose fixture files were missing, and have been written.
+    pub created: Vec<String>,
+    /// Types whose serialized form matches their fixture files.
+    pub unchanged: Vec<String>,
+    /// Types whose serialized form differs from their fixture files.
+    pub changed: Vec<FixtureChange>,
+    /// Fixture files in the directory that do not belong to any implementor.
+    pub orphaned: Vec<PathBuf>,
+}
+
+/// A type whose serialized form differs from its fixture file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FixtureChange {
+    /// The type tag.
+    pub tag: String,
+    /// The fixture file.
+    pub path: PathBuf,
+    /// The contents of the fixture file.
+    pub expected: String,
+    /// The current serialized form.
+    pub actual: String,
+}
+
+impl FixtureReport {
+    /// True if no type has changed, and there are no orphaned fixture files.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.changed.is_empty() && self.orphaned.is_empty()
+    }
+}
+
+impl fmt::Display for FixtureReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_clean() {
+            return f.write_str("serialized format matches the fixtures");
+        }
+        f.write_str("serialized format differs from the fixtures:")?;
+        for change in &self.changed {
+            write!(
+                f,
+                "\n- '{}' changed ({}):\n  expected: {}\n  actual:   {}",
+                change.tag,
+                change.path.display(),
+                change.expected.trim(),
+                change.actual.trim(),
+            )?;
+        }
+        for path in &self.orphaned {
+            write!(f, "\n- no implementor for fixture '{}'", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare the serialized default value of each serializable implementor of
+/// `P` registered in `registry` with its fixture file in `dir`, and write the
+/// fixture files that are missing. See the [module documentation](self).
+///
+/// `dir` is created if it does not exist. Changes are reported in the
+/// [`FixtureReport`], not as errors.
+///
+/// # Errors
+///
+/// Fails if a fixture file or `dir` cannot be read or written, or if a default
+/// value cannot be serialized.
+// Only implementors with `ReflectDefault` are checked, so this cannot panic.
+#[allow(clippy::missing_panics_doc)]
+pub fn check_fixtures<P: DynamicTrait + ?Sized>(
+    dir: impl AsRef<Path>,
+    registry: &TypeRegistry,
+) -> Result<FixtureReport, FixtureError> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir).map_err(|source| FixtureError::Io {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+
+    let mut implementors: Vec<(&str, &TypeRegistration)> = user_registrations(registry)
+        .filter(|registration| is_serializable::<P>(registration))
+        .map(|registration| (type_tag(registration), registration))
+        .collect();
+    implementors.sort_by_key(|(tag, _)| *tag);
+
+    let mut report = FixtureReport::default();
+    let mut paths = Vec::with_capacity(implementors.len());
+    for (tag, registration) in implementors {
+        let default = registration
+            .data::<ReflectDefault>()
+            .expect("implementor has default")
+            .default();
+        let actual = registry
+            .set_current(|| serde_json::to_string_pretty(&SerializeTagged(&*default)))
+            .map_err(|err| FixtureError::Serialize {
+                tag: tag.to_string(),
+                message: err.to_string(),
+            })?
+            + "\n";
+
+        let path = dir.join(file_name(tag));
+        match fs::read_to_string(&path) {
+            Ok(expected) => {
+                if same_json(&expected, &actual) {
+                    report.unchanged.push(tag.to_string());
+                } else {
+                    report.changed.push(FixtureChange {
+                        tag: tag.to_string(),
+                        path: path.clone(),
+                        expected,
+                        actual,
+                    });
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                fs::write(&path, actual).map_err(|source| FixtureError::Io {
+                    path: path.clone(),
+                    source,
+                })?;
+                report.created.push(tag.to_string());
+            }
+            Err(source) => return Err(FixtureError::Io { path, source }),
+        }
+        paths.push(path);
+    }
+
+    let entries = fs::read_dir(dir).map_err(|source| FixtureError::Io {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+    for entry in entries {
+        let path = entry
+            .map_err(|source| FixtureError::Io {
+                path: dir.to_path_buf(),
+                source,
+            })?
+            .path();
+        if path.extension().is_some_and(|ext| ext == "json") && !paths.contains(&path) {
+            report.orphaned.push(path);
+        }
+    }
+    report.orphaned.sort();
+    Ok(report)
+}
+
+/// Check the fixtures as in [`check_fixtures()`], and panic with a description
+/// of the changes if any type has changed.
+///
+/// # Panics
+///
+/// Panics if the serialized format differs from the fixtures, or the fixtures
+/// cannot be read or written.
+pub fn assert_fixtures<P: DynamicTrait + ?Sized>(dir: impl AsRef<Path>, registry: &TypeRegistry) {
+    match check_fixtures::<P>(dir, registry) {
+        Ok(report) if report.is_clean() => {}
+        Ok(report) => panic!("{report}"),
+        Err(err) => panic!("{err}"),
+    }
+}
+
+fn is_serializable<P: DynamicTrait + ?Sized>(registration: &TypeRegistration) -> bool {
+    P::get_type_data(registration).is_some()
+        && registration.data::<ReflectDefault>().is_some()
+        && registration.data::<ReflectSerialize>().is_some()
+}
+
+/// The name of the fixture file of the type with the tag `tag`. Characters
+/// that are not allowed in file names on all platforms are replaced.
+fn file_name(tag: &str) -> String {
+    let stem: String = tag
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' | ' ' => '_',
+            c => c,
+        })
+        .collect();
+    stem + ".json"
+}
+
+/// True if `a` and `b` are the same JSON value, or the same text if either is
+/// not valid JSON.
+fn same_json(a: &str, b: &str) -> bool {
+    match (
+        serde_json::from_str::<serde_json::Value>(a),
+        serde_json::from_str::<serde_json::Value>(b),
+    ) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{reflect_trait, Reflect};
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect};
+
+    #[reflect_trait]
+    trait Part: DowncastReflect {}
+    impl_dynamic_trait!(Part, ReflectPart);
+
+    #[derive(Reflect, Default, serde::Serialize)]
+    #[reflect(Part, Default, Serialize)]
+    struct Wheel {
+        radius: f32,
+    }
+    impl Part for Wheel {}
+
+    #[derive(Reflect, Default, serde::Serialize)]
+    #[reflect(Part, Default, Serialize)]
+    struct Engine(u32);
+    impl Part for Engine {}
+
+    // Not serializable.
+    #[derive(Reflect, Default)]
+    #[reflect(Part, Default)]
+    struct Frame;
+    impl Part for Frame {}
+
+    #[test]
+    fn detects_changes() {
+        let dir =
+            std::env::temp_dir().join(format!("reflect-steroids-fixtures-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut registry = TypeRegistry::default();
+        registry.register::<Wheel>();
+        registry.register::<Engine>();
+        registry.register::<Frame>();
+
+        let report = check_fixtures::<dyn Part>(&dir, &registry).unwrap();
+        assert_eq!(report.created, ["Engine", "Wheel"]);
+        assert!(report.is_clean());
+        let wheel = fs::read_to_string(dir.join("Wheel.json")).unwrap();
+        assert_eq!(wheel, "{\n  \"type\": \"Wheel\",\n  \"radius\": 0.0\n}\n");
+
+        // Reformatting is not a change.
+        fs::write(dir.join("Wheel.json"), r#"{"radius":0.0,"type":"Wheel"}"#).unwrap();
+        assert_fixtures::<dyn Part>(&dir, &registry);
+
+        // Renaming the tag of a type is.
+        registry.register_serialized_as::<Engine>("engine/v2");
+        let report = check_fixtures::<dyn Part>(&dir, &registry).unwrap();
+        assert_eq!(report.created, ["engine/v2"]);
+        assert_eq!(report.unchanged, ["Wheel"]);
+        assert_eq!(report.orphaned, [dir.join("Engine.json")]);
+        assert!(dir.join("engine_v2.json").exists());
+        fs::remove_file(dir.join("Engine.json")).unwrap();
+
+        fs::write(dir.join("Wheel.json"), r#"{"type":"Wheel","radius":1.0}"#).unwrap();
+        let report = check_fixtures::<dyn Part>(&dir, &registry).unwrap();
+        assert_eq!(report.changed.len(), 1);
+        assert_eq!(report.changed[0].tag, "Wheel");
+        assert!(report.to_string().contains("'Wheel' changed"), "{report}");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
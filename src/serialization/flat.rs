@@ -0,0 +1,385 @@
+//! A [`Serializer`](serde::Serializer) that writes the fields of a struct, or
+//! the entries of a map, as entries of an enclosing map, used to write the
+//! fields of a tagged struct alongside its type tag.
+//!
+//! This does the same as `#[serde(flatten)]`, but the enclosing map is written
+//! directly with [`SerializeMap`], so the type tag and the fields go through
+//! the same calls to the serializer, also when it is wrapped by another
+//! serializer, such as those of `serde_path_to_error` or `serde_ignored`.
+
+use serde::ser::{self, Error as _, Impossible, Serialize, SerializeMap};
+
+/// Serializes a struct or map as entries of the map `M`.
+pub(crate) struct FlatMapSerializer<'a, M>(pub &'a mut M);
+
+impl<M: SerializeMap> FlatMapSerializer<'_, M> {
+    fn unsupported(what: &str) -> M::Error {
+        M::Error::custom(format!(
+            "a tagged struct must serialize as a struct or map, not {what}"
+        ))
+    }
+}
+
+impl<M: SerializeMap> ser::Serializer for FlatMapSerializer<'_, M> {
+    type Ok = ();
+    type Error = M::Error;
+
+    type SerializeSeq = Impossible<(), M::Error>;
+    type SerializeTuple = Impossible<(), M::Error>;
+    type SerializeTupleStruct = Impossible<(), M::Error>;
+    type SerializeTupleVariant = Impossible<(), M::Error>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), M::Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<(), M::Error> {
+        Err(Self::unsupported("a boolean"))
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<(), M::Error> {
+        Err(Self::unsupported("an integer"))
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<(), M::Error> {
+        Err(Self::unsupported("an integer"))
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<(), M::Error> {
+        Err(Self::unsupported("an integer"))
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<(), M::Error> {
+        Err(Self::unsupported("an integer"))
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<(), M::Error> {
+        Err(Self::unsupported("an integer"))
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<(), M::Error> {
+        Err(Self::unsupported("an integer"))
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<(), M::Error> {
+        Err(Self::unsupported("an integer"))
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<(), M::Error> {
+        Err(Self::unsupported("an integer"))
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<(), M::Error> {
+        Err(Self::unsupported("a float"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<(), M::Error> {
+        Err(Self::unsupported("a float"))
+    }
+
+    fn serialize_char(self, _v: char) -> Result<(), M::Error> {
+        Err(Self::unsupported("a char"))
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<(), M::Error> {
+        Err(Self::unsupported("a string"))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<(), M::Error> {
+        Err(Self::unsupported("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), M::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), M::Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), M::Error> {
+        Err(Self::unsupported("an enum"))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), M::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), M::Error> {
+        Err(Self::unsupported("an enum"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, M::Error> {
+        Err(Self::unsupported("a sequence"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, M::Error> {
+        Err(Self::unsupported("a tuple"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, M::Error> {
+        Err(Self::unsupported("a tuple struct"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, M::Error> {
+        Err(Self::unsupported("an enum"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, M::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, M::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, M::Error> {
+        Err(Self::unsupported("an enum"))
+    }
+}
+
+impl<M: SerializeMap> ser::SerializeMap for FlatMapSerializer<'_, M> {
+    type Ok = ();
+    type Error = M::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), M::Error> {
+        self.0.serialize_key(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), M::Error> {
+        self.0.serialize_value(value)
+    }
+
+    fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<(), M::Error>
+    where
+        K: Serialize + ?Sized,
+        V: Serialize + ?Sized,
+    {
+        self.0.serialize_entry(key, value)
+    }
+
+    fn end(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+}
+
+impl<M: SerializeMap> ser::SerializeStruct for FlatMapSerializer<'_, M> {
+    type Ok = ();
+    type Error = M::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), M::Error> {
+        self.0.serialize_entry(key, value)
+    }
+
+    fn end(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    /// Writes `value` flattened after a type tag, as a tagged struct is.
+    struct Flat<'a, T>(&'a T);
+
+    impl<T: Serialize> Serialize for Flat<'_, T> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            let mut map = serializer.serialize_map(None)?;
+            map.serialize_entry("type", "T")?;
+            self.0.serialize(FlatMapSerializer(&mut map))?;
+            map.end()
+        }
+    }
+
+    fn to_json<T: Serialize>(value: &T) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&Flat(value))
+    }
+
+    #[derive(serde::Serialize)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(serde::Serialize)]
+    struct Wrapper(Point);
+
+    #[derive(serde::Serialize)]
+    struct Unit;
+
+    #[derive(serde::Serialize)]
+    enum Direction {
+        North,
+        Step(i32),
+    }
+
+    #[test]
+    fn structs_and_maps() {
+        assert_eq!(
+            to_json(&Point { x: 1, y: 2 }).unwrap(),
+            r#"{"type":"T","x":1,"y":2}"#
+        );
+        assert_eq!(
+            to_json(&Wrapper(Point { x: 1, y: 2 })).unwrap(),
+            r#"{"type":"T","x":1,"y":2}"#
+        );
+        assert_eq!(
+            to_json(&Some(Point { x: 1, y: 2 })).unwrap(),
+            r#"{"type":"T","x":1,"y":2}"#
+        );
+        assert_eq!(to_json(&None::<Point>).unwrap(), r#"{"type":"T"}"#);
+        assert_eq!(to_json(&Unit).unwrap(), r#"{"type":"T"}"#);
+
+        let map: BTreeMap<&str, u8> = [("a", 1), ("b", 2)].into();
+        assert_eq!(to_json(&map).unwrap(), r#"{"type":"T","a":1,"b":2}"#);
+    }
+
+    #[test]
+    fn nested_structs() {
+        #[derive(serde::Serialize)]
+        struct Line {
+            from: Point,
+            to: Option<Point>,
+            style: Wrapper,
+        }
+
+        // Only the outermost struct is flattened.
+        let line = Line {
+            from: Point { x: 0, y: 0 },
+            to: Some(Point { x: 3, y: 4 }),
+            style: Wrapper(Point { x: 5, y: 6 }),
+        };
+        assert_eq!(
+            to_json(&line).unwrap(),
+            r#"{"type":"T","from":{"x":0,"y":0},"to":{"x":3,"y":4},"style":{"x":5,"y":6}}"#
+        );
+    }
+
+    #[test]
+    fn non_map_values() {
+        for (result, what) in [
+            (to_json(&true), "a boolean"),
+            (to_json(&1u8), "an integer"),
+            (to_json(&-1i64), "an integer"),
+            (to_json(&1.5f32), "a float"),
+            (to_json(&'c'), "a char"),
+            (to_json(&"text"), "a string"),
+            (to_json(&vec![1, 2]), "a sequence"),
+            (to_json(&(1, 2)), "a tuple"),
+            (to_json(&Direction::North), "an enum"),
+            (to_json(&Direction::Step(1)), "an enum"),
+        ] {
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                format!("a tagged struct must serialize as a struct or map, not {what}")
+            );
+        }
+    }
+
+    #[cfg(feature = "rmp")]
+    #[test]
+    fn compact_binary_round_trip() {
+        use bevy_reflect::{
+            reflect_trait, Reflect, ReflectDeserialize, ReflectSerialize, TypeRegistry,
+        };
+
+        use crate::{impl_dynamic_trait, DowncastReflect, TypeRegistryExt};
+
+        #[reflect_trait]
+        trait Shape: DowncastReflect {}
+        impl_dynamic_trait!(Shape, ReflectShape);
+
+        #[derive(Debug, PartialEq, Reflect, serde::Serialize, serde::Deserialize)]
+        #[reflect(Serialize, Deserialize)]
+        struct Corner {
+            x: i32,
+            y: i32,
+        }
+
+        #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+        #[reflect(Shape, Serialize, Deserialize)]
+        struct Rect {
+            min: Corner,
+            max: Corner,
+            label: Option<String>,
+        }
+        impl Shape for Rect {}
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<Rect>();
+        registry.set_current(|| {
+            let rect: Box<dyn Shape> = Box::new(Rect {
+                min: Corner { x: 0, y: 1 },
+                max: Corner { x: 2, y: 3 },
+                label: None,
+            });
+
+            // By default, `rmp_serde` writes structs as arrays without field
+            // names. The fields of the tagged struct are still written as
+            // named map entries, while nested structs stay compact.
+            let bytes = rmp_serde::to_vec(&rect).unwrap();
+            let contains = |needle: &[u8]| bytes.windows(needle.len()).any(|w| w == needle);
+            assert!(contains(b"\xa3min"));
+            assert!(contains(b"\xa5label"));
+            assert!(!contains(b"\xa1x"));
+
+            let deserialized: Box<dyn Shape> = rmp_serde::from_slice(&bytes).unwrap();
+            let rect = deserialized.downcast_ref::<Rect>().unwrap();
+            assert_eq!(rect.min, Corner { x: 0, y: 1 });
+            assert_eq!(rect.max, Corner { x: 2, y: 3 });
+            assert_eq!(rect.label, None);
+        });
+    }
+}
@@ -0,0 +1,151 @@
+//! Entry point for fuzzing the tagged deserializer with untrusted input.
+//!
+//! [`deserialize_arbitrary()`] accepts any bytes, and never panics on
+//! malformed input, which makes it suitable as the body of a `cargo fuzz`
+//! target or a `proptest` property:
+//!
+//! ```rust,ignore
+//! #![no_main]
+//! use libfuzzer_sys::fuzz_target;
+//!
+//! fuzz_target!(|data: &[u8]| {
+//!     let registry = my_game::type_registry();
+//!     let _ = reflect_steroids::serialization::fuzzing::deserialize_arbitrary(data, &registry);
+//! });
+//! ```
+//!
+//! Panics from the `Deserialize` implementations of registered types are not
+//! caught, so the fuzzer finds those as well.
+
+use bevy_reflect::{Reflect, TypeRegistry};
+
+use crate::{MalformedInput, TypeRegistryExt};
+
+use super::{
+    binary::read_value,
+    de::{deserialize, with_lenient},
+    value::{ValueDeserializer, ValueError},
+    with_bevy_compat, with_limits, Limits,
+};
+
+/// The first byte of the input selects JSON instead of the binary encoding.
+#[cfg(feature = "json")]
+const JSON: u8 = 1 << 0;
+/// The first byte of the input enables lenient deserialization.
+const LENIENT: u8 = 1 << 1;
+/// The first byte of the input enables Bevy compatibility mode.
+const BEVY_COMPAT: u8 = 1 << 2;
+
+/// Deserialize a tagged object (see [`deserialize()`]) from arbitrary bytes
+/// with `registry`.
+///
+/// The first byte of `bytes` is a set of flags that select the input format
+/// and the deserialization modes to exercise, so that a fuzzer can explore all
+/// of them:
+///
+/// - `0x01`: the rest is JSON (requires the "json" crate feature; otherwise
+///   this flag is ignored), instead of the compact binary encoding used by
+///   [`stream`](super::stream).
+/// - `0x02`: deserialize with [`with_lenient()`].
+/// - `0x04`: deserialize with [`with_bevy_compat()`].
+///
+/// The input is deserialized with the default [`Limits`], so that deeply
+/// nested input is reported as an error rather than overflowing the stack.
+///
+/// # Errors
+///
+/// Fails on malformed input, unknown type tags, and any other failure.
+pub fn deserialize_arbitrary(
+    bytes: &[u8],
+    registry: &TypeRegistry,
+) -> Result<Box<dyn Reflect>, MalformedInput> {
+    let (&flags, rest) = bytes
+        .split_first()
+        .ok_or_else(|| MalformedInput("empty input".into()))?;
+
+    let decode = || decode(flags, rest);
+    let run = || {
+        if flags & LENIENT != 0 {
+            with_lenient(decode)
+        } else {
+            decode()
+        }
+    };
+    registry
+        .set_current(|| {
+            with_limits(Limits::default(), || {
+                if flags & BEVY_COMPAT != 0 {
+                    with_bevy_compat(run)
+                } else {
+                    run()
+                }
+            })
+        })
+        .map_err(MalformedInput)
+}
+
+#[cfg_attr(not(feature = "json"), allow(unused_variables))]
+fn decode(flags: u8, bytes: &[u8]) -> Result<Box<dyn Reflect>, String> {
+    #[cfg(feature = "json")]
+    if flags & JSON != 0 {
+        let mut deserializer = serde_json::Deserializer::from_slice(bytes);
+        let object = deserialize(&mut deserializer).map_err(|err| err.to_string())?;
+        deserializer.end().map_err(|err| err.to_string())?;
+        return Ok(object);
+    }
+
+    let value = read_value(bytes).map_err(|err| err.to_string())?;
+    deserialize(ValueDeserializer::<ValueError>::new(value)).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{ReflectDeserialize, ReflectSerialize};
+
+    use super::*;
+    use crate::serialization::{binary::write_value, ser::SerializeTagged, value_ser::to_value};
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Serialize, Deserialize)]
+    struct Item {
+        name: String,
+        tags: Vec<(u8, Option<i64>)>,
+    }
+
+    #[test]
+    fn no_panics_on_mutated_input() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Item>();
+
+        let item = Item {
+            name: "sword".into(),
+            tags: vec![(1, Some(-2)), (3, None)],
+        };
+        let mut valid = vec![0];
+        registry
+            .set_current(|| write_value(&to_value(&SerializeTagged(&item)).unwrap(), &mut valid));
+        let object = deserialize_arbitrary(&valid, &registry).unwrap();
+        assert_eq!(object.downcast_ref::<Item>().unwrap().name, "sword");
+
+        assert!(deserialize_arbitrary(&[], &registry).is_err());
+
+        // Truncate and flip bits at every position, in every mode.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        for flags in 0..8 {
+            for len in 1..valid.len() {
+                let mut input = valid[..len].to_vec();
+                input[0] = flags;
+                let _ = deserialize_arbitrary(&input, &registry);
+            }
+            for index in 1..valid.len() {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let mut input = valid.clone();
+                input[0] = flags;
+                input[index] ^= 1 << (state % 8);
+                let _ = deserialize_arbitrary(&input, &registry);
+            }
+        }
+    }
+}
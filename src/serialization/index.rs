@@ -0,0 +1,170 @@
+use bevy_reflect::{
+    ReflectDeserialize, ReflectSerialize, TypeInfo, TypeRegistration, TypeRegistry,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::type_registry::user_registrations;
+
+use super::manifest::{Manifest, ManifestEntry, ManifestError};
+
+/// A persistable description of the serializable types in a [`TypeRegistry`].
+///
+/// The index lists every registered type that has
+/// [`ReflectSerialize`] or [`ReflectDeserialize`] type data, with its type
+/// tag, Rust type name, [`TypeVersion`](super::TypeVersion), schema hash and
+/// fields. It can be written to a file with any serde format, and loaded later
+/// to check saved data or another registry for compatibility, or to generate
+/// type definitions for other languages.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::RegistryIndex};
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Serialize, Deserialize)]
+/// struct Foo { num: i32 }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Foo>();
+///
+/// let index = RegistryIndex::from_registry(&registry);
+/// let json = serde_json::to_string(&index).unwrap();
+///
+/// let index: RegistryIndex = serde_json::from_str(&json).unwrap();
+/// let foo = index.get("Foo").unwrap();
+/// assert_eq!(foo.kind, "struct");
+/// assert_eq!(foo.fields[0].name.as_deref(), Some("num"));
+/// assert_eq!(foo.fields[0].type_name, "i32");
+/// assert!(index.check(&registry).is_ok());
+///
+/// assert!(index.check(&TypeRegistry::default()).is_err());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegistryIndex {
+    /// The types, ordered by type tag.
+    pub types: Vec<IndexEntry>,
+}
+
+/// A serializable type in a [`RegistryIndex`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Version, schema hash and type tag, as they would appear in the
+    /// [`Manifest`] of a document.
+    #[serde(flatten)]
+    pub manifest: ManifestEntry,
+    /// The full Rust type name.
+    pub type_name: String,
+    /// The kind of type: "struct", "tuple struct", "tuple", "list", "array",
+    /// "map", or "value".
+    pub kind: String,
+    /// The fields of structs, tuple structs, and tuples.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<IndexField>,
+}
+
+/// A field of a type in a [`RegistryIndex`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexField {
+    /// The name of the field, or `None` for tuple fields.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The Rust type name of the field.
+    pub type_name: String,
+}
+
+impl RegistryIndex {
+    /// Describe the serializable types of `registry`.
+    pub fn from_registry(registry: &TypeRegistry) -> Self {
+        let mut types: Vec<_> = user_registrations(registry)
+            .filter(|registration| {
+                registration.data::<ReflectSerialize>().is_some()
+                    || registration.data::<ReflectDeserialize>().is_some()
+            })
+            .map(IndexEntry::for_registration)
+            .collect();
+        types.sort_by(|a, b| a.tag().cmp(b.tag()));
+        RegistryIndex { types }
+    }
+
+    /// Find the entry for the type tag `tag`.
+    #[must_use]
+    pub fn get(&self, tag: &str) -> Option<&IndexEntry> {
+        self.types.iter().find(|entry| entry.tag() == tag)
+    }
+
+    /// The manifest of a document that uses all types in the index.
+    #[must_use]
+    pub fn to_manifest(&self) -> Manifest {
+        Manifest {
+            entries: self
+                .types
+                .iter()
+                .map(|entry| entry.manifest.clone())
+                .collect(),
+        }
+    }
+
+    /// Check that all types in the index are present in `registry`, with the
+    /// same version and schema. See [`Manifest::check()`].
+    ///
+    /// # Errors
+    ///
+    /// Lists all types that are missing from `registry`, or have a different
+    /// version or schema.
+    pub fn check(&self, registry: &TypeRegistry) -> Result<(), ManifestError> {
+        self.to_manifest().check(registry)
+    }
+}
+
+impl IndexEntry {
+    /// Describe the type of `registration`.
+    #[must_use]
+    pub fn for_registration(registration: &TypeRegistration) -> Self {
+        let type_info = registration.type_info();
+        let (kind, fields) = match type_info {
+            TypeInfo::Struct(info) => (
+                "struct",
+                info.iter()
+                    .map(|field| IndexField {
+                        name: Some(field.name().to_string()),
+                        type_name: field.type_name().to_string(),
+                    })
+                    .collect(),
+            ),
+            TypeInfo::TupleStruct(info) => (
+                "tuple struct",
+                info.iter()
+                    .map(|field| IndexField {
+                        name: None,
+                        type_name: field.type_name().to_string(),
+                    })
+                    .collect(),
+            ),
+            TypeInfo::Tuple(info) => (
+                "tuple",
+                info.iter()
+                    .map(|field| IndexField {
+                        name: None,
+                        type_name: field.type_name().to_string(),
+                    })
+                    .collect(),
+            ),
+            TypeInfo::List(_) => ("list", Vec::new()),
+            TypeInfo::Array(_) => ("array", Vec::new()),
+            TypeInfo::Map(_) => ("map", Vec::new()),
+            _ => ("value", Vec::new()),
+        };
+
+        IndexEntry {
+            manifest: ManifestEntry::for_registration(registration),
+            type_name: registration.type_name().to_string(),
+            kind: kind.to_string(),
+            fields,
+        }
+    }
+
+    /// The type tag.
+    #[must_use]
+    pub fn tag(&self) -> &str {
+        &self.manifest.tag
+    }
+}
@@ -0,0 +1,235 @@
+//! Tagged (de)serialization of sequences of shared trait objects, such as
+//! those produced by an [`Interner`](crate::Interner), writing each object
+//! once.
+//!
+//! This module may be used in a `#[serde(with = "...")]` field attribute when
+//! the type of the field is `Vec<Arc<dyn MyTrait>>`. The first occurrence of
+//! each object (by pointer identity) is written as a tagged object (see
+//! [`serialize()`](super::serialize)), and later occurrences are written as
+//! the index of the object among the objects written so far. When
+//! deserialized, occurrences of the same object share one `Arc`.
+//!
+//! Since elements are either objects or integers, deserialization requires a
+//! self-describing format, such as JSON.
+//!
+//! ## Example
+//! ```rust
+//! # use std::sync::Arc;
+//! # use reflect_steroids::{prelude::*, reflect::TypeRegistry, Interner};
+//! #[reflect_trait]
+//! trait Modifier: DowncastReflect {}
+//! impl_dynamic_trait!(Modifier, ReflectModifier);
+//!
+//! #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+//! #[reflect(Modifier, Serialize, Deserialize)]
+//! struct Haste(u32);
+//! impl Modifier for Haste {}
+//!
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Item {
+//!     #[serde(with = "reflect_steroids::serialization::interned")]
+//!     modifiers: Vec<Arc<dyn Modifier>>,
+//! }
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register::<Haste>();
+//!
+//! let mut interner = Interner::new();
+//! let modifiers = (0..3)
+//!     .map(|_| interner.intern(Box::new(Haste(2)) as Box<dyn Modifier>))
+//!     .collect();
+//! registry.set_current(|| {
+//!     let json = serde_json::to_string(&Item { modifiers }).unwrap();
+//!     assert_eq!(json, r#"{"modifiers":[{"type":"Haste","value":2},0,0]}"#);
+//!
+//!     let item: Item = serde_json::from_str(&json).unwrap();
+//!     assert!(Arc::ptr_eq(&item.modifiers[0], &item.modifiers[2]));
+//! });
+//! ```
+
+use std::{collections::HashMap, fmt, marker::PhantomData, sync::Arc};
+
+use serde::{
+    de::{self, value::MapAccessDeserializer, MapAccess, SeqAccess, Visitor},
+    ser::SerializeSeq,
+    Deserialize,
+};
+
+use crate::DynamicTrait;
+
+use super::ser::SerializeTagged;
+
+/// Serialize shared objects, writing each object once. For use with
+/// `#[serde(with = "...")]`.
+///
+/// # Errors
+///
+/// Fails as [`serialize()`](super::serialize()) does for any of the objects.
+pub fn serialize<P, S>(objects: &[Arc<P>], serializer: S) -> Result<S::Ok, S::Error>
+where
+    P: DynamicTrait + ?Sized,
+    S: serde::Serializer,
+{
+    let mut indices: HashMap<*const (), usize> = HashMap::new();
+    let mut seq = serializer.serialize_seq(Some(objects.len()))?;
+    for object in objects {
+        let key = Arc::as_ptr(object).cast::<()>();
+        if let Some(index) = indices.get(&key) {
+            seq.serialize_element(index)?;
+        } else {
+            indices.insert(key, indices.len());
+            seq.serialize_element(&SerializeTagged(P::as_reflect(object)))?;
+        }
+    }
+    seq.end()
+}
+
+/// Deserialize shared objects written by [`serialize()`]. For use with
+/// `#[serde(with = "...")]`.
+///
+/// # Errors
+///
+/// Fails as [`deserialize()`](super::deserialize()) does for any of the
+/// objects, or if an index does not refer to an earlier object.
+pub fn deserialize<'de, P, D>(deserializer: D) -> Result<Vec<Arc<P>>, D::Error>
+where
+    P: DynamicTrait + ?Sized,
+    D: serde::Deserializer<'de>,
+{
+    struct SeqVisitor<P: ?Sized>(PhantomData<fn() -> Box<P>>);
+
+    impl<'de, P: DynamicTrait + ?Sized> Visitor<'de> for SeqVisitor<P> {
+        type Value = Vec<Arc<P>>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a sequence of objects and object indices")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Vec<Arc<P>>, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut distinct: Vec<Arc<P>> = Vec::new();
+            let mut objects = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+            while let Some(element) = seq.next_element::<Element<P>>()? {
+                let object = match element {
+                    Element::Index(index) => distinct
+                        .get(index)
+                        .ok_or_else(|| {
+                            de::Error::custom(format_args!("no object with index {index}"))
+                        })?
+                        .clone(),
+                    Element::Object(object) => {
+                        let object: Arc<P> = Arc::from(object);
+                        distinct.push(object.clone());
+                        object
+                    }
+                };
+                objects.push(object);
+            }
+            Ok(objects)
+        }
+    }
+
+    deserializer.deserialize_seq(SeqVisitor(PhantomData))
+}
+
+enum Element<P: ?Sized> {
+    Index(usize),
+    Object(Box<P>),
+}
+
+impl<'de, P: DynamicTrait + ?Sized> Deserialize<'de> for Element<P> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct ElementVisitor<P: ?Sized>(PhantomData<fn() -> Box<P>>);
+
+        impl<'de, P: DynamicTrait + ?Sized> Visitor<'de> for ElementVisitor<P> {
+            type Value = Element<P>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a tagged object or an object index")
+            }
+
+            fn visit_u64<E: de::Error>(self, index: u64) -> Result<Element<P>, E> {
+                usize::try_from(index)
+                    .map(Element::Index)
+                    .map_err(|_| E::custom("object index out of range"))
+            }
+
+            fn visit_map<A>(self, map: A) -> Result<Element<P>, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                super::deserialize_trait_object(MapAccessDeserializer::new(map))
+                    .map(Element::Object)
+            }
+        }
+
+        deserializer.deserialize_any(ElementVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{
+        reflect_trait, Reflect, ReflectDeserialize, ReflectSerialize, TypeRegistry,
+    };
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect, Interner, TypeRegistryExt};
+
+    #[reflect_trait]
+    trait Rule: DowncastReflect {}
+    impl_dynamic_trait!(Rule, ReflectRule);
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Rule, Serialize, Deserialize)]
+    struct Limit {
+        max: u32,
+    }
+    impl Rule for Limit {}
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Rules {
+        #[serde(with = "crate::serialization::interned")]
+        rules: Vec<Arc<dyn Rule>>,
+    }
+
+    #[test]
+    fn round_trip_shares_objects() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Limit>();
+
+        let mut interner = Interner::new();
+        let rules = [1, 2, 1, 1, 2]
+            .into_iter()
+            .map(|max| interner.intern(Box::new(Limit { max }) as Box<dyn Rule>))
+            .collect();
+        registry.set_current(|| {
+            let json = serde_json::to_string(&Rules { rules }).unwrap();
+            assert_eq!(
+                json,
+                r#"{"rules":[{"type":"Limit","max":1},{"type":"Limit","max":2},0,0,1]}"#
+            );
+
+            let rules: Rules = serde_json::from_str(&json).unwrap();
+            let maxes: Vec<u32> = rules
+                .rules
+                .iter()
+                .map(|rule| rule.downcast_ref::<Limit>().unwrap().max)
+                .collect();
+            assert_eq!(maxes, [1, 2, 1, 1, 2]);
+            assert!(Arc::ptr_eq(&rules.rules[0], &rules.rules[3]));
+            assert!(Arc::ptr_eq(&rules.rules[1], &rules.rules[4]));
+            assert_eq!(Arc::strong_count(&rules.rules[0]), 3);
+
+            let err = serde_json::from_str::<Rules>(r#"{"rules":[0]}"#)
+                .err()
+                .unwrap();
+            assert!(err.to_string().contains("no object with index 0"));
+        });
+    }
+}
@@ -0,0 +1,185 @@
+use bevy_reflect::{Reflect, TypeRegistry};
+
+use crate::TypeRegistryExt;
+
+use super::{ser::SerializeTagged, value::Value};
+
+impl Value<'_> {
+    /// Convert an in-memory JSON value.
+    ///
+    /// JSON numbers become `U64` if they are non-negative integers, `I64` if
+    /// they are negative integers, and `F64` otherwise. `null` becomes `Unit`.
+    pub fn from_json(value: serde_json::Value) -> Value<'static> {
+        match value {
+            serde_json::Value::Null => Value::Unit,
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) => {
+                if let Some(n) = n.as_u64() {
+                    Value::U64(n)
+                } else if let Some(n) = n.as_i64() {
+                    Value::I64(n)
+                } else {
+                    Value::F64(n.as_f64().unwrap_or(f64::NAN))
+                }
+            }
+            serde_json::Value::String(s) => Value::String(s),
+            serde_json::Value::Array(elements) => {
+                Value::Seq(elements.into_iter().map(Value::from_json).collect())
+            }
+            serde_json::Value::Object(entries) => Value::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (Value::String(key), Value::from_json(value)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Convert to an in-memory JSON value.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the value contains a map with keys that cannot be represented
+    /// as JSON object keys.
+    pub fn into_json(self) -> Result<serde_json::Value, serde_json::Error> {
+        serde_json::to_value(&self)
+    }
+}
+
+/// Serialize any dynamic trait pointer to an in-memory JSON value.
+///
+/// This is equivalent to serializing to a JSON string with
+/// [`serialize()`](super::serialize) and parsing the result, without the
+/// intermediate string.
+///
+/// Like [`serialize()`](super::serialize), this requires a current global
+/// [`TypeRegistry`](bevy_reflect::TypeRegistry). See
+/// [`TypeRegistryExt::set_current`](crate::TypeRegistryExt::set_current).
+///
+/// Requires the "json" crate feature.
+///
+/// # Errors
+///
+/// Fails if there is no current registry, or if the object cannot be
+/// serialized.
+pub fn to_json_value(this: &dyn Reflect) -> Result<serde_json::Value, serde_json::Error> {
+    super::serialize(this, serde_json::value::Serializer)
+}
+
+/// Deserialize any dynamic trait pointer from an in-memory JSON value.
+///
+/// Like [`deserialize()`](super::deserialize), this requires a current global
+/// [`TypeRegistry`](bevy_reflect::TypeRegistry). See
+/// [`TypeRegistryExt::set_current`](crate::TypeRegistryExt::set_current).
+///
+/// Requires the "json" crate feature.
+///
+/// # Errors
+///
+/// Fails if there is no current registry, if the type is not registered, or if
+/// the value does not match it.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+/// # use reflect_steroids::serialization::{from_json_value, to_json_value};
+/// #[reflect_trait]
+/// trait MyTrait: DowncastReflect {}
+/// impl_dynamic_trait!(MyTrait, ReflectMyTrait);
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(MyTrait, Serialize, Deserialize)]
+/// struct Foo { num: i32 }
+///
+/// impl MyTrait for Foo {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Foo>();
+///
+/// registry.set_current(|| {
+///     let json = serde_json::json!({"type": "Foo", "num": 123});
+///     let reflected = from_json_value(json.clone()).unwrap();
+///     assert_eq!(reflected.downcast_ref::<Foo>().unwrap().num, 123);
+///     assert_eq!(to_json_value(&*reflected).unwrap(), json);
+/// });
+/// ```
+pub fn from_json_value(value: serde_json::Value) -> Result<Box<dyn Reflect>, serde_json::Error> {
+    super::deserialize(value)
+}
+
+/// Serialize `this` to a JSON string with a type tag, using `registry`.
+///
+/// This is a shorthand for making `registry` current (see
+/// [`TypeRegistryExt::set_current`](crate::TypeRegistryExt::set_current)) and
+/// serializing with [`serialize()`](super::serialize). See also
+/// [`from_json_tagged()`].
+///
+/// Requires the "json" crate feature.
+///
+/// # Errors
+///
+/// Fails as [`serialize()`](super::serialize) does.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+/// # use reflect_steroids::serialization::{from_json_tagged, to_json_tagged};
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Serialize, Deserialize)]
+/// struct Settings { volume: u8 }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Settings>();
+///
+/// let json = to_json_tagged(&Settings { volume: 7 }, &registry).unwrap();
+/// assert_eq!(json, r#"{"type":"Settings","volume":7}"#);
+///
+/// let settings = from_json_tagged(&json, &registry).unwrap();
+/// assert_eq!(settings.downcast_ref::<Settings>().unwrap().volume, 7);
+/// ```
+pub fn to_json_tagged(
+    this: &dyn Reflect,
+    registry: &TypeRegistry,
+) -> Result<String, serde_json::Error> {
+    registry.set_current(|| serde_json::to_string(&SerializeTagged(this)))
+}
+
+/// Deserialize a tagged object from a JSON string, using `registry`.
+///
+/// This is a shorthand for making `registry` current (see
+/// [`TypeRegistryExt::set_current`](crate::TypeRegistryExt::set_current)) and
+/// deserializing with [`deserialize()`](super::deserialize). See also
+/// [`to_json_tagged()`].
+///
+/// Requires the "json" crate feature.
+///
+/// # Errors
+///
+/// Fails as [`deserialize()`](super::deserialize) does, or if there are
+/// characters other than whitespace after the object.
+pub fn from_json_tagged(
+    json: &str,
+    registry: &TypeRegistry,
+) -> Result<Box<dyn Reflect>, serde_json::Error> {
+    registry.set_current(|| {
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        let object = super::deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(object)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_round_trip() {
+        let json = serde_json::json!({
+            "a": [1, -2, 3.5, null],
+            "b": {"c": "d", "e": true},
+        });
+        let value = Value::from_json(json.clone());
+        assert_eq!(value.into_json().unwrap(), json);
+    }
+}
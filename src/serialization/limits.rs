@@ -0,0 +1,169 @@
+use std::cell::Cell;
+
+scoped_tls::scoped_thread_local!(static LIMITS: State);
+
+struct State {
+    limits: Limits,
+    depth: Cell<usize>,
+    nodes: Cell<usize>,
+}
+
+/// Limits on the input of tagged deserialization, for reading untrusted data.
+/// See [`with_limits()`].
+///
+/// The depth is the number of nested sequences, maps, options and newtypes
+/// around a value. Every value counts as a node, including the keys of maps
+/// and the values inside other values. Objects nested in other objects are
+/// buffered again when they are deserialized, so their values count once for
+/// each object they are nested in.
+///
+/// The defaults are a depth of 128, like `serde_json`, and 1,000,000 nodes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    max_depth: usize,
+    max_nodes: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_depth: 128,
+            max_nodes: 1_000_000,
+        }
+    }
+}
+
+impl Limits {
+    /// The default limits.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the nesting depth of values.
+    #[must_use]
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Limit the total number of values.
+    #[must_use]
+    pub fn max_nodes(mut self, nodes: usize) -> Self {
+        self.max_nodes = nodes;
+        self
+    }
+}
+
+/// Enforce `limits` on the input of tagged deserialization while running `f`.
+///
+/// Tagged objects (see [`deserialize()`](super::deserialize())) are buffered
+/// before they are deserialized, to find their type tag. Without limits, a
+/// small payload of deeply nested sequences can overflow the stack while it is
+/// buffered, and a large payload is buffered in full. With limits, buffering
+/// fails with an error instead. The node count is shared by everything that is
+/// deserialized while `f` runs.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::{with_limits, Limits}};
+/// #[reflect_trait]
+/// trait Asset: DowncastReflect {}
+/// impl_dynamic_trait!(Asset, ReflectAsset);
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Asset, Serialize, Deserialize)]
+/// struct Level { data: Vec<u32> }
+/// impl Asset for Level {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Level>();
+///
+/// let deep = r#"{"type":"Level","data":[[[[[[[[1]]]]]]]]}"#;
+/// let large = format!(r#"{{"type":"Level","data":[{}0]}}"#, "0,".repeat(10_000));
+///
+/// registry.set_current(|| {
+///     with_limits(Limits::new().max_depth(4).max_nodes(1000), || {
+///         let err = serde_json::from_str::<Box<dyn Asset>>(deep).unwrap_err();
+///         assert!(err.to_string().contains("input exceeds the nesting limit of 4"));
+///
+///         let err = serde_json::from_str::<Box<dyn Asset>>(&large).unwrap_err();
+///         assert!(err.to_string().contains("input exceeds the limit of 1000 values"));
+///     });
+/// });
+/// ```
+pub fn with_limits<F: FnOnce() -> R, R>(limits: Limits, f: F) -> R {
+    let state = State {
+        limits,
+        depth: Cell::new(0),
+        nodes: Cell::new(0),
+    };
+    LIMITS.set(&state, f)
+}
+
+/// Count a value and enter it, if limits are enforced. The depth is restored
+/// when the returned guard is dropped.
+pub(crate) fn enter<E: serde::de::Error>() -> Result<Nesting, E> {
+    if !LIMITS.is_set() {
+        return Ok(Nesting(false));
+    }
+    LIMITS.with(|state| {
+        let nodes = state.nodes.get() + 1;
+        if nodes > state.limits.max_nodes {
+            return Err(E::custom(format_args!(
+                "input exceeds the limit of {} values",
+                state.limits.max_nodes
+            )));
+        }
+        let depth = state.depth.get();
+        if depth > state.limits.max_depth {
+            return Err(E::custom(format_args!(
+                "input exceeds the nesting limit of {}",
+                state.limits.max_depth
+            )));
+        }
+        state.nodes.set(nodes);
+        state.depth.set(depth + 1);
+        Ok(Nesting(true))
+    })
+}
+
+/// Guard returned by [`enter()`].
+pub(crate) struct Nesting(bool);
+
+impl Drop for Nesting {
+    fn drop(&mut self) {
+        if self.0 && LIMITS.is_set() {
+            LIMITS.with(|state| state.depth.set(state.depth.get().saturating_sub(1)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialization::{
+        binary::{read_value, write_value},
+        value::Value,
+    };
+
+    fn nested(depth: usize) -> Value<'static> {
+        (0..depth).fold(Value::Unit, |value, _| Value::Seq(vec![value]))
+    }
+
+    #[test]
+    fn binary_limits() {
+        let mut bytes = Vec::new();
+        write_value(&nested(100), &mut bytes);
+        assert!(read_value(&bytes).is_ok());
+
+        let limits = Limits::new().max_depth(100);
+        assert!(with_limits(limits, || read_value(&bytes)).is_ok());
+        let limits = Limits::new().max_depth(50);
+        let err = with_limits(limits, || read_value(&bytes)).unwrap_err();
+        assert_eq!(err.to_string(), "input exceeds the nesting limit of 50");
+        let limits = Limits::new().max_nodes(100);
+        let err = with_limits(limits, || read_value(&bytes)).unwrap_err();
+        assert_eq!(err.to_string(), "input exceeds the limit of 100 values");
+    }
+}
@@ -0,0 +1,336 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    fmt::{self, Write as _},
+};
+
+use bevy_reflect::{
+    Reflect, ReflectDeserialize, ReflectSerialize, TypeInfo, TypeRegistration, TypeRegistry,
+};
+use serde::{de::IntoDeserializer, Deserialize, Serialize};
+
+use crate::{type_registry::user_registrations, TypeRegistryExt};
+
+use super::{
+    ser::SerializeTagged,
+    tag::{resolve_tag, type_tag},
+    value::{Value, ValueDeserializer},
+    value_ser::to_value,
+};
+
+scoped_tls::scoped_thread_local!(
+    static COLLECTED_TAGS: RefCell<BTreeSet<String>>
+);
+
+/// Version number of a type, recorded in the manifest of a document (see
+/// [`serialize_document()`]).
+///
+/// Types without `TypeVersion` type data have version 0. Bump the version when
+/// making an incompatible change to the serialized representation of a type,
+/// so that [`deserialize_document()`] can report old documents up front.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TypeVersion(pub u32);
+
+/// The list of types used in a document.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Manifest {
+    /// The entries, ordered by type tag.
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// A type used in a document.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// The type tag, as written in the `type` field of tagged objects.
+    #[serde(rename = "type")]
+    pub tag: String,
+    /// The [`TypeVersion`] of the type.
+    #[serde(default)]
+    pub version: u32,
+    /// A hash of the structure of the type (field names and types), if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<u64>,
+}
+
+impl ManifestEntry {
+    /// Describe the type of `registration`, as it would be written in a
+    /// manifest.
+    #[must_use]
+    pub fn for_registration(registration: &TypeRegistration) -> Self {
+        ManifestEntry {
+            tag: type_tag(registration).to_string(),
+            version: registration
+                .data::<TypeVersion>()
+                .map_or(0, |version| version.0),
+            schema: Some(schema_hash(registration.type_info())),
+        }
+    }
+}
+
+impl Manifest {
+    /// Check that all entries of the manifest are known to `registry`, with
+    /// the same version and schema.
+    ///
+    /// All incompatibilities are reported, not just the first one.
+    ///
+    /// # Errors
+    ///
+    /// Lists all entries that are unknown to `registry`, or have a different
+    /// version or schema.
+    pub fn check(&self, registry: &TypeRegistry) -> Result<(), ManifestError> {
+        let mismatches: Vec<_> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let Some(registration) = resolve_tag(registry, &entry.tag) else {
+                    return Some(ManifestMismatch::UnknownType(entry.tag.clone()));
+                };
+                let current = ManifestEntry::for_registration(registration);
+                if current.version != entry.version {
+                    Some(ManifestMismatch::Version {
+                        tag: entry.tag.clone(),
+                        document: entry.version,
+                        registry: current.version,
+                    })
+                } else if entry.schema.is_some() && current.schema != entry.schema {
+                    Some(ManifestMismatch::Schema(entry.tag.clone()))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(ManifestError(mismatches))
+        }
+    }
+}
+
+/// A single incompatibility between a [`Manifest`] and a [`TypeRegistry`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ManifestMismatch {
+    /// No type with the tag is registered.
+    #[error("unknown type '{0}'")]
+    UnknownType(String),
+    /// The type is registered with a different [`TypeVersion`].
+    #[error("type '{tag}' has version {document} in the document, but {registry} in the registry")]
+    Version {
+        /// The type tag.
+        tag: String,
+        /// The version in the document.
+        document: u32,
+        /// The version in the registry.
+        registry: u32,
+    },
+    /// The structure of the type has changed since the document was written.
+    #[error("the structure of type '{0}' has changed")]
+    Schema(String),
+}
+
+/// The manifest of a document is incompatible with the current
+/// [`TypeRegistry`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ManifestError(pub Vec<ManifestMismatch>);
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("document is incompatible with the type registry:")?;
+        for mismatch in &self.0 {
+            write!(f, "\n- {mismatch}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+/// Serialize a dynamic trait pointer as a self-describing document.
+///
+/// The document consists of a manifest listing every type tag used anywhere in
+/// the payload (including nested trait objects), followed by the payload
+/// itself, serialized with [`serialize()`](super::serialize):
+///
+/// ```json
+/// {"manifest":[{"type":"Foo","version":0,"schema":1234}],"payload":{"type":"Foo","num":123}}
+/// ```
+///
+/// Like [`serialize()`](super::serialize), this requires a current global
+/// [`TypeRegistry`]. See [`TypeRegistryExt::set_current`].
+///
+/// # Errors
+///
+/// Fails if there is no current registry, or if a type in the object cannot be
+/// serialized.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+/// # use reflect_steroids::serialization::{deserialize_document, serialize_document, TypeVersion};
+/// # use std::any::TypeId;
+/// #[reflect_trait]
+/// trait MyTrait: DowncastReflect {}
+/// impl_dynamic_trait!(MyTrait, ReflectMyTrait);
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(MyTrait, Serialize, Deserialize)]
+/// struct Foo { num: i32 }
+///
+/// impl MyTrait for Foo {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Foo>();
+///
+/// let trait_object: Box<dyn MyTrait> = Box::new(Foo { num: 123 });
+/// let mut json = Vec::new();
+/// registry.set_current(|| {
+///     serialize_document(
+///         trait_object.as_reflect(),
+///         &mut serde_json::Serializer::new(&mut json),
+///     )
+///     .unwrap();
+/// });
+///
+/// // Bump the version of `Foo`, invalidating the document.
+/// registry
+///     .get_mut(TypeId::of::<Foo>())
+///     .unwrap()
+///     .insert(TypeVersion(1));
+/// registry.set_current(|| {
+///     let err = deserialize_document(&mut serde_json::Deserializer::from_slice(&json))
+///         .unwrap_err();
+///     assert!(err
+///         .to_string()
+///         .contains("type 'Foo' has version 0 in the document, but 1 in the registry"));
+/// });
+/// ```
+pub fn serialize_document<S>(this: &dyn Reflect, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    use serde::ser::Error as _;
+
+    let tags = RefCell::new(BTreeSet::new());
+    let payload = COLLECTED_TAGS
+        .set(&tags, || to_value(&SerializeTagged(this)))
+        .map_err(S::Error::custom)?;
+
+    let manifest = TypeRegistry::try_with_current(|registry| Manifest {
+        entries: tags
+            .into_inner()
+            .iter()
+            .filter_map(|tag| resolve_tag(registry, tag))
+            .map(ManifestEntry::for_registration)
+            .collect(),
+    })
+    .map_err(S::Error::custom)?;
+
+    Document { manifest, payload }.serialize(serializer)
+}
+
+/// Deserialize a document written by [`serialize_document()`].
+///
+/// The manifest is checked against the current [`TypeRegistry`] before the
+/// payload is deserialized. If any type in the manifest is unknown, or has a
+/// different version or structure, the error lists all such types.
+///
+/// Like [`deserialize()`](super::deserialize), this requires a current global
+/// [`TypeRegistry`]. See [`TypeRegistryExt::set_current`].
+///
+/// # Errors
+///
+/// Fails if there is no current registry, if the manifest does not match it
+/// (see [`Manifest::check()`]), or if the payload cannot be deserialized.
+pub fn deserialize_document<'de, D>(deserializer: D) -> Result<Box<dyn Reflect>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error as _;
+
+    let Document { manifest, payload } = Document::deserialize(deserializer)?;
+    TypeRegistry::try_with_current(|registry| manifest.check(registry))
+        .map_err(D::Error::custom)?
+        .map_err(D::Error::custom)?;
+
+    let payload: ValueDeserializer<D::Error> = payload.into_deserializer();
+    super::deserialize(payload)
+}
+
+/// Record that `tag` was written, if a document is being serialized.
+pub(crate) fn record_tag(tag: &str) {
+    if COLLECTED_TAGS.is_set() {
+        COLLECTED_TAGS.with(|tags| {
+            let mut tags = tags.borrow_mut();
+            if !tags.contains(tag) {
+                tags.insert(tag.to_string());
+            }
+        });
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound(deserialize = "'de: 'a, 'a: 'de"))]
+struct Document<'a> {
+    manifest: Manifest,
+    payload: Value<'a>,
+}
+
+/// Stable (FNV-1a) hash of the structure of a type.
+pub(crate) fn schema_hash(type_info: &TypeInfo) -> u64 {
+    let mut schema = String::new();
+    let _ = match type_info {
+        TypeInfo::Struct(info) => {
+            schema.push_str("struct");
+            info.iter().try_for_each(|field| {
+                let name: &str = field.name();
+                write!(schema, " {}:{}", name, field.type_name())
+            })
+        }
+        TypeInfo::TupleStruct(info) => {
+            schema.push_str("tuple struct");
+            info.iter()
+                .try_for_each(|field| write!(schema, " {}", field.type_name()))
+        }
+        TypeInfo::Tuple(info) => {
+            schema.push_str("tuple");
+            info.iter()
+                .try_for_each(|field| write!(schema, " {}", field.type_name()))
+        }
+        other => write!(schema, "{}", other.type_name()),
+    };
+
+    fnv1a(schema.as_bytes())
+}
+
+/// Stable hash of the tags, versions and schemas of all serializable types in
+/// `registry`.
+pub(crate) fn registry_fingerprint(registry: &TypeRegistry) -> u64 {
+    let mut entries: Vec<_> = user_registrations(registry)
+        .filter(|registration| {
+            registration.data::<ReflectSerialize>().is_some()
+                || registration.data::<ReflectDeserialize>().is_some()
+        })
+        .map(ManifestEntry::for_registration)
+        .collect();
+    entries.sort_by(|a, b| a.tag.cmp(&b.tag));
+
+    let mut description = String::new();
+    for entry in entries {
+        let _ = writeln!(
+            description,
+            "{} {} {}",
+            entry.tag,
+            entry.version,
+            entry.schema.unwrap_or_default()
+        );
+    }
+    fnv1a(description.as_bytes())
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
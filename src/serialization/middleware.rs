@@ -0,0 +1,310 @@
+use std::{borrow::Cow, fmt};
+
+use bevy_reflect::{Reflect, TypeRegistry};
+use serde::{
+    de::{IntoDeserializer, SeqAccess, Visitor},
+    ser::SerializeMap,
+    Deserialize, Serialize,
+};
+
+use crate::TypeRegistryExt;
+
+use super::{
+    binary::{read_value, write_value},
+    de::resolve_deserialize,
+    ser::{reflect_serialize, serialized_type_tag},
+    value_ser::to_value,
+};
+
+/// Hooks for transforming or authenticating the payload of a tagged object.
+///
+/// When an object is serialized with [`serialize_with_middleware()`], its
+/// payload is first encoded as an opaque byte string, which is then passed
+/// through [`encode()`](SerializationMiddleware::encode) and
+/// [`sign()`](SerializationMiddleware::sign). The result is written as:
+///
+/// ```json
+/// {"type":"Foo","payload":<bytes>,"signature":<bytes>}
+/// ```
+///
+/// where the `signature` field is only present if `sign()` returned something.
+/// [`deserialize_with_middleware()`] runs the inverse:
+/// [`verify()`](SerializationMiddleware::verify) followed by
+/// [`decode()`](SerializationMiddleware::decode).
+///
+/// The type tag itself is left untouched, so the registry lookup on the
+/// receiving end works as usual. Nested trait objects inside the payload are
+/// covered by the payload of their outermost parent.
+///
+/// All methods have default implementations that do nothing.
+pub trait SerializationMiddleware {
+    /// Transform the encoded payload of the object tagged `tag`, for example
+    /// by encrypting it.
+    ///
+    /// # Errors
+    ///
+    /// An error fails serialization of the object.
+    fn encode(&self, tag: &str, payload: Vec<u8>) -> Result<Vec<u8>, MiddlewareError> {
+        let _ = tag;
+        Ok(payload)
+    }
+
+    /// Reverse the transformation done by
+    /// [`encode()`](SerializationMiddleware::encode).
+    ///
+    /// # Errors
+    ///
+    /// An error fails deserialization of the object.
+    fn decode(&self, tag: &str, payload: Vec<u8>) -> Result<Vec<u8>, MiddlewareError> {
+        let _ = tag;
+        Ok(payload)
+    }
+
+    /// Compute a signature for the (encoded) payload, which will be written in
+    /// a `signature` field next to the type tag.
+    fn sign(&self, tag: &str, payload: &[u8]) -> Option<Vec<u8>> {
+        let _ = (tag, payload);
+        None
+    }
+
+    /// Verify the signature of an (encoded) payload before it is decoded.
+    /// `signature` is `None` if the data did not contain a `signature` field.
+    ///
+    /// # Errors
+    ///
+    /// An error, such as for a signature that does not match, fails
+    /// deserialization of the object.
+    fn verify(
+        &self,
+        tag: &str,
+        payload: &[u8],
+        signature: Option<&[u8]>,
+    ) -> Result<(), MiddlewareError> {
+        let _ = (tag, payload, signature);
+        Ok(())
+    }
+}
+
+/// Error returned by a [`SerializationMiddleware`].
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct MiddlewareError(pub Cow<'static, str>);
+
+impl MiddlewareError {
+    /// Create an error with the given message.
+    pub fn new(message: impl Into<Cow<'static, str>>) -> Self {
+        MiddlewareError(message.into())
+    }
+}
+
+/// Serialize any dynamic trait pointer, passing its payload through
+/// `middleware`.
+///
+/// Like [`serialize()`](super::serialize), this requires a current global
+/// [`TypeRegistry`]. See [`TypeRegistryExt::set_current`].
+///
+/// # Errors
+///
+/// Fails if there is no current registry, if the object cannot be serialized,
+/// or if `middleware` fails to encode it.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+/// # use reflect_steroids::serialization::{
+/// #     deserialize_with_middleware, serialize_with_middleware, MiddlewareError,
+/// #     SerializationMiddleware,
+/// # };
+/// #[reflect_trait]
+/// trait MyTrait: DowncastReflect {}
+/// impl_dynamic_trait!(MyTrait, ReflectMyTrait);
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(MyTrait, Serialize, Deserialize)]
+/// struct Foo { num: i32 }
+///
+/// impl MyTrait for Foo {}
+///
+/// struct Checksum;
+///
+/// impl SerializationMiddleware for Checksum {
+///     fn sign(&self, _tag: &str, payload: &[u8]) -> Option<Vec<u8>> {
+///         Some(vec![payload.iter().fold(0u8, |acc, b| acc.wrapping_add(*b))])
+///     }
+///
+///     fn verify(
+///         &self,
+///         tag: &str,
+///         payload: &[u8],
+///         signature: Option<&[u8]>,
+///     ) -> Result<(), MiddlewareError> {
+///         if signature == self.sign(tag, payload).as_deref() {
+///             Ok(())
+///         } else {
+///             Err(MiddlewareError::new("checksum mismatch"))
+///         }
+///     }
+/// }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Foo>();
+/// registry.set_current(|| {
+///     let mut json = Vec::new();
+///     let trait_object: Box<dyn MyTrait> = Box::new(Foo { num: 123 });
+///     serialize_with_middleware(
+///         trait_object.as_reflect(),
+///         &Checksum,
+///         &mut serde_json::Serializer::new(&mut json),
+///     )
+///     .unwrap();
+///
+///     let deserialized = deserialize_with_middleware(
+///         &Checksum,
+///         &mut serde_json::Deserializer::from_slice(&json),
+///     )
+///     .unwrap();
+///     assert_eq!(deserialized.downcast_ref::<Foo>().unwrap().num, 123);
+/// });
+/// ```
+pub fn serialize_with_middleware<S>(
+    this: &dyn Reflect,
+    middleware: &dyn SerializationMiddleware,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    use serde::ser::Error as _;
+
+    let (tag, serialize) = TypeRegistry::try_with_current(|registry| {
+        Ok((
+            serialized_type_tag(registry, this)?.to_string(),
+            reflect_serialize(registry, this)?,
+        ))
+    })
+    .and_then(|result| result)
+    .map_err(S::Error::custom)?;
+
+    let serializable = serialize.get_serializable(this);
+    let value = to_value(serializable.borrow()).map_err(S::Error::custom)?;
+    let mut payload = Vec::new();
+    write_value(&value, &mut payload);
+
+    let payload = middleware.encode(&tag, payload).map_err(S::Error::custom)?;
+    let signature = middleware.sign(&tag, &payload);
+
+    let mut map = serializer.serialize_map(Some(if signature.is_some() { 3 } else { 2 }))?;
+    map.serialize_entry("type", &tag)?;
+    map.serialize_entry("payload", &SerializeBytes(&payload))?;
+    if let Some(signature) = signature {
+        map.serialize_entry("signature", &SerializeBytes(&signature))?;
+    }
+    map.end()
+}
+
+/// Deserialize any dynamic trait pointer that was serialized with
+/// [`serialize_with_middleware()`].
+///
+/// Like [`deserialize()`](super::deserialize), this requires a current global
+/// [`TypeRegistry`]. See [`TypeRegistryExt::set_current`].
+///
+/// # Errors
+///
+/// Fails if there is no current registry, if the type is not registered, if
+/// `middleware` rejects the signature or fails to decode the payload, or if
+/// the payload cannot be deserialized.
+pub fn deserialize_with_middleware<'de, D>(
+    middleware: &dyn SerializationMiddleware,
+    deserializer: D,
+) -> Result<Box<dyn Reflect>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error as _;
+
+    let Sealed {
+        type_name,
+        payload,
+        signature,
+    } = Sealed::deserialize(deserializer)?;
+
+    middleware
+        .verify(
+            &type_name,
+            &payload.0,
+            signature.as_ref().map(|signature| &signature.0[..]),
+        )
+        .map_err(D::Error::custom)?;
+    let payload = middleware
+        .decode(&type_name, payload.0)
+        .map_err(D::Error::custom)?;
+    let value = read_value(&payload).map_err(D::Error::custom)?;
+
+    let (_, deserialize) =
+        TypeRegistry::try_with_current(|registry| resolve_deserialize(registry, &type_name))
+            .and_then(|result| result)
+            .map_err(D::Error::custom)?;
+    deserialize.deserialize(value.into_deserializer())
+}
+
+#[derive(Deserialize)]
+struct Sealed {
+    #[serde(rename = "type")]
+    type_name: String,
+    payload: ByteBuf,
+    #[serde(default)]
+    signature: Option<ByteBuf>,
+}
+
+struct SerializeBytes<'a>(&'a [u8]);
+
+impl Serialize for SerializeBytes<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// Accepts both native byte strings and sequences of bytes (which is how
+/// formats like JSON represent bytes).
+struct ByteBuf(Vec<u8>);
+
+impl<'de> Deserialize<'de> for ByteBuf {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct ByteBufVisitor;
+
+        impl<'de> Visitor<'de> for ByteBufVisitor {
+            type Value = ByteBuf;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a byte array")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<ByteBuf, E> {
+                Ok(ByteBuf(v.to_owned()))
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<ByteBuf, E> {
+                Ok(ByteBuf(v))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<ByteBuf, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                Ok(ByteBuf(bytes))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(ByteBufVisitor)
+    }
+}
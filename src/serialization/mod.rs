@@ -1,19 +1,109 @@
 //! Type-tagged serialization/deserialization utilities.
 //!
 //! This module may be used in a `#[serde(with = "...")]` field attribute when
-//! the type of the field is `Box<dyn Reflect>`.
+//! the type of the field is `Box<dyn Reflect>`. For `Box<dyn MyTrait>`, and
+//! for `Option` and `Vec` fields, use [`dyn_field`] instead.
+//!
+//! ## Example
+//! ```rust
+//! # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Slot {
+//!     #[serde(with = "reflect_steroids::serialization")]
+//!     content: Box<dyn Reflect>,
+//! }
+//!
+//! #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+//! #[reflect(Serialize, Deserialize)]
+//! struct Foo { num: i32 }
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register::<Foo>();
+//! registry.set_current(|| {
+//!     let slot = Slot { content: Box::new(Foo { num: 1 }) };
+//!     let json = serde_json::to_string(&slot).unwrap();
+//!     assert_eq!(json, r#"{"content":{"type":"Foo","num":1}}"#);
+//!
+//!     let slot: Slot = serde_json::from_str(&json).unwrap();
+//!     assert_eq!(slot.content.downcast_ref::<Foo>().unwrap().num, 1);
+//! });
+//! ```
 
-mod de;
-mod ser;
+mod bevy_compat;
+pub(crate) mod binary;
+mod canonical;
+#[cfg(feature = "cbor")]
+mod cbor;
+mod context;
+mod cycles;
+pub(crate) mod de;
+mod deferred;
+pub mod dyn_field;
+mod filter;
+#[cfg(feature = "json")]
+pub mod fixtures;
+mod flat;
+pub mod fuzzing;
+mod index;
+pub mod interned;
+#[cfg(feature = "json")]
+mod json;
+mod limits;
+mod manifest;
+mod middleware;
+#[cfg(feature = "rmp")]
+mod msgpack;
+mod policy;
+pub mod raw;
+mod redact;
+pub(crate) mod restrict;
+mod rewrite;
+#[cfg(feature = "ron")]
+mod ron;
+mod seq;
+pub(crate) mod ser;
+pub mod stream;
+pub(crate) mod tag;
+pub mod testing;
+mod toml_layout;
+mod transparent;
 mod value;
+mod value_ser;
 
+pub use bevy_compat::{deserialize_bevy, with_bevy_compat};
+pub use canonical::{serialize_canonical, with_canonical};
+#[cfg(feature = "cbor")]
+pub use cbor::*;
+pub use context::DeserializeContext;
+pub use cycles::{with_cycle_guard, CycleMode};
 pub use de::*;
+pub use deferred::DeferredObject;
+pub use filter::TagFilter;
+pub use index::*;
+#[cfg(feature = "json")]
+pub use json::*;
+pub use limits::{with_limits, Limits};
+pub use manifest::*;
+pub use middleware::*;
+#[cfg(feature = "rmp")]
+pub use msgpack::*;
+pub use policy::SerializationPolicy;
+pub use redact::{with_redaction, Redact, REDACTED};
+pub use restrict::deserialize_trait_object;
+pub use rewrite::{with_tag_rewriter, TagRewriter};
+#[cfg(feature = "ron")]
+pub use ron::*;
+pub use seq::TaggedSeq;
 pub use ser::*;
+pub use tag::{with_tag_namespace, SerializedAs, StableId, TagDisambiguation, TagNamespace};
+pub use toml_layout::with_toml_layout;
+pub use transparent::SerializeTransparent;
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::prelude::*;
-    use crate::reflect::TypeRegistry;
+    use crate::reflect::{std_traits::ReflectDefault, TypeRegistry};
 
     #[reflect_trait]
     trait MyTrait: DowncastReflect {}
@@ -79,6 +169,49 @@ mod tests {
         });
     }
 
+    #[derive(Debug, PartialEq, Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(MyTrait, Serialize, Deserialize)]
+    enum Shape {
+        Point,
+        Circle { radius: f32 },
+        Rect(f32, f32),
+    }
+    impl MyTrait for Shape {}
+
+    #[test]
+    fn serialize_enum() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Shape>();
+        registry.set_current(|| {
+            for (shape, expected) in [
+                (Shape::Point, r#"{"type":"Shape","variant":"Point"}"#),
+                (
+                    Shape::Circle { radius: 1.0 },
+                    r#"{"type":"Shape","variant":"Circle","radius":1.0}"#,
+                ),
+                (
+                    Shape::Rect(2.0, 3.0),
+                    r#"{"type":"Shape","variant":"Rect","value":[2.0,3.0]}"#,
+                ),
+            ] {
+                let trait_object: Box<dyn MyTrait> = Box::new(shape);
+                let json = serde_json::to_string(&trait_object).unwrap();
+                assert_eq!(json, expected);
+
+                let deserialized: Box<dyn MyTrait> = serde_json::from_str(&json).unwrap();
+                assert_eq!(
+                    deserialized.downcast_ref::<Shape>(),
+                    trait_object.downcast_ref::<Shape>()
+                );
+            }
+
+            let err =
+                serde_json::from_str::<Box<dyn MyTrait>>(r#"{"type":"Shape","variant":"Hexagon"}"#)
+                    .unwrap_err();
+            assert!(err.to_string().contains("unknown variant `Hexagon`"));
+        });
+    }
+
     #[derive(Reflect, serde::Serialize, serde::Deserialize)]
     #[reflect(MyTrait, Serialize, Deserialize)]
     pub struct Nested {
@@ -118,4 +251,457 @@ mod tests {
             assert_eq!(b2.1, 999);
         });
     }
+
+    #[test]
+    fn serialized_as() {
+        let trait_object: Box<dyn MyTrait> = Box::new(Foo { num: 123 });
+        let mut registry = TypeRegistry::default();
+        registry.register_serialized_as::<Foo>("foo_v2");
+        registry.set_current(|| {
+            let json = serde_json::to_string(&*trait_object).unwrap();
+            assert_eq!(json, r#"{"type":"foo_v2","num":123}"#);
+
+            let deserialized: Box<dyn MyTrait> = serde_json::from_str(&json).unwrap();
+            assert_eq!(deserialized.downcast_ref::<Foo>().unwrap().num, 123);
+
+            // The short name is still accepted.
+            let deserialized: Box<dyn MyTrait> =
+                serde_json::from_str(r#"{"type":"Foo","num":456}"#).unwrap();
+            assert_eq!(deserialized.downcast_ref::<Foo>().unwrap().num, 456);
+        });
+    }
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(MyTrait, Default, Serialize, Deserialize)]
+    struct Versioned {
+        num: i32,
+        added: Vec<i32>,
+    }
+    impl MyTrait for Versioned {}
+
+    impl Default for Versioned {
+        fn default() -> Self {
+            Versioned {
+                num: 0,
+                added: vec![1, 2],
+            }
+        }
+    }
+
+    #[test]
+    fn lenient() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Versioned>();
+        registry.register::<Nested>();
+        registry.set_current(|| {
+            let json = r#"{"type":"Nested","a":{"type":"Versioned","num":1},"b":{"type":"Versioned","num":2,"added":[]}}"#;
+            assert!(serde_json::from_str::<Box<dyn MyTrait>>(json).is_err());
+
+            let deserialized: Box<dyn MyTrait> =
+                with_lenient(|| serde_json::from_str(json)).unwrap();
+            let nested = deserialized.downcast_ref::<Nested>().unwrap();
+            let a = nested.a.downcast_ref::<Versioned>().unwrap();
+            assert_eq!((a.num, &a.added[..]), (1, &[1, 2][..]));
+            let b = nested.b.downcast_ref::<Versioned>().unwrap();
+            assert_eq!((b.num, &b.added[..]), (2, &[][..]));
+        });
+    }
+
+    #[test]
+    fn document_manifest() {
+        let nested: Box<dyn MyTrait> = Box::new(Nested {
+            a: Box::new(Foo { num: 123 }),
+            b: Box::new(Bar(456, 789)),
+        });
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<Foo>();
+        registry.register::<Bar>();
+        registry.register::<Nested>();
+        let mut json = Vec::new();
+        registry.set_current(|| {
+            serialize_document(
+                nested.as_reflect(),
+                &mut serde_json::Serializer::new(&mut json),
+            )
+            .unwrap();
+            let deserialized =
+                deserialize_document(&mut serde_json::Deserializer::from_slice(&json)).unwrap();
+            assert!(deserialized.is::<Nested>());
+        });
+
+        let document: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let manifest: Manifest = serde_json::from_value(document["manifest"].clone()).unwrap();
+        let tags: Vec<_> = manifest.entries.iter().map(|e| &e.tag[..]).collect();
+        assert_eq!(tags, ["Bar", "Foo", "Nested"]);
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<Foo>();
+        registry.register::<Nested>();
+        assert_eq!(
+            manifest.check(&registry),
+            Err(ManifestError(vec![ManifestMismatch::UnknownType(
+                "Bar".into()
+            )]))
+        );
+    }
+
+    struct Scramble;
+
+    impl SerializationMiddleware for Scramble {
+        fn encode(&self, _tag: &str, payload: Vec<u8>) -> Result<Vec<u8>, MiddlewareError> {
+            Ok(payload.into_iter().map(|b| b ^ 0x5a).collect())
+        }
+
+        fn decode(&self, tag: &str, payload: Vec<u8>) -> Result<Vec<u8>, MiddlewareError> {
+            self.encode(tag, payload)
+        }
+
+        fn sign(&self, tag: &str, payload: &[u8]) -> Option<Vec<u8>> {
+            let sum = payload
+                .iter()
+                .chain(tag.as_bytes())
+                .fold(0u8, |acc, b| acc.wrapping_add(*b));
+            Some(vec![sum])
+        }
+
+        fn verify(
+            &self,
+            tag: &str,
+            payload: &[u8],
+            signature: Option<&[u8]>,
+        ) -> Result<(), MiddlewareError> {
+            if signature == self.sign(tag, payload).as_deref() {
+                Ok(())
+            } else {
+                Err(MiddlewareError::new("bad signature"))
+            }
+        }
+    }
+
+    #[test]
+    fn middleware() {
+        let nested: Box<dyn MyTrait> = Box::new(Nested {
+            a: Box::new(Foo { num: 123 }),
+            b: Box::new(Bar(456, 789)),
+        });
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<Foo>();
+        registry.register::<Bar>();
+        registry.register::<Nested>();
+        registry.set_current(|| {
+            let mut json = Vec::new();
+            serialize_with_middleware(
+                nested.as_reflect(),
+                &Scramble,
+                &mut serde_json::Serializer::new(&mut json),
+            )
+            .unwrap();
+
+            let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+            assert_eq!(value["type"], "Nested");
+            assert!(value["payload"].is_array());
+            assert!(value["signature"].is_array());
+
+            let deserialized = deserialize_with_middleware(
+                &Scramble,
+                &mut serde_json::Deserializer::from_slice(&json),
+            )
+            .unwrap();
+            let nested = deserialized.downcast_ref::<Nested>().unwrap();
+            assert_eq!(nested.a.downcast_ref::<Foo>().unwrap().num, 123);
+            assert_eq!(nested.b.downcast_ref::<Bar>().unwrap().1, 789);
+
+            let mut tampered = value;
+            tampered["payload"][0] = serde_json::Value::from(0);
+            let err = deserialize_with_middleware(&Scramble, tampered).unwrap_err();
+            assert_eq!(err.to_string(), "bad signature");
+        });
+    }
+
+    mod first {
+        use crate::prelude::*;
+        #[derive(Reflect)]
+        pub struct Dup;
+    }
+
+    mod second {
+        use crate::prelude::*;
+        #[derive(Reflect)]
+        pub struct Dup;
+    }
+
+    #[test]
+    fn disambiguate_short_names() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<first::Dup>();
+        registry.register::<second::Dup>();
+        registry.register_serialized_as::<Foo>("foo");
+
+        let groups = registry.ambiguous_short_names();
+        assert_eq!(groups.len(), 1);
+        let type_names: Vec<_> = groups[0].iter().map(|r| r.type_name()).collect();
+        assert_eq!(
+            type_names,
+            [
+                std::any::type_name::<first::Dup>(),
+                std::any::type_name::<second::Dup>()
+            ]
+        );
+
+        // Both types are in the same crate, so this falls back to full paths.
+        registry.disambiguate_short_names(TagDisambiguation::CrateName);
+        for type_name in type_names {
+            let registration = registry.get_with_name(type_name).unwrap();
+            assert_eq!(tag::type_tag(registration), type_name);
+            assert_eq!(
+                tag::resolve_tag(&registry, type_name).unwrap().type_name(),
+                type_name
+            );
+        }
+    }
+
+    #[test]
+    fn serialize_send_sync_from_threads() {
+        #[reflect_trait]
+        trait Shared: DowncastReflect {}
+        impl_dynamic_trait!(Shared + Send + Sync, ReflectShared);
+
+        #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+        #[reflect(Shared, Serialize, Deserialize)]
+        struct Counter {
+            num: i32,
+        }
+        impl Shared for Counter {}
+
+        let objects: Vec<Box<dyn Shared + Send + Sync>> = (0..4)
+            .map(|num| Box::new(Counter { num }) as Box<dyn Shared + Send + Sync>)
+            .collect();
+        let mut registry = TypeRegistry::default();
+        registry.register::<Counter>();
+
+        let json: Vec<String> = std::thread::scope(|scope| {
+            let workers: Vec<_> = objects
+                .iter()
+                .map(|object| {
+                    let registry = &registry;
+                    scope.spawn(move || {
+                        registry.set_current(|| serde_json::to_string(&**object).unwrap())
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        });
+        assert_eq!(json[3], r#"{"type":"Counter","num":3}"#);
+
+        let object: Box<dyn Shared + Send + Sync> =
+            registry.set_current(|| serde_json::from_str(&json[2]).unwrap());
+        assert_eq!(object.downcast_ref::<Counter>().unwrap().num, 2);
+    }
+
+    #[test]
+    fn type_tag_in_any_position() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Foo>();
+
+        registry.set_current(|| {
+            // Strings from a reader are not borrowed from the input.
+            let json = r#"{"num":5,"t\u0079pe":"Foo"}"#;
+            let object: Box<dyn MyTrait> = serde_json::from_reader(json.as_bytes()).unwrap();
+            assert_eq!(object.downcast_ref::<Foo>().unwrap().num, 5);
+
+            let err = serde_json::from_str::<Box<dyn MyTrait>>(r#"{"type":"Foo","type":"Foo"}"#)
+                .unwrap_err();
+            assert!(err.to_string().starts_with("duplicate field `type`"));
+            let err = serde_json::from_str::<Box<dyn MyTrait>>(r#"{"num":5}"#).unwrap_err();
+            assert!(err.to_string().starts_with("missing field `type`"));
+        });
+    }
+
+    #[test]
+    fn duplicate_keys_last_wins() {
+        #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+        struct Range {
+            min: i32,
+            max: i32,
+        }
+
+        #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+        #[reflect(MyTrait, Serialize, Deserialize)]
+        struct Spawner {
+            range: Range,
+        }
+        impl MyTrait for Spawner {}
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<Spawner>();
+
+        let json = r#"{"type":"Spawner","range":{"min":0,"max":5,"max":9}}"#;
+        registry.set_current(|| {
+            let err = serde_json::from_str::<Box<dyn MyTrait>>(json).unwrap_err();
+            assert!(err.to_string().starts_with("duplicate field `max`"));
+
+            let object: Box<dyn MyTrait> =
+                with_duplicate_keys(|| serde_json::from_str(json)).unwrap();
+            let range = &object.downcast_ref::<Spawner>().unwrap().range;
+            assert_eq!((range.min, range.max), (0, 9));
+        });
+    }
+
+    #[test]
+    fn tag_namespaces() {
+        let mut registry = TypeRegistry::default();
+        registry.register_serialized_as::<Foo>("foo");
+        registry.register_stable_id::<Foo>(3);
+        registry.register::<Bar>();
+
+        let foo: Box<dyn MyTrait> = Box::new(Foo { num: 1 });
+        let bar: Box<dyn MyTrait> = Box::new(Bar(1, 2));
+        registry.set_current(|| {
+            let runtime = with_tag_namespace(TagNamespace::Runtime, || {
+                serde_json::to_string(&[&foo]).unwrap()
+            });
+            assert_eq!(runtime, r#"[{"type":3,"num":1}]"#);
+            let objects: Vec<Box<dyn MyTrait>> = serde_json::from_str(&runtime).unwrap();
+            assert!(objects[0].is::<Foo>());
+            let object = with_bevy_compat(|| {
+                deserialize(&mut serde_json::Deserializer::from_str(
+                    r#"{"num":2,"type":3}"#,
+                ))
+            })
+            .unwrap();
+            assert!(object.is::<Foo>());
+
+            let err = with_tag_namespace(TagNamespace::Runtime, || serde_json::to_string(&bar))
+                .unwrap_err();
+            assert!(err.to_string().contains("has no stable ID"), "{err}");
+            let err =
+                serde_json::from_str::<Box<dyn MyTrait>>(r#"{"type":4,"num":1}"#).unwrap_err();
+            assert!(err.to_string().starts_with("unknown stable ID 4"), "{err}");
+
+            let authoring = with_tag_namespace(TagNamespace::Authoring, || {
+                serde_json::to_string(&[&foo, &bar]).unwrap()
+            });
+            assert_eq!(
+                authoring,
+                r#"[{"type":"foo","num":1},{"type":"Bar","value":[1,2]}]"#
+            );
+        });
+    }
+
+    #[test]
+    fn nested_tags() {
+        // Serializes another trait object as a field, and into a string
+        // with a separate serializer, while its own tag is being written.
+        #[derive(Reflect)]
+        #[reflect(MyTrait, Serialize)]
+        struct Envelope {
+            #[reflect(ignore)]
+            inner: Option<Box<dyn MyTrait>>,
+        }
+        impl MyTrait for Envelope {}
+
+        impl serde::Serialize for Envelope {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                use serde::ser::{Error as _, SerializeStruct};
+                let inner = self.inner.as_deref();
+                let text = serde_json::to_string(&inner).map_err(S::Error::custom)?;
+                let mut s = serializer.serialize_struct("Envelope", 2)?;
+                s.serialize_field("inner", &inner)?;
+                s.serialize_field("text", &text)?;
+                s.end()
+            }
+        }
+
+        #[derive(Reflect, serde::Serialize)]
+        #[reflect(MyTrait, Serialize)]
+        enum Signal {
+            Stop,
+        }
+        impl MyTrait for Signal {}
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<Envelope>();
+        registry.register::<Signal>();
+        registry.register::<Bar>();
+        registry.register_stable_id::<Bar>(2);
+        registry.register_stable_id::<Envelope>(1);
+
+        let envelope: Box<dyn MyTrait> = Box::new(Envelope {
+            inner: Some(Box::new(Envelope {
+                inner: Some(Box::new(Bar(1, 2))),
+            })),
+        });
+        registry.set_current(|| {
+            let json = serde_json::to_string(&*envelope).unwrap();
+            let bar = r#"{"type":"Bar","value":[1,2]}"#;
+            let inner = format!(
+                r#"{{"type":"Envelope","inner":{bar},"text":{}}}"#,
+                serde_json::to_string(bar).unwrap()
+            );
+            let expected = format!(
+                r#"{{"type":"Envelope","inner":{inner},"text":{}}}"#,
+                serde_json::to_string(&inner).unwrap()
+            );
+            assert_eq!(json, expected);
+
+            // The inner serializer writes in its own namespace.
+            let json = with_tag_namespace(TagNamespace::Runtime, || {
+                serde_json::to_value(&*envelope).unwrap()
+            });
+            assert_eq!(json["type"], 1);
+            assert_eq!(json["inner"]["inner"]["type"], 2);
+
+            let signal: Box<dyn MyTrait> = Box::new(Signal::Stop);
+            let json = serde_json::to_string(&*signal).unwrap();
+            assert_eq!(json, r#"{"type":"Signal","variant":"Stop"}"#);
+        });
+    }
+
+    #[test]
+    fn wrapped_serializers() {
+        let nested: Box<dyn MyTrait> = Box::new(Nested {
+            a: Box::new(Foo { num: 1 }),
+            b: Box::new(Nested {
+                a: Box::new(Foo { num: 2 }),
+                b: Box::new(Bar(3, 4)),
+            }),
+        });
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<Foo>();
+        registry.register::<Nested>();
+        registry.set_current(|| {
+            // `Bar` is not registered, so serialization fails at its path.
+            let mut json = Vec::new();
+            let mut serializer = serde_json::Serializer::new(&mut json);
+            let err = serde_path_to_error::serialize(&*nested, &mut serializer).unwrap_err();
+            assert_eq!(err.path().to_string(), "b.b");
+        });
+
+        registry.register::<Bar>();
+        registry.set_current(|| {
+            let mut json = Vec::new();
+            let mut serializer = serde_json::Serializer::new(&mut json);
+            serde_path_to_error::serialize(&*nested, &mut serializer).unwrap();
+            let json = String::from_utf8(json).unwrap();
+            assert_eq!(json, serde_json::to_string(&*nested).unwrap());
+            assert_eq!(
+                json,
+                r#"{"type":"Nested","a":{"type":"Foo","num":1},"b":{"type":"Nested","a":{"type":"Foo","num":2},"b":{"type":"Bar","value":[3,4]}}}"#
+            );
+
+            // The tag is consumed, not reported as an ignored field.
+            let mut ignored = Vec::new();
+            let mut deserializer = serde_json::Deserializer::from_str(&json);
+            let object: Box<dyn MyTrait> = serde_ignored::deserialize(&mut deserializer, |path| {
+                ignored.push(path.to_string());
+            })
+            .unwrap();
+            assert!(ignored.is_empty(), "{ignored:?}");
+            let nested = object.downcast_ref::<Nested>().unwrap();
+            assert_eq!(nested.a.downcast_ref::<Foo>().unwrap().num, 1);
+        });
+    }
 }
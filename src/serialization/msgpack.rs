@@ -0,0 +1,194 @@
+//! Helpers for tagged serialization to and from
+//! [MessagePack](https://msgpack.org). Requires the "rmp" crate feature.
+//!
+//! Instead of a string type tag, the type of an object is identified by its
+//! [`StableId`], written as a MessagePack extension value, which takes 6 bytes
+//! regardless of the length of the type's name. This keeps network packets
+//! small. An object is written as an array of two elements: the extension
+//! value of type [`TYPE_TAG_EXT`], holding the ID as a big-endian `u32`, and
+//! the serialized value of the object, with structs written as maps.
+//!
+//! Only the outermost object uses a stable ID. Trait objects nested in its
+//! fields are written with string type tags, as by
+//! [`serialize()`](super::serialize()).
+//!
+//! ## Example
+//! ```rust
+//! # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::{from_msgpack_tagged, to_msgpack_tagged}};
+//! #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+//! #[reflect(Serialize, Deserialize)]
+//! struct PlayerMoved { x: i16, y: i16 }
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register_stable_id::<PlayerMoved>(7);
+//!
+//! let bytes = to_msgpack_tagged(&PlayerMoved { x: 1, y: -1 }, &registry).unwrap();
+//! assert_eq!(bytes.len(), 14);
+//!
+//! let event = from_msgpack_tagged(&bytes, &registry).unwrap();
+//! assert_eq!(event.downcast_ref::<PlayerMoved>().unwrap().y, -1);
+//! ```
+
+use std::any::Any;
+
+use bevy_reflect::{Reflect, ReflectDeserialize, ReflectSerialize, TypeRegistry};
+use serde::{de::Error as _, ser::Error as _, Serialize};
+
+use crate::TypeRegistryExt;
+
+use super::tag::{resolve_stable_id, StableId};
+
+/// The MessagePack extension type of the type tags written by
+/// [`to_msgpack_tagged()`].
+pub const TYPE_TAG_EXT: i8 = 0x52;
+
+/// Marker of a fixed-size array of two elements.
+const FIXARRAY_2: u8 = 0x92;
+/// Marker of an extension value with 4 bytes of data.
+const FIXEXT_4: u8 = 0xd6;
+/// [`TYPE_TAG_EXT`] as it is written after [`FIXEXT_4`].
+const TYPE_TAG_EXT_BYTE: u8 = TYPE_TAG_EXT as u8;
+
+/// Serialize `this` to MessagePack, tagged with the [`StableId`] of its type in
+/// `registry`. See the [module documentation](self) for the layout.
+///
+/// # Errors
+///
+/// Fails if the type of `this` is not registered, has no stable ID or no
+/// `ReflectSerialize` type data, or if the value cannot be serialized.
+pub fn to_msgpack_tagged(
+    this: &dyn Reflect,
+    registry: &TypeRegistry,
+) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    let registration = registry.get(Any::type_id(this.as_any())).ok_or_else(|| {
+        rmp_serde::encode::Error::custom(format_args!("unregistered type: {}", this.type_name()))
+    })?;
+    let StableId(id) = registration.data::<StableId>().ok_or_else(|| {
+        rmp_serde::encode::Error::custom(format_args!("'{}' has no stable ID", this.type_name()))
+    })?;
+    let serialize = registration.data::<ReflectSerialize>().ok_or_else(|| {
+        rmp_serde::encode::Error::custom(format_args!(
+            "#[reflect(Serialize)] is missing from '{}'",
+            this.type_name()
+        ))
+    })?;
+
+    let mut bytes = vec![FIXARRAY_2, FIXEXT_4, TYPE_TAG_EXT_BYTE];
+    bytes.extend_from_slice(&id.to_be_bytes());
+    let serializable = serialize.get_serializable(this);
+    registry.set_current(|| {
+        let mut serializer = rmp_serde::Serializer::new(&mut bytes).with_struct_map();
+        serializable.borrow().serialize(&mut serializer)
+    })?;
+    Ok(bytes)
+}
+
+/// Deserialize an object written by [`to_msgpack_tagged()`], looking up its
+/// type by [`StableId`] in `registry`.
+///
+/// # Errors
+///
+/// Fails if `bytes` does not start with a type tag, if no type has its stable
+/// ID, if the type has no `ReflectDeserialize` type data, if the value cannot
+/// be deserialized, or if there are bytes left after the object.
+pub fn from_msgpack_tagged(
+    bytes: &[u8],
+    registry: &TypeRegistry,
+) -> Result<Box<dyn Reflect>, rmp_serde::decode::Error> {
+    let (id, mut rest) = match bytes {
+        [FIXARRAY_2, FIXEXT_4, TYPE_TAG_EXT_BYTE, a, b, c, d, rest @ ..] => {
+            (u32::from_be_bytes([*a, *b, *c, *d]), rest)
+        }
+        _ => {
+            return Err(rmp_serde::decode::Error::custom(
+                "expected an array of a type tag and a value",
+            ))
+        }
+    };
+    let registration = resolve_stable_id(registry, id).ok_or_else(|| {
+        rmp_serde::decode::Error::custom(format_args!("unknown stable type ID {id}"))
+    })?;
+    let deserialize = registration.data::<ReflectDeserialize>().ok_or_else(|| {
+        rmp_serde::decode::Error::custom(format_args!(
+            "#[reflect(Deserialize)] is missing from '{}'",
+            registration.type_name()
+        ))
+    })?;
+
+    let object = registry
+        .set_current(|| deserialize.deserialize(&mut rmp_serde::Deserializer::new(&mut rest)))?;
+    if !rest.is_empty() {
+        return Err(rmp_serde::decode::Error::custom(format_args!(
+            "{} trailing bytes after the object",
+            rest.len()
+        )));
+    }
+    Ok(object)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[reflect_trait]
+    trait Command: DowncastReflect {}
+    impl_dynamic_trait!(Command, ReflectCommand);
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Command, Serialize, Deserialize)]
+    struct Spawn {
+        name: String,
+        position: (f32, f32),
+    }
+    impl Command for Spawn {}
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Serialize, Deserialize)]
+    struct Batch {
+        commands: Vec<Box<dyn Command>>,
+        delay: Option<u32>,
+    }
+
+    #[test]
+    fn nested_objects_and_errors() {
+        let mut registry = TypeRegistry::default();
+        registry.register_stable_id::<Batch>(1000);
+        registry.register::<Spawn>();
+
+        let batch = Batch {
+            commands: vec![Box::new(Spawn {
+                name: "orc".into(),
+                position: (1.0, 2.0),
+            })],
+            delay: None,
+        };
+        let bytes = to_msgpack_tagged(&batch, &registry).unwrap();
+        assert_eq!(bytes[..7], [0x92, 0xd6, 0x52, 0, 0, 0x03, 0xe8]);
+
+        let object = from_msgpack_tagged(&bytes, &registry).unwrap();
+        let batch = object.downcast_ref::<Batch>().unwrap();
+        let spawn = batch.commands[0].downcast_ref::<Spawn>().unwrap();
+        assert_eq!(spawn.name, "orc");
+        assert_eq!(spawn.position, (1.0, 2.0));
+
+        let err = to_msgpack_tagged(
+            &Spawn {
+                name: String::new(),
+                position: (0.0, 0.0),
+            },
+            &registry,
+        )
+        .unwrap_err();
+        assert!(err.to_string().ends_with("has no stable ID"));
+
+        let mut unknown = bytes.clone();
+        unknown[6] = 0;
+        let err = from_msgpack_tagged(&unknown, &registry).unwrap_err();
+        assert_eq!(err.to_string(), "unknown stable type ID 768");
+
+        let mut trailing = bytes;
+        trailing.push(0xc0);
+        assert!(from_msgpack_tagged(&trailing, &registry).is_err());
+    }
+}
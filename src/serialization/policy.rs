@@ -0,0 +1,211 @@
+use std::borrow::Cow;
+
+use super::value::Value;
+
+/// Per-field serialization control for tagged structs.
+///
+/// When a struct has `SerializationPolicy` type data in the registry, the
+/// tagged serializer (see [`serialize()`](super::serialize)) omits the skipped
+/// fields and writes renamed fields under their new name. This is useful for
+/// keeping runtime-only state out of save files, without touching the type's
+/// `Serialize` implementation.
+///
+/// On deserialization, renamed fields are mapped back to their original name,
+/// and skipped fields are filled from the type's
+/// [`ReflectDefault`](bevy_reflect::std_traits::ReflectDefault) type data,
+/// which must be present (`#[reflect(Default)]`) for any type that skips
+/// fields. Input that contains a skipped field is rejected.
+///
+/// The policy only applies to the fields of structs with named fields.
+///
+/// See
+/// [`TypeRegistryExt::register_serialization_policy()`](crate::TypeRegistryExt::register_serialization_policy).
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, serialization::SerializationPolicy};
+/// # use reflect_steroids::reflect::{std_traits::ReflectDefault, TypeRegistry};
+/// #[reflect_trait]
+/// trait MyTrait: DowncastReflect {}
+/// impl_dynamic_trait!(MyTrait, ReflectMyTrait);
+///
+/// #[derive(Default, Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(MyTrait, Default, Serialize, Deserialize)]
+/// struct Player { hp: i32, cached_path: Vec<i32> }
+///
+/// impl MyTrait for Player {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register_serialization_policy::<Player>(
+///     SerializationPolicy::new().skip("cached_path").rename("hp", "health"),
+/// );
+///
+/// let trait_object: Box<dyn MyTrait> = Box::new(Player { hp: 10, cached_path: vec![1, 2] });
+/// registry.set_current(|| {
+///     let json = serde_json::to_string(&*trait_object).unwrap();
+///     assert_eq!(json, r#"{"type":"Player","health":10}"#);
+///
+///     let deserialized: Box<dyn MyTrait> = serde_json::from_str(&json).unwrap();
+///     let player = deserialized.downcast_ref::<Player>().unwrap();
+///     assert_eq!(player.hp, 10);
+///     assert!(player.cached_path.is_empty());
+/// });
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SerializationPolicy {
+    skip: Vec<&'static str>,
+    rename: Vec<(&'static str, &'static str)>,
+}
+
+impl SerializationPolicy {
+    /// Create a policy that serializes all fields unchanged.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Do not serialize the field `field`.
+    #[must_use]
+    pub fn skip(mut self, field: &'static str) -> Self {
+        self.skip.push(field);
+        self
+    }
+
+    /// Serialize the field `field` with the name `name`.
+    #[must_use]
+    pub fn rename(mut self, field: &'static str, name: &'static str) -> Self {
+        self.rename.push((field, name));
+        self
+    }
+
+    /// True if `field` is skipped by this policy.
+    #[must_use]
+    pub fn skips(&self, field: &str) -> bool {
+        self.skip.contains(&field)
+    }
+
+    /// The name that `field` is serialized with.
+    #[must_use]
+    pub fn serialized_name<'a>(&self, field: &'a str) -> &'a str {
+        self.rename
+            .iter()
+            .find(|(from, _)| *from == field)
+            .map_or(field, |(_, to)| to)
+    }
+
+    /// The field that was serialized with the name `name`.
+    #[must_use]
+    pub fn field_name<'a>(&self, name: &'a str) -> &'a str {
+        self.rename
+            .iter()
+            .find(|(_, to)| *to == name)
+            .map_or(name, |(from, _)| from)
+    }
+
+    /// Apply the policy to the serialized fields of a struct.
+    pub(crate) fn apply_to_fields(&self, value: &mut Value) {
+        if let Value::Map(entries) = value {
+            entries.retain(|(key, _)| !matches!(key_str(key), Some(key) if self.skips(key)));
+            for (key, _) in entries.iter_mut() {
+                if let Some(name) = key_str(key) {
+                    let renamed = self.serialized_name(name);
+                    if renamed != name {
+                        *key = Value::String(renamed.to_owned());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reverse the renames of the policy for deserialized fields.
+    pub(crate) fn unrename<'a>(&self, name: Cow<'a, str>) -> Cow<'a, str> {
+        let field = self.field_name(&name);
+        if field == name {
+            name
+        } else {
+            Cow::Owned(field.to_owned())
+        }
+    }
+}
+
+fn key_str<'a>(key: &'a Value) -> Option<&'a str> {
+    match key {
+        Value::Str(key) => Some(key),
+        Value::String(key) => Some(key),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{
+        reflect_trait, std_traits::ReflectDefault, Reflect, ReflectDeserialize, ReflectSerialize,
+        TypeRegistry,
+    };
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect, TypeRegistryExt};
+
+    #[reflect_trait]
+    trait Saved: DowncastReflect {}
+    impl_dynamic_trait!(Saved, ReflectSaved);
+
+    #[derive(Default, Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Saved, Default, Serialize, Deserialize)]
+    struct Npc {
+        name: String,
+        hp: i32,
+        cached_path: Vec<i32>,
+    }
+    impl Saved for Npc {}
+
+    fn registry() -> TypeRegistry {
+        let mut registry = TypeRegistry::default();
+        registry.register_serialization_policy::<Npc>(
+            SerializationPolicy::new()
+                .skip("cached_path")
+                .rename("hp", "health"),
+        );
+        registry
+    }
+
+    #[test]
+    fn names() {
+        let policy = SerializationPolicy::new().skip("a").rename("b", "c");
+        assert!(policy.skips("a"));
+        assert!(!policy.skips("b"));
+        assert_eq!(policy.serialized_name("b"), "c");
+        assert_eq!(policy.serialized_name("a"), "a");
+        assert_eq!(policy.field_name("c"), "b");
+        assert_eq!(policy.field_name("b"), "b");
+    }
+
+    #[test]
+    fn skipped_fields_are_denied_both_ways() {
+        let registry = registry();
+        registry.set_current(|| {
+            let npc: Box<dyn Saved> = Box::new(Npc {
+                name: "Guard".into(),
+                hp: 5,
+                cached_path: vec![1, 2, 3],
+            });
+            let json = serde_json::to_string(&*npc).unwrap();
+            assert_eq!(json, r#"{"type":"Npc","name":"Guard","health":5}"#);
+
+            let deserialized: Box<dyn Saved> = serde_json::from_str(&json).unwrap();
+            let deserialized = deserialized.downcast_ref::<Npc>().unwrap();
+            assert_eq!(deserialized.hp, 5);
+            assert!(deserialized.cached_path.is_empty());
+
+            let err = serde_json::from_str::<Box<dyn Saved>>(
+                r#"{"type":"Npc","name":"Guard","health":5,"cached_path":[4]}"#,
+            )
+            .map(|_| ())
+            .unwrap_err();
+            assert!(
+                err.to_string().contains("field `cached_path`"),
+                "unexpected error: {err}"
+            );
+        });
+    }
+}
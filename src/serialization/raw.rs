@@ -0,0 +1,42 @@
+//! Buffered, self-describing serde values.
+//!
+//! The tagged (de)serializers in this crate need to look at the type tag of an
+//! object before its payload can be interpreted, so the payload is buffered in
+//! a [`Value`] in the meantime. The same building blocks are useful for other
+//! tagging schemes, so they are exported here:
+//!
+//! - [`to_value()`] serializes anything implementing [`Serialize`] into a
+//!   [`Value`].
+//! - [`Value`] implements [`Deserialize`], buffering the contents of any
+//!   self-describing deserializer.
+//! - [`ValueDeserializer`] deserializes anything implementing [`Deserialize`]
+//!   from a [`Value`].
+//!
+//! ## Stability
+//!
+//! The variants of [`Value`] mirror the serde data model, and follow the
+//! semver guarantees of this crate. The enum is `#[non_exhaustive]` so that
+//! variants can be added if the serde data model grows. Enum variants are
+//! represented as a single-entry map from the variant name to its contents
+//! (or as a bare string, for unit variants), and `i128`/`u128` are not
+//! supported.
+//!
+//! ## Example
+//! ```rust
+//! use reflect_steroids::serialization::raw::{to_value, Value, ValueError};
+//! use serde::de::IntoDeserializer;
+//!
+//! let value = to_value(&(1, "two")).unwrap();
+//! assert_eq!(value.as_seq().map(|elements| elements.len()), Some(2));
+//!
+//! let back: (i32, String) =
+//!     serde::Deserialize::deserialize(IntoDeserializer::<ValueError>::into_deserializer(value))
+//!         .unwrap();
+//! assert_eq!(back, (1, "two".to_string()));
+//! ```
+//!
+//! [`Serialize`]: serde::Serialize
+//! [`Deserialize`]: serde::Deserialize
+
+pub use super::value::{Value, ValueDeserializer, ValueError};
+pub use super::value_ser::to_value;
@@ -0,0 +1,196 @@
+use super::value::Value;
+
+scoped_tls::scoped_thread_local!(static REDACTION: Redact);
+
+/// The string that masked values are replaced with.
+pub const REDACTED: &str = "<redacted>";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Action {
+    Omit,
+    Mask,
+}
+
+/// Rules for hiding sensitive values, such as access tokens, when serializing
+/// tagged objects in redaction mode (see [`with_redaction()`]).
+///
+/// Each rule is a path into the serialized value of an object, with segments
+/// separated by `.`. A segment is a field name, a map key, or a sequence
+/// index, and `*` matches any of those. Omitted values are left out of the
+/// output, and masked values are replaced with [`REDACTED`].
+///
+/// The rules of a type's `Redact` type data apply to the objects of that type
+/// (see
+/// [`TypeRegistryExt::register_redaction()`](crate::TypeRegistryExt::register_redaction)),
+/// and the rules passed to [`with_redaction()`] apply to all tagged objects,
+/// including trait objects nested in other objects. Paths are relative to the
+/// fields of the object, as produced by its `Serialize` implementation.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::{with_redaction, Redact}};
+/// #[reflect_trait]
+/// trait Resource: DowncastReflect {}
+/// impl_dynamic_trait!(Resource, ReflectResource);
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Resource, Serialize, Deserialize)]
+/// struct Session { user: String, token: String, history: Vec<String> }
+/// impl Resource for Session {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register_redaction::<Session>(Redact::new().mask("token"));
+///
+/// let session: Box<dyn Resource> = Box::new(Session {
+///     user: "ada".into(),
+///     token: "hunter2".into(),
+///     history: vec!["login".into()],
+/// });
+///
+/// registry.set_current(|| {
+///     let json = serde_json::to_string(&*session).unwrap();
+///     assert!(json.contains("hunter2"));
+///
+///     let json = with_redaction(&Redact::new().omit("history"), || {
+///         serde_json::to_string(&*session).unwrap()
+///     });
+///     assert_eq!(json, r#"{"type":"Session","user":"ada","token":"<redacted>"}"#);
+/// });
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Redact {
+    rules: Vec<(Vec<String>, Action)>,
+}
+
+impl Redact {
+    /// Create a set of rules that hides nothing.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leave the values at `path` out of the output.
+    #[must_use]
+    pub fn omit(self, path: &str) -> Self {
+        self.rule(path, Action::Omit)
+    }
+
+    /// Replace the values at `path` with [`REDACTED`].
+    #[must_use]
+    pub fn mask(self, path: &str) -> Self {
+        self.rule(path, Action::Mask)
+    }
+
+    /// True if there are no rules.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    fn rule(mut self, path: &str, action: Action) -> Self {
+        self.rules
+            .push((path.split('.').map(str::to_owned).collect(), action));
+        self
+    }
+
+    /// Apply the rules to the serialized value of an object.
+    pub(crate) fn apply(&self, value: &mut Value) {
+        for (path, action) in &self.rules {
+            redact(value, path, *action);
+        }
+    }
+}
+
+/// Enable redaction mode while running `f`. In redaction mode, the rules of
+/// `redaction` are applied to every tagged object that is serialized (see
+/// [`serialize()`](super::serialize())), along with the rules of the
+/// [`Redact`] type data of its type.
+pub fn with_redaction<F: FnOnce() -> R, R>(redaction: &Redact, f: F) -> R {
+    REDACTION.set(redaction, f)
+}
+
+/// True if redaction mode is enabled.
+pub(crate) fn is_redacting() -> bool {
+    REDACTION.is_set()
+}
+
+/// Apply the rules passed to [`with_redaction()`] to `value`.
+pub(crate) fn apply_current_redaction(value: &mut Value) {
+    if REDACTION.is_set() {
+        REDACTION.with(|redaction| redaction.apply(value));
+    }
+}
+
+fn redact(value: &mut Value, path: &[String], action: Action) {
+    let Some((segment, rest)) = path.split_first() else {
+        return;
+    };
+    let matches_key = |key: &Value| segment == "*" || key.as_str() == Some(segment.as_str());
+    let matches_index = |index: usize| segment == "*" || segment.parse() == Ok(index);
+
+    match value {
+        Value::Some(inner) | Value::Newtype(inner) => redact(inner, path, action),
+        Value::Map(entries) => {
+            if rest.is_empty() && action == Action::Omit {
+                entries.retain(|(key, _)| !matches_key(key));
+                return;
+            }
+            for (_, value) in entries.iter_mut().filter(|(key, _)| matches_key(key)) {
+                apply_at(value, rest, action);
+            }
+        }
+        Value::Seq(elements) => {
+            if rest.is_empty() && action == Action::Omit {
+                let mut index = 0;
+                elements.retain(|_| {
+                    index += 1;
+                    !matches_index(index - 1)
+                });
+                return;
+            }
+            for (_, value) in elements
+                .iter_mut()
+                .enumerate()
+                .filter(|(index, _)| matches_index(*index))
+            {
+                apply_at(value, rest, action);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Apply `action` to a value matched by a path segment, or descend into it
+/// with the rest of the path.
+fn apply_at(value: &mut Value, rest: &[String], action: Action) {
+    if rest.is_empty() {
+        *value = Value::Str(REDACTED);
+    } else {
+        redact(value, rest, action);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redacted(json: &str, redaction: &Redact) -> String {
+        let mut value: Value = serde_json::from_str(json).unwrap();
+        redaction.apply(&mut value);
+        serde_json::to_string(&value).unwrap()
+    }
+
+    #[test]
+    fn wildcards_and_indices() {
+        let json = r#"{"accounts":[{"name":"a","key":"1"},{"name":"b","key":"2"}]}"#;
+        let redaction = Redact::new().mask("accounts.*.key").omit("accounts.1.name");
+        assert_eq!(
+            redacted(json, &redaction),
+            r#"{"accounts":[{"name":"a","key":"<redacted>"},{"key":"<redacted>"}]}"#
+        );
+        assert_eq!(
+            redacted(r#"["a","b","c"]"#, &Redact::new().omit("1")),
+            r#"["a","c"]"#
+        );
+    }
+}
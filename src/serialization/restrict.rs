@@ -0,0 +1,188 @@
+use std::{cell::Cell, collections::HashMap};
+
+use bevy_reflect::{TypeRegistration, TypeRegistry};
+use serde::de::Error as _;
+
+use crate::{
+    type_registry::{registry_data, registry_data_mut},
+    Cast, DynamicTrait, TypeError, TypeRegistryExt,
+};
+
+use super::{bevy_compat::is_bevy_compat, tag::type_tag};
+
+scoped_tls::scoped_thread_local!(static EXPECTED_TRAIT: Cell<Option<&'static str>>);
+
+/// Registry-wide type data holding the implementors that each restricted
+/// dynamic trait may be deserialized as. See
+/// [`TypeRegistryExt::restrict()`](crate::TypeRegistryExt::restrict).
+#[derive(Clone, Default)]
+pub(crate) struct TraitRestrictions(HashMap<&'static str, Vec<String>>);
+
+pub(crate) fn restrict(registry: &mut TypeRegistry, trait_name: &'static str, allowed: &[&str]) {
+    let registration = registry_data_mut(registry);
+    if registration.data::<TraitRestrictions>().is_none() {
+        registration.insert(TraitRestrictions::default());
+    }
+    registration
+        .data_mut::<TraitRestrictions>()
+        .expect("type data was just inserted")
+        .0
+        .insert(
+            trait_name,
+            allowed.iter().map(ToString::to_string).collect(),
+        );
+}
+
+/// Check that objects of the type of `registration` may be deserialized as
+/// the dynamic trait `trait_name`.
+pub(crate) fn check_allowed(
+    registry: &TypeRegistry,
+    trait_name: &'static str,
+    registration: &TypeRegistration,
+) -> Result<(), TypeError> {
+    let Some(allowed) = registry_data::<TraitRestrictions>(registry)
+        .and_then(|restrictions| restrictions.0.get(trait_name))
+    else {
+        return Ok(());
+    };
+    let tag = type_tag(registration);
+    let names = [tag, registration.short_name(), registration.type_name()];
+    if allowed.iter().any(|name| names.contains(&name.as_str())) {
+        Ok(())
+    } else {
+        Err(TypeError::ForbiddenType(
+            registration.type_name().into(),
+            trait_name,
+        ))
+    }
+}
+
+/// Check the type of the tagged object currently being deserialized by
+/// [`deserialize_trait_object()`] against the restrictions of its trait. Only
+/// the outermost object is checked; nested objects have their own traits.
+pub(crate) fn check_expected_trait(
+    registry: &TypeRegistry,
+    registration: &TypeRegistration,
+) -> Result<(), TypeError> {
+    if !EXPECTED_TRAIT.is_set() {
+        return Ok(());
+    }
+    match EXPECTED_TRAIT.with(Cell::take) {
+        Some(trait_name) => check_allowed(registry, trait_name, registration),
+        None => Ok(()),
+    }
+}
+
+/// Deserialize a tagged trait object as `Box<dyn MyTrait>`.
+///
+/// This is what the `Deserialize` implementation of `Box<dyn MyTrait>`
+/// generated by [`impl_dynamic_trait!`](crate::impl_dynamic_trait) calls. Like
+/// [`deserialize()`](super::deserialize), it requires a current registry. If
+/// the trait has been restricted with
+/// [`TypeRegistryExt::restrict()`](crate::TypeRegistryExt::restrict), the
+/// type tag is checked before the rest of the object is deserialized.
+///
+/// This function may be used in a `#[serde(deserialize_with = "...")]`
+/// field attribute.
+///
+/// # Errors
+///
+/// Fails as [`deserialize()`](super::deserialize) does, if the object does
+/// not implement `P`, or with [`TypeError::ForbiddenType`] if the trait is
+/// restricted and does not allow the type.
+pub fn deserialize_trait_object<'de, P, D>(deserializer: D) -> Result<Box<P>, D::Error>
+where
+    P: DynamicTrait + ?Sized,
+    D: serde::Deserializer<'de>,
+{
+    let object = if is_bevy_compat() {
+        // The bevy layout is not resolved through a type tag, so the type can
+        // only be checked after deserialization.
+        let object = super::deserialize(deserializer)?;
+        TypeRegistry::try_with_current(|registry| {
+            match registry.get_with_name(object.type_name()) {
+                Some(registration) => check_allowed(registry, P::reflect_name(), registration),
+                None => Ok(()),
+            }
+        })
+        .and_then(|result| result)
+        .map_err(D::Error::custom)?;
+        object
+    } else {
+        let expected = Cell::new(Some(P::reflect_name()));
+        EXPECTED_TRAIT.set(&expected, || super::deserialize(deserializer))?
+    };
+    Cast::<P>::try_cast(object).map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{reflect_trait, Reflect, ReflectDeserialize, ReflectSerialize};
+
+    use crate::{impl_dynamic_trait, DowncastReflect};
+
+    use super::*;
+
+    #[reflect_trait]
+    trait Command: DowncastReflect {}
+    impl_dynamic_trait!(Command, ReflectCommand);
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Command, Serialize, Deserialize)]
+    struct Move {
+        x: i32,
+    }
+    impl Command for Move {}
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Command, Serialize, Deserialize)]
+    struct Shutdown;
+    impl Command for Shutdown {}
+
+    #[derive(serde::Deserialize)]
+    struct Batch {
+        #[serde(with = "crate::serialization::dyn_field")]
+        commands: Vec<Box<dyn Command>>,
+    }
+
+    #[test]
+    fn restricted_trait() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Move>();
+        registry.register::<Shutdown>();
+        registry.set_current(|| {
+            let command: Box<dyn Command> = serde_json::from_str(r#"{"type":"Shutdown"}"#).unwrap();
+            assert!(command.is::<Shutdown>());
+        });
+
+        registry.restrict::<dyn Command>(&["Move"]);
+        registry.set_current(|| {
+            let command: Box<dyn Command> =
+                serde_json::from_str(r#"{"type":"Move","x":1}"#).unwrap();
+            assert!(command.is::<Move>());
+            let batch: Batch =
+                serde_json::from_str(r#"{"commands":[{"type":"Move","x":1}]}"#).unwrap();
+            assert_eq!(batch.commands.len(), 1);
+
+            let forbidden =
+                TypeError::ForbiddenType(std::any::type_name::<Shutdown>().into(), "Command")
+                    .to_string();
+            let err =
+                serde_json::from_str::<Box<dyn Command>>(r#"{"type":"Shutdown"}"#).unwrap_err();
+            assert!(err.to_string().contains(&forbidden), "{err}");
+            let err = serde_json::from_str::<Batch>(
+                r#"{"commands":[{"type":"Move","x":1},{"type":"Shutdown"}]}"#,
+            )
+            .err()
+            .unwrap();
+            assert!(err.to_string().contains(&forbidden), "{err}");
+
+            // Restrictions only apply when deserializing as the trait.
+            let object = crate::serialization::deserialize(
+                &mut serde_json::Deserializer::from_str(r#"{"type":"Shutdown"}"#),
+            )
+            .unwrap();
+            assert!(object.is::<Shutdown>());
+        });
+    }
+}
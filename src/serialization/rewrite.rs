@@ -0,0 +1,106 @@
+scoped_tls::scoped_thread_local!(static TAG_REWRITER: TagRewriter);
+
+/// A function that substitutes the type tag of an object being deserialized,
+/// or returns `None` to keep it. See [`with_tag_rewriter()`].
+pub type TagRewriter = fn(&str) -> Option<String>;
+
+/// Rewrite type tags with `rewriter` before they are looked up in the
+/// registry, while running `f`.
+///
+/// This swaps the implementation that saved data is deserialized as, without
+/// editing the data, for example to roll out a replacement type gradually.
+/// The payload of the object is deserialized as the substituted type, so the
+/// types should have compatible serialized forms. Serialization is not
+/// affected.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::with_tag_rewriter};
+/// #[reflect_trait]
+/// trait Brain: DowncastReflect {}
+/// impl_dynamic_trait!(Brain, ReflectBrain);
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Brain, Serialize, Deserialize)]
+/// struct OldAi { aggression: f32 }
+/// impl Brain for OldAi {}
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Brain, Serialize, Deserialize)]
+/// struct NewAi { aggression: f32 }
+/// impl Brain for NewAi {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<OldAi>();
+/// registry.register::<NewAi>();
+///
+/// fn upgrade(tag: &str) -> Option<String> {
+///     (tag == "OldAi").then(|| "NewAi".to_string())
+/// }
+///
+/// let json = r#"{"type":"OldAi","aggression":0.5}"#;
+/// let brain: Box<dyn Brain> = registry
+///     .set_current(|| with_tag_rewriter(upgrade, || serde_json::from_str(json)))
+///     .unwrap();
+/// assert_eq!(brain.downcast_ref::<NewAi>().unwrap().aggression, 0.5);
+/// ```
+pub fn with_tag_rewriter<F: FnOnce() -> R, R>(rewriter: TagRewriter, f: F) -> R {
+    TAG_REWRITER.set(&rewriter, f)
+}
+
+/// The substitute for `tag`, if a rewriter is set and substitutes it.
+pub(crate) fn rewrite_tag(tag: &str) -> Option<String> {
+    if TAG_REWRITER.is_set() {
+        TAG_REWRITER.with(|rewriter| rewriter(tag))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{Reflect, ReflectDeserialize, ReflectSerialize, TypeRegistry};
+
+    use super::*;
+    use crate::{serialization::deserialize, TypeError, TypeRegistryExt};
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Serialize, Deserialize)]
+    struct Before(u32);
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Serialize, Deserialize)]
+    struct After(u32);
+
+    fn swap(tag: &str) -> Option<String> {
+        match tag {
+            "Before" => Some("After".to_string()),
+            "Gone" => Some("Missing".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn rewrites_tags() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Before>();
+        registry.register::<After>();
+        registry.set_current(|| {
+            let read = |json: &str| deserialize(&mut serde_json::Deserializer::from_str(json));
+
+            let object = read(r#"{"type":"Before","value":1}"#).unwrap();
+            assert!(object.is::<Before>());
+
+            with_tag_rewriter(swap, || {
+                let object = read(r#"{"type":"Before","value":1}"#).unwrap();
+                assert_eq!(object.downcast_ref::<After>().unwrap().0, 1);
+                let object = read(r#"{"type":"After","value":2}"#).unwrap();
+                assert!(object.is::<After>());
+
+                let err = read(r#"{"type":"Gone","value":3}"#).unwrap_err();
+                let expected = TypeError::UnregisteredShortName("Gone".into()).to_string();
+                assert!(err.to_string().contains(&expected), "{err}");
+            });
+        });
+    }
+}
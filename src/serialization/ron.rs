@@ -0,0 +1,163 @@
+//! Helpers for tagged serialization to and from [RON](https://docs.rs/ron).
+//! Requires the "ron" crate feature.
+//!
+//! RON is not fully self-describing: when a value is read without knowing its
+//! type, the names of structs and enum variants are lost, and a newtype struct
+//! cannot be told apart from a one-element tuple. Tagged objects are buffered
+//! before their type is known (see [`deserialize()`](super::deserialize())),
+//! so [`to_ron_tagged()`] writes them in the layout that `serde_json` would
+//! use: structs and tagged objects as maps, unit variants as strings, other
+//! variants as maps with a single entry, and newtypes as their inner value.
+//! Hand-written RON must follow the same layout to be read by
+//! [`from_ron_tagged()`].
+//!
+//! ## Example
+//! ```rust
+//! # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::{from_ron_tagged, to_ron_tagged}};
+//! #[derive(Reflect, FromReflect, serde::Serialize, serde::Deserialize)]
+//! #[reflect(Serialize, Deserialize)]
+//! enum Shape { Point, Circle(f32) }
+//!
+//! #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+//! #[reflect(Serialize, Deserialize)]
+//! struct Sprite { name: String, shapes: Vec<Shape> }
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register::<Sprite>();
+//!
+//! let sprite = Sprite { name: "dot".into(), shapes: vec![Shape::Point, Shape::Circle(1.0)] };
+//! let ron = to_ron_tagged(&sprite, &registry).unwrap();
+//! assert_eq!(ron, r#"{"type":"Sprite","name":"dot","shapes":["Point",{"Circle":1.0}]}"#);
+//!
+//! let sprite = from_ron_tagged(&ron, &registry).unwrap();
+//! assert_eq!(sprite.downcast_ref::<Sprite>().unwrap().shapes.len(), 2);
+//! ```
+
+use bevy_reflect::{Reflect, TypeRegistry};
+use serde::{Serialize, Serializer};
+
+use crate::TypeRegistryExt;
+
+use super::{ser::SerializeTagged, value::Value, value_ser::to_value};
+
+/// Serialize `this` to a RON string with a type tag, using `registry`. See the
+/// [module documentation](self) for the layout.
+///
+/// # Errors
+///
+/// Fails as [`serialize()`](super::serialize) does.
+pub fn to_ron_tagged(this: &dyn Reflect, registry: &TypeRegistry) -> Result<String, ron::Error> {
+    let value = registry
+        .set_current(|| to_value(&SerializeTagged(this)))
+        .map_err(|err| ron::Error::Message(err.to_string()))?;
+    ron::to_string(&RonValue(&value))
+}
+
+/// Deserialize a tagged object from a RON string, using `registry`.
+///
+/// # Errors
+///
+/// Fails if the input is not valid RON, as
+/// [`deserialize()`](super::deserialize) does, or if there is input after the
+/// object. Errors include the position in the input where they occurred.
+pub fn from_ron_tagged(
+    ron: &str,
+    registry: &TypeRegistry,
+) -> Result<Box<dyn Reflect>, ron::error::SpannedError> {
+    let mut deserializer = ron::Deserializer::from_str(ron)?;
+    registry
+        .set_current(|| super::deserialize(&mut deserializer))
+        .and_then(|object| deserializer.end().map(|()| object))
+        .map_err(|err| deserializer.span_error(err))
+}
+
+/// Serializes a [`Value`] with newtypes unwrapped, because RON reads them back
+/// as one-element tuples.
+struct RonValue<'a>(&'a Value<'a>);
+
+impl Serialize for RonValue<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.0 {
+            Value::Newtype(inner) => RonValue(inner).serialize(serializer),
+            Value::Some(inner) => serializer.serialize_some(&RonValue(inner)),
+            Value::Seq(elements) => serializer.collect_seq(elements.iter().map(RonValue)),
+            Value::Map(entries) => serializer.collect_map(
+                entries
+                    .iter()
+                    .map(|(key, value)| (RonValue(key), RonValue(value))),
+            ),
+            value => value.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{FromReflect, ReflectDeserialize, ReflectSerialize};
+
+    use super::*;
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Serialize, Deserialize)]
+    struct Marker;
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Serialize, Deserialize)]
+    struct Meters(f32);
+
+    #[derive(Reflect, FromReflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Serialize, Deserialize)]
+    enum Shape {
+        Point,
+        Circle(f32),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Serialize, Deserialize)]
+    struct Entity {
+        marker: Marker,
+        height: Meters,
+        parent: Option<u32>,
+        root: Option<u32>,
+        shapes: Vec<Shape>,
+    }
+
+    fn roundtrip(value: &dyn Reflect, registry: &TypeRegistry) {
+        let ron = to_ron_tagged(value, registry).unwrap();
+        let object = from_ron_tagged(&ron, registry).unwrap_or_else(|err| panic!("{ron}: {err}"));
+        assert_eq!(object.reflect_partial_eq(value), Some(true), "{ron}");
+    }
+
+    #[test]
+    fn corner_cases() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Entity>();
+        registry.register::<Marker>();
+        registry.register::<Meters>();
+        registry.register::<Shape>();
+
+        roundtrip(&Marker, &registry);
+        roundtrip(&Meters(2.0), &registry);
+        roundtrip(&Shape::Point, &registry);
+        roundtrip(&Shape::Circle(1.0), &registry);
+        roundtrip(&Shape::Rect { w: 1, h: 2 }, &registry);
+        roundtrip(
+            &Entity {
+                marker: Marker,
+                height: Meters(1.5),
+                parent: Some(3),
+                root: None,
+                shapes: vec![Shape::Point, Shape::Circle(2.0), Shape::Rect { w: 1, h: 2 }],
+            },
+            &registry,
+        );
+
+        let err =
+            from_ron_tagged("{\"type\":\"Meters\",\n\"value\":\"tall\"}", &registry).unwrap_err();
+        assert_eq!(err.position.line, 2);
+    }
+}
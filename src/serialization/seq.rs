@@ -0,0 +1,131 @@
+use bevy_reflect::{Reflect, TypeRegistry};
+use serde::Deserialize;
+
+use crate::TypeRegistryExt;
+
+use super::value::{Value, ValueDeserializer, ValueError};
+
+/// A buffered sequence of tagged trait objects, for deserializing large
+/// sequences in two phases.
+///
+/// The first phase, [`TaggedSeq::from_deserializer()`], parses the input into
+/// memory without looking at the types. The second phase turns the buffered
+/// elements into trait objects, either on the current thread with
+/// [`TaggedSeq::deserialize()`], or in parallel with
+/// [`TaggedSeq::deserialize_parallel()`] (requires the "rayon" crate feature).
+///
+/// No current registry is needed; the registry is passed explicitly and made
+/// current on each worker thread.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::TaggedSeq};
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Serialize, Deserialize)]
+/// struct Entity { id: u32 }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Entity>();
+///
+/// let json = r#"[{"type":"Entity","id":0},{"type":"Entity","id":1}]"#;
+/// let mut deserializer = serde_json::Deserializer::from_str(json);
+/// let seq = TaggedSeq::from_deserializer(&mut deserializer).unwrap();
+/// assert_eq!(seq.len(), 2);
+///
+/// let entities = seq.deserialize(&registry).unwrap();
+/// assert_eq!(entities[1].downcast_ref::<Entity>().unwrap().id, 1);
+/// ```
+#[derive(Debug)]
+pub struct TaggedSeq<'de> {
+    elements: Vec<Value<'de>>,
+}
+
+impl<'de> TaggedSeq<'de> {
+    /// Buffer a sequence from `deserializer`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the input is not a well-formed sequence.
+    pub fn from_deserializer<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(TaggedSeq {
+            elements: Vec::deserialize(deserializer)?,
+        })
+    }
+
+    /// The number of elements.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// True if the sequence is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Deserialize the elements on the current thread.
+    ///
+    /// # Errors
+    ///
+    /// Fails if an element has an unknown type tag, or cannot be deserialized.
+    pub fn deserialize(self, registry: &TypeRegistry) -> Result<Vec<Box<dyn Reflect>>, ValueError> {
+        deserialize_chunk(registry, self.elements)
+    }
+
+    /// Deserialize the elements in parallel, in chunks of `chunk_size`
+    /// elements, preserving their order.
+    ///
+    /// Requires the "rayon" crate feature.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the error of the first element, in order, that cannot be
+    /// deserialized.
+    #[cfg(feature = "rayon")]
+    pub fn deserialize_parallel(
+        self,
+        registry: &TypeRegistry,
+        chunk_size: usize,
+    ) -> Result<Vec<Box<dyn Reflect>>, ValueError> {
+        use rayon::prelude::*;
+
+        let len = self.elements.len();
+        let chunk_size = chunk_size.max(1);
+        let mut chunks = Vec::with_capacity(len / chunk_size + 1);
+        let mut elements = self.elements.into_iter();
+        loop {
+            let chunk: Vec<Value<'de>> = elements.by_ref().take(chunk_size).collect();
+            if chunk.is_empty() {
+                break;
+            }
+            chunks.push(chunk);
+        }
+
+        let results: Vec<_> = chunks
+            .into_par_iter()
+            .map(|chunk| deserialize_chunk(registry, chunk))
+            .collect();
+
+        let mut objects = Vec::with_capacity(len);
+        for result in results {
+            objects.extend(result?);
+        }
+        Ok(objects)
+    }
+}
+
+fn deserialize_chunk(
+    registry: &TypeRegistry,
+    chunk: Vec<Value>,
+) -> Result<Vec<Box<dyn Reflect>>, ValueError> {
+    registry.set_current(|| {
+        chunk
+            .into_iter()
+            .map(|element| super::deserialize(ValueDeserializer::<ValueError>::new(element)))
+            .collect()
+    })
+}
@@ -1,30 +1,54 @@
 use std::{any::Any, ops::Deref};
 
-use bevy_reflect::{Reflect, ReflectRef, ReflectSerialize, TypeRegistry};
+use bevy_reflect::{Reflect, ReflectRef, ReflectSerialize, TypeRegistry, VariantType};
 use serde::Serialize;
 
-use crate::{TypeError, TypeRegistryExt};
+use crate::{missing_type::report_missing, DynamicTrait, TypeError, TypeRegistryExt};
 
-scoped_tls::scoped_thread_local!(
-    static CURRENTLY_SERIALIZING_TYPE: String
-);
+use super::{
+    canonical::{canonicalize, is_canonical},
+    cycles::{serialize_reference, visit, Visit},
+    flat::FlatMapSerializer,
+    manifest::record_tag,
+    policy::SerializationPolicy,
+    redact::{apply_current_redaction, is_redacting, Redact},
+    tag::{type_tag, written_tag, WrittenTag},
+    toml_layout::{is_toml_layout, nest},
+    transparent::SerializeTransparent,
+    value::Value,
+    value_ser::to_value,
+};
 
 /// Serialize any dynamic trait pointer.
 ///
 /// This populates a `type` field in the serialized data containing the type's
-/// "short name". Note that deserialization will fail if the type's short name
-/// is ambiguous.
+/// "short name", or the name given by its
+/// [`SerializedAs`](super::SerializedAs) type data. Note that deserialization
+/// will fail if the type's short name is ambiguous. With
+/// [`with_tag_namespace()`](super::with_tag_namespace), it contains the
+/// type's [`StableId`](super::StableId) instead.
 ///
 /// If the serialized type is a struct, its fields will be serialized alongside
-/// the "type" field (flattened). Otherwise, the serialized data will be put in
-/// a field with the name "value".
+/// the "type" field (flattened), which is always written first. If it is an
+/// enum, the name of the variant is put in a field with the name "variant",
+/// alongside the fields of struct variants (flattened) or the fields of tuple
+/// variants (in "value"). Otherwise, the serialized data will be put in a
+/// field with the name "value".
 ///
 /// Serialization requires a current global
 /// [`TypeRegistry`](bevy_reflect::TypeRegistry). See
 /// [`TypeRegistryExt::set_current`].
 ///
-/// This function may be used in a `#[serde(serialize_with = "...")]` field
-/// attribute.
+/// `this` may be `dyn Reflect`, a trait object of a dynamic trait, or a `Box`
+/// of either (see [`SerializeDyn`]), so this function may be used in a
+/// `#[serde(serialize_with = "...")]` field attribute for fields of those
+/// types. Values of concrete types can be passed as
+/// [`Reflect::as_reflect()`].
+///
+/// # Errors
+///
+/// Fails if there is no current registry, if the type of `this` is not
+/// registered with `#[reflect(Serialize)]`, or if the serializer fails.
 ///
 /// ## Example
 /// ```rust
@@ -48,70 +72,189 @@ scoped_tls::scoped_thread_local!(
 ///     assert_eq!(json, r#"{"type":"Foo","num":123}"#);
 /// });
 /// ```
-pub fn serialize<S>(this: &dyn Reflect, serializer: S) -> Result<S::Ok, S::Error>
+pub fn serialize<T, S>(this: &T, serializer: S) -> Result<S::Ok, S::Error>
 where
+    T: SerializeDyn + ?Sized,
     S: serde::Serializer,
 {
     use serde::ser::Error as _;
 
-    let type_name = TypeRegistry::with_current(|registry| {
-        let registration = registry.get(this.as_reflect().type_id()).ok_or_else(|| {
-            TypeError::UnregisteredType(this.as_reflect().type_name().to_string().into())
-        })?;
-        let type_name = registration.short_name().to_string();
-        Ok(type_name)
+    let this = this.as_dyn_reflect();
+    let (type_name, written, transparent) = TypeRegistry::try_with_current(|registry| {
+        let type_name = serialized_type_tag(registry, this)?.to_string();
+        let written = written_tag(registry, this, &type_name)?;
+        let transparent = registry
+            .get_type_data::<SerializeTransparent>(Any::type_id(this.as_any()))
+            .is_some();
+        Ok((type_name, written, transparent))
     })
-    .map_err(|e: TypeError| S::Error::custom(e))?;
-
-    CURRENTLY_SERIALIZING_TYPE.set(&type_name, || {
-        match this.reflect_ref() {
-            // Serialize flattened.
-            ReflectRef::Struct(_) => {
-                let serialize = SerializeWithTypeTagFlattened {
-                    type_name: CurrentType,
-                    value: SerializePointerWithTypeTag {
-                        pointer: this.as_reflect(),
-                    },
-                };
-                serialize.serialize(serializer)
-            }
-            // For all other types, serialize unflattened.
-            _ => {
-                let serialize = SerializeWithTypeTagUnflattened {
-                    type_name: CurrentType,
-                    value: SerializePointerWithTypeTag {
-                        pointer: this.as_reflect(),
-                    },
-                };
-                serialize.serialize(serializer)
-            }
+    .and_then(|result| result)
+    .map_err(S::Error::custom)?;
+    record_tag(&type_name);
+    let _entered = match visit(this, &type_name).map_err(S::Error::custom)? {
+        Visit::Cycle(depth) => return serialize_reference(depth, serializer),
+        visit => visit,
+    };
+
+    // The tag is passed down to the structs that write it, rather than through
+    // thread-local state, so that the `Serialize` implementations of the
+    // object and its fields may serialize other tagged objects in any way.
+    let tag = &written;
+
+    // Serialize the field of a transparent wrapper in place of the wrapper.
+    if let Some(field) = transparent
+        .then(|| SerializeTransparent::field(this))
+        .flatten()
+    {
+        return serialize_unflattened(tag, field, serializer);
+    }
+
+    match this.reflect_ref() {
+        // Serialize the fields alongside the type tag.
+        ReflectRef::Struct(_) => {
+            let serialize = SerializeStructWithTypeTag {
+                tag,
+                value: SerializePointerWithTypeTag {
+                    pointer: this.as_reflect(),
+                },
+            };
+            serialize.serialize(serializer)
         }
-    })
+        // Serialize the variant name and fields alongside the type tag.
+        ReflectRef::Enum(reflect_enum) => {
+            let serialize = SerializeEnumWithTypeTag {
+                tag,
+                variant_type: reflect_enum.variant_type(),
+                value: SerializePointerWithTypeTag {
+                    pointer: this.as_reflect(),
+                },
+            };
+            serialize.serialize(serializer)
+        }
+        // For all other types, serialize unflattened.
+        _ => serialize_unflattened(tag, this.as_reflect(), serializer),
+    }
 }
 
-#[derive(Serialize)]
-#[serde(bound(
-    serialize = "Ptr: Deref<Target = dyn Reflect>",
-    // deserialize = "SerializePointerWithTypeTag<Ptr>: Deserialize<'de>"
-))]
-struct SerializeWithTypeTagFlattened<Ptr> {
-    #[serde(rename = "type")]
-    type_name: CurrentType,
-    #[serde(flatten)]
+/// Serialize `pointer` in the `value` field alongside the type tag `tag`,
+/// nested as in the TOML layout if it is enabled (see
+/// [`with_toml_layout()`](super::with_toml_layout)).
+fn serialize_unflattened<S>(
+    tag: &WrittenTag,
+    pointer: &dyn Reflect,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    use serde::ser::Error as _;
+
+    let value = SerializePointerWithTypeTag { pointer };
+    if !is_toml_layout() {
+        let serialize = SerializeWithTypeTagUnflattened {
+            type_name: tag,
+            value,
+        };
+        return serialize.serialize(serializer);
+    }
+
+    let serialize = SerializeWithTypeTagUnflattened {
+        type_name: tag,
+        value: nest(to_value(&value).map_err(S::Error::custom)?),
+    };
+    serialize.serialize(serializer)
+}
+
+/// Serializes a struct as `{"type":"Foo",...}`, writing the type tag and then
+/// the fields of the struct as entries of the same map, without going through
+/// `#[serde(flatten)]`.
+struct SerializeStructWithTypeTag<'a, Ptr> {
+    tag: &'a WrittenTag,
     value: SerializePointerWithTypeTag<Ptr>,
 }
 
+impl<Ptr: Deref<Target = dyn Reflect>> Serialize for SerializeStructWithTypeTag<'_, Ptr> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeMap as _;
+
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("type", self.tag)?;
+        self.value.serialize(FlatMapSerializer(&mut map))?;
+        map.end()
+    }
+}
+
 #[derive(Serialize)]
-#[serde(bound(
-    serialize = "Ptr: Deref<Target = dyn Reflect>",
-    // deserialize = "SerializePointerWithTypeTag<Ptr>: Deserialize<'de>"
-))]
-struct SerializeWithTypeTagUnflattened<Ptr> {
+struct SerializeWithTypeTagUnflattened<'a, T> {
     #[serde(rename = "type")]
-    type_name: CurrentType,
+    type_name: &'a WrittenTag,
+    value: T,
+}
+
+/// Serializes an enum as `{"type":"Shape","variant":"Circle",...}`. Fields of
+/// struct variants are flattened, and fields of tuple variants are put in
+/// `value`. If the `Serialize` implementation of the enum does not produce
+/// serde's default (externally tagged) representation, the serialized data
+/// is put in `value` as for other non-struct types.
+struct SerializeEnumWithTypeTag<'a, Ptr> {
+    tag: &'a WrittenTag,
+    variant_type: VariantType,
     value: SerializePointerWithTypeTag<Ptr>,
 }
 
+impl<Ptr: Deref<Target = dyn Reflect>> Serialize for SerializeEnumWithTypeTag<'_, Ptr> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::{Error as _, SerializeMap as _};
+
+        let value = to_value(&self.value).map_err(S::Error::custom)?;
+        let payload = |value| if is_toml_layout() { nest(value) } else { value };
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("type", self.tag)?;
+        match split_variant(self.variant_type, value) {
+            Ok((variant, None)) => map.serialize_entry("variant", &variant)?,
+            Ok((variant, Some(Value::Map(fields)))) if self.variant_type == VariantType::Struct => {
+                map.serialize_entry("variant", &variant)?;
+                for (name, field) in fields {
+                    map.serialize_entry(&name, &field)?;
+                }
+            }
+            Ok((variant, Some(fields))) => {
+                map.serialize_entry("variant", &variant)?;
+                map.serialize_entry("value", &payload(fields))?;
+            }
+            Err(value) => map.serialize_entry("value", &payload(value))?,
+        }
+        map.end()
+    }
+}
+
+/// Split the externally tagged representation of an enum into its variant
+/// name and fields, or give back `value` if it has a different
+/// representation.
+fn split_variant(
+    variant_type: VariantType,
+    value: Value<'_>,
+) -> Result<(Value<'_>, Option<Value<'_>>), Value<'_>> {
+    match value {
+        Value::Str(_) | Value::String(_) if variant_type == VariantType::Unit => Ok((value, None)),
+        Value::Map(mut entries) if entries.len() == 1 && variant_type != VariantType::Unit => {
+            match entries.pop() {
+                Some((variant, fields)) if variant.as_str().is_some() => {
+                    Ok((variant, Some(fields)))
+                }
+                entry => Err(Value::Map(entry.into_iter().collect())),
+            }
+        }
+        other => Err(other),
+    }
+}
+
 struct SerializePointerWithTypeTag<Ptr> {
     pointer: Ptr,
 }
@@ -123,72 +266,176 @@ impl<Ptr: Deref<Target = dyn Reflect>> Serialize for SerializePointerWithTypeTag
     {
         use serde::ser::Error as _;
 
-        let serialize = TypeRegistry::with_current(|registry| {
-            let registration =
-                match registry.get(Any::type_id(Reflect::as_any(self.pointer.as_reflect()))) {
-                    Some(type_name) => type_name,
-                    None => {
-                        return Err(TypeError::UnregisteredType(
-                            self.pointer.as_reflect().type_name().to_string().into(),
-                        ))
-                    }
-                };
-            Ok(registration
-                .data::<ReflectSerialize>()
-                .ok_or_else(|| {
-                    TypeError::UnregisteredTrait(
-                        self.pointer.as_reflect().type_name().to_string().into(),
-                        "Serialize",
-                    )
-                })?
-                .clone())
+        let (serialize, policy, redaction) = TypeRegistry::try_with_current(|registry| {
+            let redaction = is_redacting()
+                .then(|| {
+                    registry
+                        .get_type_data::<Redact>(Any::type_id(self.pointer.as_any()))
+                        .cloned()
+                })
+                .flatten();
+            Ok((
+                reflect_serialize(registry, &*self.pointer)?,
+                serialization_policy(registry, &*self.pointer),
+                redaction,
+            ))
         })
+        .and_then(|result| result)
         .map_err(S::Error::custom)?;
 
         let serializable = serialize.get_serializable(&*self.pointer);
-        serializable.borrow().serialize(serializer)
+        if policy.is_none() && !is_canonical() && !is_redacting() {
+            return serializable.borrow().serialize(serializer);
+        }
+
+        let mut value = to_value(serializable.borrow()).map_err(S::Error::custom)?;
+        if let Some(policy) = policy {
+            policy.apply_to_fields(&mut value);
+        }
+        if let Some(redaction) = redaction {
+            redaction.apply(&mut value);
+        }
+        apply_current_redaction(&mut value);
+        if is_canonical() {
+            canonicalize(&mut value);
+        }
+        value.serialize(serializer)
+    }
+}
+
+/// Get the [`SerializationPolicy`] of `this`, if it is a struct and has one.
+fn serialization_policy(
+    registry: &TypeRegistry,
+    this: &dyn Reflect,
+) -> Option<SerializationPolicy> {
+    match this.reflect_ref() {
+        ReflectRef::Struct(_) => registry
+            .get_type_data::<SerializationPolicy>(Any::type_id(this.as_any()))
+            .cloned(),
+        _ => None,
     }
 }
 
-// impl<'de> Deserialize<'de> for SerializePointerWithTypeTag<Box<dyn Reflect>> {
-//     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-//     where
-//         D: serde::Deserializer<'de>,
-//     {
-//         use serde::de::Error as _;
-
-//         let type_name = CURRENTLY_DESERIALIZING_TYPE
-//             .with(|cell| cell.borrow().clone())
-//             .expect("internal imbalance - no type being deserialized");
-
-//         let deserialize = TypeRegistry::with_current(|registry| {
-//             let registration = match registry.get_with_short_name(&type_name) {
-//                 Some(registration) => registration,
-//                 None => return Err(TypeError::UnregisteredTypeName(type_name.into())),
-//             };
-//             Ok(registration
-//                 .data::<ReflectDeserialize>()
-//                 .ok_or_else(|| TypeError::UnregisteredTrait(type_name.into(), "Deserialize"))?
-//                 .clone())
-//         })
-//         .map_err(|e: TypeError| D::Error::custom(e))?;
-
-//         Ok(SerializePointerWithTypeTag {
-//             pointer: deserialize.deserialize(deserializer)?,
-//         })
-//     }
-// }
-
-/// When (de)serializing, this registers the type name in the current
-/// (de)serialization context, such that when `SerializePointerWithTypeTag<P>`
-/// is (de)serialized, the concrete type name is known.
-struct CurrentType;
-
-impl Serialize for CurrentType {
+/// Serialize a sequence of trait objects, each with a type tag (see
+/// [`serialize()`]), directly from an iterator.
+///
+/// Unlike collecting into a `Vec<Box<dyn Reflect>>` first, this does not
+/// require cloning the objects. The length of the sequence is given to the
+/// serializer if the iterator reports an exact size.
+///
+/// # Errors
+///
+/// Fails as [`serialize()`] does for any of the objects.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::serialize_seq_of_dyn};
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Serialize, Deserialize)]
+/// struct Entity { id: u32 }
+///
+/// let entities: Vec<Entity> = (0..3).map(|id| Entity { id }).collect();
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Entity>();
+/// registry.set_current(|| {
+///     let mut json = Vec::new();
+///     serialize_seq_of_dyn(
+///         entities.iter().map(|entity| entity.as_reflect()),
+///         &mut serde_json::Serializer::new(&mut json),
+///     )
+///     .unwrap();
+///     assert_eq!(
+///         String::from_utf8(json).unwrap(),
+///         r#"[{"type":"Entity","id":0},{"type":"Entity","id":1},{"type":"Entity","id":2}]"#
+///     );
+/// });
+/// ```
+pub fn serialize_seq_of_dyn<'a, I, S>(iter: I, serializer: S) -> Result<S::Ok, S::Error>
+where
+    I: Iterator<Item = &'a dyn Reflect>,
+    S: serde::Serializer,
+{
+    use serde::ser::SerializeSeq;
+
+    let len = match iter.size_hint() {
+        (lower, Some(upper)) if lower == upper => Some(lower),
+        _ => None,
+    };
+    let mut seq = serializer.serialize_seq(len)?;
+    for element in iter {
+        seq.serialize_element(&SerializeTagged(element))?;
+    }
+    seq.end()
+}
+
+/// Adapts a reflected value to [`Serialize`], serializing it with a type tag.
+pub(crate) struct SerializeTagged<'a>(pub &'a dyn Reflect);
+
+impl Serialize for SerializeTagged<'_> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        CURRENTLY_SERIALIZING_TYPE.with(|name| name.serialize(serializer))
+        serialize(self.0, serializer)
     }
 }
+
+/// Pointers that can be serialized with a type tag by [`serialize()`].
+///
+/// This is implemented for `dyn Reflect`, for `dyn MyTrait` by
+/// [`impl_dynamic_trait!`](crate::impl_dynamic_trait), for `Box`es of those,
+/// and for references.
+pub trait SerializeDyn {
+    /// The value to serialize.
+    fn as_dyn_reflect(&self) -> &dyn Reflect;
+}
+
+impl SerializeDyn for dyn Reflect {
+    fn as_dyn_reflect(&self) -> &dyn Reflect {
+        self
+    }
+}
+
+impl<T: DynamicTrait + ?Sized> SerializeDyn for Box<T> {
+    fn as_dyn_reflect(&self) -> &dyn Reflect {
+        (**self).as_reflect()
+    }
+}
+
+impl<T: SerializeDyn + ?Sized> SerializeDyn for &T {
+    fn as_dyn_reflect(&self) -> &dyn Reflect {
+        (**self).as_dyn_reflect()
+    }
+}
+
+/// Get the type tag that `this` should be serialized with.
+pub(crate) fn serialized_type_tag<'a>(
+    registry: &'a TypeRegistry,
+    this: &dyn Reflect,
+) -> Result<&'a str, TypeError> {
+    let registration = registry.get(Any::type_id(this.as_any())).ok_or_else(|| {
+        report_missing(TypeError::UnregisteredType(
+            this.type_name().to_string().into(),
+        ))
+    })?;
+    Ok(type_tag(registration))
+}
+
+/// Get the [`ReflectSerialize`] type data for the concrete type of `this`.
+pub(crate) fn reflect_serialize(
+    registry: &TypeRegistry,
+    this: &dyn Reflect,
+) -> Result<ReflectSerialize, TypeError> {
+    let registration = registry.get(Any::type_id(this.as_any())).ok_or_else(|| {
+        report_missing(TypeError::UnregisteredType(
+            this.type_name().to_string().into(),
+        ))
+    })?;
+    Ok(registration
+        .data::<ReflectSerialize>()
+        .ok_or_else(|| {
+            TypeError::UnregisteredTrait(this.type_name().to_string().into(), "Serialize")
+        })?
+        .clone())
+}
@@ -0,0 +1,342 @@
+//! Incremental reading and writing of sequences of tagged objects.
+//!
+//! A stream is a sequence of frames, one per object. Each frame is the length
+//! of the payload (a little-endian `u32`), the codec of the payload (one byte,
+//! see [`Compression`]), and the payload: the object, tagged as by
+//! [`serialize()`](super::serialize()) and encoded with the crate's compact
+//! binary encoding, and possibly compressed. Only one object is held in memory
+//! at a time, so the peak memory use does not grow with the size of the
+//! stream.
+//!
+//! ## Example
+//! ```rust
+//! # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::stream::{Reader, Writer}};
+//! #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+//! #[reflect(Serialize, Deserialize)]
+//! struct Entity { id: u32 }
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register::<Entity>();
+//!
+//! let mut writer = Writer::new(Vec::new(), &registry);
+//! for id in 0..3 {
+//!     writer.write(&Entity { id }).unwrap();
+//! }
+//! let bytes = writer.into_inner();
+//!
+//! let reader = Reader::new(&bytes[..], &registry);
+//! let ids: Vec<u32> = reader
+//!     .map(|object| object.unwrap().downcast_ref::<Entity>().unwrap().id)
+//!     .collect();
+//! assert_eq!(ids, [0, 1, 2]);
+//! ```
+
+use std::io::{self, Read, Write};
+
+use bevy_reflect::{Reflect, TypeRegistry};
+
+use crate::{StreamError, TypeRegistryExt};
+
+use super::{
+    binary::{read_value, write_value},
+    ser::SerializeTagged,
+    value::{ValueDeserializer, ValueError},
+    value_ser::to_value,
+};
+
+/// Codec byte of uncompressed frames.
+const CODEC_NONE: u8 = 0;
+/// Codec byte of LZ4 compressed frames.
+#[cfg(feature = "lz4")]
+const CODEC_LZ4: u8 = 1;
+/// Codec byte of Zstandard compressed frames.
+#[cfg(feature = "zstd")]
+const CODEC_ZSTD: u8 = 2;
+
+/// Compression of the frames written by a [`Writer`].
+///
+/// The codec is recorded in each frame, so a [`Reader`] can read frames
+/// written with any compression, as long as the codec's crate feature is
+/// enabled. Frames that would not become smaller are written uncompressed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// Frames are not compressed.
+    #[default]
+    None,
+    /// LZ4 compression, which is fast but compresses less.
+    ///
+    /// Requires the "lz4" crate feature.
+    #[cfg(feature = "lz4")]
+    Lz4,
+    /// Zstandard compression at the given level (1-22, where 0 selects the
+    /// default level).
+    ///
+    /// Requires the "zstd" crate feature.
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+impl Compression {
+    /// Compress `data`, returning the codec byte and the compressed data, or
+    /// `None` if the data should be written uncompressed.
+    #[cfg_attr(not(feature = "zstd"), allow(clippy::unnecessary_wraps))]
+    fn compress(self, data: &[u8]) -> Result<Option<(u8, Vec<u8>)>, StreamError> {
+        let compressed: Option<(u8, Vec<u8>)> = match self {
+            Compression::None => None,
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => Some((CODEC_LZ4, lz4_flex::compress_prepend_size(data))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(level) => Some((
+                CODEC_ZSTD,
+                zstd::bulk::compress(data, level)
+                    .map_err(|err| StreamError::Compression(err.to_string()))?,
+            )),
+        };
+        Ok(compressed.filter(|(_, compressed)| compressed.len() < data.len()))
+    }
+}
+
+/// Decompress a payload with the codec `codec`.
+#[cfg_attr(not(any(feature = "lz4", feature = "zstd")), allow(unused_variables))]
+fn decompress(codec: u8, data: &[u8]) -> Result<Vec<u8>, StreamError> {
+    match codec {
+        #[cfg(feature = "lz4")]
+        CODEC_LZ4 => lz4_flex::decompress_size_prepended(data)
+            .map_err(|err| StreamError::Compression(err.to_string())),
+        #[cfg(feature = "zstd")]
+        CODEC_ZSTD => {
+            zstd::decode_all(data).map_err(|err| StreamError::Compression(err.to_string()))
+        }
+        _ => Err(StreamError::UnsupportedCodec(codec)),
+    }
+}
+
+/// Writes tagged objects to a stream, one frame at a time. See the
+/// [module documentation](self).
+pub struct Writer<'a, W> {
+    inner: W,
+    registry: &'a TypeRegistry,
+    compression: Compression,
+    buffer: Vec<u8>,
+}
+
+impl<'a, W: Write> Writer<'a, W> {
+    /// Create a writer that writes to `inner`, serializing objects with
+    /// `registry`.
+    pub fn new(inner: W, registry: &'a TypeRegistry) -> Self {
+        Writer {
+            inner,
+            registry,
+            compression: Compression::None,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Compress the frames written from now on with `compression`.
+    #[must_use]
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Write one object.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the object cannot be serialized or compressed, if it is too
+    /// large for a single frame, or if the underlying writer fails.
+    pub fn write(&mut self, object: &dyn Reflect) -> Result<(), StreamError> {
+        let value = self
+            .registry
+            .set_current(|| to_value(&SerializeTagged(object)))
+            .map_err(|err| StreamError::Encoding(err.to_string()))?;
+
+        self.buffer.clear();
+        write_value(&value, &mut self.buffer);
+        let compressed = self.compression.compress(&self.buffer)?;
+        let (codec, payload) = match compressed {
+            Some((codec, ref compressed)) => (codec, &compressed[..]),
+            None => (CODEC_NONE, &self.buffer[..]),
+        };
+
+        let len = u32::try_from(payload.len()).map_err(|_| StreamError::FrameTooLarge)?;
+        self.inner.write_all(&len.to_le_bytes())?;
+        self.inner.write_all(&[codec])?;
+        self.inner.write_all(payload)?;
+        Ok(())
+    }
+
+    /// Flush the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the underlying writer fails.
+    pub fn flush(&mut self) -> Result<(), StreamError> {
+        self.inner.flush()?;
+        Ok(())
+    }
+
+    /// Get the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads tagged objects from a stream, one frame at a time. See the
+/// [module documentation](self).
+///
+/// The reader is also an iterator over the objects, which ends at the end of
+/// the stream.
+pub struct Reader<'a, R> {
+    inner: R,
+    registry: &'a TypeRegistry,
+    buffer: Vec<u8>,
+}
+
+impl<'a, R: Read> Reader<'a, R> {
+    /// Create a reader that reads from `inner`, deserializing objects with
+    /// `registry`.
+    pub fn new(inner: R, registry: &'a TypeRegistry) -> Self {
+        Reader {
+            inner,
+            registry,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Read the next object, or `None` at the end of the stream.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the underlying reader fails, if the stream ends in the middle
+    /// of a frame, if the frame cannot be decompressed, or if the object cannot
+    /// be deserialized.
+    pub fn read(&mut self) -> Result<Option<Box<dyn Reflect>>, StreamError> {
+        let mut len = [0; 4];
+        if !read_exact_or_eof(&mut self.inner, &mut len)? {
+            return Ok(None);
+        }
+        let len = u32::from_le_bytes(len) as usize;
+
+        let mut codec = [0];
+        self.buffer.resize(len, 0);
+        self.inner
+            .read_exact(&mut codec)
+            .and_then(|()| self.inner.read_exact(&mut self.buffer))
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::UnexpectedEof => StreamError::Truncated,
+                _ => StreamError::Io(err),
+            })?;
+
+        let decompressed;
+        let bytes = match codec[0] {
+            CODEC_NONE => &self.buffer[..],
+            codec => {
+                decompressed = decompress(codec, &self.buffer)?;
+                &decompressed[..]
+            }
+        };
+        let value = read_value(bytes).map_err(|err| StreamError::Encoding(err.to_string()))?;
+        let object = self
+            .registry
+            .set_current(|| super::deserialize(ValueDeserializer::<ValueError>::new(value)))
+            .map_err(|err| StreamError::Encoding(err.to_string()))?;
+        Ok(Some(object))
+    }
+
+    /// Get the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Iterator for Reader<'_, R> {
+    type Item = Result<Box<dyn Reflect>, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+/// Fill `buf`, or return false if the reader is at its end. A partially
+/// filled `buf` is an error.
+fn read_exact_or_eof(reader: &mut impl Read, buf: &mut [u8]) -> Result<bool, StreamError> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(StreamError::Truncated),
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{ReflectDeserialize, ReflectSerialize};
+
+    use super::*;
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Serialize, Deserialize)]
+    struct Chunk {
+        name: String,
+        data: Vec<u8>,
+    }
+
+    #[test]
+    fn truncated_stream() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Chunk>();
+
+        let mut writer = Writer::new(Vec::new(), &registry);
+        let chunk = Chunk {
+            name: "a".into(),
+            data: vec![1, 2, 3],
+        };
+        writer.write(&chunk).unwrap();
+        writer.write(&chunk).unwrap();
+        let mut bytes = writer.into_inner();
+        bytes.pop();
+
+        let mut reader = Reader::new(&bytes[..], &registry);
+        let first = reader.read().unwrap().unwrap();
+        assert_eq!(first.downcast_ref::<Chunk>().unwrap().data, [1, 2, 3]);
+        assert!(matches!(reader.read(), Err(StreamError::Truncated)));
+    }
+
+    #[test]
+    fn unsupported_codec() {
+        let registry = TypeRegistry::default();
+        let bytes = [1, 0, 0, 0, 0xff, 0];
+        let mut reader = Reader::new(&bytes[..], &registry);
+        assert!(matches!(
+            reader.read(),
+            Err(StreamError::UnsupportedCodec(0xff))
+        ));
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn lz4_frames() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Chunk>();
+
+        let chunk = Chunk {
+            name: "level".into(),
+            data: vec![7; 4096],
+        };
+        let mut writer = Writer::new(Vec::new(), &registry).with_compression(Compression::Lz4);
+        writer.write(&chunk).unwrap();
+        let bytes = writer.into_inner();
+        assert!(bytes.len() < 1024);
+        assert_eq!(bytes[4], CODEC_LZ4);
+
+        let object = Reader::new(&bytes[..], &registry).next().unwrap().unwrap();
+        assert_eq!(object.downcast_ref::<Chunk>().unwrap().data, chunk.data);
+    }
+}
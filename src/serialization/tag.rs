@@ -0,0 +1,559 @@
+use std::{
+    any::TypeId,
+    collections::{BTreeMap, HashMap, HashSet},
+};
+
+use bevy_reflect::{Reflect, TypeRegistration, TypeRegistry};
+use serde::Serialize;
+
+use crate::{
+    type_registry::{registry_data, registry_data_mut, user_registrations},
+    TypeError,
+};
+
+use super::rewrite::rewrite_tag;
+
+scoped_tls::scoped_thread_local!(static NAMESPACE: TagNamespace);
+
+/// Override the type tag used when (de)serializing a type.
+///
+/// By default, the `type` field of a serialized trait object contains the
+/// type's "short name" (see
+/// [`TypeRegistration::short_name()`](bevy_reflect::TypeRegistration::short_name)).
+/// When a type has `SerializedAs` type data in the registry, the serializer
+/// writes the given name instead, and the deserializer accepts it in addition
+/// to the short name. This decouples the on-disk name of a type from its Rust
+/// identifier.
+///
+/// A tag must not refer to another type, by its tag or its short name, as
+/// objects of that type would then deserialize as the wrong type.
+/// [`TypeRegistryExt::register_serialized_as()`](crate::TypeRegistryExt::register_serialized_as)
+/// panics if the tag is taken, and a tag that becomes the short name of a type
+/// registered later is ambiguous, and no longer deserializes.
+///
+/// `SerializedAs` can only be given with
+/// [`TypeRegistryExt::register_serialized_as()`](crate::TypeRegistryExt::register_serialized_as),
+/// which checks the tag and indexes it for deserialization.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+/// #[reflect_trait]
+/// trait MyTrait: DowncastReflect {}
+/// impl_dynamic_trait!(MyTrait, ReflectMyTrait);
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(MyTrait, Serialize, Deserialize)]
+/// struct Player { hp: i32 }
+///
+/// impl MyTrait for Player {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register_serialized_as::<Player>("player_v2");
+///
+/// let trait_object: Box<dyn MyTrait> = Box::new(Player { hp: 10 });
+/// registry.set_current(|| {
+///     let json = serde_json::to_string(&*trait_object).unwrap();
+///     assert_eq!(json, r#"{"type":"player_v2","hp":10}"#);
+/// });
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SerializedAs(&'static str);
+
+impl SerializedAs {
+    /// The tag given to the type.
+    #[must_use]
+    pub fn tag(&self) -> &'static str {
+        self.0
+    }
+}
+
+/// A numeric ID that identifies a type in compact binary formats, in place of
+/// its string type tag.
+///
+/// Unlike the [`TypeId`], a stable ID does not change between builds or
+/// compiler versions, so it can be sent over the network or saved. IDs are
+/// chosen by the application, and must be unique within a registry.
+///
+/// IDs are only resolved when they are registered with
+/// [`TypeRegistryExt::register_stable_id()`](crate::TypeRegistryExt::register_stable_id),
+/// which panics if the ID already belongs to another type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StableId(pub u32);
+
+/// Which of the two names of a type is written as its type tag. See
+/// [`with_tag_namespace()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TagNamespace {
+    /// The human-readable tag: the name given by [`SerializedAs`], or the
+    /// short name of the type. This is the default.
+    #[default]
+    Authoring,
+    /// The [`StableId`] of the type, written as an integer. Serializing a type
+    /// without a stable ID fails with [`TypeError::NoStableId`].
+    Runtime,
+}
+
+/// Write type tags in `namespace` while serializing in `f`.
+///
+/// A type can have two tags in the same registry: a readable name for data
+/// written by people and design tools, and a compact [`StableId`] for network
+/// messages and caches. The namespace is chosen for each serialization, so
+/// both kinds of data are produced from one registry and cannot get out of
+/// sync.
+///
+/// [`deserialize()`](super::deserialize()) accepts tags of both namespaces
+/// regardless of this setting: strings are resolved as in the
+/// [`TagNamespace::Authoring`] namespace, and integers as stable IDs.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+/// # use reflect_steroids::serialization::{with_tag_namespace, TagNamespace};
+/// #[reflect_trait]
+/// trait Command: DowncastReflect {}
+/// impl_dynamic_trait!(Command, ReflectCommand);
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Command, Serialize, Deserialize)]
+/// struct MoveTo { x: i32, y: i32 }
+/// impl Command for MoveTo {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register_serialized_as::<MoveTo>("move_to");
+/// registry.register_stable_id::<MoveTo>(7);
+///
+/// let command: Box<dyn Command> = Box::new(MoveTo { x: 1, y: 2 });
+/// registry.set_current(|| {
+///     let authoring = serde_json::to_string(&*command).unwrap();
+///     assert_eq!(authoring, r#"{"type":"move_to","x":1,"y":2}"#);
+///
+///     let runtime = with_tag_namespace(TagNamespace::Runtime, || serde_json::to_string(&*command)).unwrap();
+///     assert_eq!(runtime, r#"{"type":7,"x":1,"y":2}"#);
+///
+///     let command: Box<dyn Command> = serde_json::from_str(&runtime).unwrap();
+///     assert!(command.is::<MoveTo>());
+/// });
+/// ```
+pub fn with_tag_namespace<F: FnOnce() -> R, R>(namespace: TagNamespace, f: F) -> R {
+    NAMESPACE.set(&namespace, f)
+}
+
+/// The namespace selected with [`with_tag_namespace()`].
+fn tag_namespace() -> TagNamespace {
+    if NAMESPACE.is_set() {
+        NAMESPACE.with(|namespace| *namespace)
+    } else {
+        TagNamespace::Authoring
+    }
+}
+
+/// A type tag as it is written, in the current namespace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum WrittenTag {
+    Name(String),
+    Id(u32),
+}
+
+impl Serialize for WrittenTag {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            WrittenTag::Name(name) => serializer.serialize_str(name),
+            WrittenTag::Id(id) => serializer.serialize_u32(*id),
+        }
+    }
+}
+
+/// Get the tag to write for `this`, whose type is registered in `registry`
+/// with the tag `tag` (see [`type_tag()`]), in the current namespace.
+pub(crate) fn written_tag(
+    registry: &TypeRegistry,
+    this: &dyn Reflect,
+    tag: &str,
+) -> Result<WrittenTag, TypeError> {
+    match tag_namespace() {
+        TagNamespace::Authoring => Ok(WrittenTag::Name(tag.to_string())),
+        TagNamespace::Runtime => registry
+            .get_type_data::<StableId>(this.as_any().type_id())
+            .map(|&StableId(id)| WrittenTag::Id(id))
+            .ok_or_else(|| TypeError::NoStableId(this.type_name().to_string().into())),
+    }
+}
+
+/// How to derive unambiguous type tags for types that share a short name.
+///
+/// Types whose short names are ambiguous cannot be deserialized by short name,
+/// because the registry cannot tell which type is meant. Calling
+/// [`TypeRegistryExt::disambiguate_short_names()`](crate::TypeRegistryExt::disambiguate_short_names)
+/// gives each such type a distinct tag derived from its full type name, which
+/// only depends on the set of registered types, not on registration order.
+///
+/// Tags given with [`SerializedAs`] take precedence over disambiguated tags.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::{self, TagDisambiguation}};
+/// mod a {
+///     # use reflect_steroids::prelude::*;
+///     #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+///     #[reflect(Serialize, Deserialize)]
+///     pub struct Item(pub i32);
+/// }
+/// mod b {
+///     # use reflect_steroids::prelude::*;
+///     #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+///     #[reflect(Serialize, Deserialize)]
+///     pub struct Item(pub i32);
+/// }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<a::Item>();
+/// registry.register::<b::Item>();
+/// assert_eq!(registry.ambiguous_short_names().len(), 1);
+///
+/// // Prefix the short name with the name of the enclosing module.
+/// registry.disambiguate_short_names(TagDisambiguation::Custom(|registration| {
+///     let mut path = registration.type_name().rsplit("::");
+///     let short_name = path.next().unwrap();
+///     format!("{}::{}", path.next().unwrap_or_default(), short_name)
+/// }));
+/// registry.set_current(|| {
+///     let mut json = Vec::new();
+///     let mut serializer = serde_json::Serializer::new(&mut json);
+///     serialization::serialize(b::Item(1).as_reflect(), &mut serializer).unwrap();
+///     let json = String::from_utf8(json).unwrap();
+///     assert_eq!(json, r#"{"type":"b::Item","value":1}"#);
+///
+///     let mut deserializer = serde_json::Deserializer::from_str(&json);
+///     let item = serialization::deserialize(&mut deserializer).unwrap();
+///     assert!(item.is::<b::Item>());
+/// });
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub enum TagDisambiguation {
+    /// Prefix the short name with the name of the crate defining the type,
+    /// e.g. `my_crate::Item`. If that is still ambiguous, the full path is
+    /// used.
+    CrateName,
+    /// Use the full type name, e.g. `my_crate::items::Item`.
+    FullPath,
+    /// Compute the tag with a function. If the function returns the same tag
+    /// for several types, the full path is used for those types.
+    Custom(fn(&TypeRegistration) -> String),
+}
+
+impl TagDisambiguation {
+    fn tag_for(self, registration: &TypeRegistration) -> String {
+        match self {
+            TagDisambiguation::CrateName => {
+                let type_name = registration.type_name();
+                let crate_name = type_name.split("::").next().unwrap_or(type_name);
+                format!("{}::{}", crate_name, registration.short_name())
+            }
+            TagDisambiguation::FullPath => registration.type_name().to_string(),
+            TagDisambiguation::Custom(f) => f(registration),
+        }
+    }
+}
+
+/// Type data holding the tag assigned by
+/// [`TypeRegistryExt::disambiguate_short_names()`](crate::TypeRegistryExt::disambiguate_short_names).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct DisambiguatedTag(pub String);
+
+/// Registry-wide type data mapping the tags and stable IDs given to types to
+/// the types, so that they can be resolved without scanning the registry.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TagIndex {
+    serialized_as: HashMap<&'static str, TypeId>,
+    disambiguated: HashMap<String, TypeId>,
+    stable_ids: HashMap<u32, TypeId>,
+}
+
+fn tag_index_mut(registry: &mut TypeRegistry) -> &mut TagIndex {
+    let registration = registry_data_mut(registry);
+    if registration.data::<TagIndex>().is_none() {
+        registration.insert(TagIndex::default());
+    }
+    registration
+        .data_mut::<TagIndex>()
+        .expect("type data was just inserted")
+}
+
+/// Give the registered type `type_id` the tag `tag`. See [`SerializedAs`].
+///
+/// # Panics
+///
+/// Panics if `tag` is the tag or short name of another registered type.
+pub(crate) fn set_serialized_as(registry: &mut TypeRegistry, type_id: TypeId, tag: &'static str) {
+    let owner = user_registrations(registry)
+        .find(|&registration| {
+            registration.type_id() != type_id
+                && (type_tag(registration) == tag || registration.short_name() == tag)
+        })
+        .map(TypeRegistration::type_name);
+    let registration = registry.get_mut(type_id).expect("type is registered");
+    if let Some(owner) = owner {
+        panic!(
+            "cannot serialize '{}' as \"{tag}\", which already refers to '{owner}'",
+            registration.type_name()
+        );
+    }
+    let previous = registration.data::<SerializedAs>().copied();
+    registration.insert(SerializedAs(tag));
+
+    let index = tag_index_mut(registry);
+    if let Some(SerializedAs(previous)) = previous {
+        if index.serialized_as.get(previous) == Some(&type_id) {
+            index.serialized_as.remove(previous);
+        }
+    }
+    index.serialized_as.insert(tag, type_id);
+}
+
+/// Give the registered type `type_id` the stable ID `id`. See [`StableId`].
+///
+/// # Panics
+///
+/// Panics if `id` is the stable ID of another registered type.
+pub(crate) fn set_stable_id(registry: &mut TypeRegistry, type_id: TypeId, id: u32) {
+    let owner = registry_data::<TagIndex>(registry)
+        .and_then(|index| index.stable_ids.get(&id))
+        .filter(|&&owner| owner != type_id)
+        .and_then(|&owner| registry.get(owner))
+        .map(TypeRegistration::type_name);
+    let registration = registry.get_mut(type_id).expect("type is registered");
+    if let Some(owner) = owner {
+        panic!(
+            "cannot give '{}' the stable ID {id}, which already belongs to '{owner}'",
+            registration.type_name()
+        );
+    }
+    let previous = registration.data::<StableId>().copied();
+    registration.insert(StableId(id));
+
+    let index = tag_index_mut(registry);
+    if let Some(StableId(previous)) = previous {
+        if index.stable_ids.get(&previous) == Some(&type_id) {
+            index.stable_ids.remove(&previous);
+        }
+    }
+    index.stable_ids.insert(id, type_id);
+}
+
+/// Get the tag that should be written for the type of `registration`.
+pub(crate) fn type_tag(registration: &TypeRegistration) -> &str {
+    match registration.data::<SerializedAs>() {
+        Some(SerializedAs(tag)) => tag,
+        None => match registration.data::<DisambiguatedTag>() {
+            Some(DisambiguatedTag(tag)) => tag,
+            None => registration.short_name(),
+        },
+    }
+}
+
+/// Groups of registered types that share a short name, ordered by short name
+/// and then by type name.
+pub(crate) fn ambiguous_short_names(registry: &TypeRegistry) -> Vec<Vec<&TypeRegistration>> {
+    let mut by_short_name: BTreeMap<&str, Vec<&TypeRegistration>> = BTreeMap::new();
+    for registration in user_registrations(registry) {
+        by_short_name
+            .entry(registration.short_name())
+            .or_default()
+            .push(registration);
+    }
+    by_short_name
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort_by_key(|registration| registration.type_name());
+            group
+        })
+        .collect()
+}
+
+pub(crate) fn disambiguate_short_names(registry: &mut TypeRegistry, policy: TagDisambiguation) {
+    let mut tags: Vec<(TypeId, String)> = Vec::new();
+    for group in ambiguous_short_names(registry) {
+        let mut group_tags: Vec<(TypeId, &'static str, String)> = group
+            .iter()
+            .map(|registration| {
+                (
+                    registration.type_id(),
+                    registration.type_name(),
+                    policy.tag_for(registration),
+                )
+            })
+            .collect();
+        let duplicates: HashSet<String> = {
+            let mut seen = HashSet::new();
+            group_tags
+                .iter()
+                .filter(|(_, _, tag)| !seen.insert(tag.as_str()))
+                .map(|(_, _, tag)| tag.clone())
+                .collect()
+        };
+        for (_, type_name, tag) in &mut group_tags {
+            if duplicates.contains(tag.as_str()) {
+                *tag = (*type_name).to_string();
+            }
+        }
+        tags.extend(group_tags.into_iter().map(|(id, _, tag)| (id, tag)));
+    }
+
+    for (type_id, tag) in &tags {
+        registry
+            .get_mut(*type_id)
+            .expect("type is registered")
+            .insert(DisambiguatedTag(tag.clone()));
+    }
+    tag_index_mut(registry)
+        .disambiguated
+        .extend(tags.into_iter().map(|(type_id, tag)| (tag, type_id)));
+}
+
+/// Find the registration corresponding to a serialized type tag, after
+/// rewriting it (see [`with_tag_rewriter()`](super::with_tag_rewriter)).
+pub(crate) fn resolve_tag<'a>(
+    registry: &'a TypeRegistry,
+    tag: &str,
+) -> Option<&'a TypeRegistration> {
+    match rewrite_tag(tag) {
+        Some(rewritten) => lookup_tag(registry, &rewritten),
+        None => lookup_tag(registry, tag),
+    }
+}
+
+fn lookup_tag<'a>(registry: &'a TypeRegistry, tag: &str) -> Option<&'a TypeRegistration> {
+    let indexed = registry_data::<TagIndex>(registry)
+        .and_then(|index| (index.serialized_as.get(tag)).or_else(|| index.disambiguated.get(tag)));
+    match indexed {
+        // A type registered after the tag was given may have it as its short
+        // name, which makes the tag ambiguous.
+        Some(&type_id) => match registry.get_with_short_name(tag) {
+            Some(other) if other.type_id() != type_id => None,
+            _ => registry.get(type_id),
+        },
+        None => registry.get_with_short_name(tag),
+    }
+}
+
+/// Find the type with the [`StableId`] `id`.
+pub(crate) fn resolve_stable_id(registry: &TypeRegistry, id: u32) -> Option<&TypeRegistration> {
+    let &type_id = registry_data::<TagIndex>(registry)?.stable_ids.get(&id)?;
+    registry.get(type_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TypeRegistryExt;
+
+    mod a {
+        use bevy_reflect::Reflect;
+
+        #[derive(Reflect)]
+        pub struct Item;
+    }
+
+    mod b {
+        use bevy_reflect::Reflect;
+
+        #[derive(Reflect)]
+        pub struct Item;
+    }
+
+    #[derive(Reflect)]
+    struct Door;
+
+    #[derive(Reflect)]
+    struct Gate;
+
+    #[derive(Reflect)]
+    struct Hatch;
+
+    fn resolved(registry: &TypeRegistry, tag: &str) -> Option<TypeId> {
+        resolve_tag(registry, tag).map(TypeRegistration::type_id)
+    }
+
+    #[test]
+    fn resolve_tags() {
+        let mut registry = TypeRegistry::empty();
+        registry.register::<Door>();
+        registry.register::<Gate>();
+        assert_eq!(resolved(&registry, "Door"), Some(TypeId::of::<Door>()));
+        assert_eq!(resolved(&registry, "door"), None);
+
+        registry.register_serialized_as::<Gate>("gate");
+        assert_eq!(resolved(&registry, "gate"), Some(TypeId::of::<Gate>()));
+        assert_eq!(resolved(&registry, "Gate"), Some(TypeId::of::<Gate>()));
+
+        // Changing the tag retires the old one.
+        registry.register_serialized_as::<Gate>("gate_v2");
+        assert_eq!(resolved(&registry, "gate"), None);
+        assert_eq!(resolved(&registry, "gate_v2"), Some(TypeId::of::<Gate>()));
+
+        // A type registered later with the tag as its short name makes the tag
+        // ambiguous.
+        registry.register_serialized_as::<Gate>("Hatch");
+        registry.register::<Hatch>();
+        assert_eq!(resolved(&registry, "Hatch"), None);
+
+        registry.register::<a::Item>();
+        registry.register::<b::Item>();
+        assert_eq!(resolved(&registry, "Item"), None);
+        registry.disambiguate_short_names(TagDisambiguation::FullPath);
+        assert_eq!(
+            resolved(&registry, std::any::type_name::<b::Item>()),
+            Some(TypeId::of::<b::Item>())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "which already refers to")]
+    fn reject_short_name_as_tag() {
+        let mut registry = TypeRegistry::empty();
+        registry.register::<Door>();
+        registry.register_serialized_as::<Gate>("Door");
+    }
+
+    #[test]
+    #[should_panic(expected = "which already refers to")]
+    fn reject_taken_tag() {
+        let mut registry = TypeRegistry::empty();
+        registry.register_serialized_as::<Door>("portal");
+        registry.register_serialized_as::<Gate>("portal");
+    }
+
+    #[test]
+    #[should_panic(expected = "which already belongs to")]
+    fn reject_taken_stable_id() {
+        let mut registry = TypeRegistry::empty();
+        registry.register_stable_id::<Door>(1);
+        registry.register_stable_id::<Gate>(1);
+    }
+
+    #[test]
+    fn resolve_stable_ids() {
+        let mut registry = TypeRegistry::empty();
+        registry.register::<Door>();
+        assert!(resolve_stable_id(&registry, 1).is_none());
+
+        registry.register_stable_id::<Door>(1);
+        registry.register_stable_id::<Gate>(2);
+        assert_eq!(
+            resolve_stable_id(&registry, 2).map(TypeRegistration::type_id),
+            Some(TypeId::of::<Gate>())
+        );
+
+        registry.register_stable_id::<Door>(3);
+        assert!(resolve_stable_id(&registry, 1).is_none());
+        assert_eq!(
+            resolve_stable_id(&registry, 3).map(TypeRegistration::type_id),
+            Some(TypeId::of::<Door>())
+        );
+    }
+}
@@ -0,0 +1,236 @@
+//! Round-trip checks for tagged serialization, for use in tests.
+//!
+//! ## Example
+//! ```rust
+//! # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::testing::assert_roundtrip};
+//! #[reflect_trait]
+//! trait Item: DowncastReflect {}
+//! impl_dynamic_trait!(Item, ReflectItem);
+//!
+//! #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+//! #[reflect(Item, Serialize, Deserialize)]
+//! struct Sword { damage: u32, name: String }
+//! impl Item for Sword {}
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register::<Sword>();
+//!
+//! assert_roundtrip::<dyn Item, _>(Sword { damage: 3, name: "Ice".into() }, &registry);
+//! ```
+
+use std::fmt::Write as _;
+
+use bevy_reflect::{Reflect, ReflectRef, TypeRegistry};
+
+use crate::{RoundtripError, TypeRegistryExt};
+
+use super::{
+    binary::{read_value, write_value},
+    dyn_field::DynField,
+    ser::SerializeTagged,
+    value::{ValueDeserializer, ValueError},
+    value_ser::to_value,
+};
+
+/// Serialize `value` with a type tag, deserialize it as `Box<P>`, and panic
+/// with a description of the first difference if the result is not equal to
+/// `value`. See [`check_roundtrip()`].
+///
+/// # Panics
+///
+/// Panics if the round trip fails.
+// Taken by value so that a value can be built in place in the call.
+#[allow(clippy::needless_pass_by_value)]
+pub fn assert_roundtrip<P, T>(value: T, registry: &TypeRegistry)
+where
+    P: crate::DynamicTrait + ?Sized,
+    T: Reflect,
+{
+    if let Err(err) = check_roundtrip::<P>(value.as_reflect(), registry) {
+        panic!("{err}");
+    }
+}
+
+/// Serialize `value` with a type tag, deserialize it as `Box<P>` with
+/// `registry`, and check that the result is equal to `value`.
+///
+/// The round trip is done with the crate's compact binary encoding (as used by
+/// [`stream`](super::stream)), and with JSON if the "json" crate feature is
+/// enabled. Values are compared with [`Reflect::reflect_partial_eq()`], field
+/// by field, so that the error points to the first field that differs.
+///
+/// # Errors
+///
+/// Fails if `value` cannot be serialized or deserialized in one of the
+/// formats, or if the result differs from `value`.
+pub fn check_roundtrip<P>(
+    value: &dyn Reflect,
+    registry: &TypeRegistry,
+) -> Result<(), RoundtripError>
+where
+    P: crate::DynamicTrait + ?Sized,
+{
+    registry.set_current(|| {
+        let encoded =
+            to_value(&SerializeTagged(value)).map_err(|err| RoundtripError::Serialize {
+                format: "binary",
+                message: err.to_string(),
+            })?;
+        let mut bytes = Vec::new();
+        write_value(&encoded, &mut bytes);
+        let decoded = read_value(&bytes)
+            .and_then(|value| {
+                Box::<P>::deserialize_dyn(ValueDeserializer::<ValueError>::new(value))
+            })
+            .map_err(|err| RoundtripError::Deserialize {
+                format: "binary",
+                message: err.to_string(),
+            })?;
+        compare("binary", value, (*decoded).as_reflect())?;
+
+        #[cfg(feature = "json")]
+        {
+            let json = serde_json::to_string(&SerializeTagged(value)).map_err(|err| {
+                RoundtripError::Serialize {
+                    format: "JSON",
+                    message: err.to_string(),
+                }
+            })?;
+            let decoded = Box::<P>::deserialize_dyn(&mut serde_json::Deserializer::from_str(&json))
+                .map_err(|err| RoundtripError::Deserialize {
+                    format: "JSON",
+                    message: err.to_string(),
+                })?;
+            compare("JSON", value, (*decoded).as_reflect())?;
+        }
+
+        Ok(())
+    })
+}
+
+fn compare(
+    format: &'static str,
+    expected: &dyn Reflect,
+    actual: &dyn Reflect,
+) -> Result<(), RoundtripError> {
+    if expected.reflect_partial_eq(actual) == Some(true) {
+        return Ok(());
+    }
+    let mut path = String::new();
+    let (expected, actual) = first_difference(expected, actual, &mut path);
+    Err(RoundtripError::Mismatch {
+        format,
+        path,
+        expected: format!("{expected:?}"),
+        actual: format!("{actual:?}"),
+    })
+}
+
+/// Find the innermost values that differ between `a` and `b`, which are known
+/// to be different, and write their path to `path`.
+fn first_difference<'a>(
+    a: &'a dyn Reflect,
+    b: &'a dyn Reflect,
+    path: &mut String,
+) -> (&'a dyn Reflect, &'a dyn Reflect) {
+    let differs = |a: &dyn Reflect, b: &dyn Reflect| a.reflect_partial_eq(b) != Some(true);
+    let child = match (a.reflect_ref(), b.reflect_ref()) {
+        _ if a.type_name() != b.type_name() => None,
+        (ReflectRef::Struct(a), ReflectRef::Struct(b)) => (0..a.field_len()).find_map(|index| {
+            let name = a.name_at(index)?;
+            let (a, b) = (a.field_at(index)?, b.field(name)?);
+            differs(a, b).then(|| {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(name);
+                (a, b)
+            })
+        }),
+        (ReflectRef::TupleStruct(a), ReflectRef::TupleStruct(b)) => {
+            (0..a.field_len()).find_map(|index| {
+                let (a, b) = (a.field(index)?, b.field(index)?);
+                differs(a, b).then(|| {
+                    let _ = write!(path, ".{index}");
+                    (a, b)
+                })
+            })
+        }
+        (ReflectRef::Tuple(a), ReflectRef::Tuple(b)) => (0..a.field_len()).find_map(|index| {
+            let (a, b) = (a.field(index)?, b.field(index)?);
+            differs(a, b).then(|| {
+                let _ = write!(path, ".{index}");
+                (a, b)
+            })
+        }),
+        (ReflectRef::List(a), ReflectRef::List(b)) if a.len() == b.len() => {
+            (0..a.len()).find_map(|index| {
+                let (a, b) = (a.get(index)?, b.get(index)?);
+                differs(a, b).then(|| {
+                    let _ = write!(path, "[{index}]");
+                    (a, b)
+                })
+            })
+        }
+        (ReflectRef::Array(a), ReflectRef::Array(b)) if a.len() == b.len() => (0..a.len())
+            .find_map(|index| {
+                let (a, b) = (a.get(index)?, b.get(index)?);
+                differs(a, b).then(|| {
+                    let _ = write!(path, "[{index}]");
+                    (a, b)
+                })
+            }),
+        (ReflectRef::Map(a), ReflectRef::Map(b)) if a.len() == b.len() => {
+            a.iter().find_map(|(key, a)| {
+                let b = b.get(key)?;
+                differs(a, b).then(|| {
+                    let _ = write!(path, "[{key:?}]");
+                    (a, b)
+                })
+            })
+        }
+        _ => None,
+    };
+
+    match child {
+        Some((a, b)) => first_difference(a, b, path),
+        None => (a, b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{ReflectDeserialize, ReflectSerialize};
+
+    use super::*;
+
+    #[derive(Clone, Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Serialize, Deserialize)]
+    struct Lossy {
+        kept: Vec<u32>,
+        #[serde(skip)]
+        dropped: u32,
+    }
+
+    #[test]
+    fn mismatch_path() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Lossy>();
+
+        let lossy = Lossy {
+            kept: vec![1, 2],
+            dropped: 0,
+        };
+        assert!(check_roundtrip::<dyn Reflect>(&lossy, &registry).is_ok());
+
+        let lossy = Lossy {
+            kept: vec![1, 2],
+            dropped: 5,
+        };
+        let err = check_roundtrip::<dyn Reflect>(&lossy, &registry).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "value changed in binary round trip at 'dropped': expected 5, found 0"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use super::value::Value;
+
+scoped_tls::scoped_thread_local!(static TOML_LAYOUT: ());
+
+/// The key of the payload in the tables written in the TOML layout.
+const ITEM: &str = "item";
+
+/// Use a layout of tagged objects that TOML can represent while running `f`.
+///
+/// TOML has no null or unit value, and a document cannot hold a bare
+/// sequence. In the TOML layout, the `value` of a tagged object that is not a
+/// struct (see [`serialize()`](super::serialize())) is written as a table,
+/// with the payload under the key `item`. If the payload is a sequence, `value`
+/// is an array of tables instead, one per element. A table without `item`
+/// stands for a unit or `None` value, which TOML leaves out.
+///
+/// The layout applies to both serialization and deserialization, so data
+/// written in the TOML layout must be read in the TOML layout as well.
+///
+/// ```toml
+/// [[rules]]
+/// type = "MinLength"
+/// [rules.value]
+/// item = 3
+///
+/// [[rules]]
+/// type = "OneOf"
+/// [[rules.value]]
+/// item = "red"
+/// [[rules.value]]
+/// item = "blue"
+/// ```
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, serialization::with_toml_layout};
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Serialize, Deserialize)]
+/// struct OneOf(Vec<String>);
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Config {
+///     #[serde(with = "reflect_steroids::serialization")]
+///     rule: Box<dyn Reflect>,
+/// }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<OneOf>();
+///
+/// let config = Config { rule: Box::new(OneOf(vec!["red".into(), "blue".into()])) };
+/// registry.set_current(|| {
+///     with_toml_layout(|| {
+///         let json = serde_json::to_string(&config).unwrap();
+///         assert_eq!(
+///             json,
+///             r#"{"rule":{"type":"OneOf","value":[{"item":"red"},{"item":"blue"}]}}"#
+///         );
+///
+///         let config: Config = serde_json::from_str(&json).unwrap();
+///         assert_eq!(config.rule.downcast_ref::<OneOf>().unwrap().0, ["red", "blue"]);
+///     });
+/// });
+/// ```
+pub fn with_toml_layout<F: FnOnce() -> R, R>(f: F) -> R {
+    TOML_LAYOUT.set(&(), f)
+}
+
+/// True if the TOML layout is enabled.
+pub(crate) fn is_toml_layout() -> bool {
+    TOML_LAYOUT.is_set()
+}
+
+/// Nest the payload of a tagged object in a table, or a sequence in an array
+/// of tables. Unit and `None` values are left out, and newtypes are unwrapped.
+pub(crate) fn nest(value: Value) -> Value {
+    let table = |value| match value {
+        Value::Unit | Value::None => Value::Map(Vec::new()),
+        value => Value::Map(vec![(Value::Str(ITEM), value)]),
+    };
+    match value {
+        Value::Newtype(inner) => nest(*inner),
+        Value::Seq(elements) => Value::Seq(elements.into_iter().map(table).collect()),
+        value => table(value),
+    }
+}
+
+/// Undo [`nest()`].
+pub(crate) fn unnest(value: Value) -> Value {
+    let item = |value| match value {
+        Value::Map(entries) => entries
+            .into_iter()
+            .find(|(key, _)| key.as_str() == Some(ITEM))
+            .map_or(Value::Unit, |(_, value)| value),
+        value => value,
+    };
+    match value {
+        Value::Seq(elements) => Value::Seq(elements.into_iter().map(item).collect()),
+        value => item(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use bevy_reflect::TypeRegistry;
+
+    #[reflect_trait]
+    trait Rule: DowncastReflect {}
+    impl_dynamic_trait!(Rule, ReflectRule);
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Rule, Serialize, Deserialize)]
+    struct MinLength(u32);
+    impl Rule for MinLength {}
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Rule, Serialize, Deserialize)]
+    struct OneOf(Vec<String>);
+    impl Rule for OneOf {}
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Rule, Serialize, Deserialize)]
+    struct MaxCount(Option<u32>);
+    impl Rule for MaxCount {}
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Config {
+        rules: Vec<Box<dyn Rule>>,
+    }
+
+    #[test]
+    fn toml_roundtrip() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<MinLength>();
+        registry.register::<OneOf>();
+        registry.register::<MaxCount>();
+
+        let config = Config {
+            rules: vec![
+                Box::new(MinLength(3)),
+                Box::new(OneOf(vec!["red".into(), "blue".into()])),
+                Box::new(MaxCount(None)),
+            ],
+        };
+        registry.set_current(|| {
+            assert!(toml::to_string(&config).is_err());
+
+            let toml = with_toml_layout(|| toml::to_string(&config)).unwrap();
+            let config: Config = with_toml_layout(|| toml::from_str(&toml)).unwrap();
+            assert_eq!(config.rules[0].downcast_ref::<MinLength>().unwrap().0, 3);
+            assert_eq!(
+                config.rules[1].downcast_ref::<OneOf>().unwrap().0,
+                ["red", "blue"]
+            );
+            assert_eq!(config.rules[2].downcast_ref::<MaxCount>().unwrap().0, None);
+        });
+    }
+}
@@ -0,0 +1,110 @@
+use std::any::TypeId;
+
+use bevy_reflect::{
+    DynamicTupleStruct, FromReflect, FromType, Reflect, ReflectRef, TypeInfo, Typed,
+};
+
+/// Type data for serializing a tuple struct with a single field, such as
+/// `Meters(f32)`, as its field.
+///
+/// The field is serialized in the `value` field of the tagged object, with the
+/// `Serialize` implementation of the field's type:
+/// `{"type":"Meters","value":1.5}`. When deserializing, the field is
+/// deserialized with the `Deserialize` implementation of its type, and the
+/// wrapper is built with `FromReflect`. The wrapper itself does not need to
+/// implement `Serialize` or `Deserialize`.
+///
+/// Register with
+/// [`TypeRegistryExt::register_serialize_transparent()`](crate::TypeRegistryExt::register_serialize_transparent).
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+/// #[reflect_trait]
+/// trait Unit: DowncastReflect {}
+/// impl_dynamic_trait!(Unit, ReflectUnit);
+///
+/// #[derive(Debug, PartialEq, Reflect, FromReflect)]
+/// #[reflect(Unit)]
+/// struct Meters(f32);
+/// impl Unit for Meters {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register_serialize_transparent::<Meters>();
+///
+/// let length: Box<dyn Unit> = Box::new(Meters(1.5));
+/// registry.set_current(|| {
+///     let json = serde_json::to_string(&length).unwrap();
+///     assert_eq!(json, r#"{"type":"Meters","value":1.5}"#);
+///
+///     let deserialized: Box<dyn Unit> = serde_json::from_str(&json).unwrap();
+///     assert_eq!(deserialized.downcast_ref::<Meters>(), Some(&Meters(1.5)));
+/// });
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct SerializeTransparent {
+    field_type_id: TypeId,
+    field_type_name: &'static str,
+    from_field: FromFieldFn,
+}
+
+type FromFieldFn = fn(Box<dyn Reflect>) -> Option<Box<dyn Reflect>>;
+
+impl SerializeTransparent {
+    /// The [`TypeId`] of the field.
+    #[must_use]
+    pub fn field_type_id(&self) -> TypeId {
+        self.field_type_id
+    }
+
+    /// The type name of the field.
+    #[must_use]
+    pub fn field_type_name(&self) -> &'static str {
+        self.field_type_name
+    }
+
+    /// Build the wrapper from a value of its field. Returns `None` if `field`
+    /// has the wrong type.
+    #[must_use]
+    pub fn from_field(&self, field: Box<dyn Reflect>) -> Option<Box<dyn Reflect>> {
+        (self.from_field)(field)
+    }
+
+    /// Get the field of `wrapper`, if it is a tuple struct with a single
+    /// field.
+    pub fn field(wrapper: &dyn Reflect) -> Option<&dyn Reflect> {
+        match wrapper.reflect_ref() {
+            ReflectRef::TupleStruct(tuple_struct) if tuple_struct.field_len() == 1 => {
+                tuple_struct.field(0)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<T: Typed + FromReflect> FromType<T> for SerializeTransparent {
+    /// # Panics
+    ///
+    /// Panics if `T` is not a tuple struct with a single field.
+    fn from_type() -> Self {
+        let field = match T::type_info() {
+            TypeInfo::TupleStruct(info) if info.field_len() == 1 => {
+                info.field_at(0).expect("tuple struct has one field")
+            }
+            _ => panic!(
+                "`SerializeTransparent` requires a tuple struct with a single field, but `{}` is not",
+                std::any::type_name::<T>()
+            ),
+        };
+
+        SerializeTransparent {
+            field_type_id: field.type_id(),
+            field_type_name: field.type_name(),
+            from_field: |field| {
+                let mut dynamic = DynamicTupleStruct::default();
+                dynamic.insert_boxed(field);
+                T::from_reflect(&dynamic).map(|wrapper| Box::new(wrapper) as Box<dyn Reflect>)
+            },
+        }
+    }
+}
@@ -6,44 +6,139 @@ use serde::de::{
     IntoDeserializer, MapAccess, SeqAccess, Unexpected, VariantAccess, Visitor,
 };
 use serde::forward_to_deserialize_any;
+use serde::ser::{self, Serialize, SerializeMap, Serializer};
 use std::fmt;
 use std::marker::PhantomData;
 
-// Used from generated code to buffer the contents of the Deserializer when
-// deserializing internally and adjacently tagged trait objects.
-#[derive(Debug)]
+/// A buffered, self-describing serde value.
+///
+/// This is used internally to buffer the contents of a deserializer when
+/// deserializing tagged trait objects, where the type tag may appear after the
+/// fields it describes. See the [module documentation](super::raw) for the
+/// stability guarantees.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
 pub enum Value<'de> {
+    /// A `bool`.
     Bool(bool),
 
+    /// A `u8`.
     U8(u8),
+    /// A `u16`.
     U16(u16),
+    /// A `u32`.
     U32(u32),
+    /// A `u64`.
     U64(u64),
 
+    /// An `i8`.
     I8(i8),
+    /// An `i16`.
     I16(i16),
+    /// An `i32`.
     I32(i32),
+    /// An `i64`.
     I64(i64),
 
+    /// An `f32`.
     F32(f32),
+    /// An `f64`.
     F64(f64),
 
+    /// A `char`.
     Char(char),
+    /// An owned string.
     String(String),
+    /// A string borrowed from the input.
     Str(&'de str),
+    /// An owned byte array.
     ByteBuf(Vec<u8>),
+    /// A byte array borrowed from the input.
     Bytes(&'de [u8]),
 
+    /// An absent optional value.
     None,
+    /// A present optional value.
     Some(Box<Value<'de>>),
 
+    /// The unit value `()`, or a unit struct.
     Unit,
+    /// A newtype struct.
     Newtype(Box<Value<'de>>),
+    /// A sequence, tuple, or tuple struct.
     Seq(Vec<Value<'de>>),
+    /// A map or struct, in the order the entries appeared.
     Map(Vec<(Value<'de>, Value<'de>)>),
 }
 
 impl<'de> Value<'de> {
+    /// Create a map value.
+    pub fn map<K: Into<Value<'de>>>(entries: impl IntoIterator<Item = (K, Value<'de>)>) -> Self {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.into(), value))
+                .collect(),
+        )
+    }
+
+    /// Create a sequence value.
+    pub fn seq(elements: impl IntoIterator<Item = Value<'de>>) -> Self {
+        Value::Seq(elements.into_iter().collect())
+    }
+
+    /// Get the string contents of a `String` or `Str` value.
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref s) => Some(s),
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Get the elements of a `Seq` value.
+    #[must_use]
+    pub fn as_seq(&self) -> Option<&[Value<'de>]> {
+        match *self {
+            Value::Seq(ref elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// Copy any data borrowed from the input, detaching the value from the
+    /// lifetime of the input.
+    pub fn into_owned(self) -> Value<'static> {
+        match self {
+            Value::Bool(b) => Value::Bool(b),
+            Value::U8(n) => Value::U8(n),
+            Value::U16(n) => Value::U16(n),
+            Value::U32(n) => Value::U32(n),
+            Value::U64(n) => Value::U64(n),
+            Value::I8(n) => Value::I8(n),
+            Value::I16(n) => Value::I16(n),
+            Value::I32(n) => Value::I32(n),
+            Value::I64(n) => Value::I64(n),
+            Value::F32(f) => Value::F32(f),
+            Value::F64(f) => Value::F64(f),
+            Value::Char(c) => Value::Char(c),
+            Value::String(s) => Value::String(s),
+            Value::Str(s) => Value::String(s.to_owned()),
+            Value::ByteBuf(b) => Value::ByteBuf(b),
+            Value::Bytes(b) => Value::ByteBuf(b.to_owned()),
+            Value::None => Value::None,
+            Value::Some(v) => Value::Some(Box::new(v.into_owned())),
+            Value::Unit => Value::Unit,
+            Value::Newtype(v) => Value::Newtype(Box::new(v.into_owned())),
+            Value::Seq(v) => Value::Seq(v.into_iter().map(Value::into_owned).collect()),
+            Value::Map(v) => Value::Map(
+                v.into_iter()
+                    .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                    .collect(),
+            ),
+        }
+    }
+
     fn unexpected(&self) -> Unexpected {
         match *self {
             Value::Bool(b) => Unexpected::Bool(b),
@@ -71,11 +166,98 @@ impl<'de> Value<'de> {
     }
 }
 
+impl Serialize for Value<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            Value::Bool(b) => serializer.serialize_bool(b),
+            Value::U8(n) => serializer.serialize_u8(n),
+            Value::U16(n) => serializer.serialize_u16(n),
+            Value::U32(n) => serializer.serialize_u32(n),
+            Value::U64(n) => serializer.serialize_u64(n),
+            Value::I8(n) => serializer.serialize_i8(n),
+            Value::I16(n) => serializer.serialize_i16(n),
+            Value::I32(n) => serializer.serialize_i32(n),
+            Value::I64(n) => serializer.serialize_i64(n),
+            Value::F32(f) => serializer.serialize_f32(f),
+            Value::F64(f) => serializer.serialize_f64(f),
+            Value::Char(c) => serializer.serialize_char(c),
+            Value::String(ref s) => serializer.serialize_str(s),
+            Value::Str(s) => serializer.serialize_str(s),
+            Value::ByteBuf(ref b) => serializer.serialize_bytes(b),
+            Value::Bytes(b) => serializer.serialize_bytes(b),
+            Value::None => serializer.serialize_none(),
+            Value::Some(ref v) => serializer.serialize_some(&**v),
+            Value::Unit => serializer.serialize_unit(),
+            Value::Newtype(ref v) => serializer.serialize_newtype_struct("Value", &**v),
+            Value::Seq(ref v) => serializer.collect_seq(v),
+            Value::Map(ref v) => {
+                let mut map = serializer.serialize_map(Some(v.len()))?;
+                for (k, v) in v {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($($t:ty => $variant:ident,)*) => {
+        $(
+            impl<'de> From<$t> for Value<'de> {
+                fn from(v: $t) -> Self {
+                    Value::$variant(v)
+                }
+            }
+        )*
+    };
+}
+
+impl_from! {
+    bool => Bool,
+    u8 => U8,
+    u16 => U16,
+    u32 => U32,
+    u64 => U64,
+    i8 => I8,
+    i16 => I16,
+    i32 => I32,
+    i64 => I64,
+    f32 => F32,
+    f64 => F64,
+    char => Char,
+    String => String,
+    &'de str => Str,
+    Vec<u8> => ByteBuf,
+    &'de [u8] => Bytes,
+}
+
+/// Error produced when converting to or from [`Value`].
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct ValueError(String);
+
+impl ser::Error for ValueError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        ValueError(msg.to_string())
+    }
+}
+
+impl de::Error for ValueError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        ValueError(msg.to_string())
+    }
+}
+
 impl<'de> Deserialize<'de> for Value<'de> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
+        let _nesting = super::limits::enter::<D::Error>()?;
         // Untagged and internally tagged enums are only supported in
         // self-describing formats.
         let visitor = ValueVisitor { value: PhantomData };
@@ -263,9 +445,20 @@ impl<'de> Visitor<'de> for ValueVisitor<'de> {
     where
         V: MapAccess<'de>,
     {
-        let mut vec = Vec::new();
-        while let Some(kv) = visitor.next_entry()? {
-            vec.push(kv);
+        let duplicate_keys = super::de::allows_duplicate_keys();
+        let mut vec: Vec<(Value<'de>, Value<'de>)> = Vec::new();
+        while let Some((key, value)) = visitor.next_entry::<Value<'de>, Value<'de>>()? {
+            // Last wins, if duplicate keys are allowed. See `with_duplicate_keys()`.
+            let existing = match key.as_str() {
+                Some(name) if duplicate_keys => {
+                    vec.iter().position(|(k, _)| k.as_str() == Some(name))
+                }
+                _ => None,
+            };
+            match existing {
+                Some(index) => vec[index].1 = value,
+                None => vec.push((key, value)),
+            }
         }
         Ok(Value::Map(vec))
     }
@@ -280,6 +473,11 @@ impl<'de> Visitor<'de> for ValueVisitor<'de> {
     }
 }
 
+/// A [`Deserializer`] reading from a [`Value`].
+///
+/// Usually obtained with [`IntoDeserializer::into_deserializer()`]. The error
+/// type `E` is chosen by the caller, so the deserializer can be used from
+/// within the `Deserialize` implementation of another format.
 pub struct ValueDeserializer<'de, E> {
     value: Value<'de>,
     err: PhantomData<E>,
@@ -723,7 +921,9 @@ where
 }
 
 impl<'de, E> ValueDeserializer<'de, E> {
-    fn new(value: Value<'de>) -> Self {
+    /// Create a deserializer reading from `value`.
+    #[must_use]
+    pub fn new(value: Value<'de>) -> Self {
         ValueDeserializer {
             value,
             err: PhantomData,
@@ -0,0 +1,389 @@
+//! A [`Serializer`](serde::Serializer) producing a [`Value`], used to buffer
+//! the serialized representation of an object so it can be inspected or
+//! transformed before it is written.
+
+use serde::ser::{self, Serialize};
+
+use super::value::{Value, ValueError};
+
+/// Serialize `value` into a [`Value`].
+///
+/// # Errors
+///
+/// Fails if the `Serialize` implementation of `value` fails.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value<'static>, ValueError> {
+    value.serialize(ValueSerializer)
+}
+
+pub(crate) struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value<'static>;
+    type Error = ValueError;
+
+    type SerializeSeq = SerializeSeq;
+    type SerializeTuple = SerializeSeq;
+    type SerializeTupleStruct = SerializeSeq;
+    type SerializeTupleVariant = SerializeTupleVariant;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeStructVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::I8(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::I16(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::I32(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::I64(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::U8(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::U16(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::U32(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::U64(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::F32(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::F64(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Char(v))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::String(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::ByteBuf(v.to_owned()))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Some(Box::new(to_value(value)?)))
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Str(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Newtype(Box::new(to_value(value)?)))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Map(vec![(Value::Str(variant), to_value(value)?)]))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(SerializeSeq {
+            elements: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Ok(SerializeTupleVariant {
+            variant,
+            elements: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(SerializeMap {
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(SerializeStructVariant {
+            variant,
+            fields: Vec::with_capacity(len),
+        })
+    }
+}
+
+pub(crate) struct SerializeSeq {
+    elements: Vec<Value<'static>>,
+}
+
+impl ser::SerializeSeq for SerializeSeq {
+    type Ok = Value<'static>;
+    type Error = ValueError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.elements.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value<'static>, ValueError> {
+        Ok(Value::Seq(self.elements))
+    }
+}
+
+impl ser::SerializeTuple for SerializeSeq {
+    type Ok = Value<'static>;
+    type Error = ValueError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value<'static>, ValueError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeSeq {
+    type Ok = Value<'static>;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value<'static>, ValueError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+pub(crate) struct SerializeTupleVariant {
+    variant: &'static str,
+    elements: Vec<Value<'static>>,
+}
+
+impl ser::SerializeTupleVariant for SerializeTupleVariant {
+    type Ok = Value<'static>;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.elements.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value<'static>, ValueError> {
+        Ok(Value::Map(vec![(
+            Value::Str(self.variant),
+            Value::Seq(self.elements),
+        )]))
+    }
+}
+
+pub(crate) struct SerializeMap {
+    entries: Vec<(Value<'static>, Value<'static>)>,
+    key: Option<Value<'static>>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Value<'static>;
+    type Error = ValueError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), ValueError> {
+        self.key = Some(to_value(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| ser::Error::custom("serialize_value called before serialize_key"))?;
+        self.entries.push((key, to_value(value)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value<'static>, ValueError> {
+        Ok(Value::Map(self.entries))
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = Value<'static>;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ValueError> {
+        self.entries.push((Value::Str(key), to_value(value)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value<'static>, ValueError> {
+        Ok(Value::Map(self.entries))
+    }
+}
+
+pub(crate) struct SerializeStructVariant {
+    variant: &'static str,
+    fields: Vec<(Value<'static>, Value<'static>)>,
+}
+
+impl ser::SerializeStructVariant for SerializeStructVariant {
+    type Ok = Value<'static>;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ValueError> {
+        self.fields.push((Value::Str(key), to_value(value)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value<'static>, ValueError> {
+        Ok(Value::Map(vec![(
+            Value::Str(self.variant),
+            Value::Map(self.fields),
+        )]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(serde::Serialize)]
+    enum Command {
+        Stop,
+        Wait(u32),
+        Move(i8, i8),
+        Say { text: &'static str },
+    }
+
+    #[derive(serde::Serialize)]
+    struct Script {
+        name: &'static str,
+        steps: Vec<Command>,
+        retries: Option<u8>,
+    }
+
+    #[test]
+    fn structure() {
+        let value = to_value(&Script {
+            name: "patrol",
+            steps: vec![
+                Command::Stop,
+                Command::Wait(3),
+                Command::Move(-1, 2),
+                Command::Say { text: "hi" },
+            ],
+            retries: Some(2),
+        })
+        .unwrap();
+
+        let expected = Value::map([
+            ("name", Value::String("patrol".into())),
+            (
+                "steps",
+                Value::seq([
+                    Value::Str("Stop"),
+                    Value::map([("Wait", Value::U32(3))]),
+                    Value::map([("Move", Value::seq([Value::I8(-1), Value::I8(2)]))]),
+                    Value::map([("Say", Value::map([("text", Value::String("hi".into()))]))]),
+                ]),
+            ),
+            ("retries", Value::Some(Box::new(Value::U8(2)))),
+        ]);
+        assert_eq!(format!("{value:?}"), format!("{expected:?}"));
+    }
+
+    #[test]
+    fn map_value_before_key() {
+        use serde::ser::{SerializeMap as _, Serializer as _};
+
+        let mut map = ValueSerializer.serialize_map(None).unwrap();
+        let err = map.serialize_value(&1).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "serialize_value called before serialize_key"
+        );
+    }
+}
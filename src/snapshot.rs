@@ -0,0 +1,326 @@
+use std::any::Any;
+
+use bevy_reflect::{Reflect, TypeRegistry};
+
+use crate::{check_apply, ReflectFromDynamic, SnapshotError};
+
+/// Owned copies of a set of reflected objects, which can be restored to the
+/// objects later, such as for rollback networking.
+///
+/// Objects are copied with their [`ReflectFromDynamic`] type data, if they
+/// have it in the registry, so that copies are values of the concrete types.
+/// Otherwise, copies are dynamic values (see [`Reflect::clone_value()`]).
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, ReflectFromDynamic, Snapshot};
+/// #[reflect_trait]
+/// trait Entity: DowncastReflect {}
+/// impl_dynamic_trait!(Entity, ReflectEntity);
+///
+/// #[derive(Reflect, FromReflect)]
+/// #[reflect(Entity, FromDynamic)]
+/// struct Ball { x: f32, trail: Vec<f32> }
+/// impl Entity for Ball {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Ball>();
+///
+/// let mut world: Vec<Box<dyn Entity>> = vec![Box::new(Ball { x: 0.0, trail: vec![] })];
+/// let snapshot = Snapshot::capture(world.iter().map(|e| e.as_reflect()), &registry);
+///
+/// let ball = world[0].downcast_mut::<Ball>().unwrap();
+/// ball.trail.push(ball.x);
+/// ball.x = 1.0;
+///
+/// snapshot
+///     .restore(world.iter_mut().map(|e| e.as_reflect_mut()), &registry)
+///     .unwrap();
+/// let ball = world[0].downcast_ref::<Ball>().unwrap();
+/// assert_eq!((ball.x, ball.trail.len()), (0.0, 0));
+/// ```
+pub struct Snapshot {
+    objects: Vec<Box<dyn Reflect>>,
+}
+
+/// The objects that changed between two [`Snapshot`]s, with copies of their
+/// newer versions. See [`Snapshot::diff()`].
+pub struct SnapshotDiff {
+    len: usize,
+    changed: Vec<(usize, Box<dyn Reflect>)>,
+}
+
+impl Snapshot {
+    /// Copy `objects`.
+    pub fn capture<'a>(
+        objects: impl IntoIterator<Item = &'a dyn Reflect>,
+        registry: &TypeRegistry,
+    ) -> Self {
+        Snapshot {
+            objects: objects
+                .into_iter()
+                .map(|object| copy(object, registry))
+                .collect(),
+        }
+    }
+
+    /// The number of objects in the snapshot.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// True if the snapshot has no objects.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// The copy of the object at `index`.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&dyn Reflect> {
+        self.objects.get(index).map(|object| &**object)
+    }
+
+    /// Restore the copies to `objects`, in order.
+    ///
+    /// Objects of the same concrete type as their copies are replaced by
+    /// copies of the copies, so they become equal to them, even if they have
+    /// grown since the snapshot was captured. Otherwise, the copy is applied
+    /// to the object (see [`Reflect::apply()`]).
+    ///
+    /// # Errors
+    ///
+    /// Fails if the number of objects differs from the snapshot, or if any
+    /// copy cannot be applied to its object. Nothing is restored then.
+    pub fn restore<'a>(
+        &self,
+        objects: impl IntoIterator<Item = &'a mut dyn Reflect>,
+        registry: &TypeRegistry,
+    ) -> Result<(), SnapshotError> {
+        let mut objects: Vec<&mut dyn Reflect> = objects.into_iter().collect();
+        if objects.len() != self.objects.len() {
+            return Err(SnapshotError::CountMismatch {
+                snapshot: self.objects.len(),
+                objects: objects.len(),
+            });
+        }
+        for (index, (object, saved)) in objects.iter().zip(&self.objects).enumerate() {
+            if !same_type(&**object, &**saved) {
+                check_apply(&**object, &**saved)
+                    .map_err(|error| SnapshotError::Apply { index, error })?;
+            }
+        }
+
+        for (object, saved) in objects.iter_mut().zip(&self.objects) {
+            if same_type(&**object, &**saved) {
+                let _ = object.set(copy(&**saved, registry));
+            } else {
+                object.apply(&**saved);
+            }
+        }
+        Ok(())
+    }
+
+    /// The objects that differ between `self` and `newer`, compared with
+    /// [`Reflect::reflect_partial_eq()`]. Objects that cannot be compared are
+    /// considered changed, as are objects that are only in `newer`.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use reflect_steroids::{prelude::*, reflect::{Reflect, TypeRegistry}, Snapshot};
+    /// let registry = TypeRegistry::default();
+    /// let mut scores = [1u32, 2, 3];
+    /// let before = Snapshot::capture(scores.iter().map(|s| s as &dyn Reflect), &registry);
+    /// scores[1] = 20;
+    /// let after = Snapshot::capture(scores.iter().map(|s| s as &dyn Reflect), &registry);
+    ///
+    /// let diff = before.diff(&after, &registry);
+    /// assert_eq!(diff.changed().collect::<Vec<_>>(), [1]);
+    ///
+    /// let mut rebuilt = before;
+    /// rebuilt.apply_diff(&diff, &registry);
+    /// assert_eq!(rebuilt.get(1).unwrap().downcast_ref::<u32>(), Some(&20));
+    /// ```
+    #[must_use]
+    pub fn diff(&self, newer: &Snapshot, registry: &TypeRegistry) -> SnapshotDiff {
+        let changed = newer
+            .objects
+            .iter()
+            .enumerate()
+            .filter(|(index, object)| {
+                self.objects
+                    .get(*index)
+                    .and_then(|old| old.reflect_partial_eq(&***object))
+                    != Some(true)
+            })
+            .map(|(index, object)| (index, copy(&**object, registry)))
+            .collect();
+        SnapshotDiff {
+            len: newer.objects.len(),
+            changed,
+        }
+    }
+
+    /// Update `self` to the newer snapshot that `diff` was computed against.
+    pub fn apply_diff(&mut self, diff: &SnapshotDiff, registry: &TypeRegistry) {
+        self.objects.truncate(diff.len);
+        for (index, object) in &diff.changed {
+            let object = copy(&**object, registry);
+            match self.objects.get_mut(*index) {
+                Some(slot) => *slot = object,
+                None => self.objects.push(object),
+            }
+        }
+    }
+}
+
+impl SnapshotDiff {
+    /// The indices of the changed objects, in order.
+    pub fn changed(&self) -> impl Iterator<Item = usize> + '_ {
+        self.changed.iter().map(|(index, _)| *index)
+    }
+
+    /// True if no objects changed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty()
+    }
+}
+
+/// Copy `object`, with `FromReflect` if possible.
+fn copy(object: &dyn Reflect, registry: &TypeRegistry) -> Box<dyn Reflect> {
+    registry
+        .get_with_name(object.type_name())
+        .and_then(|registration| registration.data::<ReflectFromDynamic>())
+        .and_then(|from_dynamic| from_dynamic.from_reflect(object))
+        .unwrap_or_else(|| object.clone_value())
+}
+
+fn same_type(a: &dyn Reflect, b: &dyn Reflect) -> bool {
+    Any::type_id(a.as_any()) == Any::type_id(b.as_any())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{reflect_trait, FromReflect};
+
+    use super::*;
+    use crate::{impl_dynamic_trait, ApplyError, DowncastReflect};
+
+    #[reflect_trait]
+    trait Unit: DowncastReflect {}
+    impl_dynamic_trait!(Unit, ReflectUnit);
+
+    #[derive(Reflect, FromReflect, Debug, PartialEq)]
+    #[reflect(Unit, FromDynamic)]
+    struct Soldier {
+        hp: i32,
+        orders: Vec<String>,
+    }
+    impl Unit for Soldier {}
+
+    #[derive(Reflect, Debug, PartialEq)]
+    #[reflect(Unit)]
+    struct Tower {
+        level: u8,
+    }
+    impl Unit for Tower {}
+
+    fn army() -> Vec<Box<dyn Unit>> {
+        vec![
+            Box::new(Soldier {
+                hp: 10,
+                orders: vec!["hold".into()],
+            }),
+            Box::new(Tower { level: 1 }),
+        ]
+    }
+
+    #[test]
+    fn capture_and_restore() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Soldier>();
+        registry.register::<Tower>();
+
+        let mut units = army();
+        let snapshot = Snapshot::capture(units.iter().map(Reflect::as_reflect), &registry);
+        assert_eq!(snapshot.len(), 2);
+        assert!(snapshot.get(0).unwrap().is::<Soldier>());
+        assert!(!snapshot.get(1).unwrap().is::<Tower>());
+
+        let soldier = units[0].downcast_mut::<Soldier>().unwrap();
+        soldier.hp = 3;
+        soldier.orders.push("charge".into());
+        units[1].downcast_mut::<Tower>().unwrap().level = 2;
+
+        assert_eq!(
+            snapshot.restore(
+                units.iter_mut().take(1).map(Reflect::as_reflect_mut),
+                &registry
+            ),
+            Err(SnapshotError::CountMismatch {
+                snapshot: 2,
+                objects: 1
+            })
+        );
+        snapshot
+            .restore(units.iter_mut().map(Reflect::as_reflect_mut), &registry)
+            .unwrap();
+        let restored = army();
+        assert_eq!(
+            units[0].downcast_ref::<Soldier>(),
+            restored[0].downcast_ref()
+        );
+        assert_eq!(units[1].downcast_ref::<Tower>(), restored[1].downcast_ref());
+
+        let mut soldier = Soldier {
+            hp: 1,
+            orders: vec![],
+        };
+        let mut level = 1u8;
+        let err = snapshot
+            .restore([&mut soldier as &mut dyn Reflect, &mut level], &registry)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            SnapshotError::Apply {
+                index: 1,
+                error: ApplyError::MismatchedKinds { .. }
+            }
+        ));
+        assert_eq!(soldier.hp, 1);
+    }
+
+    #[test]
+    fn diffs() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Soldier>();
+
+        let mut units = army();
+        let first = Snapshot::capture(units.iter().map(Reflect::as_reflect), &registry);
+        units[0].downcast_mut::<Soldier>().unwrap().hp = 5;
+        units.push(Box::new(Tower { level: 3 }));
+        let second = Snapshot::capture(units.iter().map(Reflect::as_reflect), &registry);
+
+        let diff = first.diff(&second, &registry);
+        assert_eq!(diff.changed().collect::<Vec<_>>(), [0, 2]);
+        assert!(second.diff(&second, &registry).is_empty());
+
+        let mut rebuilt = Snapshot::capture(army().iter().map(Reflect::as_reflect), &registry);
+        rebuilt.apply_diff(&diff, &registry);
+        assert_eq!(rebuilt.len(), 3);
+        assert!(second.diff(&rebuilt, &registry).is_empty());
+
+        let shrunk = first.diff(
+            &Snapshot::capture(units.iter().take(1).map(Reflect::as_reflect), &registry),
+            &registry,
+        );
+        rebuilt.apply_diff(&shrunk, &registry);
+        assert_eq!(rebuilt.len(), 1);
+        assert_eq!(
+            Soldier::from_reflect(rebuilt.get(0).unwrap()).unwrap().hp,
+            5
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! Counts and sizes of reflected objects, by concrete type and by dynamic
+//! trait, for finding the types that take up the most space in saved data.
+//!
+//! ## Example
+//! ```rust
+//! # use reflect_steroids::{prelude::*, reflect::TypeRegistry, stats};
+//! #[reflect_trait]
+//! trait Entity: DowncastReflect {}
+//! impl_dynamic_trait!(Entity, ReflectEntity, global);
+//!
+//! #[derive(Reflect, serde::Serialize)]
+//! #[reflect(Entity, Serialize)]
+//! struct Tree { height: f32 }
+//! impl Entity for Tree {}
+//!
+//! #[derive(Reflect, serde::Serialize)]
+//! #[reflect(Entity, Serialize)]
+//! struct Sign { text: String }
+//! impl Entity for Sign {}
+//!
+//! let mut registry = TypeRegistry::default();
+//! registry.register::<Tree>();
+//! registry.register::<Sign>();
+//! registry.register_global_types();
+//!
+//! let world: Vec<Box<dyn Entity>> = vec![
+//!     Box::new(Tree { height: 3.0 }),
+//!     Box::new(Tree { height: 5.0 }),
+//!     Box::new(Sign { text: "Welcome to the forest! ".repeat(10) }),
+//! ];
+//! let report = stats::summarize(world.iter().map(|e| e.as_reflect()), &registry);
+//!
+//! assert_eq!(report.by_type[std::any::type_name::<Tree>()].count, 2);
+//! assert_eq!(report.by_trait["Entity"].count, 3);
+//! assert_eq!(report.largest()[0].0, std::any::type_name::<Sign>());
+//! ```
+
+use std::{collections::BTreeMap, fmt, mem};
+
+use bevy_reflect::{Reflect, ReflectRef, TypeRegistry};
+
+use crate::{serialization::binary::encoded_size, TypeRegistryExt};
+
+/// The counts and sizes of a group of objects.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of objects.
+    pub count: usize,
+    /// The total size in bytes of the objects in the compact binary encoding
+    /// of tagged serialization. Objects that cannot be serialized are not
+    /// included.
+    pub serialized_bytes: usize,
+    /// The number of objects that could not be serialized, e.g. because their
+    /// type does not have `#[reflect(Serialize)]`.
+    pub unserializable: usize,
+    /// The approximate memory used by the objects, in bytes: the size of the
+    /// objects themselves, and of the contents of the strings, lists and maps
+    /// that they contain. Spare capacity and allocator overhead are not
+    /// included.
+    pub memory_bytes: usize,
+}
+
+impl Stats {
+    fn add(&mut self, other: &Stats) {
+        self.count += other.count;
+        self.serialized_bytes += other.serialized_bytes;
+        self.unserializable += other.unserializable;
+        self.memory_bytes += other.memory_bytes;
+    }
+}
+
+/// The result of [`summarize()`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StatsReport {
+    /// All objects.
+    pub total: Stats,
+    /// The objects of each concrete type, by type name.
+    pub by_type: BTreeMap<String, Stats>,
+    /// The objects implementing each dynamic trait, by trait name (see
+    /// [`DynamicTrait::reflect_name()`](crate::DynamicTrait::reflect_name)).
+    /// An object is counted for every trait its type implements.
+    ///
+    /// The traits of a type are only known if they are registered globally,
+    /// and the registry has been indexed (see
+    /// [`TypeRegistryExt::index_dynamic_traits()`](crate::TypeRegistryExt::index_dynamic_traits)),
+    /// which requires the "inventory" crate feature. Otherwise, this is empty.
+    pub by_trait: BTreeMap<&'static str, Stats>,
+}
+
+impl StatsReport {
+    /// The concrete types, from the largest total serialized size to the
+    /// smallest.
+    #[must_use]
+    pub fn largest(&self) -> Vec<(&str, &Stats)> {
+        let mut types: Vec<(&str, &Stats)> = self
+            .by_type
+            .iter()
+            .map(|(name, stats)| (name.as_str(), stats))
+            .collect();
+        types.sort_by(|(a_name, a), (b_name, b)| {
+            b.serialized_bytes
+                .cmp(&a.serialized_bytes)
+                .then_with(|| a_name.cmp(b_name))
+        });
+        types
+    }
+}
+
+impl fmt::Display for StatsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>8} {:>12} {:>12}  type",
+            "count", "serialized", "memory"
+        )?;
+        let rows = self
+            .largest()
+            .into_iter()
+            .chain(self.by_trait.iter().map(|(name, stats)| (*name, stats)))
+            .chain(std::iter::once(("(total)", &self.total)));
+        for (name, stats) in rows {
+            write!(
+                f,
+                "{:>8} {:>12} {:>12}  {}",
+                stats.count, stats.serialized_bytes, stats.memory_bytes, name
+            )?;
+            if stats.unserializable > 0 {
+                write!(f, " ({} not serializable)", stats.unserializable)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Count the objects, and measure their serialized size and memory use, by
+/// concrete type and by dynamic trait.
+///
+/// Objects are serialized with `registry` as the current registry.
+pub fn summarize<'a, I>(objects: I, registry: &TypeRegistry) -> StatsReport
+where
+    I: IntoIterator<Item = &'a dyn Reflect>,
+{
+    registry.set_current(|| {
+        let mut report = StatsReport::default();
+        for object in objects {
+            let mut stats = Stats {
+                count: 1,
+                memory_bytes: mem::size_of_val(object) + heap_size(object),
+                ..Stats::default()
+            };
+            match encoded_size(object) {
+                Ok(size) => stats.serialized_bytes = size,
+                Err(_) => stats.unserializable = 1,
+            }
+
+            report.total.add(&stats);
+            report
+                .by_type
+                .entry(object.type_name().to_string())
+                .or_default()
+                .add(&stats);
+            #[cfg(feature = "inventory")]
+            if let Some(traits) = registry
+                .get(object.as_any().type_id())
+                .and_then(|registration| registration.data::<crate::ImplementedTraits>())
+            {
+                for name in traits.names() {
+                    report.by_trait.entry(name).or_default().add(&stats);
+                }
+            }
+        }
+        report
+    })
+}
+
+/// The approximate size of the heap allocations owned by `value`.
+fn heap_size(value: &dyn Reflect) -> usize {
+    let owned = |value: &dyn Reflect| mem::size_of_val(value) + heap_size(value);
+    match value.reflect_ref() {
+        ReflectRef::Struct(value) => value.iter_fields().map(heap_size).sum(),
+        ReflectRef::TupleStruct(value) => value.iter_fields().map(heap_size).sum(),
+        ReflectRef::Tuple(value) => value.iter_fields().map(heap_size).sum(),
+        ReflectRef::Array(value) => value.iter().map(heap_size).sum(),
+        ReflectRef::Enum(value) => value
+            .iter_fields()
+            .map(|field| heap_size(field.value()))
+            .sum(),
+        ReflectRef::List(value) => value.iter().map(owned).sum(),
+        ReflectRef::Map(value) => value
+            .iter()
+            .map(|(key, value)| owned(key) + owned(value))
+            .sum(),
+        ReflectRef::Value(value) => value.downcast_ref::<String>().map_or(0, String::len),
+    }
+}
+
+#[cfg(all(test, feature = "inventory"))]
+mod tests {
+    use bevy_reflect::{reflect_trait, ReflectSerialize};
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect};
+
+    #[reflect_trait]
+    trait Chunk: DowncastReflect {}
+    impl_dynamic_trait!(Chunk, ReflectChunk, global);
+
+    #[derive(Reflect, serde::Serialize)]
+    #[reflect(Chunk, Serialize)]
+    struct Terrain {
+        heights: Vec<u16>,
+    }
+    impl Chunk for Terrain {}
+
+    #[derive(Reflect)]
+    #[reflect(Chunk)]
+    struct Cache(u64);
+    impl Chunk for Cache {}
+
+    #[test]
+    fn sizes_by_type_and_trait() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Terrain>();
+        registry.register::<Cache>();
+        registry.register_global_types();
+
+        let small = Terrain { heights: vec![1] };
+        let large = Terrain {
+            heights: vec![1; 100],
+        };
+        let objects: [&dyn Reflect; 4] = [&small, &large, &Cache(1), &1.5f32];
+        let report = summarize(objects, &registry);
+
+        let terrain = &report.by_type[std::any::type_name::<Terrain>()];
+        assert_eq!(terrain.count, 2);
+        assert_eq!(terrain.unserializable, 0);
+        assert!(terrain.serialized_bytes > 200);
+        assert_eq!(
+            terrain.memory_bytes,
+            2 * mem::size_of::<Terrain>() + 101 * mem::size_of::<u16>()
+        );
+
+        let cache = &report.by_type[std::any::type_name::<Cache>()];
+        assert_eq!((cache.count, cache.unserializable), (1, 1));
+        assert_eq!(cache.serialized_bytes, 0);
+
+        let chunk = &report.by_trait["Chunk"];
+        assert_eq!(chunk.count, 3);
+        assert_eq!(
+            chunk.memory_bytes,
+            terrain.memory_bytes + cache.memory_bytes
+        );
+        assert_eq!(report.total.count, 4);
+        assert_eq!(report.largest()[0].0, std::any::type_name::<Terrain>());
+        assert!(report.to_string().contains("(1 not serializable)"));
+    }
+}
@@ -0,0 +1,397 @@
+use std::{collections::BTreeMap, fmt};
+
+use bevy_reflect::Reflect;
+#[cfg(feature = "inventory")]
+use bevy_reflect::{TypeRegistration, TypeRegistry};
+use serde::{Deserialize, Serialize};
+
+use crate::DynamicTrait;
+#[cfg(feature = "inventory")]
+use crate::TypeRegistryExt as _;
+
+/// Key of an object in a [`DynStorage`].
+///
+/// Keys are generational: when an object is removed, its key becomes invalid,
+/// even if its slot is later reused by another object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct DynKey {
+    index: u32,
+    generation: u32,
+}
+
+impl DynKey {
+    /// The index of the slot of the object.
+    #[must_use]
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// The generation of the slot of the object.
+    #[must_use]
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl fmt::Display for DynKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "T: DynamicTrait")]
+struct Slot<T: DynamicTrait + ?Sized> {
+    generation: u32,
+    #[serde(with = "crate::serialization::dyn_field")]
+    value: Option<Box<T>>,
+}
+
+/// A collection of trait objects with stable keys, such as `DynStorage<dyn
+/// MyTrait>`.
+///
+/// Inserting an object returns a [`DynKey`], which stays valid until the
+/// object is removed. Slots of removed objects are reused.
+///
+/// `Serialize` and `Deserialize` use tagged serialization (see
+/// [`serialization::serialize()`](crate::serialization::serialize())), and
+/// require a current registry (see
+/// [`TypeRegistryExt::set_current()`](crate::TypeRegistryExt::set_current)).
+/// Keys are preserved.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, DynStorage};
+/// #[reflect_trait]
+/// trait Shape: DowncastReflect {
+///     fn area(&self) -> f32;
+/// }
+/// impl_dynamic_trait!(Shape, ReflectShape);
+///
+/// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+/// #[reflect(Shape, Serialize, Deserialize)]
+/// struct Square(f32);
+/// impl Shape for Square {
+///     fn area(&self) -> f32 { self.0 * self.0 }
+/// }
+///
+/// let mut shapes: DynStorage<dyn Shape> = DynStorage::new();
+/// let a = shapes.insert(Box::new(Square(2.0)));
+/// let b = shapes.insert(Box::new(Square(3.0)));
+/// assert_eq!(shapes[b].area(), 9.0);
+///
+/// shapes.remove(a);
+/// assert!(shapes.get(a).is_none());
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Square>();
+/// registry.set_current(|| {
+///     let json = serde_json::to_string(&shapes).unwrap();
+///     let shapes: DynStorage<dyn Shape> = serde_json::from_str(&json).unwrap();
+///     assert_eq!(shapes[b].area(), 9.0);
+/// });
+/// ```
+pub struct DynStorage<T: DynamicTrait + ?Sized> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+    len: usize,
+}
+
+impl<T: DynamicTrait + ?Sized> DynStorage<T> {
+    /// Create an empty storage.
+    #[must_use]
+    pub fn new() -> Self {
+        DynStorage {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// The number of objects.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// True if there are no objects.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Insert an object, and return its key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the storage has `u32::MAX` slots.
+    pub fn insert(&mut self, object: Box<T>) -> DynKey {
+        self.len += 1;
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.value = Some(object);
+            return DynKey {
+                index,
+                generation: slot.generation,
+            };
+        }
+
+        let index = u32::try_from(self.slots.len()).expect("too many slots in `DynStorage`");
+        self.slots.push(Slot {
+            generation: 0,
+            value: Some(object),
+        });
+        DynKey {
+            index,
+            generation: 0,
+        }
+    }
+
+    /// Remove the object with the given key, and return it. Returns `None` if
+    /// the key is invalid.
+    pub fn remove(&mut self, key: DynKey) -> Option<Box<T>> {
+        let slot = self.slots.get_mut(key.index as usize)?;
+        if slot.generation != key.generation {
+            return None;
+        }
+        let object = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(key.index);
+        self.len -= 1;
+        Some(object)
+    }
+
+    /// True if the key is valid.
+    #[must_use]
+    pub fn contains_key(&self, key: DynKey) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Get the object with the given key.
+    #[must_use]
+    pub fn get(&self, key: DynKey) -> Option<&T> {
+        match self.slots.get(key.index as usize) {
+            Some(slot) if slot.generation == key.generation => slot.value.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Get the object with the given key by mutable reference.
+    pub fn get_mut(&mut self, key: DynKey) -> Option<&mut T> {
+        match self.slots.get_mut(key.index as usize) {
+            Some(slot) if slot.generation == key.generation => slot.value.as_deref_mut(),
+            _ => None,
+        }
+    }
+
+    /// Remove all objects. All keys become invalid.
+    pub fn clear(&mut self) {
+        for key in self.keys().collect::<Vec<_>>() {
+            self.remove(key);
+        }
+    }
+
+    /// Iterate over the keys of the objects, in slot order.
+    pub fn keys(&self) -> impl Iterator<Item = DynKey> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Iterate over the objects and their keys, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (DynKey, &T)> {
+        self.slots.iter().zip(0..).filter_map(|(slot, index)| {
+            let key = DynKey {
+                index,
+                generation: slot.generation,
+            };
+            Some((key, slot.value.as_deref()?))
+        })
+    }
+
+    /// Iterate over the objects by mutable reference and their keys, in slot
+    /// order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (DynKey, &mut T)> {
+        self.slots.iter_mut().zip(0..).filter_map(|(slot, index)| {
+            let key = DynKey {
+                index,
+                generation: slot.generation,
+            };
+            Some((key, slot.value.as_deref_mut()?))
+        })
+    }
+
+    /// Iterate over the objects of the concrete type `C` and their keys.
+    pub fn iter_of_type<C: Reflect>(&self) -> impl Iterator<Item = (DynKey, &C)> {
+        self.iter()
+            .filter_map(|(key, object)| Some((key, object.as_reflect().downcast_ref::<C>()?)))
+    }
+
+    /// Group the keys of the objects by the type name of their concrete type.
+    #[must_use]
+    pub fn group_by_type(&self) -> BTreeMap<&str, Vec<DynKey>> {
+        let mut groups: BTreeMap<&str, Vec<DynKey>> = BTreeMap::new();
+        for (key, object) in self.iter() {
+            groups.entry(object.type_name()).or_default().push(key);
+        }
+        groups
+    }
+
+    /// Group the keys of the objects by the implementors of the dynamic trait
+    /// named `trait_name` (see
+    /// [`TypeRegistryExt::implementors_of()`](crate::TypeRegistryExt::implementors_of)).
+    /// Each implementor is listed,
+    /// even if there are no objects of its type.
+    ///
+    /// Requires the "inventory" crate feature.
+    #[must_use]
+    #[cfg(feature = "inventory")]
+    pub fn group_by_implementor<'r>(
+        &self,
+        registry: &'r TypeRegistry,
+        trait_name: &str,
+    ) -> Vec<(&'r TypeRegistration, Vec<DynKey>)> {
+        registry
+            .implementors_of(trait_name)
+            .into_iter()
+            .map(|registration| {
+                let keys = self
+                    .iter()
+                    .filter(|(_, object)| object.as_any().type_id() == registration.type_id())
+                    .map(|(key, _)| key)
+                    .collect();
+                (registration, keys)
+            })
+            .collect()
+    }
+}
+
+impl<T: DynamicTrait + ?Sized> Default for DynStorage<T> {
+    fn default() -> Self {
+        DynStorage::new()
+    }
+}
+
+impl<T: DynamicTrait + ?Sized> std::ops::Index<DynKey> for DynStorage<T> {
+    type Output = T;
+
+    fn index(&self, key: DynKey) -> &T {
+        self.get(key).expect("invalid `DynKey`")
+    }
+}
+
+impl<T: DynamicTrait + ?Sized> std::ops::IndexMut<DynKey> for DynStorage<T> {
+    fn index_mut(&mut self, key: DynKey) -> &mut T {
+        self.get_mut(key).expect("invalid `DynKey`")
+    }
+}
+
+impl<T: DynamicTrait + ?Sized> fmt::Debug for DynStorage<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter().map(|(key, object)| (key, object.as_reflect())))
+            .finish()
+    }
+}
+
+impl<T: DynamicTrait + ?Sized> Serialize for DynStorage<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.slots.serialize(serializer)
+    }
+}
+
+impl<'de, T: DynamicTrait + ?Sized> Deserialize<'de> for DynStorage<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let slots = Vec::<Slot<T>>::deserialize(deserializer)?;
+        if u32::try_from(slots.len()).is_err() {
+            return Err(serde::de::Error::custom("too many slots in `DynStorage`"));
+        }
+        let mut free: Vec<u32> = (0..)
+            .zip(&slots)
+            .filter(|(_, slot)| slot.value.is_none())
+            .map(|(index, _)| index)
+            .collect();
+        free.reverse();
+        let len = slots.iter().filter(|slot| slot.value.is_some()).count();
+        Ok(DynStorage { slots, free, len })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{reflect_trait, ReflectDeserialize, ReflectSerialize, TypeRegistry};
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect, TypeRegistryExt};
+
+    #[reflect_trait]
+    trait Item: DowncastReflect {}
+    impl_dynamic_trait!(Item, ReflectItem);
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Item, Serialize, Deserialize)]
+    struct Sword(u32);
+    impl Item for Sword {}
+
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(Item, Serialize, Deserialize)]
+    struct Potion;
+    impl Item for Potion {}
+
+    #[test]
+    fn stale_keys() {
+        let mut items: DynStorage<dyn Item> = DynStorage::new();
+        let a = items.insert(Box::new(Sword(1)));
+        let b = items.insert(Box::new(Potion));
+        assert_eq!(items.len(), 2);
+
+        assert!(items.remove(a).unwrap().is::<Sword>());
+        assert!(items.remove(a).is_none());
+        let c = items.insert(Box::new(Sword(2)));
+        assert_eq!(c.index(), a.index());
+        assert_ne!(c, a);
+        assert!(items.get(a).is_none());
+        assert_eq!(items[c].downcast_ref::<Sword>().unwrap().0, 2);
+        assert_eq!(items.keys().collect::<Vec<_>>(), [c, b]);
+
+        items.clear();
+        assert!(items.is_empty());
+        assert!(!items.contains_key(b));
+    }
+
+    #[test]
+    fn group_and_serialize() {
+        let mut items: DynStorage<dyn Item> = DynStorage::new();
+        let a = items.insert(Box::new(Sword(1)));
+        let b = items.insert(Box::new(Potion));
+        let c = items.insert(Box::new(Sword(3)));
+        items.remove(b);
+
+        let groups = items.group_by_type();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[std::any::type_name::<Sword>()], [a, c]);
+        let swords: Vec<u32> = items.iter_of_type::<Sword>().map(|(_, s)| s.0).collect();
+        assert_eq!(swords, [1, 3]);
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<Sword>();
+        registry.register::<Potion>();
+        let json = registry.set_current(|| serde_json::to_string(&items).unwrap());
+        assert_eq!(
+            json,
+            r#"[{"generation":0,"value":{"type":"Sword","value":1}},{"generation":1,"value":null},{"generation":0,"value":{"type":"Sword","value":3}}]"#
+        );
+
+        let mut items: DynStorage<dyn Item> =
+            registry.set_current(|| serde_json::from_str(&json).unwrap());
+        assert_eq!(items.len(), 2);
+        assert!(items[c].is::<Sword>());
+        let d = items.insert(Box::new(Potion));
+        assert_eq!(d.index(), b.index());
+        assert_ne!(d, b);
+    }
+}
@@ -0,0 +1,417 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::Arc,
+};
+
+use bevy_reflect::{Reflect, TypeRegistry};
+
+use crate::{
+    type_registry::{registry_data, registry_data_mut},
+    CastBox, CastMut, CastRef, DynamicTrait, TypeRegistryExt,
+};
+
+/// Conversion from the trait object `Self` to a trait object of one of its
+/// supertraits, `P`.
+///
+/// This is implemented by [`impl_supertrait!`](crate::impl_supertrait), and
+/// is required by
+/// [`TypeRegistryExt::register_supertrait()`](crate::TypeRegistryExt::register_supertrait).
+pub trait Upcast<P: ?Sized> {
+    /// Upcast a reference.
+    fn upcast_ref(&self) -> &P;
+    /// Upcast a mutable reference.
+    fn upcast_mut(&mut self) -> &mut P;
+    /// Upcast a box.
+    fn upcast_box(self: Box<Self>) -> Box<P>;
+}
+
+/// Implement [`Upcast`] from `dyn Child` to `dyn Parent` for each of the
+/// supertraits `Parent` of the trait `Child`.
+///
+/// Use [`TypeRegistryExt::register_supertrait()`] to make the relation known
+/// to a registry. The generated code relies on trait upcasting coercion, which
+/// requires Rust 1.86 or later.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+/// #[reflect_trait]
+/// trait Entity: DowncastReflect {
+///     fn id(&self) -> u32;
+/// }
+/// #[reflect_trait]
+/// trait Monster: Entity {}
+///
+/// impl_dynamic_trait!(Entity, ReflectEntity);
+/// impl_dynamic_trait!(Monster, ReflectMonster);
+/// impl_supertrait!(Monster: Entity);
+///
+/// // Note: `#[reflect(Entity)]` is missing.
+/// #[derive(Reflect)]
+/// #[reflect(Monster)]
+/// struct Goblin;
+/// impl Entity for Goblin {
+///     fn id(&self) -> u32 { 7 }
+/// }
+/// impl Monster for Goblin {}
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Goblin>();
+/// registry.register_supertrait::<dyn Monster, dyn Entity>();
+///
+/// assert!(registry.implements::<dyn Entity>(std::any::TypeId::of::<Goblin>()));
+/// let goblin = &Goblin;
+/// let entity: &dyn Entity = goblin.try_cast_with_registry(&registry).unwrap();
+/// assert_eq!(entity.id(), 7);
+/// ```
+#[macro_export]
+macro_rules! impl_supertrait {
+    ($child:ident: $($parent:ident),+ $(,)?) => {$(
+        impl $crate::Upcast<dyn $parent> for dyn $child {
+            fn upcast_ref(&self) -> &dyn $parent {
+                self
+            }
+
+            fn upcast_mut(&mut self) -> &mut dyn $parent {
+                self
+            }
+
+            fn upcast_box(self: Box<Self>) -> Box<dyn $parent> {
+                self
+            }
+        }
+    )+};
+}
+
+/// Make a marker trait that combines other dynamic traits, like
+/// `trait GameObject: Updatable + Drawable + DowncastReflect {}`, a dynamic
+/// trait of its own.
+///
+/// `impl_trait_combination!(GameObject: Updatable + Drawable,
+/// ReflectGameObject)` defines the type data `ReflectGameObject`, and does
+/// what [`impl_dynamic_trait!`](crate::impl_dynamic_trait) and
+/// [`impl_supertrait!`] do for `GameObject` and its components. The trait
+/// must not be annotated with `#[reflect_trait]`. A visibility may precede the
+/// trait name, and applies to the type data.
+///
+/// A trait object of the combination can only be made from the concrete type,
+/// so the type data must be registered for each type, with
+/// `#[reflect(GameObject)]` or
+/// [`TypeRegistryExt::register_trait_impl()`](crate::TypeRegistryExt::register_trait_impl).
+/// Casts to the combination only succeed if the type data of all the
+/// components is registered for the type as well.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, impl_trait_combination, reflect::TypeRegistry, Cast};
+/// #[reflect_trait]
+/// trait Updatable: DowncastReflect {
+///     fn update(&mut self);
+/// }
+/// #[reflect_trait]
+/// trait Drawable: DowncastReflect {
+///     fn draw(&self) -> String;
+/// }
+/// impl_dynamic_trait!(Updatable, ReflectUpdatable);
+/// impl_dynamic_trait!(Drawable, ReflectDrawable);
+///
+/// trait GameObject: Updatable + Drawable + DowncastReflect {}
+/// impl<T: Updatable + Drawable + DowncastReflect> GameObject for T {}
+/// impl_trait_combination!(GameObject: Updatable + Drawable, ReflectGameObject);
+///
+/// #[derive(Reflect)]
+/// #[reflect(Updatable, Drawable)]
+/// struct Player { frame: u32 }
+/// impl Updatable for Player {
+///     fn update(&mut self) { self.frame += 1; }
+/// }
+/// impl Drawable for Player {
+///     fn draw(&self) -> String { format!("player @ {}", self.frame) }
+/// }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Player>();
+/// registry.register_trait_impl::<Player, dyn GameObject>();
+///
+/// let mut player = Player { frame: 0 };
+/// let object = Cast::<dyn GameObject>::try_cast_with_registry(&mut player, &registry).unwrap();
+/// object.update();
+/// assert_eq!(object.draw(), "player @ 1");
+/// ```
+#[macro_export]
+macro_rules! impl_trait_combination {
+    ($vis:vis $trait_name:ident: $first:ident $(+ $rest:ident)*, $type_data_name:ident) => {
+        #[doc = concat!("Type data for casting to `dyn ", stringify!($trait_name), "`.")]
+        #[derive(Clone)]
+        $vis struct $type_data_name {
+            get_func: fn(&dyn $crate::reflect::Reflect) -> Option<&dyn $trait_name>,
+            get_mut_func: fn(&mut dyn $crate::reflect::Reflect) -> Option<&mut dyn $trait_name>,
+            get_boxed_func: fn(
+                Box<dyn $crate::reflect::Reflect>,
+            ) -> Result<Box<dyn $trait_name>, Box<dyn $crate::reflect::Reflect>>,
+        }
+
+        #[allow(dead_code)]
+        impl $type_data_name {
+            #[doc = concat!("Downcast a `&dyn Reflect` to `&dyn ", stringify!($trait_name), "`.")]
+            $vis fn get<'a>(&self, reflect_value: &'a dyn $crate::reflect::Reflect) -> Option<&'a dyn $trait_name> {
+                (self.get_func)(reflect_value)
+            }
+
+            #[doc = concat!("Downcast a `&mut dyn Reflect` to `&mut dyn ", stringify!($trait_name), "`.")]
+            $vis fn get_mut<'a>(&self, reflect_value: &'a mut dyn $crate::reflect::Reflect) -> Option<&'a mut dyn $trait_name> {
+                (self.get_mut_func)(reflect_value)
+            }
+
+            #[doc = concat!("Downcast a `Box<dyn Reflect>` to `Box<dyn ", stringify!($trait_name), ">`.")]
+            $vis fn get_boxed(
+                &self,
+                reflect_value: Box<dyn $crate::reflect::Reflect>,
+            ) -> Result<Box<dyn $trait_name>, Box<dyn $crate::reflect::Reflect>> {
+                (self.get_boxed_func)(reflect_value)
+            }
+        }
+
+        impl<T: $trait_name + $crate::reflect::Reflect> $crate::reflect::FromType<T> for $type_data_name {
+            fn from_type() -> Self {
+                $type_data_name {
+                    get_func: |reflect_value| {
+                        reflect_value
+                            .downcast_ref::<T>()
+                            .map(|value| value as &dyn $trait_name)
+                    },
+                    get_mut_func: |reflect_value| {
+                        reflect_value
+                            .downcast_mut::<T>()
+                            .map(|value| value as &mut dyn $trait_name)
+                    },
+                    get_boxed_func: |reflect_value| {
+                        reflect_value
+                            .downcast::<T>()
+                            .map(|value| value as Box<dyn $trait_name>)
+                    },
+                }
+            }
+        }
+
+        impl $crate::DynamicTrait for dyn $trait_name {
+            type TypeData = $type_data_name;
+
+            fn reflect_name() -> &'static str {
+                stringify!($trait_name)
+            }
+
+            fn get_type_data(
+                registration: &$crate::reflect::TypeRegistration,
+            ) -> Option<::std::borrow::Cow<'_, Self::TypeData>> {
+                <dyn $first as $crate::DynamicTrait>::get_type_data(registration)?;
+                $(
+                    <dyn $rest as $crate::DynamicTrait>::get_type_data(registration)?;
+                )*
+                Some(::std::borrow::Cow::Borrowed(registration.data::<$type_data_name>()?))
+            }
+        }
+
+        $crate::impl_dynamic_trait!(@impls $trait_name, $type_data_name);
+        $crate::impl_dynamic_trait!(@inherent [pub] $trait_name);
+        $crate::impl_supertrait!($trait_name: $first $(, $rest)*);
+    };
+}
+
+/// Registry-wide type data: for each parent trait object type, the casts
+/// through its registered subtraits.
+#[derive(Clone, Default)]
+struct Supertraits(HashMap<TypeId, Vec<Arc<dyn Any + Send + Sync>>>);
+
+/// The result of a box cast, giving back the original box on failure.
+type BoxCastResult<P> = Result<Box<P>, Box<dyn Reflect>>;
+
+/// Casts to the trait object `P` through one of its subtraits.
+struct SubtraitCaster<P: ?Sized + 'static> {
+    implements: fn(&TypeRegistry, TypeId) -> bool,
+    cast_ref: for<'a> fn(&'a dyn Reflect, &TypeRegistry) -> Option<&'a P>,
+    cast_mut: for<'a> fn(&'a mut dyn Reflect, &TypeRegistry) -> Option<&'a mut P>,
+    cast_box: fn(Box<dyn Reflect>, &TypeRegistry) -> BoxCastResult<P>,
+}
+
+impl<P: ?Sized + 'static> SubtraitCaster<P> {
+    fn new<C>() -> Self
+    where
+        C: DynamicTrait + Upcast<P> + ?Sized,
+    {
+        SubtraitCaster {
+            implements: |registry, type_id| registry.implements::<C>(type_id),
+            cast_ref: |this, registry| {
+                this.try_cast_ref_with_registry::<C>(registry)
+                    .ok()
+                    .map(Upcast::upcast_ref)
+            },
+            cast_mut: |this, registry| {
+                this.try_cast_mut_with_registry::<C>(registry)
+                    .ok()
+                    .map(Upcast::upcast_mut)
+            },
+            cast_box: |this, registry| match this.try_cast_box_with_registry::<C>(registry) {
+                Ok(child) => Ok(child.upcast_box()),
+                Err((this, _)) => Err(this),
+            },
+        }
+    }
+}
+
+pub(crate) fn register_supertrait<C, P>(registry: &mut TypeRegistry)
+where
+    C: DynamicTrait + Upcast<P> + ?Sized,
+    P: DynamicTrait + ?Sized,
+{
+    let registration = registry_data_mut(registry);
+    if registration.data::<Supertraits>().is_none() {
+        registration.insert(Supertraits::default());
+    }
+    registration
+        .data_mut::<Supertraits>()
+        .expect("type data was just inserted")
+        .0
+        .entry(TypeId::of::<P>())
+        .or_default()
+        .push(Arc::new(SubtraitCaster::<P>::new::<C>()));
+}
+
+fn subtrait_casters<P: ?Sized + 'static>(
+    registry: &TypeRegistry,
+) -> impl Iterator<Item = &SubtraitCaster<P>> {
+    registry_data::<Supertraits>(registry)
+        .and_then(|supertraits| supertraits.0.get(&TypeId::of::<P>()))
+        .into_iter()
+        .flatten()
+        .filter_map(|caster| caster.downcast_ref::<SubtraitCaster<P>>())
+}
+
+/// True if the type implements one of the registered subtraits of `P`.
+pub(crate) fn implements_through_subtrait<P: ?Sized + 'static>(
+    registry: &TypeRegistry,
+    type_id: TypeId,
+) -> bool {
+    subtrait_casters::<P>(registry).any(|caster| (caster.implements)(registry, type_id))
+}
+
+/// Cast `this` to `P` through one of the registered subtraits of `P`.
+pub(crate) fn upcast_ref<'a, P: ?Sized + 'static>(
+    this: &'a dyn Reflect,
+    registry: &TypeRegistry,
+) -> Option<&'a P> {
+    subtrait_casters::<P>(registry).find_map(|caster| (caster.cast_ref)(this, registry))
+}
+
+/// Cast `this` to `P` through one of the registered subtraits of `P`.
+pub(crate) fn upcast_mut<'a, P: ?Sized + 'static>(
+    this: &'a mut dyn Reflect,
+    registry: &TypeRegistry,
+) -> Option<&'a mut P> {
+    let caster = subtrait_casters::<P>(registry)
+        .find(|caster| (caster.cast_ref)(&*this, registry).is_some())?;
+    (caster.cast_mut)(this, registry)
+}
+
+/// Cast `this` to `P` through one of the registered subtraits of `P`.
+pub(crate) fn upcast_box<P: ?Sized + 'static>(
+    mut this: Box<dyn Reflect>,
+    registry: &TypeRegistry,
+) -> BoxCastResult<P> {
+    for caster in subtrait_casters::<P>(registry) {
+        match (caster.cast_box)(this, registry) {
+            Ok(upcast) => return Ok(upcast),
+            Err(original) => this = original,
+        }
+    }
+    Err(this)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::reflect_trait;
+
+    use super::*;
+    use crate::{impl_dynamic_trait, Cast, DowncastReflect};
+
+    #[reflect_trait]
+    trait Named: DowncastReflect {
+        fn name(&self) -> String;
+    }
+    #[reflect_trait]
+    trait Creature: Named {}
+    #[reflect_trait]
+    trait Dragon: Creature {
+        fn breathe_fire(&mut self);
+    }
+
+    impl_dynamic_trait!(Named, ReflectNamed);
+    impl_dynamic_trait!(Creature, ReflectCreature);
+    impl_dynamic_trait!(Dragon, ReflectDragon);
+    impl_supertrait!(Creature: Named);
+    impl_supertrait!(Dragon: Creature);
+
+    #[derive(Reflect)]
+    #[reflect(Dragon)]
+    struct Smaug {
+        fires: u32,
+    }
+    impl Named for Smaug {
+        fn name(&self) -> String {
+            format!("Smaug ({} fires)", self.fires)
+        }
+    }
+    impl Creature for Smaug {}
+    impl Dragon for Smaug {
+        fn breathe_fire(&mut self) {
+            self.fires += 1;
+        }
+    }
+
+    #[test]
+    fn transitive_upcast() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Smaug>();
+        assert!(!registry.implements::<dyn Named>(TypeId::of::<Smaug>()));
+
+        registry.register_supertrait::<dyn Creature, dyn Named>();
+        registry.register_supertrait::<dyn Dragon, dyn Creature>();
+        assert!(registry.implements::<dyn Named>(TypeId::of::<Smaug>()));
+        assert!(!registry.implements::<dyn Named>(TypeId::of::<u32>()));
+
+        let mut dragon: Box<dyn Dragon> = Box::new(Smaug { fires: 0 });
+        dragon.breathe_fire();
+        let named: &mut dyn Named = (&mut *dragon).try_cast_with_registry(&registry).unwrap();
+        assert_eq!(named.name(), "Smaug (1 fires)");
+
+        let named: Box<dyn Named> = dragon.try_cast_with_registry(&registry).unwrap();
+        assert_eq!(named.name(), "Smaug (1 fires)");
+    }
+
+    trait Monster: Named + Dragon + DowncastReflect {}
+    impl<T: Named + Dragon + DowncastReflect> Monster for T {}
+    impl_trait_combination!(Monster: Named + Dragon, ReflectMonster);
+
+    #[test]
+    fn combination_requires_components() {
+        let mut registry = TypeRegistry::default();
+        registry.register_trait_impl::<Smaug, dyn Monster>();
+        let smaug: &dyn Reflect = &Smaug { fires: 0 };
+        assert_eq!(
+            Cast::<dyn Monster>::try_cast_with_registry(smaug, &registry).err(),
+            Some(crate::TypeError::UnregisteredTrait(
+                "Smaug".into(),
+                "Monster"
+            ))
+        );
+
+        registry.register_trait_impl::<Smaug, dyn Named>();
+        let monster: Box<dyn Reflect> = Box::new(Smaug { fires: 2 });
+        let mut monster: Box<dyn Monster> = monster.try_cast_with_registry(&registry).unwrap();
+        monster.breathe_fire();
+        let named: &dyn Named = Upcast::upcast_ref(&*monster);
+        assert_eq!(named.name(), "Smaug (3 fires)");
+        assert!(registry.dynamic_traits().any(|name| name == "Monster"));
+    }
+}
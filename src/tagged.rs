@@ -0,0 +1,516 @@
+use std::{
+    any::{self, Any, TypeId},
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+use bevy_reflect::{
+    struct_debug, struct_partial_eq, utility::GenericTypeInfoCell, DynamicStruct, FieldIter,
+    FromReflect, NamedField, Reflect, ReflectMut, ReflectOwned, ReflectRef, Struct, StructInfo,
+    TypeInfo, TypeRegistration, TypeRegistry, Typed,
+};
+
+use crate::{
+    from_dynamic::build_as, serialization::tag::resolve_tag, DynamicTrait, FromDynamicError,
+    TypeError, TypeRegistryExt,
+};
+
+const FIELD_NAMES: [&str; 2] = ["type", "value"];
+
+/// A trait object that is reflected as a struct with two fields: `type`, the
+/// type name of the object as a `String`, and `value`, the object itself.
+///
+/// A `Box<dyn MyTrait>` reflects as its concrete object, so its type cannot be
+/// seen or changed by generic reflection-based tools, such as diffs and
+/// inspectors. `Tagged<dyn MyTrait>` exposes the type as data:
+///
+/// - Applying a struct whose `type` names another type (by its full type name
+///   or its type tag, as in deserialization) replaces the object with one of
+///   that type, built from
+///   the `value` field if it has that type (see
+///   [`from_dynamic()`](crate::from_dynamic)), and otherwise with the
+///   [`ReflectDefault`](bevy_reflect::std_traits::ReflectDefault) type data of
+///   the type. This requires a current registry (see
+///   [`TypeRegistryExt::set_current()`]), and panics if the object cannot be
+///   built, like other failed applications.
+/// - The `type` field cannot be mutated in place, so
+///   [`Struct::field_mut()`] returns `None` for it.
+/// - [`FromReflect`] builds the object in the same way, and returns `None`
+///   if there is no current registry.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, Tagged, reflect::{DynamicStruct, GetPath, TypeRegistry, std_traits::ReflectDefault}};
+/// #[reflect_trait]
+/// trait Weapon: DowncastReflect {
+///     fn damage(&self) -> u32;
+/// }
+/// impl_dynamic_trait!(Weapon, ReflectWeapon);
+///
+/// #[derive(Reflect, Default)]
+/// #[reflect(Weapon, Default)]
+/// struct Sword { sharpness: u32 }
+/// impl Weapon for Sword {
+///     fn damage(&self) -> u32 { self.sharpness }
+/// }
+///
+/// #[derive(Reflect, Default)]
+/// #[reflect(Weapon, Default)]
+/// struct Bow { range: u32 }
+/// impl Weapon for Bow {
+///     fn damage(&self) -> u32 { self.range / 10 }
+/// }
+///
+/// #[derive(Reflect)]
+/// struct Player { weapon: Tagged<dyn Weapon> }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Sword>();
+/// registry.register::<Bow>();
+///
+/// let mut player = Player { weapon: Tagged::new(Box::new(Sword { sharpness: 3 })) };
+/// let type_name = player.get_path::<String>("weapon.type").unwrap();
+/// assert_eq!(type_name, std::any::type_name::<Sword>());
+///
+/// let mut change = DynamicStruct::default();
+/// change.insert("type", String::from("Bow"));
+/// let mut bow = DynamicStruct::default();
+/// bow.set_name(std::any::type_name::<Bow>().to_string());
+/// bow.insert("range", 50u32);
+/// change.insert("value", bow);
+/// registry.set_current(|| player.weapon.apply(&change));
+///
+/// assert!(player.weapon.is::<Bow>());
+/// assert_eq!(player.weapon.damage(), 5);
+/// ```
+pub struct Tagged<P: ?Sized> {
+    object: Box<P>,
+    type_name: String,
+}
+
+impl<P: DynamicTrait + ?Sized> Tagged<P> {
+    /// Wrap `object`.
+    #[must_use]
+    pub fn new(object: Box<P>) -> Self {
+        let type_name = P::as_reflect(&*object).type_name().to_string();
+        Tagged { object, type_name }
+    }
+
+    /// Unwrap the trait object.
+    #[must_use]
+    pub fn into_inner(self) -> Box<P> {
+        self.object
+    }
+}
+
+/// Build an object of the type named `type_name`, from `value` if it has that
+/// type.
+fn build_object<P: DynamicTrait + ?Sized>(
+    type_name: &str,
+    value: Option<&dyn Reflect>,
+    registry: &TypeRegistry,
+) -> Result<Box<P>, FromDynamicError> {
+    let registration = resolve_type(registry, type_name)
+        .ok_or_else(|| TypeError::UnregisteredType(type_name.to_string().into()))?;
+    let value = value.filter(|value| {
+        resolve_type(registry, value.type_name())
+            .is_some_and(|other| other.type_id() == registration.type_id())
+    });
+    build_as(registration, value, registry)
+}
+
+/// Find a registered type by its full type name, or by its type tag in the
+/// same way as deserialization (see
+/// [`SerializedAs`](crate::serialization::SerializedAs)).
+fn resolve_type<'a>(registry: &'a TypeRegistry, name: &str) -> Option<&'a TypeRegistration> {
+    registry
+        .get_with_name(name)
+        .or_else(|| resolve_tag(registry, name))
+}
+
+/// True if `type_name` names the type with the ID `type_id` in the current
+/// registry.
+fn resolves_to(type_name: &str, type_id: TypeId) -> bool {
+    TypeRegistry::try_with_current(|registry| {
+        resolve_type(registry, type_name).map(TypeRegistration::type_id) == Some(type_id)
+    })
+    .unwrap_or(false)
+}
+
+impl<P: DynamicTrait + ?Sized> From<Box<P>> for Tagged<P> {
+    fn from(object: Box<P>) -> Self {
+        Tagged::new(object)
+    }
+}
+
+impl<P: ?Sized> Deref for Tagged<P> {
+    type Target = P;
+
+    fn deref(&self) -> &P {
+        &self.object
+    }
+}
+
+impl<P: ?Sized> DerefMut for Tagged<P> {
+    fn deref_mut(&mut self) -> &mut P {
+        &mut self.object
+    }
+}
+
+impl<P: DynamicTrait + ?Sized> fmt::Debug for Tagged<P>
+where
+    Box<P>: Reflect,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct_debug(self, f)
+    }
+}
+
+impl<P: DynamicTrait + ?Sized> Struct for Tagged<P>
+where
+    Box<P>: Reflect,
+{
+    fn field(&self, name: &str) -> Option<&dyn Reflect> {
+        match name {
+            "type" => Some(&self.type_name),
+            "value" => Some(P::as_reflect(&*self.object)),
+            _ => None,
+        }
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+        match name {
+            "value" => Some(P::as_reflect_mut(&mut *self.object)),
+            _ => None,
+        }
+    }
+
+    fn field_at(&self, index: usize) -> Option<&dyn Reflect> {
+        self.field(self.name_at(index)?)
+    }
+
+    fn field_at_mut(&mut self, index: usize) -> Option<&mut dyn Reflect> {
+        self.field_mut(FIELD_NAMES.get(index)?)
+    }
+
+    fn name_at(&self, index: usize) -> Option<&str> {
+        FIELD_NAMES.get(index).copied()
+    }
+
+    fn field_len(&self) -> usize {
+        FIELD_NAMES.len()
+    }
+
+    fn iter_fields(&self) -> FieldIter<'_> {
+        FieldIter::new(self)
+    }
+
+    fn clone_dynamic(&self) -> DynamicStruct {
+        let mut dynamic = DynamicStruct::default();
+        dynamic.set_name(any::type_name::<Self>().to_string());
+        dynamic.insert("type", self.type_name.clone());
+        dynamic.insert_boxed("value", P::clone_value(&*self.object));
+        dynamic
+    }
+}
+
+impl<P: DynamicTrait + ?Sized> Typed for Tagged<P>
+where
+    Box<P>: Reflect,
+{
+    fn type_info() -> &'static TypeInfo {
+        static CELL: GenericTypeInfoCell = GenericTypeInfoCell::new();
+        CELL.get_or_insert::<Self, _>(|| {
+            let fields = [
+                NamedField::new::<String>("type"),
+                NamedField::new::<Box<P>>("value"),
+            ];
+            TypeInfo::Struct(StructInfo::new::<Self>("Tagged", &fields))
+        })
+    }
+}
+
+impl<P: DynamicTrait + ?Sized> Reflect for Tagged<P>
+where
+    Box<P>: Reflect,
+{
+    fn type_name(&self) -> &str {
+        any::type_name::<Self>()
+    }
+
+    fn get_type_info(&self) -> &'static TypeInfo {
+        <Self as Typed>::type_info()
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_reflect(self: Box<Self>) -> Box<dyn Reflect> {
+        self
+    }
+
+    fn as_reflect(&self) -> &dyn Reflect {
+        self
+    }
+
+    fn as_reflect_mut(&mut self) -> &mut dyn Reflect {
+        self
+    }
+
+    fn apply(&mut self, value: &dyn Reflect) {
+        let ReflectRef::Struct(value) = value.reflect_ref() else {
+            panic!("attempted to apply a non-struct value to a Tagged");
+        };
+        let object = value.field("value");
+        let current = P::as_reflect(&*self.object).as_any().type_id();
+        match value.field("type").and_then(|t| t.downcast_ref::<String>()) {
+            Some(type_name) if *type_name != self.type_name && !resolves_to(type_name, current) => {
+                let object = TypeRegistry::try_with_current(|registry| {
+                    build_object::<P>(type_name, object, registry)
+                })
+                .map_err(FromDynamicError::from)
+                .and_then(|result| result)
+                .unwrap_or_else(|err| panic!("cannot change the type of a Tagged: {err}"));
+                *self = Tagged::new(object);
+            }
+            _ => {
+                if let Some(object) = object {
+                    P::apply(&mut *self.object, object);
+                }
+            }
+        }
+    }
+
+    fn set(&mut self, value: Box<dyn Reflect>) -> Result<(), Box<dyn Reflect>> {
+        *self = value.take()?;
+        Ok(())
+    }
+
+    fn reflect_ref(&self) -> ReflectRef<'_> {
+        ReflectRef::Struct(self)
+    }
+
+    fn reflect_mut(&mut self) -> ReflectMut<'_> {
+        ReflectMut::Struct(self)
+    }
+
+    fn reflect_owned(self: Box<Self>) -> ReflectOwned {
+        ReflectOwned::Struct(self)
+    }
+
+    fn clone_value(&self) -> Box<dyn Reflect> {
+        Box::new(self.clone_dynamic())
+    }
+
+    fn reflect_partial_eq(&self, value: &dyn Reflect) -> Option<bool> {
+        struct_partial_eq(self, value)
+    }
+
+    fn debug(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct_debug(self, f)
+    }
+}
+
+impl<P: DynamicTrait + ?Sized> FromReflect for Tagged<P>
+where
+    Box<P>: Reflect,
+{
+    fn from_reflect(reflect: &dyn Reflect) -> Option<Self> {
+        let ReflectRef::Struct(value) = reflect.reflect_ref() else {
+            return None;
+        };
+        let type_name = value.field("type")?.downcast_ref::<String>()?;
+        TypeRegistry::try_with_current(|registry| {
+            build_object(type_name, value.field("value"), registry).ok()
+        })
+        .ok()
+        .flatten()
+        .map(Tagged::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{reflect_trait, std_traits::ReflectDefault};
+
+    use super::*;
+    use crate::{
+        compat::{reflect_path, reflect_path_mut},
+        impl_dynamic_trait, DowncastReflect, ReflectFromDynamic,
+    };
+
+    #[reflect_trait]
+    trait Shape: DowncastReflect {
+        fn area(&self) -> f32;
+    }
+    impl_dynamic_trait!(Shape, ReflectShape);
+
+    #[derive(Reflect, Default)]
+    #[reflect(Shape, Default)]
+    struct Square {
+        side: f32,
+    }
+    impl Shape for Square {
+        fn area(&self) -> f32 {
+            self.side * self.side
+        }
+    }
+
+    #[derive(Reflect, FromReflect)]
+    #[reflect(Shape, FromDynamic)]
+    struct Rect {
+        width: f32,
+        height: f32,
+    }
+    impl Shape for Rect {
+        fn area(&self) -> f32 {
+            self.width * self.height
+        }
+    }
+
+    #[derive(Reflect, FromReflect)]
+    struct Drawing {
+        shapes: Vec<Tagged<dyn Shape>>,
+    }
+
+    fn registry() -> TypeRegistry {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Square>();
+        registry.register::<Rect>();
+        registry
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn fields_and_type_changes() {
+        let registry = registry();
+        let mut drawing = Drawing {
+            shapes: vec![Tagged::new(Box::new(Square { side: 2.0 }))],
+        };
+        assert_eq!(
+            reflect_path(&drawing, "shapes[0].type")
+                .unwrap()
+                .downcast_ref::<String>()
+                .unwrap(),
+            any::type_name::<Square>()
+        );
+        *reflect_path_mut(&mut drawing, "shapes[0].value.side")
+            .unwrap()
+            .downcast_mut::<f32>()
+            .unwrap() = 3.0;
+        assert_eq!(drawing.shapes[0].area(), 9.0);
+        assert!(drawing.shapes[0].field_mut("type").is_none());
+
+        // Same type: the value is applied in place.
+        let mut patch = drawing.shapes[0].clone_dynamic();
+        patch.insert("value", {
+            let mut square = DynamicStruct::default();
+            square.insert("side", 4.0f32);
+            square
+        });
+        drawing.shapes[0].apply(&patch);
+        assert_eq!(drawing.shapes[0].area(), 16.0);
+
+        // Another type with a value of that type is built from the value.
+        let mut patch = DynamicStruct::default();
+        patch.insert("type", String::from("Rect"));
+        patch.insert(
+            "value",
+            Rect {
+                width: 2.0,
+                height: 5.0,
+            },
+        );
+        registry.set_current(|| drawing.shapes[0].apply(&patch));
+        assert!(drawing.shapes[0].is::<Rect>());
+        assert_eq!(drawing.shapes[0].area(), 10.0);
+        assert_eq!(
+            reflect_path(&drawing, "shapes[0].type")
+                .unwrap()
+                .downcast_ref::<String>()
+                .unwrap(),
+            any::type_name::<Rect>()
+        );
+
+        // Otherwise, the new object is the default value of the type.
+        let mut patch = DynamicStruct::default();
+        patch.insert("type", any::type_name::<Square>().to_string());
+        registry.set_current(|| drawing.shapes[0].apply(&patch));
+        assert!(drawing.shapes[0].is::<Square>());
+        assert_eq!(drawing.shapes[0].area(), 0.0);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn apply_with_type_tags() {
+        let mut registry = registry();
+        let mut shape: Tagged<dyn Shape> = Tagged::new(Box::new(Square { side: 2.0 }));
+        let patch = |tag: &str, side: f32| {
+            let mut value = DynamicStruct::default();
+            value.insert("side", side);
+            let mut patch = DynamicStruct::default();
+            patch.insert("type", tag.to_string());
+            patch.insert("value", value);
+            patch
+        };
+
+        // A short name or a tag naming the current type patches the object in
+        // place, rather than replacing it with a default value.
+        registry.set_current(|| shape.apply(&patch("Square", 3.0)));
+        assert_eq!(shape.area(), 9.0);
+        registry.register_serialized_as::<Square>("square/v1");
+        registry.set_current(|| shape.apply(&patch("square/v1", 4.0)));
+        assert_eq!(shape.area(), 16.0);
+        assert_eq!(
+            shape
+                .field("type")
+                .unwrap()
+                .downcast_ref::<String>()
+                .unwrap(),
+            any::type_name::<Square>()
+        );
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn clone_and_from_reflect() {
+        let registry = registry();
+        let drawing = Drawing {
+            shapes: vec![
+                Tagged::new(Box::new(Square { side: 2.0 })),
+                Tagged::new(Box::new(Rect {
+                    width: 1.0,
+                    height: 3.0,
+                })),
+            ],
+        };
+        let dynamic = drawing.clone_value();
+        assert_eq!(dynamic.reflect_partial_eq(&drawing), Some(true));
+
+        assert!(Drawing::from_reflect(&*dynamic).is_none());
+        let copy = registry
+            .set_current(|| Drawing::from_reflect(&*dynamic))
+            .unwrap();
+        assert_eq!(copy.shapes[0].area(), 4.0);
+        assert_eq!(copy.shapes[1].area(), 3.0);
+        assert_eq!(copy.reflect_partial_eq(&drawing), Some(true));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot change the type of a Tagged")]
+    fn unregistered_type_panics() {
+        let mut shape: Tagged<dyn Shape> = Tagged::new(Box::new(Square { side: 1.0 }));
+        let mut patch = DynamicStruct::default();
+        patch.insert("type", String::from("Circle"));
+        registry().set_current(|| shape.apply(&patch));
+    }
+}
@@ -0,0 +1,177 @@
+use std::any::TypeId;
+
+use bevy_reflect::{TypeRegistration, TypeRegistry};
+
+use crate::{
+    global_registration::DynamicTraitFn,
+    type_registry::{is_registry_data, user_registrations},
+};
+
+/// A dynamic trait that was registered globally with
+/// [`impl_dynamic_trait!(MyTrait, ReflectMyTrait, global)`](crate::impl_dynamic_trait).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DynamicTraitInfo {
+    /// The name of the trait (see
+    /// [`DynamicTrait::reflect_name()`](crate::DynamicTrait::reflect_name)).
+    pub name: &'static str,
+    /// The [`TypeId`] of the trait's [`TypeData`](bevy_reflect::TypeData),
+    /// i.e. `ReflectMyTrait`.
+    pub type_data_id: TypeId,
+}
+
+/// All dynamic traits registered globally, in any crate linked into the
+/// program.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, global_dynamic_traits};
+/// #[reflect_trait]
+/// trait Plugin: DowncastReflect {}
+/// impl_dynamic_trait!(Plugin, ReflectPlugin, global);
+///
+/// assert!(global_dynamic_traits().iter().any(|info| info.name == "Plugin"));
+/// ```
+#[must_use]
+pub fn global_dynamic_traits() -> Vec<DynamicTraitInfo> {
+    inventory::iter::<DynamicTraitFn>
+        .into_iter()
+        .map(|submission| DynamicTraitInfo {
+            name: submission.name,
+            type_data_id: (submission.type_data_id)(),
+        })
+        .collect()
+}
+
+/// Type data listing the globally registered dynamic traits that a type
+/// implements.
+///
+/// This is inserted by
+/// [`TypeRegistryExt::index_dynamic_traits()`](crate::TypeRegistryExt::index_dynamic_traits),
+/// and is what
+/// [`TypeRegistryExt::implementors_of()`](crate::TypeRegistryExt::implementors_of)
+/// looks up.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImplementedTraits(Vec<&'static str>);
+
+impl ImplementedTraits {
+    /// The names of the traits.
+    #[must_use]
+    pub fn names(&self) -> &[&'static str] {
+        &self.0
+    }
+
+    /// True if the trait named `name` is implemented.
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains(&name)
+    }
+}
+
+pub(crate) fn index_dynamic_traits(registry: &mut TypeRegistry) {
+    let traits: Vec<&DynamicTraitFn> = inventory::iter::<DynamicTraitFn>.into_iter().collect();
+    for registration in registry.iter_mut() {
+        if is_registry_data(registration.type_id()) {
+            continue;
+        }
+        let names: Vec<&'static str> = traits
+            .iter()
+            .filter(|submission| (submission.implemented_by)(registration))
+            .map(|submission| submission.name)
+            .collect();
+        if !names.is_empty() {
+            registration.insert(ImplementedTraits(names));
+        }
+    }
+}
+
+pub(crate) fn implementors_of<'a>(
+    registry: &'a TypeRegistry,
+    trait_name: &str,
+) -> Vec<&'a TypeRegistration> {
+    let mut implementors: Vec<_> = user_registrations(registry)
+        .filter(|registration| {
+            matches!(registration.data::<ImplementedTraits>(), Some(traits) if traits.contains(trait_name))
+        })
+        .collect();
+    implementors.sort_by_key(|registration| registration.type_name());
+    implementors
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{reflect_trait, Reflect};
+
+    use crate::{impl_dynamic_trait, DowncastReflect, TypeRegistryExt};
+
+    use super::*;
+
+    #[reflect_trait]
+    trait Tool: DowncastReflect {}
+    impl_dynamic_trait!(Tool, ReflectTool, global);
+
+    #[derive(Reflect)]
+    #[reflect(Tool)]
+    struct Hammer;
+    impl Tool for Hammer {}
+
+    #[derive(Reflect)]
+    #[reflect(Tool)]
+    struct Saw;
+    impl Tool for Saw {}
+
+    #[derive(Reflect)]
+    struct Nail;
+
+    fn short_names(registrations: Vec<&TypeRegistration>) -> Vec<&str> {
+        registrations
+            .into_iter()
+            .map(TypeRegistration::short_name)
+            .collect()
+    }
+
+    #[test]
+    fn global_traits() {
+        let info = global_dynamic_traits()
+            .into_iter()
+            .find(|info| info.name == "Tool")
+            .unwrap();
+        assert_eq!(info.type_data_id, TypeId::of::<ReflectTool>());
+    }
+
+    #[test]
+    fn index() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Saw>();
+        registry.register::<Nail>();
+        assert!(registry.implementors_of("Tool").is_empty());
+
+        registry.index_dynamic_traits();
+        assert_eq!(short_names(registry.implementors_of("Tool")), ["Saw"]);
+        assert!(registry.implementors_of("Fastener").is_empty());
+        assert!(registry
+            .get_type_data::<ImplementedTraits>(TypeId::of::<Saw>())
+            .unwrap()
+            .contains("Tool"));
+        assert!(registry
+            .get_type_data::<ImplementedTraits>(TypeId::of::<Nail>())
+            .is_none());
+    }
+
+    #[test]
+    fn registered_after_indexing() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Saw>();
+        registry.index_dynamic_traits();
+
+        // Types registered after the index is built are not included until
+        // the registry is indexed again.
+        registry.register::<Hammer>();
+        assert_eq!(short_names(registry.implementors_of("Tool")), ["Saw"]);
+
+        registry.index_dynamic_traits();
+        assert_eq!(
+            short_names(registry.implementors_of("Tool")),
+            ["Hammer", "Saw"]
+        );
+    }
+}
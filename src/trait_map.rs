@@ -0,0 +1,218 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt,
+};
+
+use bevy_reflect::{Reflect, TypeRegistry};
+
+use crate::{CastBox, DynamicTrait, TypeError};
+
+struct Entry {
+    trait_name: &'static str,
+    /// `Box<P>`, where `P` is the dynamic trait.
+    object: Box<dyn Any>,
+}
+
+/// A map holding at most one object per dynamic trait, such as one `dyn
+/// Renderer` and one `dyn AudioBackend`.
+///
+/// Objects are inserted as `Box<dyn Reflect>`, and cast to the trait with a
+/// [`TypeRegistry`] when they are inserted, so that lookups do not need the
+/// registry.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, TraitMap};
+/// #[reflect_trait]
+/// trait Renderer: DowncastReflect {
+///     fn name(&self) -> &str;
+/// }
+/// impl_dynamic_trait!(Renderer, ReflectRenderer);
+///
+/// #[derive(Reflect)]
+/// #[reflect(Renderer)]
+/// struct Vulkan;
+/// impl Renderer for Vulkan {
+///     fn name(&self) -> &str { "vulkan" }
+/// }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Vulkan>();
+///
+/// let mut services = TraitMap::new();
+/// let plugin_object: Box<dyn Reflect> = Box::new(Vulkan);
+/// services.insert::<dyn Renderer>(plugin_object, &registry).unwrap();
+///
+/// assert_eq!(services.get::<dyn Renderer>().unwrap().name(), "vulkan");
+/// assert!(services.get::<dyn Reflect>().is_none());
+/// ```
+#[derive(Default)]
+pub struct TraitMap {
+    objects: HashMap<TypeId, Entry>,
+}
+
+type InsertResult<P> = Result<Option<Box<P>>, (Box<dyn Reflect>, TypeError)>;
+
+impl TraitMap {
+    /// Create an empty map.
+    #[must_use]
+    pub fn new() -> Self {
+        TraitMap::default()
+    }
+
+    /// The number of objects.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// True if there are no objects.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Cast `object` to the dynamic trait `P` using type information from
+    /// `registry`, and insert it. Returns the object that was previously
+    /// inserted for `P`, if any.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `object` cannot be cast to `P`. `object` is given back with
+    /// the error, and the map is unchanged.
+    pub fn insert<P: DynamicTrait + ?Sized>(
+        &mut self,
+        object: Box<dyn Reflect>,
+        registry: &TypeRegistry,
+    ) -> InsertResult<P> {
+        let object = object.try_cast_box_with_registry::<P>(registry)?;
+        Ok(self.insert_boxed(object))
+    }
+
+    /// Insert an object that is already a `Box<P>`. Returns the object that
+    /// was previously inserted for `P`, if any.
+    pub fn insert_boxed<P: DynamicTrait + ?Sized>(&mut self, object: Box<P>) -> Option<Box<P>> {
+        let entry = Entry {
+            trait_name: P::reflect_name(),
+            object: Box::new(object),
+        };
+        self.objects
+            .insert(TypeId::of::<P>(), entry)
+            .map(|previous| unbox(previous.object))
+    }
+
+    /// True if there is an object for `P`.
+    #[must_use]
+    pub fn contains<P: DynamicTrait + ?Sized>(&self) -> bool {
+        self.objects.contains_key(&TypeId::of::<P>())
+    }
+
+    /// Get the object for `P`.
+    #[must_use]
+    // Entries are keyed by the `TypeId` of their trait, so this cannot panic.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn get<P: DynamicTrait + ?Sized>(&self) -> Option<&P> {
+        let entry = self.objects.get(&TypeId::of::<P>())?;
+        let object = entry
+            .object
+            .downcast_ref::<Box<P>>()
+            .expect("entry is keyed by its trait");
+        Some(&**object)
+    }
+
+    /// Get the object for `P` by mutable reference.
+    // Entries are keyed by the `TypeId` of their trait, so this cannot panic.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn get_mut<P: DynamicTrait + ?Sized>(&mut self) -> Option<&mut P> {
+        let entry = self.objects.get_mut(&TypeId::of::<P>())?;
+        let object = entry
+            .object
+            .downcast_mut::<Box<P>>()
+            .expect("entry is keyed by its trait");
+        Some(&mut **object)
+    }
+
+    /// Remove the object for `P`, and return it.
+    pub fn remove<P: DynamicTrait + ?Sized>(&mut self) -> Option<Box<P>> {
+        self.objects
+            .remove(&TypeId::of::<P>())
+            .map(|entry| unbox(entry.object))
+    }
+
+    /// Iterate over the names of the traits that have an object (see
+    /// [`DynamicTrait::reflect_name()`]), in no particular order.
+    pub fn trait_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.objects.values().map(|entry| entry.trait_name)
+    }
+}
+
+impl fmt::Debug for TraitMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.trait_names()).finish()
+    }
+}
+
+fn unbox<P: DynamicTrait + ?Sized>(object: Box<dyn Any>) -> Box<P> {
+    *object
+        .downcast::<Box<P>>()
+        .expect("entry is keyed by its trait")
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::reflect_trait;
+
+    use super::*;
+    use crate::{impl_dynamic_trait, DowncastReflect};
+
+    #[reflect_trait]
+    trait Logger: DowncastReflect {
+        fn level(&self) -> u32;
+    }
+    impl_dynamic_trait!(Logger, ReflectLogger);
+
+    #[reflect_trait]
+    trait Clock: DowncastReflect {}
+    impl_dynamic_trait!(Clock, ReflectClock);
+
+    #[derive(Reflect)]
+    #[reflect(Logger)]
+    struct StdoutLogger(u32);
+    impl Logger for StdoutLogger {
+        fn level(&self) -> u32 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn checked_insert_and_replace() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<StdoutLogger>();
+        let mut map = TraitMap::new();
+
+        let (object, err) = map
+            .insert::<dyn Clock>(Box::new(StdoutLogger(1)), &registry)
+            .unwrap_err();
+        assert!(matches!(err, TypeError::UnregisteredTrait(_, "Clock")));
+        assert!(object.is::<StdoutLogger>());
+        assert!(map.is_empty());
+
+        let previous = map
+            .insert::<dyn Logger>(Box::new(StdoutLogger(1)), &registry)
+            .unwrap();
+        assert!(previous.is_none());
+        map.get_mut::<dyn Logger>()
+            .unwrap()
+            .downcast_mut::<StdoutLogger>()
+            .unwrap()
+            .0 = 2;
+
+        let previous = map.insert_boxed::<dyn Logger>(Box::new(StdoutLogger(3)));
+        assert_eq!(previous.unwrap().level(), 2);
+        assert_eq!(map.trait_names().collect::<Vec<_>>(), ["Logger"]);
+        assert!(!map.contains::<dyn Clock>());
+        assert_eq!(map.remove::<dyn Logger>().unwrap().level(), 3);
+        assert!(map.get::<dyn Logger>().is_none());
+    }
+}
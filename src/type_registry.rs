@@ -1,9 +1,75 @@
-use bevy_reflect::TypeRegistry;
+use std::{
+    any::{Any, TypeId},
+    collections::BTreeSet,
+};
+
+use bevy_reflect::{
+    FromReflect, FromType, GetTypeRegistration, Reflect, TypeData, TypeRegistration, TypeRegistry,
+    Typed,
+};
+
+use crate::{
+    register_hooks,
+    serialization::{
+        registry_fingerprint, schema_hash, Redact, SerializationPolicy, SerializeTransparent,
+        TagDisambiguation,
+    },
+    supertrait, CastPolicy, DynamicTrait, MissingTypeRetry, ReflectInto, TypeError, Upcast,
+};
 
 scoped_tls::scoped_thread_local!(
     static CURRENT_TYPE_REGISTRY: TypeRegistry
 );
 
+/// A registration that carries type data belonging to the registry as a
+/// whole, such as its [`MissingTypeRetry`].
+///
+/// Its short name is registered along with those of user types, so it is
+/// prefixed with the name of the crate to keep it from making the short name
+/// of a user type ambiguous. Code iterating over the registry skips it with
+/// [`user_registrations()`].
+#[derive(Reflect)]
+struct ReflectSteroidsRegistryData;
+
+/// True if `type_id` is the type of the registration carrying registry-wide
+/// type data, which is not a user type. See [`ReflectSteroidsRegistryData`].
+pub(crate) fn is_registry_data(type_id: TypeId) -> bool {
+    type_id == TypeId::of::<ReflectSteroidsRegistryData>()
+}
+
+/// The registrations of `registry`, without the one carrying registry-wide
+/// type data.
+pub(crate) fn user_registrations(
+    registry: &TypeRegistry,
+) -> impl Iterator<Item = &TypeRegistration> + '_ {
+    registry
+        .iter()
+        .filter(|&registration| !is_registry_data(registration.type_id()))
+}
+
+/// Get registry-wide type data. See [`ReflectSteroidsRegistryData`].
+pub(crate) fn registry_data<T: TypeData>(registry: &TypeRegistry) -> Option<&T> {
+    registry.get_type_data::<T>(TypeId::of::<ReflectSteroidsRegistryData>())
+}
+
+/// Get the registration carrying registry-wide type data, registering it if
+/// necessary. See [`ReflectSteroidsRegistryData`].
+pub(crate) fn registry_data_mut(registry: &mut TypeRegistry) -> &mut TypeRegistration {
+    if registry
+        .get(TypeId::of::<ReflectSteroidsRegistryData>())
+        .is_none()
+    {
+        registry.register::<ReflectSteroidsRegistryData>();
+    }
+    registry
+        .get_mut(TypeId::of::<ReflectSteroidsRegistryData>())
+        .expect("type was just registered")
+}
+
+/// The names of the dynamic traits known to a registry.
+#[derive(Clone, Default)]
+struct DynamicTraitNames(BTreeSet<&'static str>);
+
 /// Extension methods for [`TypeRegistry`](bevy_reflect::TypeRegistry).
 pub trait TypeRegistryExt {
     /// True if a registry has been set for the current thread with
@@ -13,44 +79,639 @@ pub trait TypeRegistryExt {
     }
 
     /// Get the current thread's [`TypeRegistry`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if no registry has been set. See
+    /// [`TypeRegistryExt::try_with_current`].
     fn with_current<F: FnOnce(&TypeRegistry) -> R, R>(f: F) -> R {
         CURRENT_TYPE_REGISTRY.with(f)
     }
 
+    /// Get the current thread's [`TypeRegistry`].
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`TypeError::NoCurrentRegistry`] if no registry has been set.
+    fn try_with_current<F: FnOnce(&TypeRegistry) -> R, R>(f: F) -> Result<R, TypeError> {
+        if CURRENT_TYPE_REGISTRY.is_set() {
+            Ok(CURRENT_TYPE_REGISTRY.with(f))
+        } else {
+            Err(TypeError::NoCurrentRegistry)
+        }
+    }
+
     /// Set the current thread's [`TypeRegistry`].
     ///
     /// Upon return, the previously set [`TypeRegistry`] will become current
     /// again.
     fn set_current<F: FnOnce() -> R, R>(&self, f: F) -> R;
 
+    /// Register `T` (if it isn't already) and make it (de)serialize with the
+    /// type tag `tag` instead of its short name. See
+    /// [`SerializedAs`](crate::serialization::SerializedAs).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tag` is already the tag or short name of another registered
+    /// type.
+    fn register_serialized_as<T: Reflect + GetTypeRegistration>(&mut self, tag: &'static str);
+
+    /// Register `T` (if it isn't already) and identify it by `id` in compact
+    /// binary formats. See [`StableId`](crate::serialization::StableId).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is already the stable ID of another registered type.
+    fn register_stable_id<T: Reflect + GetTypeRegistration>(&mut self, id: u32);
+
+    /// Register `T` (if it isn't already) and make it (de)serialize as its
+    /// single field. See [`SerializeTransparent`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` is not a tuple struct with a single field.
+    fn register_serialize_transparent<T>(&mut self)
+    where
+        T: Reflect + Typed + FromReflect + GetTypeRegistration;
+
+    /// Register `T` (if it isn't already) and control how its fields are
+    /// serialized. See [`SerializationPolicy`].
+    fn register_serialization_policy<T: Reflect + GetTypeRegistration>(
+        &mut self,
+        policy: SerializationPolicy,
+    );
+
+    /// Register `T` (if it isn't already) and hide some of its fields when it
+    /// is serialized in redaction mode. See [`Redact`].
+    fn register_redaction<T: Reflect + GetTypeRegistration>(&mut self, redaction: Redact);
+
+    /// Make the dynamic trait `P` known to the registry, so that it is listed
+    /// by [`dynamic_traits()`](TypeRegistryExt::dynamic_traits).
+    ///
+    /// Traits registered globally (see
+    /// [`impl_dynamic_trait!`](crate::impl_dynamic_trait)) are added by
+    /// [`register_global_types()`](TypeRegistryExt::register_global_types).
+    fn register_dynamic_trait<P: DynamicTrait + ?Sized>(&mut self);
+
+    /// The names (see [`DynamicTrait::reflect_name()`]) of the dynamic traits
+    /// known to the registry, in alphabetical order.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+    /// #[reflect_trait]
+    /// trait Interactable: DowncastReflect {}
+    /// impl_dynamic_trait!(Interactable, ReflectInteractable);
+    ///
+    /// let mut registry = TypeRegistry::default();
+    /// registry.register_dynamic_trait::<dyn Interactable>();
+    /// assert!(registry.dynamic_traits().any(|name| name == "Interactable"));
+    /// ```
+    fn dynamic_traits(&self) -> Box<dyn Iterator<Item = &'static str> + '_>;
+
+    /// Only allow the types named in `allowed` to be deserialized as
+    /// `Box<P>`, e.g. when deserializing untrusted data from the network.
+    ///
+    /// Types may be named by their type tag, short name, or full type name.
+    /// Deserializing any other type as `Box<P>` fails with
+    /// [`TypeError::ForbiddenType`], before the payload of the object is
+    /// deserialized. Restricting the trait again replaces the list.
+    ///
+    /// The restriction applies to `Box<P>`, including in
+    /// [`dyn_field`](crate::serialization::dyn_field) fields (see
+    /// [`deserialize_trait_object()`](crate::serialization::deserialize_trait_object)),
+    /// but not to `Box<dyn Reflect>` or other traits implemented by the same
+    /// types.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+    /// #[reflect_trait]
+    /// trait Command: DowncastReflect {}
+    /// impl_dynamic_trait!(Command, ReflectCommand);
+    ///
+    /// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    /// #[reflect(Command, Serialize, Deserialize)]
+    /// struct Move { x: i32, y: i32 }
+    /// impl Command for Move {}
+    ///
+    /// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    /// #[reflect(Command, Serialize, Deserialize)]
+    /// struct GrantAdmin { user: String }
+    /// impl Command for GrantAdmin {}
+    ///
+    /// let mut registry = TypeRegistry::default();
+    /// registry.register::<Move>();
+    /// registry.register::<GrantAdmin>();
+    /// registry.restrict::<dyn Command>(&["Move"]);
+    ///
+    /// registry.set_current(|| {
+    ///     let json = r#"{"type":"Move","x":1,"y":2}"#;
+    ///     assert!(serde_json::from_str::<Box<dyn Command>>(json).is_ok());
+    ///
+    ///     let json = r#"{"type":"GrantAdmin","user":"mallory"}"#;
+    ///     let err = serde_json::from_str::<Box<dyn Command>>(json).unwrap_err();
+    ///     assert!(err.to_string().contains("may not be deserialized as dyn Command"));
+    /// });
+    /// ```
+    fn restrict<P: DynamicTrait + ?Sized>(&mut self, allowed: &[&str]);
+
+    /// Declare that `P` is a supertrait of `C` (`dyn C: dyn P`).
+    ///
+    /// Any type that implements `C` according to the registry is then also
+    /// considered to implement `P` by
+    /// [`implements()`](TypeRegistryExt::implements), and can be cast to `P`
+    /// through `C`, even if `#[reflect(P)]` is missing from the type. See
+    /// [`impl_supertrait!`](crate::impl_supertrait).
+    fn register_supertrait<C, P>(&mut self)
+    where
+        C: DynamicTrait + Upcast<P> + ?Sized,
+        P: DynamicTrait + ?Sized;
+
+    /// Register `T` (if it isn't already) and make it convertible to the trait
+    /// object `P` with `f`, by inserting [`ReflectInto<P>`] type data. See
+    /// [`convert()`](crate::convert()).
+    fn register_conversion<T, P, F>(&mut self, f: F)
+    where
+        T: Reflect + GetTypeRegistration,
+        P: DynamicTrait + ?Sized,
+        F: Fn(T) -> Box<P> + Send + Sync + 'static;
+
+    /// Register `T` (if it isn't already) and insert the type data of the
+    /// dynamic trait `P` for it, as `#[reflect(MyTrait)]` would.
+    ///
+    /// This makes types that are defined without knowledge of the trait, such
+    /// as types from other crates, castable to it. The type data is created
+    /// with [`FromType<T>`], which [`bevy_reflect::reflect_trait`] implements
+    /// for the types that implement the trait. `P` is also made known to the
+    /// registry, as by [`register_dynamic_trait()`](TypeRegistryExt::register_dynamic_trait).
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, Cast};
+    /// #[reflect_trait]
+    /// trait Describe: DowncastReflect {
+    ///     fn describe(&self) -> String;
+    /// }
+    /// impl_dynamic_trait!(Describe, ReflectDescribe);
+    ///
+    /// // Imagine this type lives in a crate that does not know `Describe`.
+    /// #[derive(Reflect)]
+    /// struct Point { x: i32, y: i32 }
+    ///
+    /// impl Describe for Point {
+    ///     fn describe(&self) -> String {
+    ///         format!("({}, {})", self.x, self.y)
+    ///     }
+    /// }
+    ///
+    /// let mut registry = TypeRegistry::default();
+    /// registry.register_trait_impl::<Point, dyn Describe>();
+    ///
+    /// let point: &dyn Reflect = &Point { x: 1, y: 2 };
+    /// let described = Cast::<dyn Describe>::try_cast_with_registry(point, &registry).unwrap();
+    /// assert_eq!(described.describe(), "(1, 2)");
+    /// ```
+    fn register_trait_impl<T, P>(&mut self)
+    where
+        T: Reflect + GetTypeRegistration,
+        P: DynamicTrait + ?Sized,
+        P::TypeData: FromType<T>;
+
+    /// True if the registered type with the given [`TypeId`] implements the
+    /// dynamic trait `P`, directly or through a subtrait registered with
+    /// [`register_supertrait()`](TypeRegistryExt::register_supertrait).
+    fn implements<P: DynamicTrait + ?Sized>(&self, type_id: TypeId) -> bool;
+
+    /// Groups of registered types that share a short name, and therefore
+    /// cannot be deserialized by their short name. Groups are ordered by short
+    /// name, and types within a group by type name.
+    fn ambiguous_short_names(&self) -> Vec<Vec<&TypeRegistration>>;
+
+    /// A stable hash of the structure of the registered type `T`: its kind,
+    /// and the names, types and order of its fields. Returns `None` if `T` is
+    /// not registered.
+    ///
+    /// The hash is the same as the `schema` of `T` in a
+    /// [`Manifest`](crate::serialization::Manifest). Only the names of field
+    /// types are hashed, not their structure; use
+    /// [`registry_fingerprint()`](TypeRegistryExt::registry_fingerprint) to
+    /// detect changes anywhere in a set of types.
+    fn layout_hash<T: 'static>(&self) -> Option<u64>;
+
+    /// A stable hash of the tags, [`TypeVersion`](crate::serialization::TypeVersion)s
+    /// and layout hashes of all registered types with `ReflectSerialize` or
+    /// `ReflectDeserialize` type data.
+    ///
+    /// Store the fingerprint in the header of a save file, and compare it
+    /// before deserializing to detect incompatible schema changes up front.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+    /// mod v1 {
+    ///     # use reflect_steroids::prelude::*;
+    ///     #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    ///     #[reflect(Serialize, Deserialize)]
+    ///     pub struct Player { pub name: String }
+    /// }
+    /// mod v2 {
+    ///     # use reflect_steroids::prelude::*;
+    ///     #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    ///     #[reflect(Serialize, Deserialize)]
+    ///     pub struct Player { pub name: String, pub level: u32 }
+    /// }
+    ///
+    /// let mut old = TypeRegistry::default();
+    /// old.register::<v1::Player>();
+    /// let mut new = TypeRegistry::default();
+    /// new.register::<v2::Player>();
+    ///
+    /// assert_ne!(old.layout_hash::<v1::Player>(), new.layout_hash::<v2::Player>());
+    /// assert_ne!(old.registry_fingerprint(), new.registry_fingerprint());
+    /// ```
+    fn registry_fingerprint(&self) -> u64;
+
+    /// Assign distinct type tags to all types with ambiguous short names,
+    /// according to `policy`. See [`TagDisambiguation`].
+    ///
+    /// Call this after registering all types; types registered later are not
+    /// disambiguated.
+    fn disambiguate_short_names(&mut self, policy: TagDisambiguation);
+
+    /// Install a callback that is given a chance to register missing types
+    /// when an operation run with
+    /// [`retry_on_missing_type()`](TypeRegistryExt::retry_on_missing_type)
+    /// fails because of an unregistered type. See [`MissingTypeRetry`].
+    ///
+    /// The callback is not invoked by casts or (de)serialization that are not
+    /// wrapped in `retry_on_missing_type()`.
+    fn set_missing_type_retry<F>(&mut self, handler: F)
+    where
+        F: Fn(&mut TypeRegistry, &TypeError) -> bool + Send + Sync + 'static;
+
+    /// Install a policy that decides which dynamic traits objects may be cast
+    /// to, depending on the context set with
+    /// [`with_cast_context()`](crate::with_cast_context). See [`CastPolicy`].
+    fn set_cast_policy<F>(&mut self, policy: F)
+    where
+        F: Fn(&str, &TypeRegistration, Option<&dyn Any>) -> bool + Send + Sync + 'static;
+
+    /// Run `f`, which may cast or (de)serialize trait objects with the
+    /// registry. If it fails because a type is not registered, the callback
+    /// installed with
+    /// [`set_missing_type_retry()`](TypeRegistryExt::set_missing_type_retry)
+    /// is invoked, and if it returns true, `f` is run once more.
+    ///
+    /// This is the only place the callback is invoked: `f` takes the registry
+    /// by shared reference, so the callback can only register types between
+    /// attempts.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the last attempt of `f`.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, TypeError};
+    /// #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    /// #[reflect(Serialize, Deserialize)]
+    /// struct Foo { num: i32 }
+    ///
+    /// let mut registry = TypeRegistry::default();
+    /// registry.set_missing_type_retry(|registry, missing| match missing {
+    ///     TypeError::UnregisteredShortName(tag) if tag == "Foo" => {
+    ///         registry.register::<Foo>();
+    ///         true
+    ///     }
+    ///     _ => false,
+    /// });
+    ///
+    /// let object = registry.retry_on_missing_type(|registry| {
+    ///     registry.set_current(|| {
+    ///         let mut deserializer = serde_json::Deserializer::from_str(r#"{"type":"Foo","num":1}"#);
+    ///         reflect_steroids::serialization::deserialize(&mut deserializer)
+    ///     })
+    /// });
+    /// assert!(object.unwrap().is::<Foo>());
+    /// ```
+    fn retry_on_missing_type<F, R, E>(&mut self, f: F) -> Result<R, E>
+    where
+        F: FnMut(&TypeRegistry) -> Result<R, E>;
+
+    /// Install a hook that is called once for every type in the registry, so
+    /// that caches of registry contents can be updated incrementally.
+    ///
+    /// The hook is called right away for the types that are already
+    /// registered. Types registered later are reported when the hooks are
+    /// run: by [`register_with_hooks()`](TypeRegistryExt::register_with_hooks),
+    /// by the other `register_*` methods of this trait, which run the hooks
+    /// after inserting their type data, and by
+    /// [`run_register_hooks()`](TypeRegistryExt::run_register_hooks), for types
+    /// registered with the methods of [`TypeRegistry`] itself.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use std::sync::{Arc, Mutex};
+    /// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+    /// #[derive(Reflect)]
+    /// struct Rock;
+    ///
+    /// #[derive(Reflect)]
+    /// struct Tree;
+    ///
+    /// let names = Arc::new(Mutex::new(Vec::new()));
+    /// let mut registry = TypeRegistry::empty();
+    /// registry.register::<Rock>();
+    ///
+    /// let hook_names = names.clone();
+    /// registry.on_register(move |_, registration| {
+    ///     hook_names.lock().unwrap().push(registration.short_name().to_string());
+    /// });
+    /// registry.register_with_hooks::<Tree>();
+    /// assert_eq!(*names.lock().unwrap(), ["Rock", "Tree"]);
+    /// ```
+    fn on_register<F>(&mut self, hook: F)
+    where
+        F: Fn(TypeId, &TypeRegistration) + Send + Sync + 'static;
+
+    /// Register `T` (if it isn't already) and run the hooks installed with
+    /// [`on_register()`](TypeRegistryExt::on_register).
+    fn register_with_hooks<T: Reflect + GetTypeRegistration>(&mut self);
+
+    /// Run the hooks installed with
+    /// [`on_register()`](TypeRegistryExt::on_register) for all types
+    /// registered since they last ran.
+    fn run_register_hooks(&mut self);
+
     /// Include types in the type registry that have been mentioned by the
     /// [`enable_global_type_registration`](crate::enable_global_type_registration)
     /// macro.
+    ///
+    /// This also indexes the implementors of globally registered dynamic
+    /// traits (see [`TypeRegistryExt::index_dynamic_traits()`]).
     #[cfg(feature = "inventory")]
     fn register_global_types(&mut self);
+
+    /// Record which globally registered dynamic traits each registered type
+    /// implements, as [`ImplementedTraits`](crate::ImplementedTraits) type
+    /// data. Call this again after registering more types.
+    ///
+    /// Dynamic traits are registered globally with
+    /// [`impl_dynamic_trait!(MyTrait, ReflectMyTrait, global)`](crate::impl_dynamic_trait).
+    #[cfg(feature = "inventory")]
+    fn index_dynamic_traits(&mut self);
+
+    /// The registered types that implement the globally registered dynamic
+    /// trait named `trait_name`, ordered by type name. The registry must have
+    /// been indexed (see [`TypeRegistryExt::index_dynamic_traits()`]). Types
+    /// registered after the registry was last indexed are not included.
+    ///
+    /// ## Example
+    /// ```rust
+    /// # use reflect_steroids::{prelude::*, reflect::TypeRegistry};
+    /// #[reflect_trait]
+    /// trait Tool: DowncastReflect {}
+    /// impl_dynamic_trait!(Tool, ReflectTool, global);
+    ///
+    /// #[derive(Reflect)]
+    /// #[reflect(Tool)]
+    /// struct Hammer;
+    /// impl Tool for Hammer {}
+    ///
+    /// let mut registry = TypeRegistry::default();
+    /// registry.register::<Hammer>();
+    /// registry.register_global_types();
+    ///
+    /// let tools = registry.implementors_of("Tool");
+    /// assert_eq!(tools.len(), 1);
+    /// assert_eq!(tools[0].short_name(), "Hammer");
+    /// ```
+    #[cfg(feature = "inventory")]
+    fn implementors_of(&self, trait_name: &str) -> Vec<&TypeRegistration>;
 }
 
 #[cfg(feature = "inventory")]
 inventory::collect!(crate::global_registration::RegisterFn);
 
+#[cfg(feature = "inventory")]
+inventory::collect!(crate::global_registration::DynamicTraitFn);
+
 impl TypeRegistryExt for TypeRegistry {
     fn set_current<F: FnOnce() -> R, R>(&self, f: F) -> R {
         CURRENT_TYPE_REGISTRY.set(self, f)
     }
 
+    fn register_serialized_as<T: Reflect + GetTypeRegistration>(&mut self, tag: &'static str) {
+        if self.get(TypeId::of::<T>()).is_none() {
+            self.register::<T>();
+        }
+        crate::serialization::tag::set_serialized_as(self, TypeId::of::<T>(), tag);
+        register_hooks::run_hooks(self);
+    }
+
+    fn register_stable_id<T: Reflect + GetTypeRegistration>(&mut self, id: u32) {
+        if self.get(TypeId::of::<T>()).is_none() {
+            self.register::<T>();
+        }
+        crate::serialization::tag::set_stable_id(self, TypeId::of::<T>(), id);
+        register_hooks::run_hooks(self);
+    }
+
+    fn register_serialize_transparent<T>(&mut self)
+    where
+        T: Reflect + Typed + FromReflect + GetTypeRegistration,
+    {
+        if self.get(TypeId::of::<T>()).is_none() {
+            self.register::<T>();
+        }
+        self.get_mut(TypeId::of::<T>())
+            .expect("type was just registered")
+            .insert(<SerializeTransparent as FromType<T>>::from_type());
+        register_hooks::run_hooks(self);
+    }
+
+    fn register_serialization_policy<T: Reflect + GetTypeRegistration>(
+        &mut self,
+        policy: SerializationPolicy,
+    ) {
+        if self.get(TypeId::of::<T>()).is_none() {
+            self.register::<T>();
+        }
+        self.get_mut(TypeId::of::<T>())
+            .expect("type was just registered")
+            .insert(policy);
+        register_hooks::run_hooks(self);
+    }
+
+    fn register_redaction<T: Reflect + GetTypeRegistration>(&mut self, redaction: Redact) {
+        if self.get(TypeId::of::<T>()).is_none() {
+            self.register::<T>();
+        }
+        self.get_mut(TypeId::of::<T>())
+            .expect("type was just registered")
+            .insert(redaction);
+        register_hooks::run_hooks(self);
+    }
+
+    fn register_dynamic_trait<P: DynamicTrait + ?Sized>(&mut self) {
+        add_dynamic_trait_name(self, P::reflect_name());
+    }
+
+    fn dynamic_traits(&self) -> Box<dyn Iterator<Item = &'static str> + '_> {
+        match registry_data::<DynamicTraitNames>(self) {
+            Some(names) => Box::new(names.0.iter().copied()),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+
+    fn restrict<P: DynamicTrait + ?Sized>(&mut self, allowed: &[&str]) {
+        crate::serialization::restrict::restrict(self, P::reflect_name(), allowed);
+    }
+
+    fn register_supertrait<C, P>(&mut self)
+    where
+        C: DynamicTrait + Upcast<P> + ?Sized,
+        P: DynamicTrait + ?Sized,
+    {
+        supertrait::register_supertrait::<C, P>(self);
+    }
+
+    fn register_conversion<T, P, F>(&mut self, f: F)
+    where
+        T: Reflect + GetTypeRegistration,
+        P: DynamicTrait + ?Sized,
+        F: Fn(T) -> Box<P> + Send + Sync + 'static,
+    {
+        if self.get(TypeId::of::<T>()).is_none() {
+            self.register::<T>();
+        }
+        self.get_mut(TypeId::of::<T>())
+            .expect("type was just registered")
+            .insert(ReflectInto::<P>::new(f));
+        register_hooks::run_hooks(self);
+    }
+
+    fn register_trait_impl<T, P>(&mut self)
+    where
+        T: Reflect + GetTypeRegistration,
+        P: DynamicTrait + ?Sized,
+        P::TypeData: FromType<T>,
+    {
+        if self.get(TypeId::of::<T>()).is_none() {
+            self.register::<T>();
+        }
+        self.get_mut(TypeId::of::<T>())
+            .expect("type was just registered")
+            .insert(<P::TypeData as FromType<T>>::from_type());
+        add_dynamic_trait_name(self, P::reflect_name());
+        register_hooks::run_hooks(self);
+    }
+
+    fn implements<P: DynamicTrait + ?Sized>(&self, type_id: TypeId) -> bool {
+        let direct = self
+            .get(type_id)
+            .is_some_and(|registration| P::get_type_data(registration).is_some());
+        direct || supertrait::implements_through_subtrait::<P>(self, type_id)
+    }
+
+    fn ambiguous_short_names(&self) -> Vec<Vec<&TypeRegistration>> {
+        crate::serialization::tag::ambiguous_short_names(self)
+    }
+
+    fn layout_hash<T: 'static>(&self) -> Option<u64> {
+        self.get(TypeId::of::<T>())
+            .map(|registration| schema_hash(registration.type_info()))
+    }
+
+    fn registry_fingerprint(&self) -> u64 {
+        registry_fingerprint(self)
+    }
+
+    fn disambiguate_short_names(&mut self, policy: TagDisambiguation) {
+        crate::serialization::tag::disambiguate_short_names(self, policy);
+    }
+
+    fn set_missing_type_retry<F>(&mut self, handler: F)
+    where
+        F: Fn(&mut TypeRegistry, &TypeError) -> bool + Send + Sync + 'static,
+    {
+        registry_data_mut(self).insert(MissingTypeRetry::new(handler));
+    }
+
+    fn set_cast_policy<F>(&mut self, policy: F)
+    where
+        F: Fn(&str, &TypeRegistration, Option<&dyn Any>) -> bool + Send + Sync + 'static,
+    {
+        registry_data_mut(self).insert(CastPolicy::new(policy));
+    }
+
+    fn retry_on_missing_type<F, R, E>(&mut self, f: F) -> Result<R, E>
+    where
+        F: FnMut(&TypeRegistry) -> Result<R, E>,
+    {
+        crate::missing_type::retry_on_missing_type(self, f)
+    }
+
+    fn on_register<F>(&mut self, hook: F)
+    where
+        F: Fn(TypeId, &TypeRegistration) + Send + Sync + 'static,
+    {
+        register_hooks::add_hook(self, hook);
+    }
+
+    fn register_with_hooks<T: Reflect + GetTypeRegistration>(&mut self) {
+        if self.get(TypeId::of::<T>()).is_none() {
+            self.register::<T>();
+        }
+        register_hooks::run_hooks(self);
+    }
+
+    fn run_register_hooks(&mut self) {
+        register_hooks::run_hooks(self);
+    }
+
     #[cfg(feature = "inventory")]
     fn register_global_types(&mut self) {
         for register_fn in inventory::iter::<crate::global_registration::RegisterFn> {
             (register_fn.0)(self);
         }
+        for info in crate::global_dynamic_traits() {
+            add_dynamic_trait_name(self, info.name);
+        }
+        self.index_dynamic_traits();
+        register_hooks::run_hooks(self);
+    }
+
+    #[cfg(feature = "inventory")]
+    fn index_dynamic_traits(&mut self) {
+        crate::trait_index::index_dynamic_traits(self);
+    }
+
+    #[cfg(feature = "inventory")]
+    fn implementors_of(&self, trait_name: &str) -> Vec<&TypeRegistration> {
+        crate::trait_index::implementors_of(self, trait_name)
+    }
+}
+
+fn add_dynamic_trait_name(registry: &mut TypeRegistry, name: &'static str) {
+    let registration = registry_data_mut(registry);
+    if registration.data::<DynamicTraitNames>().is_none() {
+        registration.insert(DynamicTraitNames::default());
     }
+    registration
+        .data_mut::<DynamicTraitNames>()
+        .expect("type data was just inserted")
+        .0
+        .insert(name);
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "inventory"))]
 mod tests {
     use std::any::TypeId;
 
-    use bevy_reflect::TypeRegistry;
+    use bevy_reflect::{ReflectDeserialize, ReflectSerialize, TypeRegistration, TypeRegistry};
 
     use crate::prelude::*;
 
@@ -66,4 +727,67 @@ mod tests {
             .get(TypeId::of::<TestGlobal>())
             .expect("not registered");
     }
+
+    #[reflect_trait]
+    trait GlobalTrait: DowncastReflect {}
+    impl_dynamic_trait!(GlobalTrait, ReflectGlobalTrait, global);
+
+    #[derive(Reflect)]
+    #[reflect(GlobalTrait)]
+    struct Implementor;
+    impl GlobalTrait for Implementor {}
+    enable_global_type_registration!(Implementor);
+
+    #[test]
+    fn global_dynamic_trait() {
+        let info = crate::global_dynamic_traits()
+            .into_iter()
+            .find(|info| info.name == "GlobalTrait")
+            .expect("trait not registered");
+        assert_eq!(info.type_data_id, TypeId::of::<ReflectGlobalTrait>());
+
+        let mut registry = TypeRegistry::new();
+        registry.register_global_types();
+        assert!(registry.dynamic_traits().any(|name| name == "GlobalTrait"));
+        let implementors = registry.implementors_of("GlobalTrait");
+        assert_eq!(implementors.len(), 1);
+        assert_eq!(implementors[0].type_id(), TypeId::of::<Implementor>());
+        assert!(registry
+            .get_type_data::<crate::ImplementedTraits>(TypeId::of::<TestGlobal>())
+            .is_none());
+    }
+
+    /// A user type with the same name as the type carrying registry-wide
+    /// type data.
+    #[derive(Reflect, serde::Serialize, serde::Deserialize)]
+    #[reflect(GlobalTrait, Serialize, Deserialize)]
+    struct RegistryData {
+        value: i32,
+    }
+    impl GlobalTrait for RegistryData {}
+
+    #[test]
+    fn registry_data_does_not_collide() {
+        let mut registry = TypeRegistry::new();
+        registry.set_missing_type_retry(|_, _| false);
+        registry.register::<RegistryData>();
+        registry.register_global_types();
+
+        assert!(registry.ambiguous_short_names().is_empty());
+        assert_eq!(
+            registry
+                .get_with_short_name("RegistryData")
+                .map(TypeRegistration::type_id),
+            Some(TypeId::of::<RegistryData>())
+        );
+        assert_eq!(registry.implementors_of("GlobalTrait").len(), 2);
+
+        registry.set_current(|| {
+            let object: Box<dyn GlobalTrait> = Box::new(RegistryData { value: 3 });
+            let json = serde_json::to_string(&object).unwrap();
+            assert_eq!(json, r#"{"type":"RegistryData","value":3}"#);
+            let object: Box<dyn GlobalTrait> = serde_json::from_str(&json).unwrap();
+            assert_eq!(object.downcast_ref::<RegistryData>().unwrap().value, 3);
+        });
+    }
 }
@@ -0,0 +1,183 @@
+use bevy_reflect::{FromType, Reflect, TypeRegistry};
+
+use crate::{walk, ValidationError, Visit};
+
+/// Validation rules of a type. Register them with `#[reflect(Validate)]`,
+/// which adds [`ReflectValidate`] type data.
+pub trait Validate {
+    /// Check the value, and return the rules that it breaks. The paths of the
+    /// errors are relative to the value.
+    fn validate(&self) -> Vec<ValidationError>;
+}
+
+/// Type data for validating values of a type. See [`validate_deep()`].
+///
+/// Created with `#[reflect(Validate)]` on types that implement [`Validate`],
+/// or with [`ReflectValidate::new()`] for rules of types that cannot implement
+/// it, such as foreign types.
+#[derive(Clone)]
+pub struct ReflectValidate {
+    validate: fn(&dyn Reflect) -> Vec<ValidationError>,
+}
+
+impl ReflectValidate {
+    /// Create type data that validates with `validate`.
+    pub fn new(validate: fn(&dyn Reflect) -> Vec<ValidationError>) -> Self {
+        ReflectValidate { validate }
+    }
+
+    /// Check `value`, which must be of the type that the type data is
+    /// registered for.
+    pub fn validate(&self, value: &dyn Reflect) -> Vec<ValidationError> {
+        (self.validate)(value)
+    }
+}
+
+impl<T: Validate + Reflect> FromType<T> for ReflectValidate {
+    fn from_type() -> Self {
+        ReflectValidate {
+            validate: |value| {
+                value
+                    .downcast_ref::<T>()
+                    .map(T::validate)
+                    .unwrap_or_default()
+            },
+        }
+    }
+}
+
+/// Check `object` and every value inside it (see [`walk()`]) with the
+/// [`ReflectValidate`] type data of their types in `registry`, and return all
+/// broken rules, with paths relative to `object`.
+///
+/// Values of types without `ReflectValidate`, or that are not registered, are
+/// considered valid, but their fields are still checked.
+///
+/// # Errors
+///
+/// Fails with the broken rules, if there are any.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, reflect::TypeRegistry, validate_deep, ReflectValidate, Validate, ValidationError};
+/// #[derive(Reflect)]
+/// #[reflect(Validate)]
+/// struct Health { current: u32, max: u32 }
+///
+/// impl Validate for Health {
+///     fn validate(&self) -> Vec<ValidationError> {
+///         if self.current > self.max {
+///             vec![ValidationError::at("current", "exceeds max")]
+///         } else {
+///             vec![]
+///         }
+///     }
+/// }
+///
+/// #[derive(Reflect)]
+/// struct Unit { health: Health }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Unit>();
+/// registry.register::<Health>();
+///
+/// let unit = Unit { health: Health { current: 20, max: 10 } };
+/// let errors = validate_deep(&unit, &registry).unwrap_err();
+/// assert_eq!(errors[0].to_string(), "invalid value at 'health.current': exceeds max");
+/// ```
+pub fn validate_deep(
+    object: &dyn Reflect,
+    registry: &TypeRegistry,
+) -> Result<(), Vec<ValidationError>> {
+    let mut errors = Vec::new();
+    walk(object, &mut |path: &str, value: &dyn Reflect| {
+        let validate = registry.get_type_data::<ReflectValidate>(value.as_any().type_id());
+        if let Some(validate) = validate {
+            errors.extend(validate.validate(value).into_iter().map(|mut error| {
+                error.path = join_path(path, &error.path);
+                error
+            }));
+        }
+        Visit::Continue
+    });
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn join_path(base: &str, relative: &str) -> String {
+    if base.is_empty() || relative.is_empty() || relative.starts_with(['.', '[']) {
+        format!("{base}{relative}")
+    } else {
+        format!("{base}.{relative}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::FromReflect;
+
+    use super::*;
+
+    #[derive(Reflect, FromReflect)]
+    #[reflect(Validate)]
+    struct Name(String);
+
+    impl Validate for Name {
+        fn validate(&self) -> Vec<ValidationError> {
+            if self.0.is_empty() {
+                vec![ValidationError::new("must not be empty")]
+            } else {
+                vec![]
+            }
+        }
+    }
+
+    #[derive(Reflect)]
+    struct Team {
+        names: Vec<Name>,
+        scores: [i32; 2],
+    }
+
+    #[test]
+    fn aggregate_paths() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<Team>();
+        registry.register::<Name>();
+        registry.register::<i32>();
+        registry
+            .get_mut(std::any::TypeId::of::<i32>())
+            .expect("type was just registered")
+            .insert(ReflectValidate::new(|value| {
+                match value.downcast_ref::<i32>() {
+                    Some(score) if *score < 0 => vec![ValidationError::new("negative")],
+                    _ => vec![],
+                }
+            }));
+
+        let team = Team {
+            names: vec![Name("a".into()), Name(String::new())],
+            scores: [-1, 2],
+        };
+        let errors = validate_deep(&team, &registry).unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                ValidationError::at("names[1]", "must not be empty"),
+                ValidationError::at("scores[0]", "negative"),
+            ]
+        );
+
+        let team = Team {
+            names: vec![],
+            scores: [0, 0],
+        };
+        assert_eq!(validate_deep(&team, &registry), Ok(()));
+        assert_eq!(join_path("a", "b"), "a.b");
+        assert_eq!(join_path("a", "[0]"), "a[0]");
+        assert_eq!(join_path("", "b"), "b");
+    }
+}
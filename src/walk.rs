@@ -0,0 +1,411 @@
+use std::fmt::Write as _;
+
+use bevy_reflect::{Reflect, ReflectMut, ReflectRef, TypeRegistry, VariantField};
+
+use crate::{CastMut, CastRef, DynamicTrait};
+
+/// What [`walk()`] does after visiting a value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Visit {
+    /// Visit the children of the value.
+    #[default]
+    Continue,
+    /// Do not visit the children of the value, but continue with its
+    /// siblings.
+    SkipChildren,
+    /// Stop walking.
+    Stop,
+}
+
+/// A visitor of the values in an object graph. See [`walk()`].
+///
+/// Implemented for closures taking the path and the value.
+pub trait ReflectVisitor {
+    /// Visit `value`, at `path` relative to the root, in the syntax of
+    /// [`bevy_reflect::GetPath`]. The path of the root is empty.
+    fn visit(&mut self, path: &str, value: &dyn Reflect) -> Visit;
+}
+
+impl<F: FnMut(&str, &dyn Reflect) -> Visit> ReflectVisitor for F {
+    fn visit(&mut self, path: &str, value: &dyn Reflect) -> Visit {
+        self(path, value)
+    }
+}
+
+/// Visit `object` and every value inside it, depth first, with `visitor`.
+///
+/// The object graph is walked through struct, tuple and enum fields, list and
+/// array elements, and map values, including boxed trait objects (see
+/// [`impl_dynamic_trait!`](crate::impl_dynamic_trait)), which are visited as
+/// their concrete type. Returns `false` if the visitor stopped the walk.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, walk, Visit};
+/// #[reflect_trait]
+/// trait Component: DowncastReflect {}
+/// impl_dynamic_trait!(Component, ReflectComponent);
+///
+/// #[derive(Reflect)]
+/// struct Health { current: u32, max: u32 }
+/// impl Component for Health {}
+///
+/// #[derive(Reflect)]
+/// struct Entity { name: String, components: Vec<Box<dyn Component>> }
+///
+/// let entity = Entity {
+///     name: "player".into(),
+///     components: vec![Box::new(Health { current: 7, max: 10 })],
+/// };
+///
+/// let mut numbers = Vec::new();
+/// walk(&entity, &mut |path: &str, value: &dyn Reflect| {
+///     if let Some(n) = value.downcast_ref::<u32>() {
+///         numbers.push(format!("{path} = {n}"));
+///     }
+///     Visit::Continue
+/// });
+/// assert_eq!(numbers, ["components[0].current = 7", "components[0].max = 10"]);
+/// ```
+pub fn walk(object: &dyn Reflect, visitor: &mut dyn ReflectVisitor) -> bool {
+    walk_at(object, visitor, &mut String::new())
+}
+
+fn walk_at(object: &dyn Reflect, visitor: &mut dyn ReflectVisitor, path: &mut String) -> bool {
+    match visitor.visit(path, object) {
+        Visit::Continue => (),
+        Visit::SkipChildren => return true,
+        Visit::Stop => return false,
+    }
+    each_child(object, path, &mut |path, value| {
+        walk_at(value, visitor, path)
+    })
+}
+
+/// Find every value implementing the dynamic trait `P` in the object graph of
+/// `root`, including `root` itself, with its path relative to `root`.
+///
+/// The object graph is walked as by [`walk()`], and the paths are in the same
+/// syntax. Values are cast to `P` with `registry`, so they must be registered
+/// with `#[reflect(MyTrait)]` (or implement a dynamic trait that has `P` as a
+/// registered supertrait). Values found inside other values that were found
+/// are included as well.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, find_all, reflect::TypeRegistry};
+/// #[reflect_trait]
+/// trait Light: DowncastReflect {
+///     fn intensity(&self) -> f32;
+/// }
+/// impl_dynamic_trait!(Light, ReflectLight);
+///
+/// #[derive(Reflect, FromReflect)]
+/// #[reflect(Light)]
+/// struct Lamp(f32);
+/// impl Light for Lamp {
+///     fn intensity(&self) -> f32 { self.0 }
+/// }
+///
+/// #[derive(Reflect, FromReflect)]
+/// struct Room { lamps: Vec<Lamp>, ceiling: Option<Lamp> }
+///
+/// #[derive(Reflect)]
+/// struct Scene { rooms: Vec<Room>, sun: Box<dyn Light> }
+///
+/// let scene = Scene {
+///     rooms: vec![Room { lamps: vec![Lamp(0.5)], ceiling: Some(Lamp(1.0)) }],
+///     sun: Box::new(Lamp(100.0)),
+/// };
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Lamp>();
+///
+/// let lights: Vec<(String, f32)> = find_all::<dyn Light>(&scene, &registry)
+///     .into_iter()
+///     .map(|(path, light)| (path, light.intensity()))
+///     .collect();
+/// assert_eq!(lights, [
+///     ("rooms[0].lamps[0]".to_string(), 0.5),
+///     ("rooms[0].ceiling.0".to_string(), 1.0),
+///     ("sun".to_string(), 100.0),
+/// ]);
+/// ```
+pub fn find_all<'a, P: DynamicTrait + ?Sized>(
+    root: &'a dyn Reflect,
+    registry: &TypeRegistry,
+) -> Vec<(String, &'a P)> {
+    fn find_at<'a, P: DynamicTrait + ?Sized>(
+        object: &'a dyn Reflect,
+        registry: &TypeRegistry,
+        path: &mut String,
+        found: &mut Vec<(String, &'a P)>,
+    ) -> bool {
+        if let Ok(object) = object.try_cast_ref_with_registry::<P>(registry) {
+            found.push((path.clone(), object));
+        }
+        each_child(object, path, &mut |path, value| {
+            find_at(value, registry, path, found)
+        })
+    }
+
+    let mut found = Vec::new();
+    find_at(root, registry, &mut String::new(), &mut found);
+    found
+}
+
+/// Call `f` with every value implementing the dynamic trait `P` in the object
+/// graph of `root`, including `root` itself, in a single mutable traversal.
+/// Returns the number of values that `f` was called with.
+///
+/// This is the mutable counterpart of [`find_all()`]: the object graph is
+/// walked in the same order, and values are cast to `P` with `registry`. `f`
+/// is called with a value before the values inside it are visited, so the
+/// values inside it are the ones left by `f`.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, update_all, reflect::TypeRegistry};
+/// #[reflect_trait]
+/// trait Light: DowncastReflect {
+///     fn dim(&mut self, factor: f32);
+/// }
+/// impl_dynamic_trait!(Light, ReflectLight);
+///
+/// #[derive(Reflect, FromReflect)]
+/// #[reflect(Light)]
+/// struct Lamp(f32);
+/// impl Light for Lamp {
+///     fn dim(&mut self, factor: f32) { self.0 *= factor; }
+/// }
+///
+/// #[derive(Reflect)]
+/// struct Scene { lamps: Vec<Lamp>, sun: Box<dyn Light> }
+///
+/// let mut scene = Scene { lamps: vec![Lamp(1.0), Lamp(0.5)], sun: Box::new(Lamp(100.0)) };
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Lamp>();
+///
+/// let count = update_all::<dyn Light>(&mut scene, &registry, |light| light.dim(0.5));
+/// assert_eq!(count, 3);
+/// assert_eq!(scene.lamps[1].0, 0.25);
+/// assert_eq!(scene.sun.downcast_ref::<Lamp>().unwrap().0, 50.0);
+/// ```
+pub fn update_all<P: DynamicTrait + ?Sized>(
+    root: &mut dyn Reflect,
+    registry: &TypeRegistry,
+    mut f: impl FnMut(&mut P),
+) -> usize {
+    fn update_at<P: DynamicTrait + ?Sized>(
+        object: &mut dyn Reflect,
+        registry: &TypeRegistry,
+        f: &mut dyn FnMut(&mut P),
+    ) -> usize {
+        let mut count = 0;
+        if let Ok(object) = (&mut *object).try_cast_mut_with_registry::<P>(registry) {
+            f(object);
+            count += 1;
+        }
+        each_child_mut(object, &mut |value| count += update_at(value, registry, f));
+        count
+    }
+
+    update_at(root, registry, &mut f)
+}
+
+/// Call `f` with each value directly inside `object` and its path, which is
+/// `path` followed by the field, index or key of the value. `path` is restored
+/// after each call. Returns `false` as soon as `f` does.
+fn each_child<'a>(
+    object: &'a dyn Reflect,
+    path: &mut String,
+    f: &mut dyn FnMut(&mut String, &'a dyn Reflect) -> bool,
+) -> bool {
+    let len = path.len();
+    let mut child = |path: &mut String, value: &'a dyn Reflect| {
+        let result = f(path, value);
+        path.truncate(len);
+        result
+    };
+    match object.reflect_ref() {
+        ReflectRef::Struct(s) => s.iter_fields().enumerate().all(|(index, field)| {
+            push_field(path, s.name_at(index).unwrap_or_default());
+            child(path, field)
+        }),
+        ReflectRef::TupleStruct(s) => s.iter_fields().enumerate().all(|(index, field)| {
+            let _ = write!(path, ".{index}");
+            child(path, field)
+        }),
+        ReflectRef::Tuple(t) => t.iter_fields().enumerate().all(|(index, field)| {
+            let _ = write!(path, ".{index}");
+            child(path, field)
+        }),
+        ReflectRef::List(list) => list.iter().enumerate().all(|(index, element)| {
+            let _ = write!(path, "[{index}]");
+            child(path, element)
+        }),
+        ReflectRef::Array(array) => array.iter().enumerate().all(|(index, element)| {
+            let _ = write!(path, "[{index}]");
+            child(path, element)
+        }),
+        ReflectRef::Map(map) => map.iter().all(|(key, value)| {
+            let _ = write!(path, "[{key:?}]");
+            child(path, value)
+        }),
+        ReflectRef::Enum(e) => e.iter_fields().enumerate().all(|(index, field)| {
+            match field {
+                VariantField::Struct(name, _) => push_field(path, name),
+                VariantField::Tuple(_) => {
+                    let _ = write!(path, ".{index}");
+                }
+            }
+            child(path, field.value())
+        }),
+        ReflectRef::Value(_) => true,
+    }
+}
+
+/// Call `f` with each value directly inside `object`, in the order of
+/// [`each_child()`].
+fn each_child_mut(object: &mut dyn Reflect, f: &mut dyn FnMut(&mut dyn Reflect)) {
+    match object.reflect_mut() {
+        ReflectMut::Struct(s) => {
+            for index in 0..s.field_len() {
+                if let Some(value) = s.field_at_mut(index) {
+                    f(value);
+                }
+            }
+        }
+        ReflectMut::TupleStruct(s) => {
+            for index in 0..s.field_len() {
+                if let Some(value) = s.field_mut(index) {
+                    f(value);
+                }
+            }
+        }
+        ReflectMut::Tuple(t) => {
+            for index in 0..t.field_len() {
+                if let Some(value) = t.field_mut(index) {
+                    f(value);
+                }
+            }
+        }
+        ReflectMut::List(list) => {
+            for index in 0..list.len() {
+                if let Some(value) = list.get_mut(index) {
+                    f(value);
+                }
+            }
+        }
+        ReflectMut::Array(array) => {
+            for index in 0..array.len() {
+                if let Some(value) = array.get_mut(index) {
+                    f(value);
+                }
+            }
+        }
+        ReflectMut::Map(map) => {
+            let keys: Vec<Box<dyn Reflect>> =
+                map.iter().map(|(key, _)| key.clone_value()).collect();
+            for key in keys {
+                if let Some(value) = map.get_mut(&*key) {
+                    f(value);
+                }
+            }
+        }
+        ReflectMut::Enum(e) => {
+            for index in 0..e.field_len() {
+                if let Some(value) = e.field_at_mut(index) {
+                    f(value);
+                }
+            }
+        }
+        ReflectMut::Value(_) => (),
+    }
+}
+
+fn push_field(path: &mut String, name: &str) {
+    if !path.is_empty() {
+        path.push('.');
+    }
+    path.push_str(name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Reflect)]
+    struct Inner(u8, u8);
+
+    #[derive(Reflect)]
+    struct Outer {
+        inner: Inner,
+        list: Vec<u8>,
+        pair: (u8, bool),
+    }
+
+    fn outer() -> Outer {
+        Outer {
+            inner: Inner(1, 2),
+            list: vec![3],
+            pair: (4, true),
+        }
+    }
+
+    #[test]
+    fn paths() {
+        let mut paths = Vec::new();
+        walk(&outer(), &mut |path: &str, _: &dyn Reflect| {
+            paths.push(path.to_string());
+            Visit::Continue
+        });
+        assert_eq!(
+            paths,
+            ["", "inner", "inner.0", "inner.1", "list", "list[0]", "pair", "pair.0", "pair.1"]
+        );
+    }
+
+    #[test]
+    fn skip_and_stop() {
+        let mut paths = Vec::new();
+        let completed = walk(&outer(), &mut |path: &str, value: &dyn Reflect| {
+            paths.push(path.to_string());
+            if value.is::<Inner>() {
+                Visit::SkipChildren
+            } else if value.is::<u8>() {
+                Visit::Stop
+            } else {
+                Visit::Continue
+            }
+        });
+        assert!(!completed);
+        assert_eq!(paths, ["", "inner", "list", "list[0]"]);
+    }
+
+    #[test]
+    fn find_root_and_nested_objects() {
+        use crate::prelude::*;
+
+        #[reflect_trait]
+        trait Node: DowncastReflect {}
+        impl_dynamic_trait!(Node, ReflectNode);
+
+        #[derive(Reflect)]
+        #[reflect(Node)]
+        struct Group(Vec<Box<dyn Node>>);
+        impl Node for Group {}
+
+        let root = Group(vec![Box::new(Group(vec![])), Box::new(Group(vec![]))]);
+        let mut registry = TypeRegistry::default();
+        assert!(find_all::<dyn Node>(&root, &registry).is_empty());
+
+        registry.register::<Group>();
+        let paths: Vec<String> = find_all::<dyn Node>(&root, &registry)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(paths, ["", ".0[0]", ".0[1]"]);
+    }
+}
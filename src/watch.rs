@@ -0,0 +1,209 @@
+use bevy_reflect::Reflect;
+
+use crate::{compat::reflect_path, WatchError};
+
+/// Start watching the value at `path` in `object` for changes.
+///
+/// `path` uses the syntax of [`GetPath`](bevy_reflect::GetPath), e.g.
+/// `"stats.hp"` or `"items[0].count"`. The returned [`Watch`] holds a snapshot
+/// of the current value, which later versions of the object can be compared
+/// against.
+///
+/// Values are compared with [`Reflect::reflect_partial_eq()`]. If a value does
+/// not support it, it is always considered changed. Hashes are not used as a
+/// fallback, because a collision would hide a change.
+///
+/// # Errors
+///
+/// Fails if `path` cannot be resolved in `object`.
+///
+/// ## Example
+/// ```rust
+/// # use reflect_steroids::{prelude::*, watch};
+/// #[reflect_trait]
+/// trait Unit: DowncastReflect {}
+/// impl_dynamic_trait!(Unit, ReflectUnit);
+///
+/// #[derive(Reflect)]
+/// struct Stats { hp: i32, mana: i32 }
+///
+/// #[derive(Reflect)]
+/// struct Player { stats: Stats }
+/// impl Unit for Player {}
+///
+/// let mut unit: Box<dyn Unit> = Box::new(Player { stats: Stats { hp: 10, mana: 5 } });
+/// let mut hp = watch(unit.as_reflect(), "stats.hp").unwrap();
+///
+/// unit.downcast_mut::<Player>().unwrap().stats.mana = 4;
+/// assert!(!hp.update(unit.as_reflect()).unwrap());
+///
+/// unit.downcast_mut::<Player>().unwrap().stats.hp = 9;
+/// assert!(hp.update(unit.as_reflect()).unwrap());
+/// assert!(!hp.update(unit.as_reflect()).unwrap());
+/// ```
+pub fn watch(object: &dyn Reflect, path: &str) -> Result<Watch, WatchError> {
+    let value = resolve(object, path)?;
+    Ok(Watch {
+        path: path.to_string(),
+        snapshot: value.clone_value(),
+    })
+}
+
+/// A handle observing the value at a path inside a reflected object. See
+/// [`watch()`].
+#[derive(Debug)]
+pub struct Watch {
+    path: String,
+    snapshot: Box<dyn Reflect>,
+}
+
+impl Watch {
+    /// The watched path.
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The value at the time of the last snapshot.
+    #[must_use]
+    pub fn snapshot(&self) -> &dyn Reflect {
+        &*self.snapshot
+    }
+
+    /// True if the watched value in `object` differs from the snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the path cannot be resolved in `object`.
+    pub fn changed(&self, object: &dyn Reflect) -> Result<bool, WatchError> {
+        Ok(differs(&*self.snapshot, resolve(object, &self.path)?))
+    }
+
+    /// Like [`changed()`](Watch::changed), but also takes a new snapshot if
+    /// the value changed.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the path cannot be resolved in `object`.
+    pub fn update(&mut self, object: &dyn Reflect) -> Result<bool, WatchError> {
+        let value = resolve(object, &self.path)?;
+        let changed = differs(&*self.snapshot, value);
+        if changed {
+            self.snapshot = value.clone_value();
+        }
+        Ok(changed)
+    }
+
+    /// True if the watched value differs between two versions of an object,
+    /// regardless of the snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the path cannot be resolved in either object.
+    pub fn changed_between(
+        &self,
+        before: &dyn Reflect,
+        after: &dyn Reflect,
+    ) -> Result<bool, WatchError> {
+        Ok(differs(
+            resolve(before, &self.path)?,
+            resolve(after, &self.path)?,
+        ))
+    }
+}
+
+fn resolve<'a>(object: &'a dyn Reflect, path: &str) -> Result<&'a dyn Reflect, WatchError> {
+    reflect_path(object, path).map_err(|err| WatchError::InvalidPath {
+        path: path.to_string(),
+        message: err.to_string(),
+    })
+}
+
+fn differs(a: &dyn Reflect, b: &dyn Reflect) -> bool {
+    a.reflect_partial_eq(b) != Some(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Reflect)]
+    #[reflect_value]
+    struct Opaque;
+
+    #[derive(Reflect)]
+    struct Stats {
+        hp: i32,
+        tags: Vec<String>,
+        opaque: Opaque,
+    }
+
+    fn stats(hp: i32) -> Stats {
+        Stats {
+            hp,
+            tags: vec!["hero".into()],
+            opaque: Opaque,
+        }
+    }
+
+    #[test]
+    fn update() {
+        let mut object = stats(10);
+        let mut hp = watch(&object, "hp").unwrap();
+        let mut tags = watch(&object, "tags").unwrap();
+        assert_eq!(hp.path(), "hp");
+        assert!(!hp.changed(&object).unwrap());
+        assert!(!tags.update(&object).unwrap());
+
+        object.hp = 9;
+        object.tags.push("tired".into());
+        assert!(hp.changed(&object).unwrap());
+        assert!(hp.changed(&object).unwrap());
+        assert!(hp.update(&object).unwrap());
+        assert!(!hp.update(&object).unwrap());
+        assert_eq!(hp.snapshot().downcast_ref::<i32>(), Some(&9));
+        assert!(tags.update(&object).unwrap());
+        assert!(!tags.changed(&object).unwrap());
+    }
+
+    #[test]
+    fn changed_between() {
+        let before = stats(10);
+        let after = stats(12);
+        let hp = watch(&before, "hp").unwrap();
+        assert!(hp.changed_between(&before, &after).unwrap());
+        assert!(!hp.changed_between(&after, &after).unwrap());
+
+        // The snapshot is not involved.
+        assert!(!hp.changed(&before).unwrap());
+        assert!(!hp.changed_between(&stats(3), &stats(3)).unwrap());
+    }
+
+    #[test]
+    fn incomparable_values_always_change() {
+        let object = stats(10);
+        let mut opaque = watch(&object, "opaque").unwrap();
+        assert!(opaque.update(&object).unwrap());
+        assert!(opaque.changed_between(&object, &object).unwrap());
+    }
+
+    #[test]
+    fn invalid_path() {
+        let object = stats(10);
+        let err = watch(&object, "mana").unwrap_err();
+        assert!(
+            matches!(err, WatchError::InvalidPath { ref path, .. } if path == "mana"),
+            "{err:?}"
+        );
+
+        let hp = watch(&object, "hp").unwrap();
+        assert!(matches!(
+            hp.changed(&12i32).unwrap_err(),
+            WatchError::InvalidPath { .. }
+        ));
+        assert!(matches!(
+            hp.changed_between(&object, &12i32).unwrap_err(),
+            WatchError::InvalidPath { .. }
+        ));
+    }
+}